@@ -7,9 +7,12 @@ use cita_trie::MemoryDB;
 
 use framework::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
 use framework::binding::state::{GeneralServiceState, MPTTrie};
-use protocol::traits::{NoopDispatcher, Storage};
+use protocol::traits::{
+    ChainStats, EventRecord, NoopDispatcher, Storage, StorageCategory, TransactionWithPosition,
+};
 use protocol::types::{
-    Address, Block, Hash, Proof, Receipt, ServiceContext, ServiceContextParams, SignedTransaction,
+    Address, Block, BlockHeader, Hash, Proof, Receipt, ServiceContext, ServiceContextParams,
+    SignedTransaction,
 };
 use protocol::{types::Bytes, ProtocolResult};
 
@@ -250,18 +253,69 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn insert_transactions_with_receipts(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_block_data(
+        &self,
+        _: Block,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_blocks_data(
+        &self,
+        _: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn update_latest_proof(&self, _: Proof) -> ProtocolResult<()> {
         unimplemented!()
     }
 
+    async fn remove_transactions(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_receipts(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_block(&self, _: u64, _: bool) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn prune_below(&self, _: u64, _: &[StorageCategory]) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn get_transaction_by_hash(&self, _: Hash) -> ProtocolResult<SignedTransaction> {
         unimplemented!()
     }
 
+    async fn get_transaction_with_position(
+        &self,
+        _: Hash,
+    ) -> ProtocolResult<TransactionWithPosition> {
+        unimplemented!()
+    }
+
     async fn get_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
         unimplemented!()
     }
 
+    async fn filter_unknown_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn get_latest_block(&self) -> ProtocolResult<Block> {
         unimplemented!()
     }
@@ -274,6 +328,18 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_header_by_height(&self, _: u64) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_header_by_hash(&self, _: Hash) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(&self, _: u64, _: u64) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn get_receipt(&self, _: Hash) -> ProtocolResult<Receipt> {
         unimplemented!()
     }
@@ -282,6 +348,14 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_block_receipts(&self, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
+    async fn get_block_receipts_by_hash(&self, _: Hash) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
         unimplemented!()
     }
@@ -293,4 +367,46 @@ impl Storage for MockStorage {
     async fn load_overlord_wal(&self) -> ProtocolResult<Bytes> {
         unimplemented!()
     }
+
+    async fn update_evidence(&self, _evidence: Bytes) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn load_evidence(&self) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+
+    async fn update_liveness(&self, _missed: Bytes) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn load_liveness(&self) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+
+    async fn get_chain_stats(&self) -> ProtocolResult<ChainStats> {
+        unimplemented!()
+    }
+
+    async fn get_events(
+        &self,
+        _: &str,
+        _: u64,
+        _: u64,
+        _: usize,
+    ) -> ProtocolResult<Vec<EventRecord>> {
+        unimplemented!()
+    }
+
+    async fn update_pool_transactions(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn get_pool_transactions(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
 }
@@ -1,32 +1,605 @@
 #[cfg(test)]
 mod tests;
+pub mod types;
 
-use binding_macro::{cycles, genesis, service};
-use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
-use protocol::types::{Metadata, ServiceContext, METADATA_KEY};
+use std::collections::HashSet;
+
+use derive_more::Display;
+
+use binding_macro::{cycles, genesis, hook_after, hook_before, service};
+use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK, StoreArray, StoreMap};
+use protocol::types::{Address, Metadata, ServiceContext, ValidatorExtend, METADATA_KEY};
+
+use crate::types::{
+    GetMetadataByHeightPayload, InitGenesisPayload, JailValidatorPayload, JailedValidator,
+    MetadataChangedEvent, MetadataRecord, RemoveValidatorPayload, ScheduleMetadataPayload,
+    SetCyclesLimitPayload, SetIntervalPayload, UpdateAdminPayload, ValidatorsResponse,
+    VersionedMetadata,
+};
+
+const ADMIN_KEY: &str = "admin";
+
+// Block interval bounds, in milliseconds.
+const MIN_INTERVAL: u64 = 100;
+const MAX_INTERVAL: u64 = 60_000;
 
 pub struct MetadataService<SDK> {
-    sdk: SDK,
+    sdk:              SDK,
+    allowed_services: Box<dyn StoreMap<String, bool>>,
+    history:          Box<dyn StoreArray<MetadataRecord>>,
+    // Holds at most one record, the change waiting for its activation height
+    pending:          Box<dyn StoreArray<MetadataRecord>>,
+    // Validators out of the verifier list until they unjail, by address
+    jailed:           Box<dyn StoreMap<Address, JailedValidator>>,
 }
 
 #[service]
 impl<SDK: ServiceSDK> MetadataService<SDK> {
-    pub fn new(sdk: SDK) -> Self {
-        Self { sdk }
+    pub fn new(mut sdk: SDK) -> Self {
+        let allowed_services: Box<dyn StoreMap<String, bool>> =
+            sdk.alloc_or_recover_map("allowed_services");
+        let history: Box<dyn StoreArray<MetadataRecord>> =
+            sdk.alloc_or_recover_array("metadata_history");
+        let pending: Box<dyn StoreArray<MetadataRecord>> =
+            sdk.alloc_or_recover_array("pending_metadata");
+        let jailed: Box<dyn StoreMap<Address, JailedValidator>> =
+            sdk.alloc_or_recover_map("jailed_validators");
+
+        Self {
+            sdk,
+            allowed_services,
+            history,
+            pending,
+            jailed,
+        }
     }
 
     #[genesis]
-    fn init_genesis(&mut self, metadata: Metadata) {
-        self.sdk.set_value(METADATA_KEY.to_string(), metadata)
+    fn init_genesis(&mut self, payload: InitGenesisPayload) {
+        for service_name in payload.allowed_services.into_iter() {
+            self.allowed_services.insert(service_name, true);
+        }
+
+        self.sdk.set_value(ADMIN_KEY.to_owned(), payload.admin);
+        self.save_metadata(0, payload.metadata)
+    }
+
+    // Rewrites metadata stored in an older layout, as part of the first block
+    // executed after the upgrade. Reads decode either layout until then.
+    // Before genesis there is nothing stored yet.
+    #[hook_before]
+    fn migrate_metadata(&mut self, _params: &ExecutorParams) {
+        let stored: Option<VersionedMetadata> = self.sdk.get_value(&METADATA_KEY.to_owned());
+        if let Some(stored) = stored {
+            if stored.is_outdated() {
+                self.sdk.set_value(
+                    METADATA_KEY.to_owned(),
+                    VersionedMetadata::new(stored.metadata),
+                );
+            }
+        }
+    }
+
+    #[hook_after]
+    fn promote_pending_metadata(&mut self, params: &ExecutorParams) {
+        if let Some(record) = self.get_pending() {
+            if record.effective_height <= params.height {
+                self.pending.remove(0);
+                self.save_metadata(record.effective_height, record.metadata);
+            }
+        }
     }
 
     #[cycles(210_00)]
     #[read]
-    fn get_metadata(&self, ctx: ServiceContext) -> ServiceResponse<Metadata> {
-        let metadata: Metadata = self
+    fn get_admin(&self, ctx: ServiceContext) -> ServiceResponse<Address> {
+        let admin: Address = self
             .sdk
-            .get_value(&METADATA_KEY.to_owned())
-            .expect("metadata should not be none");
+            .get_value(&ADMIN_KEY.to_owned())
+            .expect("admin should not be none");
+        ServiceResponse::<Address>::from_succeed(admin)
+    }
+
+    #[cycles(210_00)]
+    #[read]
+    fn get_metadata(&self, ctx: ServiceContext) -> ServiceResponse<Metadata> {
+        let metadata = self.get_active_metadata(ctx.get_current_height());
         ServiceResponse::<Metadata>::from_succeed(metadata)
     }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_metadata_version(&self, ctx: ServiceContext) -> ServiceResponse<u8> {
+        ServiceResponse::<u8>::from_succeed(self.get_stored_metadata().version)
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_validators(&self, ctx: ServiceContext) -> ServiceResponse<ValidatorsResponse> {
+        let metadata = self.get_active_metadata(ctx.get_current_height());
+        ServiceResponse::<ValidatorsResponse>::from_succeed(ValidatorsResponse {
+            verifier_list: metadata.verifier_list,
+        })
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_jailed_validators(&self, ctx: ServiceContext) -> ServiceResponse<ValidatorsResponse> {
+        let verifier_list = self
+            .jailed
+            .iter()
+            .map(|(_, jailed)| jailed.validator)
+            .collect();
+        ServiceResponse::<ValidatorsResponse>::from_succeed(ValidatorsResponse { verifier_list })
+    }
+
+    #[cycles(100_00)]
+    #[read]
+    fn get_interval(&self, ctx: ServiceContext) -> ServiceResponse<u64> {
+        let metadata = self.get_active_metadata(ctx.get_current_height());
+        ServiceResponse::<u64>::from_succeed(metadata.interval)
+    }
+
+    #[cycles(210_00)]
+    #[read]
+    fn get_pending_metadata(&self, ctx: ServiceContext) -> ServiceResponse<Option<MetadataRecord>> {
+        ServiceResponse::<Option<MetadataRecord>>::from_succeed(self.get_pending())
+    }
+
+    #[cycles(210_00)]
+    #[read]
+    fn get_metadata_by_height(
+        &self,
+        ctx: ServiceContext,
+        payload: GetMetadataByHeightPayload,
+    ) -> ServiceResponse<Metadata> {
+        if let Some(record) = self.get_pending() {
+            if record.effective_height <= payload.height {
+                return ServiceResponse::<Metadata>::from_succeed(record.metadata);
+            }
+        }
+
+        // Records are appended in height order, so search for the last one
+        // whose effective height is not above the requested height.
+        let (mut low, mut high) = (0, self.history.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.history.get(mid).effective_height <= payload.height {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        if low == 0 {
+            let e = ServiceError::MetadataNotFound {
+                height: payload.height,
+            };
+            return ServiceResponse::<Metadata>::from_error(e.code(), e.to_string());
+        }
+
+        let record = self.history.get(low - 1);
+        ServiceResponse::<Metadata>::from_succeed(record.metadata)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn write_metadata(&mut self, ctx: ServiceContext, metadata: Metadata) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let activation_height = ctx.get_current_height() + 1;
+        self.update_metadata(&ctx, metadata, activation_height)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn schedule_metadata(
+        &mut self,
+        ctx: ServiceContext,
+        payload: ScheduleMetadataPayload,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        if payload.activation_height <= ctx.get_current_height() {
+            let e = ServiceError::InvalidActivationHeight {
+                height: payload.activation_height,
+            };
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        self.update_metadata(&ctx, payload.metadata, payload.activation_height)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn add_validator(
+        &mut self,
+        ctx: ServiceContext,
+        validator: ValidatorExtend,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let mut metadata = self.get_latest_metadata();
+        if metadata
+            .verifier_list
+            .iter()
+            .any(|v| v.address == validator.address)
+        {
+            let e = ServiceError::DuplicateValidator {
+                address: validator.address.as_hex(),
+            };
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        metadata.verifier_list.push(validator);
+        let activation_height = ctx.get_current_height() + 1;
+        self.update_metadata(&ctx, metadata, activation_height)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn remove_validator(
+        &mut self,
+        ctx: ServiceContext,
+        payload: RemoveValidatorPayload,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let mut metadata = self.get_latest_metadata();
+        let index = metadata
+            .verifier_list
+            .iter()
+            .position(|v| v.address == payload.address);
+
+        let e = match index {
+            Some(_) if metadata.verifier_list.len() == 1 => ServiceError::LastValidator,
+            Some(index) => {
+                metadata.verifier_list.remove(index);
+                let activation_height = ctx.get_current_height() + 1;
+                return self.update_metadata(&ctx, metadata, activation_height);
+            }
+            None => ServiceError::ValidatorNotFound {
+                address: payload.address.as_hex(),
+            },
+        };
+        ServiceResponse::<()>::from_error(e.code(), e.to_string())
+    }
+
+    // Takes a validator found offline out of the verifier list from the next
+    // height, until it unjails.
+    #[cycles(210_00)]
+    #[write]
+    fn jail_validator(
+        &mut self,
+        ctx: ServiceContext,
+        payload: JailValidatorPayload,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let mut metadata = self.get_latest_metadata();
+        let index = metadata
+            .verifier_list
+            .iter()
+            .position(|v| v.address == payload.address);
+
+        let e = match index {
+            Some(_) if metadata.verifier_list.len() == 1 => ServiceError::LastValidator,
+            Some(index) => {
+                let validator = metadata.verifier_list.remove(index);
+                let activation_height = ctx.get_current_height() + 1;
+                let res = self.update_metadata(&ctx, metadata, activation_height);
+                if !res.is_error() {
+                    self.jailed.insert(payload.address, JailedValidator {
+                        jailed_at: activation_height,
+                        validator,
+                    });
+                }
+                return res;
+            }
+            None => ServiceError::ValidatorNotFound {
+                address: payload.address.as_hex(),
+            },
+        };
+        ServiceResponse::<()>::from_error(e.code(), e.to_string())
+    }
+
+    // Puts the jailed caller back into the verifier list from the next
+    // height, with the weights it had.
+    #[cycles(210_00)]
+    #[write]
+    fn unjail(&mut self, ctx: ServiceContext) -> ServiceResponse<()> {
+        let caller = ctx.get_caller();
+        let jailed = match self.jailed.get(&caller) {
+            Some(jailed) => jailed,
+            None => {
+                let e = ServiceError::ValidatorNotJailed {
+                    address: caller.as_hex(),
+                };
+                return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+            }
+        };
+
+        // Added back some other way in the meantime
+        let mut metadata = self.get_latest_metadata();
+        if metadata.verifier_list.iter().any(|v| v.address == caller) {
+            self.jailed.remove(&caller);
+            return ServiceResponse::<()>::from_succeed(());
+        }
+
+        metadata.verifier_list.push(jailed.validator);
+        let activation_height = ctx.get_current_height() + 1;
+        let res = self.update_metadata(&ctx, metadata, activation_height);
+        if !res.is_error() {
+            self.jailed.remove(&caller);
+        }
+        res
+    }
+
+    #[cycles(100_00)]
+    #[write]
+    fn set_interval(
+        &mut self,
+        ctx: ServiceContext,
+        payload: SetIntervalPayload,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let mut metadata = self.get_latest_metadata();
+        metadata.interval = payload.interval;
+        let activation_height = ctx.get_current_height() + 1;
+        self.update_metadata(&ctx, metadata, activation_height)
+    }
+
+    #[cycles(100_00)]
+    #[write]
+    fn set_cycles_limit(
+        &mut self,
+        ctx: ServiceContext,
+        payload: SetCyclesLimitPayload,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = self.verify_permission(&ctx) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let mut metadata = self.get_latest_metadata();
+        metadata.cycles_limit = payload.cycles_limit;
+        let activation_height = ctx.get_current_height() + 1;
+        self.update_metadata(&ctx, metadata, activation_height)
+    }
+
+    #[cycles(210_00)]
+    #[write]
+    fn update_admin(
+        &mut self,
+        ctx: ServiceContext,
+        payload: UpdateAdminPayload,
+    ) -> ServiceResponse<()> {
+        if !self.is_admin(&ctx) {
+            let e = ServiceError::PermissionDenied;
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        self.sdk.set_value(ADMIN_KEY.to_owned(), payload.admin);
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    fn get_pending(&self) -> Option<MetadataRecord> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.get(0))
+        }
+    }
+
+    // The metadata in effect at `height`. A pending change counts once its
+    // activation height is reached, even before the hook promotes it.
+    fn get_active_metadata(&self, height: u64) -> Metadata {
+        match self.get_pending() {
+            Some(record) if record.effective_height <= height => record.metadata,
+            _ => self.get_stored_metadata().metadata,
+        }
+    }
+
+    fn get_stored_metadata(&self) -> VersionedMetadata {
+        self.sdk
+            .get_value(&METADATA_KEY.to_owned())
+            .expect("metadata should not be none")
+    }
+
+    // The newest metadata including the pending change, used as the base for
+    // further updates.
+    fn get_latest_metadata(&self) -> Metadata {
+        self.get_active_metadata(u64::max_value())
+    }
+
+    // Validate the new metadata, then stage it and emit `MetadataChangedEvent`.
+    // A staged change replaces any previous pending one. Callers must check
+    // permission first.
+    fn update_metadata(
+        &mut self,
+        ctx: &ServiceContext,
+        metadata: Metadata,
+        activation_height: u64,
+    ) -> ServiceResponse<()> {
+        if let Err(e) = verify_metadata(&metadata) {
+            return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+        }
+
+        let old_metadata = self.get_latest_metadata();
+        let event = MetadataChangedEvent::new(
+            ctx.get_caller(),
+            activation_height,
+            &old_metadata,
+            &metadata,
+        );
+        let event_str = match serde_json::to_string(&event) {
+            Ok(s) => s,
+            Err(e) => {
+                let e = ServiceError::JsonParse(e.to_string());
+                return ServiceResponse::<()>::from_error(e.code(), e.to_string());
+            }
+        };
+
+        // A pending change that is already active must not be overwritten
+        if let Some(record) = self.get_pending() {
+            self.pending.remove(0);
+            if record.effective_height <= ctx.get_current_height() {
+                self.save_metadata(record.effective_height, record.metadata);
+            }
+        }
+        self.pending.push(MetadataRecord {
+            effective_height: activation_height,
+            metadata,
+        });
+        ctx.emit_event(event_str);
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    fn save_metadata(&mut self, effective_height: u64, metadata: Metadata) {
+        self.history.push(MetadataRecord {
+            effective_height,
+            metadata: metadata.clone(),
+        });
+        self.sdk
+            .set_value(METADATA_KEY.to_owned(), VersionedMetadata::new(metadata))
+    }
+
+    fn verify_permission(&self, ctx: &ServiceContext) -> Result<(), ServiceError> {
+        if self.is_admin(ctx) || self.is_allowed_service(ctx) {
+            Ok(())
+        } else {
+            Err(ServiceError::PermissionDenied)
+        }
+    }
+
+    fn is_admin(&self, ctx: &ServiceContext) -> bool {
+        let admin: Option<Address> = self.sdk.get_value(&ADMIN_KEY.to_owned());
+        admin.map_or(false, |admin| admin == ctx.get_caller())
+    }
+
+    // The calling service is recorded by the framework when a service calls
+    // in through `ServiceSDK::write`, transactions can't set it.
+    fn is_allowed_service(&self, ctx: &ServiceContext) -> bool {
+        ctx.get_caller_service().map_or(false, |name| {
+            self.allowed_services.contains(&name.to_owned())
+        })
+    }
+}
+
+fn verify_metadata(metadata: &Metadata) -> Result<(), ServiceError> {
+    if metadata.verifier_list.is_empty() {
+        return Err(ServiceError::EmptyVerifierList);
+    }
+
+    let mut addresses = HashSet::with_capacity(metadata.verifier_list.len());
+    for validator in metadata.verifier_list.iter() {
+        if !addresses.insert(validator.address.clone()) {
+            return Err(ServiceError::DuplicateValidator {
+                address: validator.address.as_hex(),
+            });
+        }
+
+        if validator.propose_weight == 0 || validator.vote_weight == 0 {
+            return Err(ServiceError::ZeroWeight {
+                address: validator.address.as_hex(),
+            });
+        }
+    }
+
+    if metadata.interval < MIN_INTERVAL || metadata.interval > MAX_INTERVAL {
+        return Err(ServiceError::InvalidInterval {
+            interval: metadata.interval,
+        });
+    }
+
+    let limits = [
+        ("cycles_limit", metadata.cycles_limit),
+        ("cycles_price", metadata.cycles_price),
+        ("timeout_gap", metadata.timeout_gap),
+        ("tx_num_limit", metadata.tx_num_limit),
+        ("max_tx_size", metadata.max_tx_size),
+    ];
+    for (field, value) in limits.iter() {
+        if *value == 0 {
+            return Err(ServiceError::ZeroField { field: *field });
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Display)]
+pub enum ServiceError {
+    #[display(fmt = "verifier list is empty")]
+    EmptyVerifierList,
+
+    #[display(fmt = "duplicate validator {}", address)]
+    DuplicateValidator { address: String },
+
+    #[display(fmt = "validator {} has zero propose or vote weight", address)]
+    ZeroWeight { address: String },
+
+    #[display(
+        fmt = "interval {} out of range [{}, {}]",
+        interval,
+        MIN_INTERVAL,
+        MAX_INTERVAL
+    )]
+    InvalidInterval { interval: u64 },
+
+    #[display(fmt = "metadata field {} must be non-zero", field)]
+    ZeroField { field: &'static str },
+
+    #[display(fmt = "permission denied")]
+    PermissionDenied,
+
+    #[display(fmt = "no metadata recorded at height {}", height)]
+    MetadataNotFound { height: u64 },
+
+    #[display(fmt = "json parse error: {}", _0)]
+    JsonParse(String),
+
+    #[display(fmt = "can't remove the last validator")]
+    LastValidator,
+
+    #[display(fmt = "validator {} not found", address)]
+    ValidatorNotFound { address: String },
+
+    #[display(fmt = "activation height {} is not in the future", height)]
+    InvalidActivationHeight { height: u64 },
+
+    #[display(fmt = "validator {} is not jailed", address)]
+    ValidatorNotJailed { address: String },
+}
+
+impl ServiceError {
+    pub fn code(&self) -> u64 {
+        match self {
+            ServiceError::EmptyVerifierList => 101,
+            ServiceError::DuplicateValidator { .. } => 102,
+            ServiceError::ZeroWeight { .. } => 103,
+            ServiceError::InvalidInterval { .. } => 104,
+            ServiceError::ZeroField { .. } => 105,
+            ServiceError::PermissionDenied => 106,
+            ServiceError::MetadataNotFound { .. } => 107,
+            ServiceError::JsonParse(_) => 108,
+            ServiceError::LastValidator => 109,
+            ServiceError::ValidatorNotFound { .. } => 110,
+            ServiceError::InvalidActivationHeight { .. } => 111,
+            ServiceError::ValidatorNotJailed { .. } => 112,
+        }
+    }
 }
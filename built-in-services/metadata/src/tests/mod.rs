@@ -7,15 +7,25 @@ use cita_trie::MemoryDB;
 
 use framework::binding::sdk::{DefalutServiceSDK, DefaultChainQuerier};
 use framework::binding::state::{GeneralServiceState, MPTTrie};
-use protocol::traits::{NoopDispatcher, ServiceSDK, Storage};
+use protocol::traits::{
+    ChainStats, EventRecord, ExecutorParams, NoopDispatcher, ServiceSDK, Storage, StorageCategory,
+    TransactionWithPosition,
+};
 use protocol::types::{
-    Address, Block, Hash, Hex, Metadata, Proof, Receipt, ServiceContext, ServiceContextParams,
-    SignedTransaction, ValidatorExtend, METADATA_KEY,
+    Address, Block, BlockHeader, Hash, Hex, Metadata, Proof, Receipt, ServiceContext,
+    ServiceContextParams, SignedTransaction, ValidatorExtend, METADATA_KEY,
 };
 use protocol::{types::Bytes, ProtocolResult};
 
+use crate::types::{
+    GetMetadataByHeightPayload, InitGenesisPayload, JailValidatorPayload, MetadataChangedEvent,
+    RemoveValidatorPayload, ScheduleMetadataPayload, SetCyclesLimitPayload, SetIntervalPayload,
+    UpdateAdminPayload, VersionedMetadata, MAX_EVENT_VERIFIERS, METADATA_VERSION,
+};
 use crate::MetadataService;
 
+const ALLOWED_SERVICE: &str = "governance";
+
 #[test]
 fn test_get_metadata() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -30,28 +40,519 @@ fn test_get_metadata() {
     assert_eq!(metadata, init_metadata);
 }
 
-fn new_metadata_service_with_metadata(
-    metadata: Metadata,
-) -> MetadataService<
-    DefalutServiceSDK<
-        GeneralServiceState<MemoryDB>,
-        DefaultChainQuerier<MockStorage>,
-        NoopDispatcher,
-    >,
-> {
+#[test]
+fn test_write_metadata() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller);
+
+    let mut service = new_metadata_service_with_metadata(mock_metadata());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+    new_metadata
+        .verifier_list
+        .push(mock_validator("0x755cdba6ae4f479f7164792b318b2a06c759833b"));
+
+    let res = service.write_metadata(context, new_metadata.clone());
+    assert!(!res.is_error());
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 2);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, new_metadata);
+}
+
+#[test]
+fn test_write_invalid_metadata() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, caller);
+
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+
+    let mut empty_list = mock_metadata();
+    empty_list.verifier_list.clear();
+    let res = service.write_metadata(context.clone(), empty_list);
+    assert_eq!(res.code, 101);
+
+    let mut duplicate = mock_metadata();
+    let validator = duplicate.verifier_list[0].clone();
+    duplicate.verifier_list.push(validator);
+    let res = service.write_metadata(context.clone(), duplicate);
+    assert_eq!(res.code, 102);
+
+    let mut zero_weight = mock_metadata();
+    zero_weight.verifier_list[0].vote_weight = 0;
+    let res = service.write_metadata(context.clone(), zero_weight);
+    assert_eq!(res.code, 103);
+
+    let mut zero_interval = mock_metadata();
+    zero_interval.interval = 0;
+    let res = service.write_metadata(context.clone(), zero_interval);
+    assert_eq!(res.code, 104);
+
+    let mut huge_interval = mock_metadata();
+    huge_interval.interval = 60_001;
+    let res = service.write_metadata(context.clone(), huge_interval);
+    assert_eq!(res.code, 104);
+
+    let mut zero_cycles_limit = mock_metadata();
+    zero_cycles_limit.cycles_limit = 0;
+    let res = service.write_metadata(context.clone(), zero_cycles_limit);
+    assert_eq!(res.code, 105);
+    assert!(res.error_message.contains("cycles_limit"));
+
+    let mut zero_tx_num_limit = mock_metadata();
+    zero_tx_num_limit.tx_num_limit = 0;
+    let res = service.write_metadata(context.clone(), zero_tx_num_limit);
+    assert_eq!(res.code, 105);
+    assert!(res.error_message.contains("tx_num_limit"));
+
+    // Rejected writes must leave the stored metadata untouched
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, init_metadata);
+}
+
+#[test]
+fn test_write_metadata_permission() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let mut service = new_metadata_service_with_metadata(mock_metadata());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+
+    let stranger = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, stranger.clone());
+    let res = service.write_metadata(context.clone(), new_metadata.clone());
+    assert_eq!(res.code, 106);
+
+    // Naming an allowed service in `extra` passes nothing
+    let spoofed = ServiceContext::with_context(
+        &context,
+        Some(Bytes::from(ALLOWED_SERVICE)),
+        "metadata".to_owned(),
+        "write_metadata".to_owned(),
+        "".to_owned(),
+    );
+    let res = service.write_metadata(spoofed, new_metadata.clone());
+    assert_eq!(res.code, 106);
+
+    // Called by a service that isn't allowed
+    let unknown_service = ServiceContext::with_context(
+        &context,
+        None,
+        "metadata".to_owned(),
+        "write_metadata".to_owned(),
+        "".to_owned(),
+    );
+    let res = service.write_metadata(unknown_service, new_metadata.clone());
+    assert_eq!(res.code, 106);
+
+    let mut params = mock_params(cycles_limit, stranger, 1);
+    params.service_name = ALLOWED_SERVICE.to_owned();
+    let allowed_service = ServiceContext::with_context(
+        &ServiceContext::new(params),
+        None,
+        "metadata".to_owned(),
+        "write_metadata".to_owned(),
+        "".to_owned(),
+    );
+    let res = service.write_metadata(allowed_service, new_metadata.clone());
+    assert!(!res.is_error());
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 2);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, new_metadata);
+}
+
+#[test]
+fn test_update_admin() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let mut service = new_metadata_service_with_metadata(mock_metadata());
+
+    let new_admin = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let old_admin_context = mock_context(cycles_limit, mock_admin());
+    let new_admin_context = mock_context(cycles_limit, new_admin.clone());
+
+    let res = service.update_admin(new_admin_context.clone(), UpdateAdminPayload {
+        admin: new_admin.clone(),
+    });
+    assert_eq!(res.code, 106);
+
+    let res = service.update_admin(old_admin_context.clone(), UpdateAdminPayload {
+        admin: new_admin.clone(),
+    });
+    assert!(!res.is_error());
+
+    let admin = service.get_admin(old_admin_context.clone()).succeed_data;
+    assert_eq!(admin, new_admin);
+
+    let res = service.write_metadata(old_admin_context, mock_metadata());
+    assert_eq!(res.code, 106);
+
+    let res = service.write_metadata(new_admin_context, mock_metadata());
+    assert!(!res.is_error());
+}
+
+#[test]
+fn test_get_metadata_by_height() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let genesis_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(genesis_metadata.clone());
+
+    let mut versions = vec![];
+    for (height, interval) in [(5, 1000), (10, 2000), (20, 4000)].iter() {
+        let mut metadata = mock_metadata();
+        metadata.interval = *interval;
+
+        let context = mock_context_with_height(cycles_limit, mock_admin(), *height);
+        assert!(!service.write_metadata(context, metadata.clone()).is_error());
+        service.promote_pending_metadata(&mock_executor_params(*height + 1));
+        versions.push(metadata);
+    }
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 21);
+    let expects = [
+        (0, &genesis_metadata),
+        (5, &genesis_metadata),
+        (6, &versions[0]),
+        (10, &versions[0]),
+        (11, &versions[1]),
+        (20, &versions[1]),
+        (21, &versions[2]),
+        (1000, &versions[2]),
+    ];
+    for (height, expect) in expects.iter() {
+        let res = service.get_metadata_by_height(context.clone(), GetMetadataByHeightPayload {
+            height: *height,
+        });
+        assert_eq!(&res.succeed_data, *expect);
+    }
+
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, versions[2]);
+}
+
+#[test]
+fn test_write_metadata_event() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let context = mock_context(cycles_limit, mock_admin());
+
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+    new_metadata.verifier_list = (0..MAX_EVENT_VERIFIERS + 2)
+        .map(|i| mock_validator(&format!("0x{:040x}", i + 1)))
+        .collect();
+
+    let res = service.write_metadata(context.clone(), new_metadata.clone());
+    assert!(!res.is_error());
+
+    let events = context.get_events();
+    assert_eq!(events.len(), 1);
+
+    let event: MetadataChangedEvent = serde_json::from_str(&events[0].data).unwrap();
+    assert_eq!(event.caller, mock_admin());
+    assert_eq!(event.interval, 1000);
+    let old_address = init_metadata.verifier_list[0].address.clone();
+    assert_eq!(event.old_verifiers, vec![old_address]);
+    assert_eq!(event.old_verifier_count, 1);
+    assert_eq!(event.new_verifiers.len(), MAX_EVENT_VERIFIERS);
+    assert_eq!(
+        event.new_verifiers[0],
+        new_metadata.verifier_list[0].address
+    );
+    assert_eq!(event.new_verifier_count, (MAX_EVENT_VERIFIERS + 2) as u64);
+}
+
+#[test]
+fn test_add_and_remove_validator() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let context = mock_context(cycles_limit, mock_admin());
+
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+
+    let validator = mock_validator("0x666cdba6ae4f479f7164792b318b2a06c759833b");
+    let res = service.add_validator(context.clone(), validator.clone());
+    assert!(!res.is_error());
+
+    let res = service.add_validator(context.clone(), validator.clone());
+    assert_eq!(res.code, 102);
+
+    let next_context = mock_context_with_height(cycles_limit, mock_admin(), 2);
+    let metadata = service.get_metadata(next_context.clone()).succeed_data;
+    assert_eq!(metadata.verifier_list.len(), 2);
+    assert_eq!(metadata.verifier_list[1], validator);
+
+    let first_address = init_metadata.verifier_list[0].address.clone();
+    let res = service.remove_validator(context.clone(), RemoveValidatorPayload {
+        address: first_address.clone(),
+    });
+    assert!(!res.is_error());
+
+    let res = service.remove_validator(context.clone(), RemoveValidatorPayload {
+        address: first_address,
+    });
+    assert_eq!(res.code, 110);
+
+    let res = service.remove_validator(context, RemoveValidatorPayload {
+        address: validator.address.clone(),
+    });
+    assert_eq!(res.code, 109);
+
+    let metadata = service.get_metadata(next_context).succeed_data;
+    assert_eq!(metadata.verifier_list, vec![validator]);
+}
+
+#[test]
+fn test_jail_and_unjail_validator() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let context_at = |caller: &Address, height: u64| {
+        mock_context_with_height(cycles_limit, caller.clone(), height)
+    };
+    let admin = mock_admin();
+
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+    let mut validator = mock_validator("0x666cdba6ae4f479f7164792b318b2a06c759833b");
+    validator.vote_weight = 3;
+    let res = service.add_validator(context_at(&admin, 1), validator.clone());
+    assert!(!res.is_error());
+
+    let jail = |address: &Address| JailValidatorPayload {
+        address: address.clone(),
+    };
+    let stranger = Address::from_hex("0x777cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let res = service.jail_validator(context_at(&stranger, 2), jail(&validator.address));
+    assert_eq!(res.code, 106);
+    let res = service.jail_validator(context_at(&admin, 2), jail(&stranger));
+    assert_eq!(res.code, 110);
+
+    let res = service.jail_validator(context_at(&admin, 2), jail(&validator.address));
+    assert!(!res.is_error());
+    let first_address = &init_metadata.verifier_list[0].address;
+    let res = service.jail_validator(context_at(&admin, 2), jail(first_address));
+    assert_eq!(res.code, 109);
+
+    // Out from the next height
+    let metadata = service.get_metadata(context_at(&admin, 2)).succeed_data;
+    assert_eq!(metadata.verifier_list.len(), 2);
+    let metadata = service.get_metadata(context_at(&admin, 3)).succeed_data;
+    assert_eq!(metadata.verifier_list, init_metadata.verifier_list);
+    let jailed = service
+        .get_jailed_validators(context_at(&admin, 3))
+        .succeed_data;
+    assert_eq!(jailed.verifier_list, vec![validator.clone()]);
+
+    let res = service.unjail(context_at(&stranger, 4));
+    assert_eq!(res.code, 112);
+    let res = service.unjail(context_at(&validator.address, 4));
+    assert!(!res.is_error());
+
+    // Back with its weights from the next height
+    let metadata = service.get_metadata(context_at(&admin, 4)).succeed_data;
+    assert_eq!(metadata.verifier_list, init_metadata.verifier_list);
+    let metadata = service.get_metadata(context_at(&admin, 5)).succeed_data;
+    assert_eq!(metadata.verifier_list[1], validator);
+    let jailed = service
+        .get_jailed_validators(context_at(&admin, 5))
+        .succeed_data;
+    assert!(jailed.verifier_list.is_empty());
+    assert_eq!(service.unjail(context_at(&validator.address, 5)).code, 112);
+}
+
+#[test]
+fn test_set_interval_and_cycles_limit() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let context = mock_context(cycles_limit, mock_admin());
+
+    let mut service = new_metadata_service_with_metadata(mock_metadata());
+
+    let res = service.set_interval(context.clone(), SetIntervalPayload { interval: 0 });
+    assert_eq!(res.code, 104);
+
+    let res = service.set_interval(context.clone(), SetIntervalPayload { interval: 1000 });
+    assert!(!res.is_error());
+
+    let res = service.set_cycles_limit(context.clone(), SetCyclesLimitPayload { cycles_limit: 0 });
+    assert_eq!(res.code, 105);
+
+    let res = service.set_cycles_limit(context, SetCyclesLimitPayload {
+        cycles_limit: 1_000_000,
+    });
+    assert!(!res.is_error());
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 2);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata.interval, 1000);
+    assert_eq!(metadata.cycles_limit, 1_000_000);
+
+    let stranger = Address::from_hex("0x666cdba6ae4f479f7164792b318b2a06c759833b").unwrap();
+    let context = mock_context(cycles_limit, stranger);
+    let res = service.set_interval(context, SetIntervalPayload { interval: 2000 });
+    assert_eq!(res.code, 106);
+}
+
+#[test]
+fn test_metadata_activation() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 10);
+    let res = service.write_metadata(context.clone(), new_metadata.clone());
+    assert!(!res.is_error());
+
+    let metadata = service.get_metadata(context.clone()).succeed_data;
+    assert_eq!(metadata, init_metadata);
+
+    let pending = service.get_pending_metadata(context.clone()).succeed_data;
+    let pending = pending.expect("pending metadata should exist");
+    assert_eq!(pending.effective_height, 11);
+    assert_eq!(pending.metadata, new_metadata);
+
+    // The block that wrote the change doesn't promote it
+    service.promote_pending_metadata(&mock_executor_params(10));
+    let metadata = service.get_metadata(context.clone()).succeed_data;
+    assert_eq!(metadata, init_metadata);
+
+    let next_context = mock_context_with_height(cycles_limit, mock_admin(), 11);
+    let metadata = service.get_metadata(next_context.clone()).succeed_data;
+    assert_eq!(metadata, new_metadata);
+
+    service.promote_pending_metadata(&mock_executor_params(11));
+    let pending = service
+        .get_pending_metadata(next_context.clone())
+        .succeed_data;
+    assert!(pending.is_none());
+    let metadata = service.get_metadata(next_context).succeed_data;
+    assert_eq!(metadata, new_metadata);
+}
+
+#[test]
+fn test_schedule_metadata() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let init_metadata = mock_metadata();
+    let mut service = new_metadata_service_with_metadata(init_metadata.clone());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 10);
+    let res = service.schedule_metadata(context.clone(), ScheduleMetadataPayload {
+        metadata:          new_metadata.clone(),
+        activation_height: 10,
+    });
+    assert_eq!(res.code, 111);
+
+    let res = service.schedule_metadata(context, ScheduleMetadataPayload {
+        metadata:          new_metadata.clone(),
+        activation_height: 20,
+    });
+    assert!(!res.is_error());
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 19);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, init_metadata);
+
+    let context = mock_context_with_height(cycles_limit, mock_admin(), 20);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, new_metadata);
+}
+
+#[test]
+fn test_get_validators_and_interval() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let mut service = new_metadata_service_with_metadata(mock_metadata());
+
+    let mut new_metadata = mock_metadata();
+    new_metadata.interval = 1000;
+    new_metadata
+        .verifier_list
+        .push(mock_validator("0x666cdba6ae4f479f7164792b318b2a06c759833b"));
+
+    let context = mock_context(cycles_limit, mock_admin());
+    let res = service.write_metadata(context.clone(), new_metadata);
+    assert!(!res.is_error());
+
+    let next_context = mock_context_with_height(cycles_limit, mock_admin(), 2);
+    for context in [context, next_context].iter() {
+        let metadata = service.get_metadata(context.clone()).succeed_data;
+
+        let validators = service.get_validators(context.clone()).succeed_data;
+        assert_eq!(validators.verifier_list, metadata.verifier_list);
+
+        let interval = service.get_interval(context.clone()).succeed_data;
+        assert_eq!(interval, metadata.interval);
+    }
+}
+
+#[test]
+fn test_migrate_v1_metadata() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let context = mock_context(cycles_limit, mock_admin());
+
+    // Version 1 stored the plain rlp encoded `Metadata`
+    let v1_metadata = mock_metadata();
+    let mut sdk = new_sdk();
+    sdk.set_value(METADATA_KEY.to_owned(), v1_metadata.clone());
+
+    let stored: VersionedMetadata = sdk.get_value(&METADATA_KEY.to_owned()).unwrap();
+    assert_eq!(stored.version, 1);
+
+    // Creating the service, as every read does, leaves the state alone
+    let mut service = MetadataService::new(sdk);
+    let version = service.get_metadata_version(context.clone()).succeed_data;
+    assert_eq!(version, 1);
+    let metadata = service.get_metadata(context.clone()).succeed_data;
+    assert_eq!(metadata, v1_metadata);
+
+    service.migrate_metadata(&mock_executor_params(1));
+
+    let version = service.get_metadata_version(context.clone()).succeed_data;
+    assert_eq!(version, METADATA_VERSION);
+    let metadata = service.get_metadata(context).succeed_data;
+    assert_eq!(metadata, v1_metadata);
+}
+
+type MockSDK = DefalutServiceSDK<
+    GeneralServiceState<MemoryDB>,
+    DefaultChainQuerier<MockStorage>,
+    NoopDispatcher,
+>;
+
+fn new_metadata_service_with_metadata(metadata: Metadata) -> MetadataService<MockSDK> {
+    let mut service = MetadataService::new(new_sdk());
+    service.init_genesis(InitGenesisPayload {
+        metadata,
+        admin: mock_admin(),
+        allowed_services: vec![ALLOWED_SERVICE.to_owned()],
+    });
+
+    service
+}
+
+fn new_sdk() -> MockSDK {
     let chain_db = DefaultChainQuerier::new(Arc::new(MockStorage {}));
     let trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
     let state = GeneralServiceState::new(trie);
 
-    let mut sdk = DefalutServiceSDK::new(
+    DefalutServiceSDK::new(
         Rc::new(RefCell::new(state)),
         Rc::new(chain_db),
         NoopDispatcher {},
-    );
-
-    sdk.set_value(METADATA_KEY.to_string(), metadata);
+    )
+}
 
-    MetadataService::new(sdk)
+fn mock_admin() -> Address {
+    Address::from_hex("0x755cdba6ae4f479f7164792b318b2a06c759833b").unwrap()
 }
 
 fn mock_metadata() -> Metadata {
@@ -78,24 +579,48 @@ fn mock_metadata() -> Metadata {
     }
 }
 
+fn mock_validator(address: &str) -> ValidatorExtend {
+    ValidatorExtend {
+        bls_pub_key:    Hex::from_string("0x04102947214862a503c73904deb5818298a186d68c7907bb609583192a7de6331493835e5b8281f4d9ee705537c0e765580e06f86ddce5867812fceb42eecefd209f0eddd0389d6b7b0100f00fb119ef9ab23826c6ea09aadcc76fa6cea6a32724".to_owned()).unwrap(),
+        address:        Address::from_hex(address).unwrap(),
+        propose_weight: 1,
+        vote_weight:    1,
+    }
+}
+
+fn mock_executor_params(height: u64) -> ExecutorParams {
+    ExecutorParams {
+        state_root: Hash::default(),
+        height,
+        timestamp: 0,
+        cycles_limit: u64::max_value(),
+    }
+}
+
 fn mock_context(cycles_limit: u64, caller: Address) -> ServiceContext {
-    let params = ServiceContextParams {
+    mock_context_with_height(cycles_limit, caller, 1)
+}
+
+fn mock_context_with_height(cycles_limit: u64, caller: Address, height: u64) -> ServiceContext {
+    ServiceContext::new(mock_params(cycles_limit, caller, height))
+}
+
+fn mock_params(cycles_limit: u64, caller: Address, height: u64) -> ServiceContextParams {
+    ServiceContextParams {
         tx_hash: None,
         nonce: None,
         cycles_limit,
         cycles_price: 1,
         cycles_used: Rc::new(RefCell::new(0)),
         caller,
-        height: 1,
+        height,
         timestamp: 0,
         service_name: "service_name".to_owned(),
         service_method: "service_method".to_owned(),
         service_payload: "service_payload".to_owned(),
         extra: None,
         events: Rc::new(RefCell::new(vec![])),
-    };
-
-    ServiceContext::new(params)
+    }
 }
 
 struct MockStorage;
@@ -114,18 +639,69 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn insert_transactions_with_receipts(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_block_data(
+        &self,
+        _: Block,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_blocks_data(
+        &self,
+        _: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn update_latest_proof(&self, _: Proof) -> ProtocolResult<()> {
         unimplemented!()
     }
 
+    async fn remove_transactions(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_receipts(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_block(&self, _: u64, _: bool) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn prune_below(&self, _: u64, _: &[StorageCategory]) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn get_transaction_by_hash(&self, _: Hash) -> ProtocolResult<SignedTransaction> {
         unimplemented!()
     }
 
+    async fn get_transaction_with_position(
+        &self,
+        _: Hash,
+    ) -> ProtocolResult<TransactionWithPosition> {
+        unimplemented!()
+    }
+
     async fn get_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
         unimplemented!()
     }
 
+    async fn filter_unknown_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn get_latest_block(&self) -> ProtocolResult<Block> {
         unimplemented!()
     }
@@ -138,6 +714,18 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_header_by_height(&self, _: u64) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_header_by_hash(&self, _: Hash) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(&self, _: u64, _: u64) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn get_receipt(&self, _: Hash) -> ProtocolResult<Receipt> {
         unimplemented!()
     }
@@ -146,6 +734,14 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_block_receipts(&self, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
+    async fn get_block_receipts_by_hash(&self, _: Hash) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
         unimplemented!()
     }
@@ -157,4 +753,46 @@ impl Storage for MockStorage {
     async fn load_overlord_wal(&self) -> ProtocolResult<Bytes> {
         unimplemented!()
     }
+
+    async fn update_evidence(&self, _evidence: Bytes) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn load_evidence(&self) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+
+    async fn update_liveness(&self, _missed: Bytes) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn load_liveness(&self) -> ProtocolResult<Bytes> {
+        unimplemented!()
+    }
+
+    async fn get_chain_stats(&self) -> ProtocolResult<ChainStats> {
+        unimplemented!()
+    }
+
+    async fn get_events(
+        &self,
+        _: &str,
+        _: u64,
+        _: u64,
+        _: usize,
+    ) -> ProtocolResult<Vec<EventRecord>> {
+        unimplemented!()
+    }
+
+    async fn update_pool_transactions(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn get_pool_transactions(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
 }
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+
+use bytes::Bytes;
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::types::{Address, Metadata, ValidatorExtend};
+use protocol::ProtocolResult;
+
+/// Genesis payload. The metadata fields are flattened so the payload stays
+/// readable as a plain `Metadata` by the node bootstrap.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct InitGenesisPayload {
+    #[serde(flatten)]
+    pub metadata:         Metadata,
+    pub admin:            Address,
+    #[serde(default)]
+    pub allowed_services: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct UpdateAdminPayload {
+    pub admin: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RemoveValidatorPayload {
+    pub address: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct JailValidatorPayload {
+    pub address: Address,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SetIntervalPayload {
+    pub interval: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SetCyclesLimitPayload {
+    pub cycles_limit: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ScheduleMetadataPayload {
+    pub metadata:          Metadata,
+    pub activation_height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct GetMetadataByHeightPayload {
+    pub height: u64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+pub struct ValidatorsResponse {
+    pub verifier_list: Vec<ValidatorExtend>,
+}
+
+/// Verifier lists longer than this are truncated in `MetadataChangedEvent`,
+/// the full length is still reported through the count fields.
+pub const MAX_EVENT_VERIFIERS: usize = 128;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct MetadataChangedEvent {
+    pub caller:             Address,
+    pub activation_height:  u64,
+    pub interval:           u64,
+    pub old_verifiers:      Vec<Address>,
+    pub old_verifier_count: u64,
+    pub new_verifiers:      Vec<Address>,
+    pub new_verifier_count: u64,
+}
+
+impl MetadataChangedEvent {
+    pub fn new(caller: Address, activation_height: u64, old: &Metadata, new: &Metadata) -> Self {
+        let addresses = |metadata: &Metadata| -> Vec<Address> {
+            metadata
+                .verifier_list
+                .iter()
+                .take(MAX_EVENT_VERIFIERS)
+                .map(|v| v.address.clone())
+                .collect()
+        };
+
+        MetadataChangedEvent {
+            caller,
+            activation_height,
+            interval: new.interval,
+            old_verifiers: addresses(old),
+            old_verifier_count: old.verifier_list.len() as u64,
+            new_verifiers: addresses(new),
+            new_verifier_count: new.verifier_list.len() as u64,
+        }
+    }
+}
+
+/// A metadata version together with the height from which it applies.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+pub struct MetadataRecord {
+    pub effective_height: u64,
+    pub metadata:         Metadata,
+}
+
+impl rlp::Decodable for MetadataRecord {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            effective_height: rlp.at(0)?.as_val()?,
+            metadata:         rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for MetadataRecord {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.effective_height)
+            .append(&self.metadata);
+    }
+}
+
+impl FixedCodec for MetadataRecord {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+/// A validator taken out of the verifier list by `jail_validator`, kept to be
+/// put back as it was when it unjails.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct JailedValidator {
+    pub jailed_at: u64,
+    pub validator: ValidatorExtend,
+}
+
+impl rlp::Decodable for JailedValidator {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(Self {
+            jailed_at: rlp.at(0)?.as_val()?,
+            validator: rlp.at(1)?.as_val()?,
+        })
+    }
+}
+
+impl rlp::Encodable for JailedValidator {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append(&self.jailed_at)
+            .append(&self.validator);
+    }
+}
+
+impl FixedCodec for JailedValidator {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+/// Layout version written in front of the stored metadata.
+///
+/// Version 1 is the original unprefixed rlp encoding. An rlp list always
+/// starts with a byte of at least 0xc0, so it can't be mistaken for a
+/// version byte.
+pub const METADATA_VERSION: u8 = 2;
+
+const RLP_LIST_OFFSET: u8 = 0xc0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionedMetadata {
+    pub version:  u8,
+    pub metadata: Metadata,
+}
+
+impl VersionedMetadata {
+    pub fn new(metadata: Metadata) -> Self {
+        VersionedMetadata {
+            version: METADATA_VERSION,
+            metadata,
+        }
+    }
+
+    pub fn is_outdated(&self) -> bool {
+        self.version < METADATA_VERSION
+    }
+}
+
+impl FixedCodec for VersionedMetadata {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        let mut bytes = vec![METADATA_VERSION];
+        bytes.extend_from_slice(&rlp::encode(&self.metadata));
+
+        Ok(Bytes::from(bytes))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        let (version, metadata) = match bytes.first() {
+            Some(b) if *b >= RLP_LIST_OFFSET => (1, decode_v1(&bytes)?),
+            Some(b) if *b == METADATA_VERSION => {
+                let metadata = rlp::decode(&bytes[1..]).map_err(FixedCodecError::from)?;
+                (METADATA_VERSION, metadata)
+            }
+            _ => {
+                return Err(FixedCodecError::from(rlp::DecoderError::Custom(
+                    "unknown metadata version",
+                ))
+                .into())
+            }
+        };
+
+        Ok(VersionedMetadata { version, metadata })
+    }
+}
+
+// Version 1 has the same fields as the current `Metadata`. Fields added in
+// later versions must be filled with defaults here.
+fn decode_v1(bytes: &[u8]) -> ProtocolResult<Metadata> {
+    Ok(rlp::decode(bytes).map_err(FixedCodecError::from)?)
+}
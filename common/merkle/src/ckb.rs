@@ -0,0 +1,165 @@
+use blake2b_simd::Params;
+
+use crate::calculate_root;
+
+const CKB_HASH_PERSONALIZATION: &[u8] = b"ckb-default-hash";
+
+/// Proof for leaves of a CKB merkle tree, such as the one returned by CKB's
+/// `get_transaction_proof` RPC.
+///
+/// CKB stores the `n` leaves in order at the end of the node array, starting
+/// at `n - 1`, and merges with blake2b-256 personalized by
+/// `ckb-default-hash`. `indices` are node array positions, as CKB returns
+/// them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CkbProof {
+    pub indices: Vec<u32>,
+    pub lemmas:  Vec<[u8; 32]>,
+}
+
+impl CkbProof {
+    pub fn new(indices: Vec<u32>, lemmas: Vec<[u8; 32]>) -> Self {
+        CkbProof { indices, lemmas }
+    }
+
+    /// `leaves` are matched with `indices` by position.
+    pub fn verify(&self, root: &[u8; 32], leaves: &[[u8; 32]]) -> bool {
+        match calculate_root(&self.indices, &self.lemmas, leaves, CkbProof::merge) {
+            Some(hash) => &hash == root,
+            None => false,
+        }
+    }
+
+    /// Checks transaction hashes against a block's `transactions_root`, with
+    /// the `witnesses_root` that `get_transaction_proof` returns next to the
+    /// proof.
+    pub fn verify_transactions_root(
+        &self,
+        transactions_root: &[u8; 32],
+        witnesses_root: &[u8; 32],
+        tx_hashes: &[[u8; 32]],
+    ) -> bool {
+        match calculate_root(&self.indices, &self.lemmas, tx_hashes, CkbProof::merge) {
+            Some(raw_root) => &CkbProof::merge(&raw_root, witnesses_root) == transactions_root,
+            None => false,
+        }
+    }
+
+    /// CKB's merge function. A block's `transactions_root` is the merge of the
+    /// raw transactions root, which a proof over transaction hashes rebuilds,
+    /// and the witnesses root.
+    pub fn merge(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let mut hasher = Params::new()
+            .hash_length(32)
+            .personal(CKB_HASH_PERSONALIZATION)
+            .to_state();
+        hasher.update(left);
+        hasher.update(right);
+
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(hasher.finalize().as_bytes());
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CkbProof;
+
+    // Generated with a separate implementation of CKB's CBMT. Leaf `i` is the
+    // CKB hash of the single byte `i`, five leaves in total.
+    const ROOT: &str = "47bda231b73a389d5e487dd23b4d672f2243bfefd9125dd951ae68d1d4e6df91";
+    const LEAVES: [&str; 5] = [
+        "ef3f1252fe6f373c05e5e9c6371e230e29c1226b21752ab21611479b57a0f9d6",
+        "b9aaddf96f7f5c742950611835c040af6b7024adf1148cda2acb087f0129befb",
+        "10ad3f5012ce514f409e4da4c011c24a314434881872c124ae064012afaf389d",
+        "f37dfa5b009ea001acd3617886d9efecf31bb153bee72b7fb73f9e03ff3d5a34",
+        "97bff01bcad316a4b534ef221bd66da97018df9058d313dee1fa4455bf41332e",
+    ];
+
+    fn h256(s: &str) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&hex::decode(s).unwrap());
+        hash
+    }
+
+    fn leaves(input_indices: &[usize]) -> Vec<[u8; 32]> {
+        input_indices.iter().map(|i| h256(LEAVES[*i])).collect()
+    }
+
+    #[test]
+    fn test_ckb_merge() {
+        // CKB's hash of empty input, the usual check for the personalization
+        let hasher = blake2b_simd::Params::new()
+            .hash_length(32)
+            .personal(b"ckb-default-hash")
+            .hash(&[]);
+        assert_eq!(
+            hex::encode(hasher.as_bytes()),
+            "44f4c69744d5f8c55d642062949dcae49bc4e7ef43d388c5a12f42b5633d163e"
+        );
+
+        // Node 3 of the tree, parent of the last two leaves
+        assert_eq!(
+            CkbProof::merge(&h256(LEAVES[3]), &h256(LEAVES[4])),
+            h256("12856b65ff6854990d1570540144335d92d6446af82cb0b32186fe9de87f20ed")
+        );
+    }
+
+    #[test]
+    fn test_ckb_proof_verify() {
+        let root = h256(ROOT);
+
+        let proof = CkbProof::new(vec![7, 5], vec![
+            h256("97bff01bcad316a4b534ef221bd66da97018df9058d313dee1fa4455bf41332e"),
+            h256("10ad3f5012ce514f409e4da4c011c24a314434881872c124ae064012afaf389d"),
+            h256("ef3f1252fe6f373c05e5e9c6371e230e29c1226b21752ab21611479b57a0f9d6"),
+        ]);
+        assert!(proof.verify(&root, &leaves(&[3, 1])));
+        assert!(!proof.verify(&root, &leaves(&[1, 3])));
+
+        let proof = CkbProof::new(vec![4], vec![
+            h256("12856b65ff6854990d1570540144335d92d6446af82cb0b32186fe9de87f20ed"),
+            h256("01547b0c5569a3e83f55e6d4987928b2253ee010bbd25c6531b46fcf70e75168"),
+        ]);
+        assert!(proof.verify(&root, &leaves(&[0])));
+        assert!(!proof.verify(&root, &leaves(&[1])));
+
+        let proof = CkbProof::new(vec![8, 6, 4], vec![
+            h256("f37dfa5b009ea001acd3617886d9efecf31bb153bee72b7fb73f9e03ff3d5a34"),
+            h256("b9aaddf96f7f5c742950611835c040af6b7024adf1148cda2acb087f0129befb"),
+        ]);
+        assert!(proof.verify(&root, &leaves(&[4, 2, 0])));
+
+        // Extra lemmas or missing leaves must not verify
+        let mut extra = proof.clone();
+        extra.lemmas.push(h256(LEAVES[1]));
+        assert!(!extra.verify(&root, &leaves(&[4, 2, 0])));
+        assert!(!proof.verify(&root, &leaves(&[4, 2])));
+        assert!(!proof.verify(&root, &[]));
+    }
+
+    // Generated with a separate implementation of CKB's CBMT, for a block of
+    // three transactions whose hashes are the CKB hashes of "tx0", "tx1" and
+    // "tx2", and witnesses hashed from "witness0".. the same way.
+    #[test]
+    fn test_ckb_transactions_root() {
+        let transactions_root =
+            h256("b7ad9dcf4fa4af5d8a11663d0b0d10df229c560fbe3a1b51d6cc7ae40ec79881");
+        let witnesses_root =
+            h256("ba5e3d8f9438678cf33663f7914ba1d9c78d82c77c57c6bd6e6de10dd88f52df");
+        let tx1 = h256("52355820b7a2741b3e3529aae8b09723a938f3906f074e9338ce247a7844ed0e");
+
+        let proof = CkbProof::new(vec![3], vec![
+            h256("bcdaa376a239b307ce959e5c6e67d71a1eb65a14b9261ef138ac337999409aba"),
+            h256("45a1a1be4d7b04285a498a4c6eea067f30e8b5cdfc5c684db5f3b20dd2967c99"),
+        ]);
+        assert!(proof.verify_transactions_root(&transactions_root, &witnesses_root, &[tx1]));
+        assert!(!proof.verify_transactions_root(&transactions_root, &tx1, &[tx1]));
+        assert!(!proof.verify(&transactions_root, &[tx1]));
+
+        // The raw transactions root alone
+        let raw_root = h256("61989da0e6e35bb0437ea46c09d03a7fb2b419cb922cb7310e531ef84df3abb0");
+        assert!(proof.verify(&raw_root, &[tx1]));
+    }
+}
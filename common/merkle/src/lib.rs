@@ -1,6 +1,62 @@
-use static_merkle_tree::Tree;
+#![feature(test)]
 
-use protocol::{types::Hash, Bytes};
+mod ckb;
+
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+use parking_lot::RwLock;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::types::{Hash, Receipt, SignedTransaction};
+use protocol::{Bytes, ProtocolResult};
+
+pub use ckb::CkbProof;
+
+/// Hashes two child nodes into their parent.
+pub trait MergeHasher {
+    fn merge(left: &Hash, right: &Hash) -> Hash;
+}
+
+/// `Hash::digest` over the concatenated children, the merge every block root
+/// is built with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DefaultMerge;
+
+impl MergeHasher for DefaultMerge {
+    fn merge(left: &Hash, right: &Hash) -> Hash {
+        let left = left.as_bytes();
+        let right = right.as_bytes();
+
+        let mut root = Vec::with_capacity(left.len() + right.len());
+        root.extend_from_slice(&left);
+        root.extend_from_slice(&right);
+        Hash::digest(Bytes::from(root))
+    }
+}
+
+/// blake2b-256 personalized by `ckb-default-hash` over the concatenated
+/// children, the merge of CKB. Trees keep this crate's layout, which isn't
+/// CKB's, so proofs from CKB go through `CkbProof`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Blake2bMerge;
+
+impl MergeHasher for Blake2bMerge {
+    fn merge(left: &Hash, right: &Hash) -> Hash {
+        let mut left_bytes = [0u8; 32];
+        left_bytes.copy_from_slice(&left.as_bytes());
+        let mut right_bytes = [0u8; 32];
+        right_bytes.copy_from_slice(&right.as_bytes());
+
+        let root = CkbProof::merge(&left_bytes, &right_bytes);
+        Hash::from_bytes(Bytes::from(root.to_vec())).expect("blake2b-256 output is 32 bytes")
+    }
+}
+
+pub type Merkle = MerkleTree<DefaultMerge>;
+pub type Proof = MerkleProof<DefaultMerge>;
 
 #[derive(Debug, Clone)]
 pub struct ProofNode {
@@ -8,45 +64,794 @@ pub struct ProofNode {
     pub hash:     Hash,
 }
 
-pub struct Merkle {
-    tree: Tree<Hash>,
+/// Below this many leaves `from_hashes_parallel` builds on the current thread,
+/// handing out work costs more than it saves.
+pub const PARALLEL_THRESHOLD: usize = 1024;
+
+/// A complete binary tree stored as an array, node `i` has children `2i + 1`
+/// and `2i + 2`. Leaves keep their input order from left to right, the root
+/// is node 0.
+///
+/// A tree without leaves has no root, `ordered_root` reports
+/// `Hash::from_empty()` for it. A tree with a single leaf has that leaf as
+/// its root, and its proofs carry no lemmas.
+pub struct MerkleTree<H: MergeHasher> {
+    nodes:        Vec<Hash>,
+    leaves_count: usize,
+    // Leaf hash to input index, built on the first lookup by hash
+    leaf_indices: RwLock<Option<HashMap<Hash, u32>>>,
+    hasher:       PhantomData<H>,
 }
 
-impl Merkle {
+impl<H: MergeHasher> MerkleTree<H> {
     pub fn from_hashes(hashes: Vec<Hash>) -> Self {
-        let tree = Tree::from_hashes(hashes, merge);
-        Merkle { tree }
+        let mut nodes = place_leaves(hashes);
+        for index in (1..nodes.len()).rev().step_by(2) {
+            nodes[parent(index)] = H::merge(&nodes[index - 1], &nodes[index]);
+        }
+
+        Self::from_nodes(nodes)
     }
 
-    pub fn get_root_hash(&self) -> Option<Hash> {
-        match self.tree.get_root_hash() {
-            Some(hash) => Some(hash.clone()),
-            None => None,
+    /// Same tree as `from_hashes`, built on the rayon pool. Each row of the
+    /// tree only depends on the row below it, so the nodes of a row are
+    /// split across workers and the rows are built bottom up.
+    pub fn from_hashes_parallel(hashes: Vec<Hash>) -> Self {
+        if hashes.len() < PARALLEL_THRESHOLD {
+            return Self::from_hashes(hashes);
+        }
+
+        let leaves_count = hashes.len();
+        let mut nodes = place_leaves(hashes);
+        // Nodes before the first leaf are exactly the inner nodes
+        let (row_start, _) = bottom_row(leaves_count);
+        let mut start = row_start / 2;
+        while start > 0 {
+            let (upper, lower) = nodes.split_at_mut(start * 2 - 1);
+            let end = (start * 2 - 1).min(leaves_count - 1);
+            let lower_start = start * 2 - 1;
+
+            upper[start - 1..end]
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(offset, node)| {
+                    let left = (start - 1 + offset) * 2 + 1 - lower_start;
+                    *node = H::merge(&lower[left], &lower[left + 1]);
+                });
+
+            start /= 2;
         }
+
+        Self::from_nodes(nodes)
+    }
+
+    /// Build the tree over receipts, each leaf is the digest of the receipt's
+    /// `FixedCodec` bytes.
+    pub fn from_receipts(receipts: &[Receipt]) -> ProtocolResult<Self> {
+        Ok(Self::from_hashes(digest_all(receipts)?))
+    }
+
+    /// Build the tree over signed transactions, each leaf is the digest of
+    /// the transaction's `FixedCodec` bytes. Note the block's `order_root` is
+    /// built over `tx_hash` instead.
+    pub fn from_signed_txs(txs: &[SignedTransaction]) -> ProtocolResult<Self> {
+        Ok(Self::from_hashes(digest_all(txs)?))
+    }
+
+    /// Root over `hashes` in order. An empty slice gives `Hash::from_empty()`,
+    /// the value block headers already use for empty blocks.
+    pub fn ordered_root(hashes: &[Hash]) -> Hash {
+        root_of::<H>(hashes).unwrap_or_else(Hash::from_empty)
+    }
+
+    fn from_nodes(nodes: Vec<Hash>) -> Self {
+        MerkleTree {
+            leaves_count: (nodes.len() + 1) / 2,
+            nodes,
+            leaf_indices: RwLock::new(None),
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn get_root_hash(&self) -> Option<Hash> {
+        self.nodes.first().cloned()
     }
 
     pub fn get_proof_by_input_index(&self, input_index: usize) -> Option<Vec<ProofNode>> {
-        self.tree
-            .get_proof_by_input_index(input_index)
-            .map(|proof| {
-                proof
-                    .0
-                    .into_iter()
-                    .map(|node| ProofNode {
-                        is_right: node.is_right,
-                        hash:     node.hash,
-                    })
-                    .collect()
-            })
-    }
-}
-
-fn merge(left: &Hash, right: &Hash) -> Hash {
-    let left = left.as_bytes();
-    let right = right.as_bytes();
-
-    let mut root = Vec::with_capacity(left.len() + right.len());
-    root.extend_from_slice(&left);
-    root.extend_from_slice(&right);
-    Hash::digest(Bytes::from(root))
+        if input_index >= self.leaves_count {
+            return None;
+        }
+
+        let mut index = node_index(self.leaves_count, input_index);
+        let mut proof = vec![];
+        while index > 0 {
+            let sibling = sibling(index);
+            proof.push(ProofNode {
+                is_right: is_left(index),
+                hash:     self.nodes[sibling].clone(),
+            });
+            index = parent(index);
+        }
+
+        Some(proof)
+    }
+
+    /// Build a single proof covering every leaf in `input_indices`. Returns
+    /// `None` if no index is given, or an index is out of range or appears
+    /// twice.
+    pub fn get_proof(&self, input_indices: &[u32]) -> Option<MerkleProof<H>> {
+        if input_indices.is_empty() {
+            return None;
+        }
+
+        let mut indices = Vec::with_capacity(input_indices.len());
+        for input_index in input_indices.iter() {
+            let input_index = *input_index as usize;
+            if input_index >= self.leaves_count {
+                return None;
+            }
+            indices.push(node_index(self.leaves_count, input_index) as u32);
+        }
+
+        let mut queue = indices.clone();
+        queue.sort_by(|a, b| b.cmp(a));
+        if queue.windows(2).any(|pair| pair[0] == pair[1]) {
+            return None;
+        }
+
+        let mut queue: VecDeque<u32> = queue.into();
+        let mut lemmas = vec![];
+        while let Some(index) = queue.pop_front() {
+            let index = index as usize;
+            if index == 0 {
+                break;
+            }
+
+            let sibling = sibling(index);
+            if queue.front() == Some(&(sibling as u32)) {
+                queue.pop_front();
+            } else {
+                lemmas.push(self.nodes[sibling].clone());
+            }
+
+            let parent = parent(index);
+            if parent != 0 {
+                queue.push_back(parent as u32);
+            }
+        }
+
+        Some(MerkleProof::new(indices, lemmas))
+    }
+
+    /// Same as `get_proof`, but takes the leaf hashes. Returns `None` if a
+    /// hash isn't a leaf of this tree or is passed twice. The proof verifies
+    /// against `leaves` in the given order.
+    pub fn get_proof_by_leaves(&self, leaves: &[Hash]) -> Option<MerkleProof<H>> {
+        let input_indices = leaves
+            .iter()
+            .map(|leaf| self.leaf_index_of(leaf))
+            .collect::<Option<Vec<_>>>()?;
+
+        self.get_proof(&input_indices)
+    }
+
+    /// Input index of `leaf`. If the same hash was passed in more than once,
+    /// the first index is returned.
+    pub fn leaf_index_of(&self, leaf: &Hash) -> Option<u32> {
+        if let Some(leaf_indices) = self.leaf_indices.read().as_ref() {
+            return leaf_indices.get(leaf).cloned();
+        }
+
+        let mut leaf_indices = self.leaf_indices.write();
+        leaf_indices
+            .get_or_insert_with(|| self.build_leaf_indices())
+            .get(leaf)
+            .cloned()
+    }
+
+    fn build_leaf_indices(&self) -> HashMap<Hash, u32> {
+        let mut leaf_indices = HashMap::with_capacity(self.leaves_count);
+        for input_index in 0..self.leaves_count {
+            let leaf = self.nodes[node_index(self.leaves_count, input_index)].clone();
+            leaf_indices.entry(leaf).or_insert(input_index as u32);
+        }
+        leaf_indices
+    }
+}
+
+/// Collects leaves one at a time, e.g. while transactions come out of the
+/// mempool, and gives the same root as `Merkle::from_hashes` over the same
+/// sequence.
+///
+/// Where a leaf lands in the tree depends on the final leaf count, so only
+/// the leaves are kept. `root` hashes them on demand and `into_tree` hands
+/// them over without copying.
+#[derive(Default, Clone, Debug)]
+pub struct IncrementalMerkle {
+    leaves: Vec<Hash>,
+}
+
+impl IncrementalMerkle {
+    pub fn new() -> Self {
+        IncrementalMerkle::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        IncrementalMerkle {
+            leaves: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, hash: Hash) {
+        self.leaves.push(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Same value as `Merkle::ordered_root` over the pushed leaves.
+    pub fn root(&self) -> Hash {
+        Merkle::ordered_root(&self.leaves)
+    }
+
+    pub fn into_tree(self) -> Merkle {
+        Merkle::from_hashes(self.leaves)
+    }
+}
+
+/// Proof for one or more leaves of a `MerkleTree` built with the same
+/// hasher.
+///
+/// `indices` are positions in the tree's node array, in the order the
+/// leaves are passed to `verify`. `lemmas` are the sibling hashes needed to
+/// rebuild the root, ordered from the bottom of the tree up.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<H: MergeHasher> {
+    pub indices: Vec<u32>,
+    pub lemmas:  Vec<Hash>,
+    #[serde(skip)]
+    hasher:      PhantomData<H>,
+}
+
+impl<H: MergeHasher> MerkleProof<H> {
+    pub fn new(indices: Vec<u32>, lemmas: Vec<Hash>) -> Self {
+        MerkleProof {
+            indices,
+            lemmas,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn verify(&self, root: &Hash, leaves: &[Hash]) -> bool {
+        match self.root(leaves) {
+            Some(hash) => &hash == root,
+            None => false,
+        }
+    }
+
+    fn root(&self, leaves: &[Hash]) -> Option<Hash> {
+        calculate_root(&self.indices, &self.lemmas, leaves, H::merge)
+    }
+
+    pub fn to_bytes(&self) -> Bytes {
+        Bytes::from(rlp::encode(self))
+    }
+
+    pub fn from_bytes(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
+}
+
+impl<H: MergeHasher> rlp::Encodable for MerkleProof<H> {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        s.begin_list(2)
+            .append_list(&self.indices)
+            .append_list(&self.lemmas);
+    }
+}
+
+impl<H: MergeHasher> rlp::Decodable for MerkleProof<H> {
+    fn decode(r: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        Ok(MerkleProof::new(r.list_at(0)?, r.list_at(1)?))
+    }
+}
+
+impl<H: MergeHasher> FixedCodec for MerkleProof<H> {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(self.to_bytes())
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        MerkleProof::from_bytes(bytes)
+    }
+}
+
+// Rebuild the root from the leaves at node `indices` and the sibling
+// `lemmas`, walking up from the deepest node. Works for any array tree with
+// node `i`'s children at `2i + 1` and `2i + 2`.
+pub(crate) fn calculate_root<T: Clone, F: Fn(&T, &T) -> T>(
+    indices: &[u32],
+    lemmas: &[T],
+    leaves: &[T],
+    merge: F,
+) -> Option<T> {
+    if leaves.is_empty() || leaves.len() != indices.len() {
+        return None;
+    }
+
+    let mut pairs: Vec<(u32, T)> = indices
+        .iter()
+        .cloned()
+        .zip(leaves.iter().cloned())
+        .collect();
+    pairs.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut queue: VecDeque<(u32, T)> = pairs.into();
+    let mut lemmas = lemmas.iter();
+    while let Some((index, node)) = queue.pop_front() {
+        let index = index as usize;
+        if index == 0 {
+            // Every lemma and leaf must be used on the way up
+            if queue.is_empty() && lemmas.next().is_none() {
+                return Some(node);
+            }
+            return None;
+        }
+
+        let sibling = sibling(index);
+        let sibling_node = match queue.front() {
+            Some((front, _)) if *front as usize == sibling => queue.pop_front()?.1,
+            _ => lemmas.next()?.clone(),
+        };
+
+        let parent_node = if is_left(index) {
+            merge(&node, &sibling_node)
+        } else {
+            merge(&sibling_node, &node)
+        };
+        queue.push_back((parent(index) as u32, parent_node));
+    }
+
+    None
+}
+
+// Node array with the leaves in place and the inner nodes still unset.
+fn place_leaves(hashes: Vec<Hash>) -> Vec<Hash> {
+    let leaves_count = hashes.len();
+    if leaves_count == 0 {
+        return vec![];
+    }
+
+    let mut nodes = vec![Hash::default(); leaves_count * 2 - 1];
+    for (input_index, hash) in hashes.into_iter().enumerate() {
+        nodes[node_index(leaves_count, input_index)] = hash;
+    }
+    nodes
+}
+
+// Position of the `input_index`th leaf in the node array. The leftmost leaves
+// fill the partial bottom row, the rest sit at the end of the row above it.
+fn node_index(leaves_count: usize, input_index: usize) -> usize {
+    let (row_start, bottom_count) = bottom_row(leaves_count);
+
+    if input_index < bottom_count {
+        row_start - 1 + input_index
+    } else {
+        leaves_count - 1 + (input_index - bottom_count)
+    }
+}
+
+// Width of the last full row and the number of leaves below it.
+fn bottom_row(leaves_count: usize) -> (usize, usize) {
+    let nodes_count = leaves_count * 2 - 1;
+    let mut row_start = 1;
+    while row_start * 2 <= nodes_count {
+        row_start *= 2;
+    }
+
+    (row_start, nodes_count - (row_start - 1))
+}
+
+// Root of the tree over `leaves` without building the node array. Merging
+// the bottom leaves pairwise leaves a full row, which then halves until only
+// the root remains.
+fn root_of<H: MergeHasher>(leaves: &[Hash]) -> Option<Hash> {
+    match leaves.len() {
+        0 => return None,
+        1 => return Some(leaves[0].clone()),
+        _ => (),
+    }
+
+    let (_, bottom_count) = bottom_row(leaves.len());
+    let (bottom, rest) = leaves.split_at(bottom_count);
+    let mut row: Vec<Hash> = bottom
+        .chunks(2)
+        .map(|pair| H::merge(&pair[0], &pair[1]))
+        .chain(rest.iter().cloned())
+        .collect();
+
+    while row.len() > 1 {
+        row = row
+            .chunks(2)
+            .map(|pair| H::merge(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    row.pop()
+}
+
+fn parent(index: usize) -> usize {
+    (index - 1) / 2
+}
+
+fn sibling(index: usize) -> usize {
+    if is_left(index) {
+        index + 1
+    } else {
+        index - 1
+    }
+}
+
+fn is_left(index: usize) -> bool {
+    index & 1 == 1
+}
+
+fn digest_all<T: FixedCodec>(items: &[T]) -> ProtocolResult<Vec<Hash>> {
+    items
+        .iter()
+        .map(|item| Ok(Hash::digest(item.encode_fixed()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate test;
+
+    use protocol::fixed_codec::FixedCodec;
+    use protocol::traits::ServiceResponse;
+    use protocol::types::{Hash, Receipt, ReceiptResponse};
+    use protocol::Bytes;
+
+    use rand::Rng;
+    use test::Bencher;
+
+    use super::{DefaultMerge, IncrementalMerkle, MergeHasher, Merkle, Proof, PARALLEL_THRESHOLD};
+
+    fn mock_hashes(count: usize) -> Vec<Hash> {
+        (0..count)
+            .map(|i| Hash::digest(Bytes::from(i.to_string())))
+            .collect()
+    }
+
+    fn mock_receipt(height: u64) -> Receipt {
+        Receipt {
+            state_root: Hash::from_empty(),
+            height,
+            tx_hash: Hash::digest(Bytes::from(height.to_string())),
+            cycles_used: 10,
+            events: vec![],
+            response: ReceiptResponse {
+                service_name: "asset".to_owned(),
+                method:       "transfer".to_owned(),
+                response:     ServiceResponse::<String>::from_succeed("".to_owned()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_root_matches_static_merkle_tree() {
+        for count in 1..40 {
+            let hashes = mock_hashes(count);
+            let expect = static_merkle_tree::Tree::from_hashes(hashes.clone(), DefaultMerge::merge)
+                .get_root_hash()
+                .cloned();
+
+            assert_eq!(Merkle::from_hashes(hashes).get_root_hash(), expect);
+        }
+    }
+
+    // Roots are part of block headers, these must never change.
+    #[test]
+    fn test_ordered_root_golden() {
+        let cases = vec![
+            (
+                0,
+                "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            ),
+            (
+                1,
+                "044852b2a670ade5407e78fb2863c51de9fcb96542a07186fe3aeda6bb8a116d",
+            ),
+            (
+                2,
+                "0b4aa17bff8fc189efb37609ac5ea9fca0df4c834a6fbac74b24c8119c40fef2",
+            ),
+            (
+                3,
+                "0f1d00f4d84258b8d99bfc4748ff45b8039f108f43ca47e22ac5a1eab2e8c02d",
+            ),
+            (
+                4,
+                "9e031569905bf7098e9e3b14d5c8e2ed05f6e8dc1acaaad6a221cd6603e01d3b",
+            ),
+            (
+                5,
+                "dc9570b9babc4b241bc3d50fa72933e5f6283386fcc641cb59f585b4eb1bffab",
+            ),
+            (
+                8,
+                "568ff5eb286f51b8a3e8de4e53aa8daed44594a246deebbde119ea2eb27acd6b",
+            ),
+            (
+                13,
+                "8fbe5f5ee46d2a872a2b51dde5ec024974e60a1a05d614c75cafdc713ec9fa12",
+            ),
+        ];
+
+        for (count, root) in cases.into_iter() {
+            let root = Hash::from_hex(root).unwrap();
+            assert_eq!(Merkle::ordered_root(&mock_hashes(count)), root);
+        }
+        assert_eq!(Merkle::ordered_root(&[]), Hash::from_empty());
+
+        let single = mock_hashes(1);
+        assert_eq!(Merkle::ordered_root(&single), single[0]);
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let merkle = Merkle::from_hashes(vec![]);
+
+        assert_eq!(merkle.get_root_hash(), None);
+        assert_eq!(Merkle::ordered_root(&[]), Hash::from_empty());
+        assert_eq!(IncrementalMerkle::new().root(), Hash::from_empty());
+        assert!(merkle.get_proof(&[]).is_none());
+        assert!(merkle.get_proof(&[0]).is_none());
+        assert!(merkle.get_proof_by_leaves(&[]).is_none());
+        assert!(merkle.get_proof_by_input_index(0).is_none());
+    }
+
+    #[test]
+    fn test_single_leaf_tree() {
+        let leaf = Hash::digest(Bytes::from("leaf"));
+        let merkle = Merkle::from_hashes(vec![leaf.clone()]);
+
+        assert_eq!(merkle.get_root_hash(), Some(leaf.clone()));
+        assert_eq!(Merkle::ordered_root(&[leaf.clone()]), leaf);
+        assert!(merkle.get_proof(&[]).is_none());
+        assert!(merkle.get_proof_by_input_index(0).unwrap().is_empty());
+
+        let proof = merkle.get_proof(&[0]).unwrap();
+        assert!(proof.lemmas.is_empty());
+        assert!(proof.verify(&leaf, &[leaf.clone()]));
+        assert!(!proof.verify(&leaf, &[Hash::from_empty()]));
+        assert!(!proof.verify(&leaf, &[]));
+    }
+
+    #[test]
+    fn test_degenerate_proof_verify() {
+        let hashes = mock_hashes(4);
+        let root = Merkle::ordered_root(&hashes);
+
+        let empty = Proof::new(vec![], vec![]);
+        assert!(!empty.verify(&root, &[]));
+        assert!(!empty.verify(&root, &hashes[..1]));
+
+        let root_with_lemma = Proof::new(vec![0], vec![hashes[0].clone()]);
+        assert!(!root_with_lemma.verify(&root, &[root.clone()]));
+
+        let missing_lemmas = Proof::new(vec![u32::max_value()], vec![]);
+        assert!(!missing_lemmas.verify(&root, &hashes[..1]));
+
+        let too_few_lemmas = Proof::new(vec![u32::max_value()], hashes.clone());
+        assert!(!too_few_lemmas.verify(&root, &hashes[..1]));
+    }
+
+    #[test]
+    fn test_incremental_matches_batch() {
+        let mut rng = rand::thread_rng();
+        let mut counts = vec![0, 1, 2, 3, 999];
+        counts.extend((0..20).map(|_| rng.gen_range(0, 1000)));
+
+        for count in counts.into_iter() {
+            let hashes = mock_hashes(count);
+            let expect = Merkle::from_hashes(hashes.clone()).get_root_hash();
+
+            let mut incremental = IncrementalMerkle::new();
+            for hash in hashes.into_iter() {
+                incremental.push(hash);
+            }
+            assert_eq!(incremental.len(), count);
+            assert_eq!(
+                incremental.root(),
+                expect.clone().unwrap_or_else(Hash::from_empty)
+            );
+            assert_eq!(incremental.into_tree().get_root_hash(), expect);
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_serial() {
+        let mut rng = rand::thread_rng();
+        let mut counts = vec![
+            PARALLEL_THRESHOLD - 1,
+            PARALLEL_THRESHOLD,
+            PARALLEL_THRESHOLD + 1,
+            4096,
+            5000,
+        ];
+        counts.extend((0..5).map(|_| rng.gen_range(1, 20_000)));
+
+        for count in counts.into_iter() {
+            let hashes = mock_hashes(count);
+            let serial = Merkle::from_hashes(hashes.clone());
+            let parallel = Merkle::from_hashes_parallel(hashes);
+
+            assert_eq!(parallel.nodes, serial.nodes);
+            assert_eq!(parallel.leaves_count, count);
+        }
+        assert_eq!(Merkle::from_hashes_parallel(vec![]).get_root_hash(), None);
+    }
+
+    #[test]
+    fn test_blake2b_merge() {
+        use super::{Blake2bMerge, MerkleTree};
+
+        // Computed with Python's hashlib:
+        //   h = lambda *xs: blake2b(b"".join(xs), digest_size=32,
+        //                           person=b"ckb-default-hash").digest()
+        //   h(h(leaf0, leaf1), leaf2)
+        let root =
+            Hash::from_hex("6c9be62771b0c5a1e40d59b3ba396916212d7a4c5cc79e1470410c01495acf00")
+                .unwrap();
+        let hashes = [[0x11u8; 32], [0x22; 32], [0x33; 32]]
+            .iter()
+            .map(|leaf| Hash::from_bytes(Bytes::from(leaf.to_vec())).unwrap())
+            .collect::<Vec<_>>();
+
+        let tree = MerkleTree::<Blake2bMerge>::from_hashes(hashes.clone());
+        assert_eq!(tree.get_root_hash(), Some(root.clone()));
+        assert_eq!(MerkleTree::<Blake2bMerge>::ordered_root(&hashes), root);
+        assert_ne!(Merkle::ordered_root(&hashes), root);
+
+        let proof = tree.get_proof(&[2, 0]).unwrap();
+        assert!(proof.verify(&root, &[hashes[2].clone(), hashes[0].clone()]));
+    }
+
+    #[test]
+    fn test_from_receipts() {
+        let receipts = (0..3).map(mock_receipt).collect::<Vec<_>>();
+        let hashes = receipts
+            .iter()
+            .map(|r| Hash::digest(r.encode_fixed().unwrap()))
+            .collect::<Vec<_>>();
+
+        let merkle = Merkle::from_receipts(&receipts).unwrap();
+        assert_eq!(merkle.get_root_hash(), Some(Merkle::ordered_root(&hashes)));
+        assert_eq!(Merkle::from_receipts(&[]).unwrap().get_root_hash(), None);
+    }
+
+    #[test]
+    fn test_proof_by_input_index() {
+        let hashes = mock_hashes(11);
+        let merkle = Merkle::from_hashes(hashes.clone());
+        let root = merkle.get_root_hash().unwrap();
+
+        for (i, leaf) in hashes.into_iter().enumerate() {
+            let proof = merkle.get_proof_by_input_index(i).unwrap();
+            let hash = proof.into_iter().fold(leaf, |hash, node| {
+                if node.is_right {
+                    DefaultMerge::merge(&hash, &node.hash)
+                } else {
+                    DefaultMerge::merge(&node.hash, &hash)
+                }
+            });
+            assert_eq!(hash, root);
+        }
+        assert!(merkle.get_proof_by_input_index(11).is_none());
+    }
+
+    #[test]
+    fn test_proof_verify() {
+        let hashes = mock_hashes(7);
+        let merkle = Merkle::from_hashes(hashes.clone());
+        let root = merkle.get_root_hash().unwrap();
+
+        let proof = merkle.get_proof(&[5, 1, 2]).unwrap();
+        assert!(proof.verify(&root, &[
+            hashes[5].clone(),
+            hashes[1].clone(),
+            hashes[2].clone()
+        ]));
+        assert!(!proof.verify(&root, &[
+            hashes[1].clone(),
+            hashes[5].clone(),
+            hashes[2].clone()
+        ]));
+        assert!(!proof.verify(&root, &[hashes[5].clone(), hashes[1].clone()]));
+
+        assert!(merkle.get_proof(&[7]).is_none());
+        assert!(merkle.get_proof(&[3, 3]).is_none());
+    }
+
+    #[test]
+    fn test_proof_by_leaves() {
+        let hashes = mock_hashes(13);
+        let merkle = Merkle::from_hashes(hashes.clone());
+        let root = merkle.get_root_hash().unwrap();
+
+        for (i, leaf) in hashes.iter().enumerate() {
+            assert_eq!(merkle.leaf_index_of(leaf), Some(i as u32));
+        }
+
+        let leaves = vec![
+            hashes[9].clone(),
+            hashes[0].clone(),
+            hashes[12].clone(),
+            hashes[4].clone(),
+        ];
+        let proof = merkle.get_proof_by_leaves(&leaves).unwrap();
+        assert_eq!(proof, merkle.get_proof(&[9, 0, 12, 4]).unwrap());
+        assert!(proof.verify(&root, &leaves));
+
+        let unknown = Hash::digest(Bytes::from("unknown"));
+        assert_eq!(merkle.leaf_index_of(&unknown), None);
+        assert!(merkle
+            .get_proof_by_leaves(&[hashes[1].clone(), unknown])
+            .is_none());
+        assert!(merkle
+            .get_proof_by_leaves(&[hashes[1].clone(), hashes[1].clone()])
+            .is_none());
+    }
+
+    #[test]
+    fn test_proof_json_round_trip() {
+        let hashes = mock_hashes(9);
+        let merkle = Merkle::from_hashes(hashes.clone());
+        let proof = merkle.get_proof(&[0, 8]).unwrap();
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let decoded: Proof = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, proof);
+        assert!(decoded.verify(&merkle.get_root_hash().unwrap(), &[
+            hashes[0].clone(),
+            hashes[8].clone()
+        ]));
+    }
+
+    #[test]
+    fn test_proof_fixed_codec_round_trip() {
+        let hashes = mock_hashes(9);
+        let merkle = Merkle::from_hashes(hashes.clone());
+        let proof = merkle.get_proof(&[3]).unwrap();
+
+        let bytes = proof.encode_fixed().unwrap();
+        assert_eq!(bytes, proof.to_bytes());
+
+        let decoded = Proof::decode_fixed(bytes).unwrap();
+        assert_eq!(decoded, proof);
+        assert_eq!(Proof::from_bytes(proof.to_bytes()).unwrap(), proof);
+        assert!(decoded.verify(&merkle.get_root_hash().unwrap(), &[hashes[3].clone()]));
+    }
+
+    const BENCH_LEAVES: usize = 50_000;
+
+    #[bench]
+    fn bench_from_hashes(b: &mut Bencher) {
+        let hashes = mock_hashes(BENCH_LEAVES);
+
+        b.iter(|| Merkle::from_hashes(hashes.clone()));
+    }
+
+    #[bench]
+    fn bench_from_hashes_parallel(b: &mut Bencher) {
+        let hashes = mock_hashes(BENCH_LEAVES);
+
+        b.iter(|| Merkle::from_hashes_parallel(hashes.clone()));
+    }
 }
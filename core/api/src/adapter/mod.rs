@@ -6,7 +6,8 @@ use derive_more::Display;
 use async_trait::async_trait;
 use protocol::traits::ExecutorFactory;
 use protocol::traits::{
-    APIAdapter, Context, ExecutorParams, MemPool, ServiceMapping, ServiceResponse, Storage,
+    APIAdapter, Context, ExecutorParams, MemPool, PendingTx, PoolStats, RejectionRecord,
+    ServiceMapping, ServiceResponse, Storage, TransactionWithPosition,
 };
 use protocol::types::{Address, Block, Hash, Receipt, SignedTransaction, TransactionRequest};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -114,6 +115,42 @@ impl<
         self.storage.get_transaction_by_hash(tx_hash).await
     }
 
+    async fn get_transaction_with_position(
+        &self,
+        _: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<TransactionWithPosition> {
+        self.storage.get_transaction_with_position(tx_hash).await
+    }
+
+    async fn get_pending_transaction(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        self.mempool.get(ctx, &tx_hash).await
+    }
+
+    async fn get_pool_stats(&self, _: Context) -> ProtocolResult<PoolStats> {
+        Ok(self.mempool.stats())
+    }
+
+    async fn get_pool_content(&self, _: Context, limit: usize) -> ProtocolResult<Vec<PendingTx>> {
+        Ok(self.mempool.content(limit))
+    }
+
+    async fn get_rejection(
+        &self,
+        _: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<RejectionRecord>> {
+        Ok(self.mempool.rejection_reason(&tx_hash))
+    }
+
+    async fn get_rejections(&self, _: Context) -> ProtocolResult<Vec<RejectionRecord>> {
+        Ok(self.mempool.rejections())
+    }
+
     async fn query_service(
         &self,
         ctx: Context,
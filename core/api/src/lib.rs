@@ -20,9 +20,13 @@ use protocol::traits::{APIAdapter, Context};
 use crate::config::GraphQLConfig;
 use crate::schema::{
     to_signed_transaction, to_transaction, Address, Block, Bytes, Hash, InputRawTransaction,
-    InputTransactionEncryption, Receipt, ServiceResponse, SignedTransaction, Uint64,
+    InputTransactionEncryption, PendingTx, PoolStats, Receipt, Rejection, ServiceResponse,
+    SignedTransaction, Uint64,
 };
 
+// Most transactions `getPoolContent` lists at once
+const MAX_POOL_CONTENT: u64 = 1000;
+
 lazy_static! {
     static ref GRAPHIQL_HTML: &'static str = include_str!("../source/graphiql.html");
 }
@@ -61,12 +65,27 @@ impl Query {
     async fn get_transaction(state_ctx: &State, tx_hash: Hash) -> FieldResult<SignedTransaction> {
         let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
 
-        let stx = state_ctx
+        let err = match state_ctx
             .adapter
-            .get_transaction_by_hash(Context::new(), hash)
-            .await?;
+            .get_transaction_with_position(Context::new(), hash.clone())
+            .await
+        {
+            Ok(with_position) => return Ok(SignedTransaction::from(with_position)),
+            Err(err) => err,
+        };
 
-        Ok(SignedTransaction::from(stx))
+        // Not committed yet, it may still wait in the mempool
+        match state_ctx
+            .adapter
+            .get_pending_transaction(Context::new(), hash)
+            .await?
+        {
+            Some(stx) => Ok(SignedTransaction {
+                pending: true,
+                ..SignedTransaction::from(stx)
+            }),
+            None => Err(err.into()),
+        }
     }
 
     #[graphql(
@@ -84,6 +103,59 @@ impl Query {
         Ok(Receipt::from(receipt))
     }
 
+    #[graphql(name = "getPoolStats", description = "Get what the mempool holds")]
+    async fn get_pool_stats(state_ctx: &State) -> FieldResult<PoolStats> {
+        let stats = state_ctx.adapter.get_pool_stats(Context::new()).await?;
+
+        Ok(PoolStats::from(stats))
+    }
+
+    #[graphql(
+        name = "getPoolContent",
+        description = "Get the pending transactions in package order, 100 unless limited"
+    )]
+    async fn get_pool_content(
+        state_ctx: &State,
+        limit: Option<Uint64>,
+    ) -> FieldResult<Vec<PendingTx>> {
+        let limit = match limit {
+            Some(limit) => cmp::min(limit.try_into_u64()?, MAX_POOL_CONTENT),
+            None => 100,
+        };
+
+        let content = state_ctx
+            .adapter
+            .get_pool_content(Context::new(), limit as usize)
+            .await?;
+
+        Ok(content.into_iter().map(PendingTx::from).collect())
+    }
+
+    #[graphql(
+        name = "getRejection",
+        description = "Get why the mempool recently turned a transaction away"
+    )]
+    async fn get_rejection(state_ctx: &State, tx_hash: Hash) -> FieldResult<Option<Rejection>> {
+        let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
+
+        let rejection = state_ctx
+            .adapter
+            .get_rejection(Context::new(), hash)
+            .await?;
+
+        Ok(rejection.map(Rejection::from))
+    }
+
+    #[graphql(
+        name = "getRejections",
+        description = "Get the transactions the mempool recently turned away, oldest first"
+    )]
+    async fn get_rejections(state_ctx: &State) -> FieldResult<Vec<Rejection>> {
+        let rejections = state_ctx.adapter.get_rejections(Context::new()).await?;
+
+        Ok(rejections.into_iter().map(Rejection::from).collect())
+    }
+
     #[graphql(name = "queryService", description = "query service")]
     async fn query_service(
         state_ctx: &State,
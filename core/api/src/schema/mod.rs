@@ -1,4 +1,5 @@
 mod block;
+mod pool;
 mod receipt;
 mod transaction;
 
@@ -10,6 +11,7 @@ use std::num::ParseIntError;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 pub use block::{Block, BlockHeader};
+pub use pool::{PendingTx, PoolStats, Rejection};
 pub use receipt::{Event, Receipt, ReceiptResponse};
 pub use transaction::{
     to_signed_transaction, to_transaction, InputRawTransaction, InputTransactionEncryption,
@@ -0,0 +1,70 @@
+use crate::schema::{Address, Hash, Uint64};
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct PoolStats {
+    #[graphql(description = "Transactions waiting to be packaged")]
+    pub pending:          Uint64,
+    #[graphql(description = "Transactions pulled in by propose sync")]
+    pub parked:           Uint64,
+    #[graphql(description = "Pending transactions gossiped by peers")]
+    pub from_peers:       Uint64,
+    pub capacity:         Uint64,
+    pub min_price:        Option<Uint64>,
+    pub max_price:        Option<Uint64>,
+    #[graphql(description = "Arrival of the longest waiting transaction, unix milliseconds")]
+    pub oldest_timestamp: Option<Uint64>,
+    #[graphql(description = "Approximate size of the held transactions")]
+    pub bytes:            Uint64,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct PendingTx {
+    pub tx_hash:      Hash,
+    pub sender:       Address,
+    pub nonce:        Hash,
+    pub cycles_price: Uint64,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct Rejection {
+    pub tx_hash:   Hash,
+    pub reason:    String,
+    #[graphql(description = "When the tx was turned away, unix milliseconds")]
+    pub timestamp: Uint64,
+}
+
+impl From<protocol::traits::PoolStats> for PoolStats {
+    fn from(stats: protocol::traits::PoolStats) -> Self {
+        Self {
+            pending:          Uint64::from(stats.pending as u64),
+            parked:           Uint64::from(stats.parked as u64),
+            from_peers:       Uint64::from(stats.from_peers as u64),
+            capacity:         Uint64::from(stats.capacity as u64),
+            min_price:        stats.min_price.map(Uint64::from),
+            max_price:        stats.max_price.map(Uint64::from),
+            oldest_timestamp: stats.oldest_timestamp.map(Uint64::from),
+            bytes:            Uint64::from(stats.bytes as u64),
+        }
+    }
+}
+
+impl From<protocol::traits::PendingTx> for PendingTx {
+    fn from(pending: protocol::traits::PendingTx) -> Self {
+        Self {
+            tx_hash:      Hash::from(pending.tx_hash),
+            sender:       Address::from(pending.sender),
+            nonce:        Hash::from(pending.nonce),
+            cycles_price: Uint64::from(pending.cycles_price),
+        }
+    }
+}
+
+impl From<protocol::traits::RejectionRecord> for Rejection {
+    fn from(record: protocol::traits::RejectionRecord) -> Self {
+        Self {
+            tx_hash:   Hash::from(record.tx_hash),
+            reason:    record.reason,
+            timestamp: Uint64::from(record.timestamp),
+        }
+    }
+}
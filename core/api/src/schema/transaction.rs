@@ -15,6 +15,37 @@ pub struct SignedTransaction {
     pub tx_hash:      Hash,
     pub pubkey:       Bytes,
     pub signature:    Bytes,
+    #[graphql(description = "Where the transaction was committed, null while it is pending")]
+    pub position:     Option<TransactionPosition>,
+    #[graphql(description = "Whether the transaction is still waiting in the mempool")]
+    pub pending:      bool,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct TransactionPosition {
+    pub block_height:  Uint64,
+    pub block_hash:    Hash,
+    #[graphql(description = "Index of the transaction in the block")]
+    pub index:         Uint64,
+    #[graphql(description = "Blocks from the committing one up to the latest, both included")]
+    pub confirmations: Uint64,
+}
+
+impl From<protocol::traits::TransactionWithPosition> for SignedTransaction {
+    fn from(with_position: protocol::traits::TransactionWithPosition) -> Self {
+        let latest_height = with_position.latest_height;
+        let position = with_position.position.map(|position| TransactionPosition {
+            block_height:  Uint64::from(position.height),
+            block_hash:    Hash::from(position.block_hash),
+            index:         Uint64::from(u64::from(position.index)),
+            confirmations: Uint64::from(latest_height.saturating_sub(position.height) + 1),
+        });
+
+        Self {
+            position,
+            ..SignedTransaction::from(with_position.transaction)
+        }
+    }
 }
 
 impl From<protocol::types::SignedTransaction> for SignedTransaction {
@@ -31,6 +62,8 @@ impl From<protocol::types::SignedTransaction> for SignedTransaction {
             tx_hash:      Hash::from(stx.tx_hash),
             pubkey:       Bytes::from(stx.pubkey),
             signature:    Bytes::from(stx.signature),
+            position:     None,
+            pending:      false,
         }
     }
 }
@@ -13,20 +13,25 @@ use tokio::sync::mpsc::{channel, Receiver, Sender};
 use common_merkle::Merkle;
 use protocol::traits::{
     CommonConsensusAdapter, ConsensusAdapter, Context, ExecutorFactory, ExecutorParams,
-    ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, Priority, Rpc, ServiceMapping,
-    Storage, SynchronizationAdapter,
+    ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, PeerMisbehavior, PeerTrust,
+    Priority, Rpc, ServiceMapping, Storage, SynchronizationAdapter,
 };
 use protocol::types::{
     Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignedTransaction,
     TransactionRequest, Validator,
 };
-use protocol::{fixed_codec::FixedCodec, ProtocolResult};
+use protocol::ProtocolResult;
 
 use crate::consensus::gen_overlord_status;
-use crate::fixed_types::{FixedBlock, FixedHeight, FixedPill, FixedSignedTxs, PullTxsRequest};
-use crate::message::{BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_TXS};
+use crate::fixed_types::{
+    FixedBlock, FixedHeight, FixedPill, FixedProof, FixedSignedTxs, PullTxsRequest,
+};
+use crate::message::{
+    BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
+};
 use crate::status::{ExecutedInfo, StatusAgent};
 use crate::util::ExecuteInfo;
+use crate::validators::LastValidators;
 use crate::ConsensusError;
 
 const OVERLORD_GAP: usize = 10;
@@ -47,6 +52,7 @@ pub struct OverlordConsensusAdapter<
     trie_db:          Arc<DB>,
     service_mapping:  Arc<Mapping>,
     overlord_handler: RwLock<Option<OverlordHandler<FixedPill>>>,
+    last_validators:  LastValidators,
 
     exec_queue:  Sender<ExecuteInfo>,
     exec_demons: Option<ExecDemons<S, DB, EF, Mapping>>,
@@ -58,7 +64,7 @@ impl<EF, G, M, R, S, DB, Mapping> ConsensusAdapter
 where
     EF: ExecutorFactory<DB, S, Mapping>,
     G: Gossip + Sync + Send,
-    R: Rpc + Sync + Send,
+    R: Rpc + PeerTrust + Sync + Send,
     M: MemPool + 'static,
     S: Storage + 'static,
     DB: cita_trie::DB + 'static,
@@ -90,6 +96,12 @@ where
         self.mempool.get_full_txs(ctx, txs).await
     }
 
+    fn contains_txs(&self, _: Context, tx_hashes: &[Hash]) -> bool {
+        tx_hashes
+            .iter()
+            .all(|tx_hash| self.mempool.contains(tx_hash))
+    }
+
     async fn transmit(
         &self,
         ctx: Context,
@@ -146,11 +158,10 @@ where
 
     async fn get_last_validators(
         &self,
-        _ctx: Context,
+        ctx: Context,
         height: u64,
     ) -> ProtocolResult<Vec<Validator>> {
-        let block = self.storage.get_block_by_height(height).await?;
-        Ok(block.header.validators)
+        self.last_validators.get(self, ctx, height).await
     }
 
     async fn save_overlord_wal(&self, _ctx: Context, info: Bytes) -> ProtocolResult<()> {
@@ -161,6 +172,22 @@ where
         self.storage.load_overlord_wal().await
     }
 
+    async fn save_evidence(&self, _ctx: Context, evidence: Bytes) -> ProtocolResult<()> {
+        self.storage.update_evidence(evidence).await
+    }
+
+    async fn load_evidence(&self, _ctx: Context) -> ProtocolResult<Bytes> {
+        self.storage.load_evidence().await
+    }
+
+    async fn save_liveness(&self, _ctx: Context, missed: Bytes) -> ProtocolResult<()> {
+        self.storage.update_liveness(missed).await
+    }
+
+    async fn load_liveness(&self, _ctx: Context) -> ProtocolResult<Bytes> {
+        self.storage.load_liveness().await
+    }
+
     async fn pull_block(&self, ctx: Context, height: u64, end: &str) -> ProtocolResult<Block> {
         log::debug!("consensus: send rpc pull block {}", height);
         let res = self
@@ -202,7 +229,7 @@ impl<EF, G, M, R, S, DB, Mapping> SynchronizationAdapter
 where
     EF: ExecutorFactory<DB, S, Mapping>,
     G: Gossip + Sync + Send,
-    R: Rpc + Sync + Send,
+    R: Rpc + PeerTrust + Sync + Send,
     M: MemPool + 'static,
     S: Storage + 'static,
     DB: cita_trie::DB + 'static,
@@ -288,6 +315,20 @@ where
             .await?;
         Ok(res.inner)
     }
+
+    /// Pull the proof of the block at the given height from other nodes.
+    async fn get_proof_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Proof> {
+        let res = self
+            .rpc
+            .call::<FixedHeight, FixedProof>(
+                ctx,
+                RPC_SYNC_PULL_PROOF,
+                FixedHeight::new(height),
+                Priority::High,
+            )
+            .await?;
+        Ok(res.inner)
+    }
 }
 
 #[async_trait]
@@ -296,7 +337,7 @@ impl<EF, G, M, R, S, DB, Mapping> CommonConsensusAdapter
 where
     EF: ExecutorFactory<DB, S, Mapping>,
     G: Gossip + Sync + Send,
-    R: Rpc + Sync + Send,
+    R: Rpc + PeerTrust + Sync + Send,
     M: MemPool + 'static,
     S: Storage + 'static,
     DB: cita_trie::DB + 'static,
@@ -324,11 +365,47 @@ where
         self.storage.insert_receipts(receipts).await
     }
 
+    async fn save_signed_txs_with_receipts(
+        &self,
+        _: Context,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .insert_transactions_with_receipts(signed_txs, receipts)
+            .await
+    }
+
+    async fn save_block_data(
+        &self,
+        _: Context,
+        block: Block,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .insert_block_data(block, signed_txs, receipts)
+            .await
+    }
+
+    async fn save_blocks_data(
+        &self,
+        _: Context,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        self.storage.insert_blocks_data(blocks).await
+    }
+
     /// Flush the given transactions in the mempool.
     async fn flush_mempool(&self, ctx: Context, ordered_tx_hashes: &[Hash]) -> ProtocolResult<()> {
         self.mempool.flush(ctx, ordered_tx_hashes.to_vec()).await
     }
 
+    /// Drop the transactions in the mempool timed out at the given height.
+    async fn expire_mempool(&self, ctx: Context, height: u64) -> ProtocolResult<usize> {
+        self.mempool.expire(ctx, height).await
+    }
+
     /// Get a block corresponding to the given height.
     async fn get_block_by_height(&self, _: Context, height: u64) -> ProtocolResult<Block> {
         self.storage.get_block_by_height(height).await
@@ -390,13 +467,17 @@ where
         self.mempool
             .set_args(timeout_gap, cycles_limit, max_tx_size);
     }
+
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+        self.rpc.report(ctx, misbehavior);
+    }
 }
 
 impl<EF, G, M, R, S, DB, Mapping> OverlordConsensusAdapter<EF, G, M, R, S, DB, Mapping>
 where
     EF: ExecutorFactory<DB, S, Mapping>,
     G: Gossip + Sync + Send,
-    R: Rpc + Sync + Send,
+    R: Rpc + PeerTrust + Sync + Send,
     M: MemPool + 'static,
     S: Storage + 'static,
     DB: cita_trie::DB + 'static,
@@ -428,6 +509,7 @@ where
             trie_db,
             service_mapping,
             overlord_handler: RwLock::new(None),
+            last_validators: LastValidators::new(),
             exec_queue,
             exec_demons,
         };
@@ -538,15 +620,10 @@ where
 fn gen_executed_info(exec_resp: ExecutorResp, height: u64, order_root: MerkleRoot) -> ExecutedInfo {
     let cycles = exec_resp.all_cycles_used;
 
-    let receipt = Merkle::from_hashes(
-        exec_resp
-            .receipts
-            .iter()
-            .map(|r| Hash::digest(r.to_owned().encode_fixed().unwrap()))
-            .collect::<Vec<_>>(),
-    )
-    .get_root_hash()
-    .unwrap_or_else(Hash::from_empty);
+    let receipt = Merkle::from_receipts(&exec_resp.receipts)
+        .unwrap()
+        .get_root_hash()
+        .unwrap_or_else(Hash::from_empty);
 
     ExecutedInfo {
         exec_height:  height,
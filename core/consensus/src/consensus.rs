@@ -1,27 +1,38 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use creep::Context;
 use futures::lock::Mutex;
 use overlord::types::{
-    AggregatedVote, Node, OverlordMsg, SignedChoke, SignedProposal, SignedVote, Status,
+    AggregatedVote, Node, OverlordMsg, SignedChoke, SignedProposal, SignedVote, Status, VoteType,
 };
-use overlord::{DurationConfig, Overlord, OverlordHandler};
+use overlord::{Crypto, DurationConfig, Overlord, OverlordHandler};
 
 use common_crypto::{BlsCommonReference, BlsPrivateKey, BlsPublicKey};
 
-use protocol::traits::{Consensus, ConsensusAdapter, NodeInfo};
-use protocol::types::Validator;
+use protocol::traits::{Consensus, ConsensusAdapter, NodeInfo, PeerMisbehavior};
+use protocol::types::{Hash, Validator};
 use protocol::{Bytes, ProtocolResult};
 
 use crate::engine::ConsensusEngine;
+use crate::evidence::{Evidence, EvidencePool, SignedKind, SignedMessage};
 use crate::fixed_types::FixedPill;
+use crate::liveness::LivenessTracker;
+use crate::metrics::ConsensusMetrics;
 use crate::status::StatusAgent;
+use crate::stop::Stopper;
+use crate::timestamp::TimestampRules;
 use crate::util::OverlordCrypto;
 use crate::wal::SignedTxsWAL;
 use crate::{ConsensusError, ConsensusType};
 
+// Signed proposals and votes kept to catch double signing, in all and at
+// any one height
+const EVIDENCE_WINDOW: usize = 4096;
+const EVIDENCE_HEIGHT_WINDOW: usize = 1024;
+
 /// Provide consensus
 pub struct OverlordConsensus<Adapter: ConsensusAdapter + 'static> {
     /// Overlord consensus protocol instance.
@@ -30,13 +41,37 @@ pub struct OverlordConsensus<Adapter: ConsensusAdapter + 'static> {
     >,
     /// An overlord consensus protocol handler.
     handler: OverlordHandler<FixedPill>,
+
+    status_agent: StatusAgent,
+    adapter:      Arc<Adapter>,
+    crypto:       Arc<OverlordCrypto>,
+    evidence:     EvidencePool,
+    txs_wal:      Arc<SignedTxsWAL>,
+    stopper:      Stopper,
 }
 
 #[async_trait]
 impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapter> {
     async fn set_proposal(&self, ctx: Context, proposal: Vec<u8>) -> ProtocolResult<()> {
-        let signed_proposal: SignedProposal<FixedPill> = rlp::decode(&proposal)
-            .map_err(|_| ConsensusError::DecodeErr(ConsensusType::SignedProposal))?;
+        let signed_proposal: SignedProposal<FixedPill> = match rlp::decode(&proposal) {
+            Ok(signed_proposal) => signed_proposal,
+            Err(_) => {
+                self.adapter
+                    .report_bad(ctx, PeerMisbehavior::InvalidProposal);
+                return Err(ConsensusError::DecodeErr(ConsensusType::SignedProposal).into());
+            }
+        };
+        let proposal = &signed_proposal.proposal;
+        self.observe_signed(ctx.clone(), SignedMessage {
+            kind:       SignedKind::Proposal,
+            height:     proposal.height,
+            round:      proposal.round,
+            signer:     proposal.proposer.clone(),
+            block_hash: proposal.block_hash.clone(),
+            hash:       Hash::digest(Bytes::from(rlp::encode(proposal))).as_bytes(),
+            signature:  signed_proposal.signature.clone(),
+        })
+        .await;
         self.handler
             .send_msg(ctx, OverlordMsg::SignedProposal(signed_proposal))
             .map_err(|e| ConsensusError::OverlordErr(Box::new(e)))?;
@@ -46,6 +81,20 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
     async fn set_vote(&self, ctx: Context, vote: Vec<u8>) -> ProtocolResult<()> {
         let signed_vote: SignedVote =
             rlp::decode(&vote).map_err(|_| ConsensusError::DecodeErr(ConsensusType::SignedVote))?;
+        let kind = match signed_vote.vote.vote_type {
+            VoteType::Prevote => SignedKind::Prevote,
+            VoteType::Precommit => SignedKind::Precommit,
+        };
+        self.observe_signed(ctx.clone(), SignedMessage {
+            kind,
+            height: signed_vote.vote.height,
+            round: signed_vote.vote.round,
+            signer: signed_vote.voter.clone(),
+            block_hash: signed_vote.vote.block_hash.clone(),
+            hash: Hash::digest(Bytes::from(rlp::encode(&signed_vote.vote))).as_bytes(),
+            signature: signed_vote.signature.clone(),
+        })
+        .await;
         self.handler
             .send_msg(ctx, OverlordMsg::SignedVote(signed_vote))
             .map_err(|e| ConsensusError::OverlordErr(Box::new(e)))?;
@@ -81,22 +130,30 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         txs_wal: Arc<SignedTxsWAL>,
         adapter: Arc<Adapter>,
         lock: Arc<Mutex<()>>,
+        stopper: Stopper,
+        timestamp_rules: TimestampRules,
+        metrics: Arc<dyn ConsensusMetrics>,
+        liveness: Option<LivenessTracker>,
     ) -> Self {
         let crypto = Arc::new(OverlordCrypto::new(priv_key, addr_pubkey_map, common_ref));
 
         let engine = Arc::new(ConsensusEngine::new(
             status_agent.clone(),
             node_info.clone(),
-            txs_wal,
+            Arc::clone(&txs_wal),
             Arc::clone(&adapter),
             Arc::clone(&crypto),
             lock,
+            stopper.clone(),
+            timestamp_rules,
+            metrics,
+            liveness,
         ));
 
         let overlord = Overlord::new(
             node_info.self_address.as_bytes(),
             Arc::clone(&engine),
-            crypto,
+            Arc::clone(&crypto),
             Arc::clone(&engine),
         );
         let overlord_handler = overlord.get_handler();
@@ -120,8 +177,90 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         }
 
         Self {
-            inner:   Arc::new(overlord),
+            inner: Arc::new(overlord),
             handler: overlord_handler,
+            status_agent,
+            adapter,
+            crypto,
+            evidence: EvidencePool::new(EVIDENCE_WINDOW, EVIDENCE_HEIGHT_WINDOW),
+            txs_wal,
+            stopper,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stopper.is_running()
+    }
+
+    /// Stops committing after the height in progress, waiting for it at most
+    /// `timeout`, and flushes the WAL.
+    pub async fn stop(&self, timeout: Duration) {
+        if !self.stopper.stop(timeout).await {
+            log::warn!(
+                "[consensus]: height {} still in progress after {:?}",
+                self.status_agent.to_inner().current_height + 1,
+                timeout
+            );
+        }
+
+        if let Err(e) = self.txs_wal.flush() {
+            log::error!("[consensus]: flush wal {:?}", e);
+        }
+    }
+
+    pub fn resume(&self) {
+        self.stopper.resume()
+    }
+
+    /// Double signing found among the proposals and votes received.
+    pub fn pending_evidence(&self) -> Vec<Evidence> {
+        self.evidence.pending_evidence()
+    }
+
+    /// Puts back the evidence saved before the node restarted.
+    pub async fn load_evidence(&self) -> ProtocolResult<()> {
+        let evidence = self.adapter.load_evidence(Context::new()).await?;
+        self.evidence.restore(&evidence)
+    }
+
+    async fn observe_signed(&self, ctx: Context, message: SignedMessage) {
+        let status = self.status_agent.to_inner();
+        self.evidence.prune(status.current_height);
+        let authority = status
+            .validators
+            .iter()
+            .map(|v| v.address.as_bytes())
+            .collect::<Vec<_>>();
+
+        let crypto = &self.crypto;
+        let verify = |message: &SignedMessage| {
+            crypto
+                .verify_signature(
+                    message.signature.clone(),
+                    message.hash.clone(),
+                    message.signer.clone(),
+                )
+                .is_ok()
+        };
+        let found = self.evidence.observe(message, &authority, verify);
+        if let Some(evidence) = found {
+            log::warn!(
+                "[consensus]: {} signed {:?} at height {} round {} for both {:?} and {:?}",
+                hex::encode(&evidence.first.signer),
+                evidence.first.kind,
+                evidence.first.height,
+                evidence.first.round,
+                evidence.first.block_hash,
+                evidence.second.block_hash
+            );
+
+            let saved = match self.evidence.encode() {
+                Ok(encoded) => self.adapter.save_evidence(ctx, encoded).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = saved {
+                log::error!("[consensus]: save evidence {:?}", e);
+            }
         }
     }
 
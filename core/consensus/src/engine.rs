@@ -19,7 +19,7 @@ use common_crypto::BlsPublicKey;
 use common_merkle::Merkle;
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{ConsensusAdapter, Context, MessageTarget, NodeInfo};
+use protocol::traits::{ConsensusAdapter, Context, MessageTarget, NodeInfo, PeerMisbehavior};
 use protocol::types::{
     Address, Block, BlockHeader, Hash, MerkleRoot, Metadata, Pill, Proof, SignedTransaction,
     Validator,
@@ -27,12 +27,19 @@ use protocol::types::{
 use protocol::{Bytes, ProtocolError, ProtocolResult};
 
 use crate::fixed_types::FixedPill;
+use crate::liveness::LivenessTracker;
 use crate::message::{
     END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
     END_GOSSIP_SIGNED_VOTE,
 };
+use crate::metrics::{CheckRejection, ConsensusMetrics};
 use crate::status::StatusAgent;
-use crate::util::{check_list_roots, OverlordCrypto};
+use crate::stop::Stopper;
+use crate::timestamp::TimestampRules;
+use crate::util::{
+    check_block_limits, check_list_roots, check_proposal_origin, check_tx_num, fit_block_limits,
+    OverlordCrypto,
+};
 use crate::wal::SignedTxsWAL;
 use crate::ConsensusError;
 
@@ -49,6 +56,17 @@ pub struct ConsensusEngine<Adapter> {
     txs_wal: Arc<SignedTxsWAL>,
     crypto:  Arc<OverlordCrypto>,
     lock:    Arc<Mutex<()>>,
+    stopper: Stopper,
+
+    timestamp_rules: TimestampRules,
+
+    metrics:      Arc<dyn ConsensusMetrics>,
+    // Height the node last built or checked a block at, and since when
+    height_start: RwLock<Option<(u64, Instant)>>,
+    liveness:     Option<LivenessTracker>,
+
+    // Block proposed at a height, proposed again in its later rounds
+    proposal_cache: RwLock<Option<(u64, FixedPill, Bytes)>>,
 }
 
 #[async_trait]
@@ -58,12 +76,16 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         ctx: Context,
         next_height: u64,
     ) -> Result<(FixedPill, Bytes), Box<dyn Error + Send>> {
+        self.mark_height_start(next_height);
+        if let Some(proposal) = self.cached_proposal(ctx.clone(), next_height) {
+            return Ok(proposal);
+        }
         let current_consensus_status = self.status_agent.to_inner();
 
-        let (ordered_tx_hashes, propose_hashes) = self
+        let (mut ordered_tx_hashes, propose_hashes) = self
             .adapter
             .get_txs_from_mempool(
-                ctx,
+                ctx.clone(),
                 next_height,
                 current_consensus_status.cycles_limit,
                 current_consensus_status.tx_num_limit,
@@ -78,7 +100,26 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             .into());
         }
 
-        let order_root = Merkle::from_hashes(ordered_tx_hashes.clone()).get_root_hash();
+        // Others reject a proposal over the limits, whatever the pool packaged
+        let txs = self
+            .adapter
+            .get_full_txs(ctx, ordered_tx_hashes.clone())
+            .await?;
+        let txs_cycles = txs.iter().map(|tx| tx.raw.cycles_limit).collect::<Vec<_>>();
+        let fit = fit_block_limits(
+            &txs_cycles,
+            current_consensus_status.cycles_limit,
+            current_consensus_status.tx_num_limit,
+        );
+        if fit < ordered_tx_hashes.len() {
+            log::warn!(
+                "[consensus-engine]: drop {} transactions over the block limits",
+                ordered_tx_hashes.len() - fit
+            );
+            ordered_tx_hashes.truncate(fit);
+        }
+
+        let order_root = Merkle::ordered_root(&ordered_tx_hashes);
 
         let state_root = current_consensus_status.get_latest_state_root();
         let header = BlockHeader {
@@ -88,7 +129,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             exec_height: current_consensus_status.exec_height,
             timestamp: time_now(),
             logs_bloom: current_consensus_status.list_logs_bloom,
-            order_root: order_root.unwrap_or_else(Hash::from_empty),
+            order_root,
             confirm_root: current_consensus_status.list_confirm_root,
             state_root,
             receipt_root: current_consensus_status.list_receipt_root.clone(),
@@ -114,6 +155,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         let mut set = self.exemption_hash.write();
         set.insert(hash.clone());
 
+        *self.proposal_cache.write() = Some((next_height, fixed_pill.clone(), hash.clone()));
         Ok((fixed_pill, hash))
     }
 
@@ -124,6 +166,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         hash: Bytes,
         block: FixedPill,
     ) -> Result<(), Box<dyn Error + Send>> {
+        self.mark_height_start(next_height);
         let time = Instant::now();
 
         let order_hashes = block.get_ordered_hashes();
@@ -134,10 +177,24 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         // If the block is proposed by self, it does not need to check. Get full signed
         // transactions directly.
         if !exemption {
-            self.check_block_roots(&block.inner.block.header)?;
+            // Before any of the transactions are fetched
+            let tx_num_limit = self.status_agent.to_inner().tx_num_limit;
+            check_tx_num(order_hashes_len, tx_num_limit)
+                .map_err(|e| self.rejected(CheckRejection::Txs, e.into()))?;
+            self.check_proposal_origin(&block.inner.block.header)
+                .map_err(|e| self.reported(&ctx, CheckRejection::Origin, e))?;
+            self.check_prev_hash(&block.inner.block.header)
+                .map_err(|e| self.reported(&ctx, CheckRejection::Roots, e))?;
+            self.check_block_roots(&block.inner.block.header)
+                .map_err(|e| self.rejected(CheckRejection::Roots, e))?;
+            self.check_timestamp(ctx.clone(), &block.inner.block.header)
+                .await
+                .map_err(|e| self.rejected(CheckRejection::Timestamp, e))?;
             self.adapter
                 .check_txs(ctx.clone(), order_hashes.clone())
-                .await?;
+                .await
+                .map_err(|e| self.rejected(CheckRejection::Txs, e))?;
+            self.metrics.on_check_block(time.elapsed());
 
             let adapter = Arc::clone(&self.adapter);
             let ctx_clone = ctx.clone();
@@ -153,7 +210,13 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             Instant::now() - time
         );
         let time = Instant::now();
-        let txs = self.adapter.get_full_txs(ctx, order_hashes).await?;
+        let txs = self.adapter.get_full_txs(ctx.clone(), order_hashes).await?;
+        if !exemption {
+            let status = self.status_agent.to_inner();
+            let txs_cycles = txs.iter().map(|tx| tx.raw.cycles_limit).collect::<Vec<_>>();
+            check_block_limits(&txs_cycles, status.cycles_limit, status.tx_num_limit)
+                .map_err(|e| self.rejected(CheckRejection::Txs, e.into()))?;
+        }
 
         log::info!(
             "[consensus-engine]: get txs cost {:?}",
@@ -187,29 +250,45 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
                 ProtocolError::from(ConsensusError::Other("lock in sync".to_string())).into(),
             );
         }
+        if !self.stopper.is_running() {
+            return Err(ProtocolError::from(ConsensusError::Stopped).into());
+        }
 
         let current_consensus_status = self.status_agent.to_inner();
+        let block_hash = Hash::from_bytes(commit.proof.block_hash.clone())?;
+        let authority = covert_to_overlord_authority(&current_consensus_status.validators);
+
+        // The same commit delivered again, as after a WAL replay
+        if current_consensus_status.current_height == current_height {
+            if current_consensus_status.current_hash != block_hash {
+                error!(
+                    "[consensus-engine]: conflicting commit at height {}",
+                    current_height
+                );
+                return Err(ProtocolError::from(ConsensusError::ConflictingCommit {
+                    height:    current_height,
+                    committed: current_consensus_status.current_hash,
+                    block:     block_hash,
+                })
+                .into());
+            }
+
+            log::debug!(
+                "[consensus-engine]: block of height {} already committed",
+                current_height
+            );
+            return Ok(self.overlord_status(current_height));
+        }
+
         if current_consensus_status.exec_height == current_height {
-            let status = Status {
-                height:         current_height + 1,
-                interval:       Some(current_consensus_status.consensus_interval),
-                timer_config:   Some(DurationConfig {
-                    propose_ratio:   current_consensus_status.propose_ratio,
-                    prevote_ratio:   current_consensus_status.prevote_ratio,
-                    precommit_ratio: current_consensus_status.precommit_ratio,
-                    brake_ratio:     current_consensus_status.brake_ratio,
-                }),
-                authority_list: covert_to_overlord_authority(&current_consensus_status.validators),
-            };
-            return Ok(status);
+            return Ok(self.overlord_status(current_height));
         }
 
         let pill = commit.content.inner;
-        let block_hash = Hash::from_bytes(commit.proof.block_hash.clone())?;
+        let round = commit.proof.round;
         let signature = commit.proof.signature.signature.clone();
         let bitmap = commit.proof.signature.address_bitmap.clone();
 
-        // Sorage save the lastest proof.
         let proof = Proof {
             height: commit.proof.height,
             round: commit.proof.round,
@@ -218,8 +297,6 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             bitmap,
         };
 
-        self.adapter.save_proof(ctx.clone(), proof.clone()).await?;
-
         // Get full transactions from mempool. If is error, try get from wal.
         let ordered_tx_hashes = pill.block.ordered_tx_hashes.clone();
         let signed_txs = match self
@@ -246,11 +323,16 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
                 .is_ok()
             {
                 break;
+            } else if !self.stopper.is_running() {
+                return Err(ProtocolError::from(ConsensusError::Stopped).into());
             } else {
                 Delay::new(Duration::from_millis(RETRY_COMMIT_INTERVAL)).await;
             }
         }
 
+        // Sorage save the lastest proof.
+        self.adapter.save_proof(ctx.clone(), proof.clone()).await?;
+
         trace_block(&pill.block);
         let block_exec_height = pill.block.header.exec_height;
         let metadata = self.adapter.get_metadata(
@@ -267,10 +349,40 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
 
         self.update_status(metadata, pill.block, proof, signed_txs)
             .await?;
+        self.metrics.on_commit(
+            current_height,
+            round,
+            ordered_tx_hashes.len(),
+            self.height_latency(current_height),
+        );
+        if let Some(liveness) = self.liveness.as_ref() {
+            liveness.observe(
+                ctx.clone(),
+                &authority,
+                &commit.proof.signature.address_bitmap,
+            );
+
+            let saved = match liveness.encode() {
+                Ok(missed) => self.adapter.save_liveness(ctx.clone(), missed).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = saved {
+                log::error!("[consensus-liveness]: save missed heights {:?}", e);
+            }
+        }
 
         self.adapter
             .flush_mempool(ctx.clone(), &ordered_tx_hashes)
             .await?;
+        let expired = self
+            .adapter
+            .expire_mempool(ctx.clone(), current_height)
+            .await?;
+        log::info!(
+            "[consensus]: {} txs expired in mempool at height {}",
+            expired,
+            current_height
+        );
 
         self.adapter
             .broadcast_height(ctx.clone(), current_height)
@@ -279,21 +391,9 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
 
         let mut set = self.exemption_hash.write();
         set.clear();
+        *self.proposal_cache.write() = None;
 
-        let current_consensus_status = self.status_agent.to_inner();
-        let status = Status {
-            height:         current_height + 1,
-            interval:       Some(current_consensus_status.consensus_interval),
-            timer_config:   Some(DurationConfig {
-                propose_ratio:   current_consensus_status.propose_ratio,
-                prevote_ratio:   current_consensus_status.prevote_ratio,
-                precommit_ratio: current_consensus_status.precommit_ratio,
-                brake_ratio:     current_consensus_status.brake_ratio,
-            }),
-            authority_list: covert_to_overlord_authority(&current_consensus_status.validators),
-        };
-
-        Ok(status)
+        Ok(self.overlord_status(current_height))
     }
 
     /// Only signed proposal and aggregated vote will be broadcast to others.
@@ -421,6 +521,10 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         adapter: Arc<Adapter>,
         crypto: Arc<OverlordCrypto>,
         lock: Arc<Mutex<()>>,
+        stopper: Stopper,
+        timestamp_rules: TimestampRules,
+        metrics: Arc<dyn ConsensusMetrics>,
+        liveness: Option<LivenessTracker>,
     ) -> Self {
         Self {
             status_agent,
@@ -430,6 +534,80 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             adapter,
             crypto,
             lock,
+            stopper,
+            timestamp_rules,
+            metrics,
+            height_start: RwLock::new(None),
+            liveness,
+            proposal_cache: RwLock::new(None),
+        }
+    }
+
+    /// The block already proposed at `height`, unless some of its
+    /// transactions have left the mempool since, as a replaced one does.
+    fn cached_proposal(&self, ctx: Context, height: u64) -> Option<(FixedPill, Bytes)> {
+        let (pill, hash) = match self.proposal_cache.read().as_ref() {
+            Some((cached_height, pill, hash)) if *cached_height == height => {
+                (pill.clone(), hash.clone())
+            }
+            _ => return None,
+        };
+
+        if !self
+            .adapter
+            .contains_txs(ctx, &pill.inner.block.ordered_tx_hashes)
+        {
+            log::info!(
+                "[consensus-engine]: rebuild the proposal of height {}, txs left the mempool",
+                height
+            );
+            return None;
+        }
+        Some((pill, hash))
+    }
+
+    fn mark_height_start(&self, height: u64) {
+        let mut height_start = self.height_start.write();
+        match *height_start {
+            Some((start_height, _)) if start_height == height => (),
+            _ => *height_start = Some((height, Instant::now())),
+        }
+    }
+
+    fn height_latency(&self, height: u64) -> Option<Duration> {
+        match *self.height_start.read() {
+            Some((start_height, start)) if start_height == height => Some(start.elapsed()),
+            _ => None,
+        }
+    }
+
+    fn rejected(&self, reason: CheckRejection, err: ProtocolError) -> ProtocolError {
+        self.metrics.on_rejection(reason);
+        err
+    }
+
+    // Only for the checks a lagging node passes too, like the proposer and
+    // the parent hash. The execution roots trail on a node whose executor
+    // lags, so they can fail against an honest proposer.
+    fn reported(&self, ctx: &Context, reason: CheckRejection, err: ProtocolError) -> ProtocolError {
+        self.adapter
+            .report_bad(ctx.clone(), PeerMisbehavior::InvalidProposal);
+        self.rejected(reason, err)
+    }
+
+    /// The status overlord goes on with after committing `height`.
+    fn overlord_status(&self, height: u64) -> Status {
+        let status = self.status_agent.to_inner();
+        Status {
+            height:         height + 1,
+            interval:       Some(status.consensus_interval),
+            timer_config:   Some(DurationConfig {
+                propose_ratio:   status.propose_ratio,
+                prevote_ratio:   status.prevote_ratio,
+                precommit_ratio: status.precommit_ratio,
+                brake_ratio:     status.brake_ratio,
+            }),
+            authority_list: covert_to_overlord_authority(&status.validators),
         }
     }
 
@@ -459,10 +637,37 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             .await
     }
 
-    fn check_block_roots(&self, block: &BlockHeader) -> ProtocolResult<()> {
+    async fn check_timestamp(&self, ctx: Context, block: &BlockHeader) -> ProtocolResult<()> {
+        let parent_timestamp = if block.height > 1 {
+            let parent = self
+                .adapter
+                .get_block_by_height(ctx, block.height - 1)
+                .await?;
+            Some(parent.header.timestamp)
+        } else {
+            None
+        };
+
+        let interval = self.status_agent.to_inner().consensus_interval;
+        self.timestamp_rules
+            .check(block.timestamp, parent_timestamp, interval, time_now())
+    }
+
+    fn check_proposal_origin(&self, block: &BlockHeader) -> ProtocolResult<()> {
+        let status = self.status_agent.to_inner();
+        check_proposal_origin(
+            block.height,
+            &block.proposer,
+            &block.proof,
+            &status.validators,
+            &status.current_hash,
+        )?;
+        Ok(())
+    }
+
+    fn check_prev_hash(&self, block: &BlockHeader) -> ProtocolResult<()> {
         let status = self.status_agent.to_inner();
 
-        // check previous hash
         if status.current_hash != block.pre_hash {
             trace::error(
                 "check_block_prev_hash_diff".to_string(),
@@ -477,6 +682,11 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             }
             .into());
         }
+        Ok(())
+    }
+
+    fn check_block_roots(&self, block: &BlockHeader) -> ProtocolResult<()> {
+        let status = self.status_agent.to_inner();
 
         // check state root
         if status.latest_commited_state_root != block.state_root
@@ -574,10 +784,8 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
 
     /// After get the signed transactions:
     /// 1. Execute the signed transactions.
-    /// 2. Save the signed transactions.
-    /// 3. Save the latest proof.
-    /// 4. Save the new block.
-    /// 5. Save the receipt.
+    /// 2. Save the new block together with its signed transactions.
+    /// 3. Save the receipt once the executor is done.
     pub async fn update_status(
         &self,
         metadata: Metadata,
@@ -585,12 +793,10 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         proof: Proof,
         txs: Vec<SignedTransaction>,
     ) -> ProtocolResult<()> {
-        // Save signed transactions
-        self.adapter.save_signed_txs(Context::new(), txs).await?;
-
-        // Save the block.
+        // Save the block and its signed transactions. Receipts are written
+        // by the executor, which runs behind consensus.
         self.adapter
-            .save_block(Context::new(), block.clone())
+            .save_block_data(Context::new(), block.clone(), txs, Vec::new())
             .await?;
 
         // update timeout_gap of mempool
@@ -0,0 +1,229 @@
+//! Validators signing two different blocks at the same height and round.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use protocol::{Bytes, ProtocolResult};
+
+use crate::ConsensusError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SignedKind {
+    Proposal,
+    Prevote,
+    Precommit,
+}
+
+/// A signature a validator made over a block at some height and round.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedMessage {
+    pub kind:       SignedKind,
+    pub height:     u64,
+    pub round:      u64,
+    pub signer:     Bytes,
+    pub block_hash: Bytes,
+    /// Hash of the payload the signature is over
+    pub hash:       Bytes,
+    pub signature:  Bytes,
+}
+
+// Where a signer signs at most one block in a height
+type Slot = (SignedKind, u64, Bytes);
+
+impl SignedMessage {
+    fn slot(&self) -> Slot {
+        (self.kind, self.round, self.signer.clone())
+    }
+}
+
+/// Two messages of one signer for different blocks in the same slot.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Evidence {
+    pub first:  SignedMessage,
+    pub second: SignedMessage,
+}
+
+// Messages by height. Anyone can send messages for heights far ahead, so
+// the window holds at most `capacity` messages and `height_capacity` per
+// height. Once full, the heights farthest ahead make room for nearer ones.
+struct SignedWindow {
+    capacity:        usize,
+    height_capacity: usize,
+    len:             usize,
+    heights:         BTreeMap<u64, HashMap<Slot, SignedMessage>>,
+}
+
+impl SignedWindow {
+    fn get(&self, message: &SignedMessage) -> Option<&SignedMessage> {
+        self.heights
+            .get(&message.height)
+            .and_then(|height| height.get(&message.slot()))
+    }
+
+    fn insert(&mut self, message: SignedMessage) {
+        let slot = message.slot();
+        if let Some(seen) = self
+            .heights
+            .get_mut(&message.height)
+            .and_then(|height| height.get_mut(&slot))
+        {
+            *seen = message;
+            return;
+        }
+
+        let height_len = self.heights.get(&message.height).map_or(0, HashMap::len);
+        if height_len >= self.height_capacity {
+            return;
+        }
+        while self.len >= self.capacity {
+            let farthest = match self.heights.keys().next_back() {
+                Some(farthest) if *farthest > message.height => *farthest,
+                _ => return,
+            };
+            if let Some(dropped) = self.heights.remove(&farthest) {
+                self.len -= dropped.len();
+            }
+        }
+
+        self.heights
+            .entry(message.height)
+            .or_insert_with(HashMap::new)
+            .insert(slot, message);
+        self.len += 1;
+    }
+
+    fn prune(&mut self, committed_height: u64) {
+        let kept = self.heights.split_off(&(committed_height + 1));
+        let dropped = std::mem::replace(&mut self.heights, kept);
+        self.len -= dropped.values().map(HashMap::len).sum::<usize>();
+    }
+}
+
+/// Remembers the first message of every signer in each slot above the
+/// committed height, and the latest `capacity` pieces of evidence found.
+pub struct EvidencePool {
+    capacity:      usize,
+    signed:        Mutex<SignedWindow>,
+    evidence:      Mutex<VecDeque<Evidence>>,
+    pruned_height: AtomicU64,
+}
+
+impl EvidencePool {
+    /// Keeps at most `capacity` messages, `height_capacity` of them at any
+    /// one height.
+    pub fn new(capacity: usize, height_capacity: usize) -> Self {
+        EvidencePool {
+            capacity,
+            signed: Mutex::new(SignedWindow {
+                capacity,
+                height_capacity,
+                len: 0,
+                heights: BTreeMap::new(),
+            }),
+            evidence: Mutex::new(VecDeque::new()),
+            pruned_height: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the evidence `message` makes with the one seen before in its
+    /// slot, once per slot. Signatures are only checked by `verify` on a
+    /// conflict, so a forged message can't frame a validator. Messages of
+    /// signers outside `authority` take no room in the window.
+    pub fn observe<F>(
+        &self,
+        message: SignedMessage,
+        authority: &[Bytes],
+        verify: F,
+    ) -> Option<Evidence>
+    where
+        F: Fn(&SignedMessage) -> bool,
+    {
+        if !authority.contains(&message.signer) {
+            return None;
+        }
+        let mut signed = self.signed.lock();
+
+        let seen = match signed.get(&message) {
+            Some(seen) => seen.clone(),
+            None => {
+                signed.insert(message);
+                return None;
+            }
+        };
+        if seen.block_hash == message.block_hash || !verify(&message) {
+            return None;
+        }
+        if !verify(&seen) {
+            signed.insert(message);
+            return None;
+        }
+
+        let mut evidence = self.evidence.lock();
+        let reported = evidence
+            .iter()
+            .any(|e| e.first.height == seen.height && e.first.slot() == seen.slot());
+        if reported {
+            return None;
+        }
+        if evidence.len() == self.capacity {
+            evidence.pop_front();
+        }
+        let found = Evidence {
+            first:  seen,
+            second: message,
+        };
+        evidence.push_back(found.clone());
+        Some(found)
+    }
+
+    /// Forgets the messages of heights up to `committed_height`.
+    pub fn prune(&self, committed_height: u64) {
+        if self.pruned_height.load(Ordering::SeqCst) >= committed_height {
+            return;
+        }
+        self.pruned_height.store(committed_height, Ordering::SeqCst);
+        self.signed.lock().prune(committed_height);
+    }
+
+    pub fn pending_evidence(&self) -> Vec<Evidence> {
+        self.evidence.lock().iter().cloned().collect()
+    }
+
+    /// The pending evidence, as kept in storage.
+    pub fn encode(&self) -> ProtocolResult<Bytes> {
+        let evidence = bincode::serialize(&self.pending_evidence())
+            .map_err(|e| ConsensusError::EvidenceErr(e.to_string()))?;
+        Ok(Bytes::from(evidence))
+    }
+
+    /// Puts back the evidence encoded before a restart, nothing if `encoded`
+    /// is empty.
+    pub fn restore(&self, encoded: &[u8]) -> ProtocolResult<()> {
+        if encoded.is_empty() {
+            return Ok(());
+        }
+        let restored: Vec<Evidence> = bincode::deserialize(encoded)
+            .map_err(|e| ConsensusError::EvidenceErr(e.to_string()))?;
+
+        let mut evidence = self.evidence.lock();
+        for found in restored {
+            if evidence.len() == self.capacity {
+                evidence.pop_front();
+            }
+            evidence.push_back(found);
+        }
+        Ok(())
+    }
+
+    /// Messages kept in the window.
+    pub fn len(&self) -> usize {
+        self.signed.lock().len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
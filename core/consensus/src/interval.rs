@@ -0,0 +1,53 @@
+//! Block interval following how full the blocks are.
+
+use std::cmp;
+
+use serde::{Deserialize, Serialize};
+
+use protocol::ProtocolResult;
+
+use crate::ConsensusError;
+
+/// Shrinks the interval of the metadata by a quarter for the height after a
+/// block of at least `busy_txs` transactions, and relaxes it by a quarter
+/// for the height after an empty block, always within
+/// `min_interval..=max_interval`. The interval only depends on the parent
+/// block, so every node agrees on it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdaptiveInterval {
+    pub(crate) min_interval: u64,
+    pub(crate) max_interval: u64,
+    pub(crate) busy_txs:     usize,
+}
+
+impl AdaptiveInterval {
+    pub fn new(min_interval: u64, max_interval: u64, busy_txs: usize) -> ProtocolResult<Self> {
+        if min_interval > max_interval {
+            return Err(ConsensusError::InvalidAdaptiveInterval {
+                min: min_interval,
+                max: max_interval,
+            }
+            .into());
+        }
+
+        Ok(AdaptiveInterval {
+            min_interval,
+            max_interval,
+            busy_txs,
+        })
+    }
+
+    /// The interval of the height after a block of `txs` transactions, given
+    /// the one of the metadata.
+    pub fn next(&self, base: u64, txs: usize) -> u64 {
+        let next = if txs >= self.busy_txs {
+            base - base / 4
+        } else if txs == 0 {
+            base.saturating_add(cmp::max(base / 4, 1))
+        } else {
+            base
+        };
+
+        cmp::min(cmp::max(next, self.min_interval), self.max_interval)
+    }
+}
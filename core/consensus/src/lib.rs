@@ -3,20 +3,32 @@
 pub mod adapter;
 pub mod consensus;
 mod engine;
+pub mod evidence;
 pub mod fixed_types;
+pub mod interval;
+pub mod liveness;
 pub mod message;
+pub mod metrics;
 pub mod status;
+pub mod stop;
 pub mod synchronization;
 #[cfg(test)]
 mod tests;
+pub mod timestamp;
 pub mod trace;
 pub mod util;
+pub mod validators;
 pub mod wal;
 mod wal_proto;
 
 pub use crate::adapter::OverlordConsensusAdapter;
 pub use crate::consensus::OverlordConsensus;
+pub use crate::interval::AdaptiveInterval;
+pub use crate::liveness::{JailHook, JailValidatorHook, LivenessTracker, LogJailHook};
+pub use crate::metrics::{AtomicConsensusMetrics, ConsensusMetrics};
+pub use crate::stop::Stopper;
 pub use crate::synchronization::{OverlordSynchronization, RichBlock};
+pub use crate::timestamp::TimestampRules;
 pub use crate::wal::SignedTxsWAL;
 pub use overlord::{types::Node, DurationConfig};
 
@@ -98,9 +110,82 @@ pub enum ConsensusError {
     #[display(fmt = "Crypto error {:?}", _0)]
     CryptoErr(Box<CryptoError>),
 
-    /// The synchronous block does not pass the checks.
-    #[display(fmt = "Synchronization {} block error", _0)]
-    SyncBlockHashErr(u64),
+    /// Overlord committed a block other than the one already committed at
+    /// that height.
+    #[display(
+        fmt = "Block {:?} conflicts with the committed {:?} at height {}",
+        block,
+        committed,
+        height
+    )]
+    ConflictingCommit {
+        height:    u64,
+        committed: Hash,
+        block:     Hash,
+    },
+
+    /// The proposed block does not come from where it should.
+    #[display(fmt = "Invalid proposal, {}", _0)]
+    InvalidProposal(String),
+
+    /// The proposed block holds more than the status allows.
+    #[display(fmt = "Block {} {} exceeds the limit {}", name, value, limit)]
+    ExceedBlockLimit {
+        name:  &'static str,
+        value: u64,
+        limit: u64,
+    },
+
+    /// The proposed block's timestamp breaks the `TimestampRules`.
+    #[display(fmt = "Invalid block timestamp {}, {}", timestamp, reason)]
+    InvalidTimestamp { timestamp: u64, reason: String },
+
+    /// The proof is not signed by enough validators of its height.
+    #[display(fmt = "Invalid proof of block {}, {}", height, reason)]
+    InvalidProof { height: u64, reason: String },
+
+    /// The synchronous block or the proof it carries does not link to the
+    /// committed block.
+    #[display(
+        fmt = "Synchronization block {} links to {:?}, expect {:?}",
+        height,
+        actual,
+        expect
+    )]
+    InvalidSyncBlock {
+        height: u64,
+        expect: Hash,
+        actual: Hash,
+    },
+
+    /// Synchronization stopped at the block of this height, the blocks
+    /// before it are committed.
+    #[display(fmt = "Synchronization failed at block {}: {}", height, reason)]
+    SyncFailed { height: u64, reason: String },
+
+    /// A transaction of the synchronous block is not signed by its sender.
+    #[display(
+        fmt = "Synchronization block {} has invalid transaction {:?}",
+        height,
+        tx_hash
+    )]
+    InvalidSyncTransaction { height: u64, tx_hash: Hash },
+
+    /// The adaptive interval can not reach its maximum from its minimum.
+    #[display(fmt = "Adaptive interval min {} exceeds max {}", min, max)]
+    InvalidAdaptiveInterval { min: u64, max: u64 },
+
+    /// The evidence kept in storage can not be encoded or decoded.
+    #[display(fmt = "Evidence codec error {:?}", _0)]
+    EvidenceErr(String),
+
+    /// The missed heights kept in storage can not be encoded or decoded.
+    #[display(fmt = "Liveness codec error {:?}", _0)]
+    LivenessErr(String),
+
+    /// The node is stopping, nothing more is committed.
+    #[display(fmt = "Consensus stopped")]
+    Stopped,
 
     /// The Rpc response mismatch the request.
     #[display(fmt = "Synchronization Rpc {:?} message mismatch", _0)]
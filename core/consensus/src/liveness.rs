@@ -0,0 +1,195 @@
+//! Validators that keep leaving their signature out of commit proofs.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use overlord::types::Node;
+use parking_lot::Mutex;
+use serde_json::json;
+
+use common_crypto::{
+    Crypto, PrivateKey, PublicKey, Secp256k1, Secp256k1PrivateKey, Signature, ToPublicKey,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{Context, MemPool};
+use protocol::types::{Address, Hash, RawTransaction, SignedTransaction, TransactionRequest};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::status::StatusAgent;
+use crate::util::is_signed;
+use crate::ConsensusError;
+
+/// Told about a validator found offline. Taking it out of the verifier list
+/// has to go through a transaction every node executes.
+pub trait JailHook: Send + Sync {
+    fn jail(&self, ctx: Context, address: Address);
+}
+
+/// Logs the recommendation for an operator to act on.
+pub struct LogJailHook;
+
+impl JailHook for LogJailHook {
+    fn jail(&self, _ctx: Context, address: Address) {
+        log::warn!(
+            "[consensus-liveness]: validator {} keeps missing commits, consider jailing it",
+            address.as_hex()
+        );
+    }
+}
+
+/// Submits the metadata service's `jail_validator` for the validator, signed
+/// with the node key. It only executes if that is the admin key, so this hook
+/// is for the admin's node. The validator is out from the height after the
+/// transaction and back once it sends `unjail`.
+pub struct JailValidatorHook<M> {
+    mempool:      Arc<M>,
+    status_agent: StatusAgent,
+    chain_id:     Hash,
+    timeout_gap:  u64,
+    priv_key:     Secp256k1PrivateKey,
+}
+
+impl<M> JailValidatorHook<M> {
+    pub fn new(
+        mempool: Arc<M>,
+        status_agent: StatusAgent,
+        chain_id: Hash,
+        timeout_gap: u64,
+        priv_key: Secp256k1PrivateKey,
+    ) -> Self {
+        JailValidatorHook {
+            mempool,
+            status_agent,
+            chain_id,
+            timeout_gap,
+            priv_key,
+        }
+    }
+
+    pub fn jail_validator_tx(&self, address: &Address) -> ProtocolResult<SignedTransaction> {
+        let status = self.status_agent.to_inner();
+        let height = status.current_height;
+        let nonce = [address.as_bytes().as_ref(), &height.to_be_bytes()].concat();
+
+        let raw = RawTransaction {
+            chain_id:     self.chain_id.clone(),
+            nonce:        Hash::digest(Bytes::from(nonce)),
+            timeout:      height + self.timeout_gap,
+            cycles_price: status.cycles_price,
+            cycles_limit: status.cycles_limit,
+            request:      TransactionRequest {
+                service_name: "metadata".to_owned(),
+                method:       "jail_validator".to_owned(),
+                payload:      json!({ "address": address }).to_string(),
+            },
+        };
+        let tx_hash = Hash::digest(raw.encode_fixed()?);
+        let signature = Secp256k1::sign_message(&tx_hash.as_bytes(), &self.priv_key.to_bytes())
+            .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+
+        Ok(SignedTransaction {
+            raw,
+            tx_hash,
+            pubkey: self.priv_key.pub_key().to_bytes(),
+            signature: signature.to_bytes(),
+        })
+    }
+}
+
+impl<M: MemPool + 'static> JailHook for JailValidatorHook<M> {
+    fn jail(&self, ctx: Context, address: Address) {
+        let stx = match self.jail_validator_tx(&address) {
+            Ok(stx) => stx,
+            Err(e) => {
+                log::error!("[consensus-liveness]: sign jail_validator {}", e);
+                return;
+            }
+        };
+
+        log::warn!(
+            "[consensus-liveness]: validator {} keeps missing commits, submit {:?}",
+            address.as_hex(),
+            stx.tx_hash
+        );
+        let mempool = Arc::clone(&self.mempool);
+        tokio::spawn(async move {
+            if let Err(e) = mempool.insert(ctx, stx).await {
+                log::error!("[consensus-liveness]: submit jail_validator {}", e);
+            }
+        });
+    }
+}
+
+/// Counts the heights each validator's signature has been missing from in a
+/// row. The engine saves the counts after each commit, `restore` puts them
+/// back when the node starts.
+pub struct LivenessTracker {
+    max_missed: u64,
+    hook:       Arc<dyn JailHook>,
+    missed:     Mutex<HashMap<Bytes, u64>>,
+}
+
+impl LivenessTracker {
+    pub fn new(max_missed: u64, hook: Arc<dyn JailHook>) -> Self {
+        LivenessTracker {
+            max_missed,
+            hook,
+            missed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes the sorted authority list of a height and the signer bitmap of
+    /// its proof. A validator that has just missed more than `max_missed`
+    /// heights in a row goes to the hook, once until it signs again.
+    pub fn observe(&self, ctx: Context, authority: &[Node], bitmap: &[u8]) {
+        let mut offline = vec![];
+        {
+            let mut missed = self.missed.lock();
+            missed.retain(|address, _| authority.iter().any(|node| &node.address == address));
+
+            for (index, node) in authority.iter().enumerate() {
+                if is_signed(bitmap, index) {
+                    missed.remove(&node.address);
+                    continue;
+                }
+
+                let count = missed.entry(node.address.clone()).or_insert(0);
+                *count += 1;
+                if *count == self.max_missed + 1 {
+                    offline.push(node.address.clone());
+                }
+            }
+        }
+
+        for address in offline.into_iter() {
+            match Address::from_bytes(address) {
+                Ok(address) => self.hook.jail(ctx.clone(), address),
+                Err(e) => log::error!("[consensus-liveness]: invalid address {}", e),
+            }
+        }
+    }
+
+    pub fn missed(&self, address: &Bytes) -> u64 {
+        self.missed.lock().get(address).cloned().unwrap_or(0)
+    }
+
+    /// The counts, as kept in storage.
+    pub fn encode(&self) -> ProtocolResult<Bytes> {
+        let missed = bincode::serialize(&*self.missed.lock())
+            .map_err(|e| ConsensusError::LivenessErr(e.to_string()))?;
+        Ok(Bytes::from(missed))
+    }
+
+    /// Puts back the counts encoded before a restart, nothing if `encoded` is
+    /// empty.
+    pub fn restore(&self, encoded: &[u8]) -> ProtocolResult<()> {
+        if encoded.is_empty() {
+            return Ok(());
+        }
+        let missed: HashMap<Bytes, u64> = bincode::deserialize(encoded)
+            .map_err(|e| ConsensusError::LivenessErr(e.to_string()))?;
+
+        *self.missed.lock() = missed;
+        Ok(())
+    }
+}
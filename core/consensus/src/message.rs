@@ -12,9 +12,11 @@ use serde::{Deserialize, Serialize};
 use protocol::traits::{
     Consensus, Context, MessageHandler, Priority, Rpc, Storage, Synchronization,
 };
-use protocol::ProtocolError;
+use protocol::types::Proof;
+use protocol::{ProtocolError, ProtocolResult};
 
-use crate::fixed_types::{FixedBlock, FixedHeight, FixedSignedTxs, PullTxsRequest};
+use crate::fixed_types::{FixedBlock, FixedHeight, FixedProof, FixedSignedTxs, PullTxsRequest};
+use crate::ConsensusError;
 
 pub const END_GOSSIP_SIGNED_PROPOSAL: &str = "/gossip/consensus/signed_proposal";
 pub const END_GOSSIP_SIGNED_VOTE: &str = "/gossip/consensus/signed_vote";
@@ -24,6 +26,8 @@ pub const RPC_SYNC_PULL_BLOCK: &str = "/rpc_call/consensus/sync_pull_block";
 pub const RPC_RESP_SYNC_PULL_BLOCK: &str = "/rpc_resp/consensus/sync_pull_block";
 pub const RPC_SYNC_PULL_TXS: &str = "/rpc_call/consensus/sync_pull_txs";
 pub const RPC_RESP_SYNC_PULL_TXS: &str = "/rpc_resp/consensus/sync_pull_txs";
+pub const RPC_SYNC_PULL_PROOF: &str = "/rpc_call/consensus/sync_pull_proof";
+pub const RPC_RESP_SYNC_PULL_PROOF: &str = "/rpc_resp/consensus/sync_pull_proof";
 pub const BROADCAST_HEIGHT: &str = "/gossip/consensus/broadcast_height";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -245,3 +249,53 @@ impl<R: Rpc + 'static, S: Storage + 'static> MessageHandler for PullTxsRpcHandle
             .await;
     }
 }
+
+#[derive(Debug)]
+pub struct PullProofRpcHandler<R, S> {
+    rpc:     Arc<R>,
+    storage: Arc<S>,
+}
+
+impl<R, S> PullProofRpcHandler<R, S>
+where
+    R: Rpc + 'static,
+    S: Storage + 'static,
+{
+    pub fn new(rpc: Arc<R>, storage: Arc<S>) -> Self {
+        PullProofRpcHandler { rpc, storage }
+    }
+
+    // The proof of the latest block is saved alone, the proof of any other
+    // block is carried by the block after it.
+    async fn get_proof(&self, height: u64) -> ProtocolResult<Proof> {
+        let latest_proof = self.storage.get_latest_proof().await?;
+        if latest_proof.height == height {
+            return Ok(latest_proof);
+        }
+
+        let proof = self
+            .storage
+            .get_block_by_height(height + 1)
+            .await?
+            .header
+            .proof;
+        if proof.height != height {
+            return Err(ConsensusError::MissingProof(height).into());
+        }
+        Ok(proof)
+    }
+}
+
+#[async_trait]
+impl<R: Rpc + 'static, S: Storage + 'static> MessageHandler for PullProofRpcHandler<R, S> {
+    type Message = FixedHeight;
+
+    async fn process(&self, ctx: Context, msg: FixedHeight) {
+        let ret = self.get_proof(msg.inner).await.map(FixedProof::new);
+
+        self.rpc
+            .response(ctx, RPC_RESP_SYNC_PULL_PROOF, ret, Priority::High)
+            .unwrap_or_else(move |e: ProtocolError| warn!("[core_consensus] push proof {}", e))
+            .await;
+    }
+}
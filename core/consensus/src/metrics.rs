@@ -0,0 +1,120 @@
+//! Counters on how heights go, for telling why rounds time out without
+//! digging through the logs.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The check a proposal failed in `check_block`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckRejection {
+    /// The proposer is no validator, or the proof is not of the parent.
+    Origin,
+    /// The header does not extend the current status.
+    Roots,
+    Timestamp,
+    Txs,
+}
+
+/// Where the engine reports its progress. It is called on the consensus
+/// path, so an implementation must not block.
+pub trait ConsensusMetrics: Send + Sync {
+    /// `latency` runs from the first block this node built or checked at
+    /// `height`, `None` if it saw none before the commit.
+    fn on_commit(&self, height: u64, round: u64, tx_count: usize, latency: Option<Duration>);
+
+    fn on_check_block(&self, duration: Duration);
+
+    fn on_rejection(&self, reason: CheckRejection);
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsensusMetricsSnapshot {
+    pub committed_heights:        u64,
+    pub latest_height:            u64,
+    /// Rounds summed over the committed heights, one for a round 0 commit.
+    pub rounds:                   u64,
+    pub committed_txs:            u64,
+    pub latest_block_txs:         u64,
+    pub commit_latency_ms:        u64,
+    pub latest_commit_latency_ms: u64,
+    pub checked_blocks:           u64,
+    pub check_block_ms:           u64,
+    pub rejected_origin:          u64,
+    pub rejected_roots:           u64,
+    pub rejected_timestamp:       u64,
+    pub rejected_txs:             u64,
+}
+
+/// Keeps running totals in atomics. Each counter is read on its own, so a
+/// snapshot taken during a commit may be off by that commit.
+#[derive(Default)]
+pub struct AtomicConsensusMetrics {
+    committed_heights:        AtomicU64,
+    latest_height:            AtomicU64,
+    rounds:                   AtomicU64,
+    committed_txs:            AtomicU64,
+    latest_block_txs:         AtomicU64,
+    commit_latency_ms:        AtomicU64,
+    latest_commit_latency_ms: AtomicU64,
+    checked_blocks:           AtomicU64,
+    check_block_ms:           AtomicU64,
+    rejected_origin:          AtomicU64,
+    rejected_roots:           AtomicU64,
+    rejected_timestamp:       AtomicU64,
+    rejected_txs:             AtomicU64,
+}
+
+impl AtomicConsensusMetrics {
+    pub fn snapshot(&self) -> ConsensusMetricsSnapshot {
+        ConsensusMetricsSnapshot {
+            committed_heights:        self.committed_heights.load(Ordering::Relaxed),
+            latest_height:            self.latest_height.load(Ordering::Relaxed),
+            rounds:                   self.rounds.load(Ordering::Relaxed),
+            committed_txs:            self.committed_txs.load(Ordering::Relaxed),
+            latest_block_txs:         self.latest_block_txs.load(Ordering::Relaxed),
+            commit_latency_ms:        self.commit_latency_ms.load(Ordering::Relaxed),
+            latest_commit_latency_ms: self.latest_commit_latency_ms.load(Ordering::Relaxed),
+            checked_blocks:           self.checked_blocks.load(Ordering::Relaxed),
+            check_block_ms:           self.check_block_ms.load(Ordering::Relaxed),
+            rejected_origin:          self.rejected_origin.load(Ordering::Relaxed),
+            rejected_roots:           self.rejected_roots.load(Ordering::Relaxed),
+            rejected_timestamp:       self.rejected_timestamp.load(Ordering::Relaxed),
+            rejected_txs:             self.rejected_txs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl ConsensusMetrics for AtomicConsensusMetrics {
+    fn on_commit(&self, height: u64, round: u64, tx_count: usize, latency: Option<Duration>) {
+        self.committed_heights.fetch_add(1, Ordering::Relaxed);
+        self.latest_height.store(height, Ordering::Relaxed);
+        self.rounds.fetch_add(round + 1, Ordering::Relaxed);
+        self.committed_txs
+            .fetch_add(tx_count as u64, Ordering::Relaxed);
+        self.latest_block_txs
+            .store(tx_count as u64, Ordering::Relaxed);
+
+        if let Some(latency) = latency {
+            let latency = latency.as_millis() as u64;
+            self.commit_latency_ms.fetch_add(latency, Ordering::Relaxed);
+            self.latest_commit_latency_ms
+                .store(latency, Ordering::Relaxed);
+        }
+    }
+
+    fn on_check_block(&self, duration: Duration) {
+        self.checked_blocks.fetch_add(1, Ordering::Relaxed);
+        self.check_block_ms
+            .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn on_rejection(&self, reason: CheckRejection) {
+        let counter = match reason {
+            CheckRejection::Origin => &self.rejected_origin,
+            CheckRejection::Roots => &self.rejected_roots,
+            CheckRejection::Timestamp => &self.rejected_timestamp,
+            CheckRejection::Txs => &self.rejected_txs,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -8,9 +8,11 @@ use serde_json::json;
 
 use common_merkle::Merkle;
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::ExecutorResp;
+use protocol::traits::{ExecutorResp, Storage};
 use protocol::types::{Block, Bloom, Hash, MerkleRoot, Metadata, Proof, Validator};
+use protocol::ProtocolResult;
 
+use crate::interval::AdaptiveInterval;
 use crate::util::check_list_roots;
 
 #[derive(Clone, Debug)]
@@ -41,23 +43,12 @@ impl StatusAgent {
             .update_by_commited(metadata, block, block_hash, current_proof)
     }
 
-    // TODO(yejiayu): Is there a better way to write it?
+    pub fn update_proof(&self, proof: Proof) {
+        self.status.write().current_proof = proof;
+    }
+
     pub fn replace(&self, new_status: CurrentConsensusStatus) {
-        let mut status = self.status.write();
-        status.cycles_price = new_status.cycles_price;
-        status.cycles_limit = new_status.cycles_limit;
-        status.current_height = new_status.current_height;
-        status.exec_height = new_status.exec_height;
-        status.current_hash = new_status.current_hash;
-        status.latest_commited_state_root = new_status.latest_commited_state_root;
-        status.list_logs_bloom = new_status.list_logs_bloom;
-        status.list_confirm_root = new_status.list_confirm_root;
-        status.list_state_root = new_status.list_state_root;
-        status.list_receipt_root = new_status.list_receipt_root;
-        status.list_cycles_used = new_status.list_cycles_used;
-        status.current_proof = new_status.current_proof;
-        status.validators = new_status.validators;
-        status.consensus_interval = new_status.consensus_interval;
+        *self.status.write() = new_status;
     }
 
     pub fn to_inner(&self) -> CurrentConsensusStatus {
@@ -65,6 +56,25 @@ impl StatusAgent {
     }
 }
 
+/// The proof of `latest_block` to restart the status from. A header carries
+/// the proof of its parent, the proof of the latest block is the one kept in
+/// storage once it commits, when it matches.
+pub async fn restore_latest_proof<S: Storage>(
+    storage: &S,
+    latest_block: &Block,
+) -> ProtocolResult<Proof> {
+    let block_hash = Hash::digest(latest_block.encode_fixed()?);
+
+    match storage.get_latest_proof().await {
+        Ok(proof)
+            if proof.height == latest_block.header.height && proof.block_hash == block_hash =>
+        {
+            Ok(proof)
+        }
+        _ => Ok(latest_block.header.proof.clone()),
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Display)]
 #[display(
     fmt = "current_height {}, exec height {}, current_hash {:?}, latest_commited_state_root {:?} list state root {:?}, list receipt root {:?}, list confirm root {:?}, list cycle used {:?}, logs bloom {:?}",
@@ -99,6 +109,8 @@ pub struct CurrentConsensusStatus {
     pub brake_ratio:                u64,
     pub tx_num_limit:               u64,
     pub max_tx_size:                u64,
+    #[serde(default)]
+    pub adaptive_interval:          Option<AdaptiveInterval>,
 }
 
 impl CurrentConsensusStatus {
@@ -149,10 +161,19 @@ impl CurrentConsensusStatus {
         self.current_hash = block_hash;
         self.current_proof = current_proof;
         self.latest_commited_state_root = block.header.state_root.clone();
+        self.adapt_interval(block.ordered_tx_hashes.len());
 
         self.split_off(&block);
     }
 
+    /// Adapts the interval of the metadata to the committed block of `txs`
+    /// transactions, if the interval is adaptive.
+    pub fn adapt_interval(&mut self, txs: usize) {
+        if let Some(adaptive) = self.adaptive_interval.as_ref() {
+            self.consensus_interval = adaptive.next(self.consensus_interval, txs);
+        }
+    }
+
     fn set_metadata(&mut self, metadata: Metadata) {
         self.cycles_limit = metadata.cycles_limit;
         self.cycles_price = metadata.cycles_price;
@@ -237,14 +258,10 @@ impl ExecutedInfo {
     pub fn new(height: u64, order_root: MerkleRoot, resp: ExecutorResp) -> Self {
         let cycles = resp.all_cycles_used;
 
-        let receipt = Merkle::from_hashes(
-            resp.receipts
-                .iter()
-                .map(|r| Hash::digest(r.to_owned().encode_fixed().unwrap()))
-                .collect::<Vec<_>>(),
-        )
-        .get_root_hash()
-        .unwrap_or_else(Hash::from_empty);
+        let receipt = Merkle::from_receipts(&resp.receipts)
+            .unwrap()
+            .get_root_hash()
+            .unwrap_or_else(Hash::from_empty);
 
         Self {
             exec_height:  height,
@@ -0,0 +1,55 @@
+//! Stopping consensus and synchronization between two heights.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use futures::lock::Mutex;
+use futures::pin_mut;
+use futures_timer::Delay;
+
+/// Milliseconds shutdown waits for the height in progress
+pub const DEFAULT_STOP_TIMEOUT: u64 = 30_000;
+
+/// Shared by the engine and the synchronization, which hold `lock` while
+/// they commit and check the flag before each height.
+#[derive(Clone, Debug)]
+pub struct Stopper {
+    lock:    Arc<Mutex<()>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Stopper {
+    pub fn new(lock: Arc<Mutex<()>>) -> Self {
+        Stopper {
+            lock,
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Keeps new heights from being committed and waits for the one in
+    /// progress, for at most `timeout`. Returns false if it is still in
+    /// progress then.
+    pub async fn stop(&self, timeout: Duration) -> bool {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        let in_progress = self.lock.lock();
+        let timeout = Delay::new(timeout);
+        pin_mut!(in_progress, timeout);
+
+        match future::select(in_progress, timeout).await {
+            Either::Left(_) => true,
+            Either::Right(_) => false,
+        }
+    }
+
+    /// Commits continue from the status left by the last one.
+    pub fn resume(&self) {
+        self.stopped.store(false, Ordering::SeqCst);
+    }
+}
@@ -1,22 +1,32 @@
+use std::cmp;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::future::join_all;
 use futures::lock::Mutex;
 use futures_timer::Delay;
+use rayon::prelude::*;
+
+use common_crypto::{Crypto, Secp256k1};
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    Context, ExecutorParams, ExecutorResp, Synchronization, SynchronizationAdapter,
+    Context, ExecutorParams, ExecutorResp, PeerMisbehavior, Synchronization, SynchronizationAdapter,
 };
-use protocol::types::{Block, Hash, Receipt, SignedTransaction};
-use protocol::ProtocolResult;
+use protocol::types::{Block, Hash, Metadata, Proof, Receipt, SignedTransaction};
+use protocol::{ProtocolError, ProtocolResult};
 
-use crate::status::{ExecutedInfo, StatusAgent};
+use crate::status::{CurrentConsensusStatus, ExecutedInfo, StatusAgent};
+use crate::stop::Stopper;
+use crate::util::{common_ref_of, verify_proof_signature};
 use crate::ConsensusError;
 
 const POLLING_BROADCAST: u64 = 2000;
 const WAIT_EXECUTION: u64 = 1000;
+// Blocks pulled from remote at once
+const SYNC_BATCH_BLOCKS: u64 = 16;
 
 #[derive(Clone, Debug)]
 pub struct RichBlock {
@@ -28,6 +38,7 @@ pub struct OverlordSynchronization<Adapter: SynchronizationAdapter> {
     adapter: Arc<Adapter>,
     status:  StatusAgent,
     lock:    Arc<Mutex<()>>,
+    stopper: Stopper,
     syncing: Mutex<()>,
 
     sync_txs_chunk_size: usize,
@@ -37,7 +48,7 @@ pub struct OverlordSynchronization<Adapter: SynchronizationAdapter> {
 impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronization<Adapter> {
     async fn receive_remote_block(&self, ctx: Context, remote_height: u64) -> ProtocolResult<()> {
         let syncing_lock = self.syncing.try_lock();
-        if syncing_lock.is_none() {
+        if syncing_lock.is_none() || !self.stopper.is_running() {
             return Ok(());
         }
 
@@ -72,16 +83,24 @@ impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronizatio
                 remote_height,
             )
             .await;
-        let sync_status = sync_status_agent.to_inner();
-
         if let Err(e) = sync_resp {
             log::error!(
                 "[synchronization]: err, current_height {:?} err_msg: {:?}",
-                sync_status.current_height,
+                sync_status_agent.to_inner().current_height,
                 e
             );
         }
 
+        // The next proposal carries this proof, it must be the proof of the
+        // last synced block
+        if let Err(e) = self
+            .sync_latest_proof(ctx.clone(), sync_status_agent.clone())
+            .await
+        {
+            log::error!("[synchronization]: pull latest proof err_msg: {:?}", e);
+        }
+        let sync_status = sync_status_agent.to_inner();
+
         self.status.replace(sync_status.clone());
         self.adapter.update_status(
             ctx.clone(),
@@ -109,6 +128,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         adapter: Arc<Adapter>,
         status: StatusAgent,
         lock: Arc<Mutex<()>>,
+        stopper: Stopper,
     ) -> Self {
         let syncing = Mutex::new(());
 
@@ -116,6 +136,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             adapter,
             status,
             lock,
+            stopper,
             syncing,
 
             sync_txs_chunk_size,
@@ -143,94 +164,422 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
     ) -> ProtocolResult<()> {
         let mut current_height = current_height;
 
+        // The proof of a block comes with the block after it, so the last
+        // block pulled is held back until the next batch unless it is the tip
+        let mut lookahead: Option<RichBlock> = None;
+
+        // Blocks of a batch are pulled concurrently and inserted together, a
+        // block failing to be pulled leaves the ones before it inserted
         loop {
-            let current_block = self
-                .adapter
-                .get_block_by_height(ctx.clone(), current_height)
+            let batch_end = cmp::min(current_height + SYNC_BATCH_BLOCKS + 1, remote_height);
+            let pull_from = current_height + if lookahead.is_some() { 2 } else { 1 };
+
+            let mut rich_blocks: Vec<RichBlock> = lookahead.take().into_iter().collect();
+            let mut pull_err = None;
+            let pulled = join_all(
+                (pull_from..=batch_end)
+                    .map(|height| self.get_rich_block_from_remote(ctx.clone(), height)),
+            )
+            .await;
+            for (height, res) in (pull_from..=batch_end).zip(pulled.into_iter()) {
+                match res {
+                    Ok(rich_block) => rich_blocks.push(rich_block),
+                    Err(e) => {
+                        pull_err = Some(sync_failed(height, e));
+                        break;
+                    }
+                }
+            }
+            if pull_err.is_none() && batch_end < remote_height {
+                lookahead = rich_blocks.pop();
+            }
+
+            let proofs = (0..rich_blocks.len())
+                .map(|i| {
+                    let next = rich_blocks.get(i + 1).or_else(|| lookahead.as_ref());
+                    self.proof_of(&rich_blocks[i].block, next)
+                })
+                .collect::<ProtocolResult<Vec<_>>>()?;
+            let blocks = rich_blocks
+                .into_iter()
+                .zip(proofs.into_iter())
+                .map(|(rich_block, proof)| (rich_block.block, rich_block.txs, proof))
+                .collect();
+
+            self.insert_sync_blocks(ctx.clone(), sync_status_agent.clone(), blocks)
                 .await?;
+            if let Some(e) = pull_err {
+                return Err(e);
+            }
+
+            current_height = sync_status_agent.to_inner().current_height;
+            if current_height >= remote_height {
+                return Ok(());
+            }
+        }
+    }
 
-            let next_height = current_height + 1;
+    /// Inserts consecutive synced blocks, each with its transactions and
+    /// proof, on top of `status_agent`. The blocks are checked against each
+    /// other and their transactions verified in parallel, then their proofs
+    /// are verified against the validators of the proof heights as they are
+    /// executed in order, and they are written to storage at once. The blocks
+    /// before the first one failing are still inserted, the error names its
+    /// height.
+    ///
+    /// Callers hold the commit lock.
+    pub async fn insert_sync_blocks(
+        &self,
+        ctx: Context,
+        status_agent: StatusAgent,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Proof)>,
+    ) -> ProtocolResult<()> {
+        let blocks = blocks
+            .into_iter()
+            .map(|(block, txs, proof)| (RichBlock { block, txs }, proof))
+            .collect::<Vec<_>>();
+        let snapshot = status_agent.to_inner();
+        let (blocks, mut err) = self.verify_blocks(&snapshot, blocks);
+        if err.is_some() {
+            self.adapter
+                .report_bad(ctx.clone(), PeerMisbehavior::InvalidSyncResponse);
+        }
 
-            let next_rich_block = self
-                .get_rich_block_from_remote(ctx.clone(), next_height)
-                .await?;
+        let mut executed = Vec::with_capacity(blocks.len());
+        let mut latest = None;
+        // The hash and the following metadata of each block executed here
+        let mut synced = HashMap::new();
+        let mut last_proof = snapshot.current_proof.clone();
+        for (rich_block, proof) in blocks.into_iter() {
+            if !self.stopper.is_running() {
+                err = Some(ConsensusError::Stopped.into());
+                break;
+            }
 
-            self.verify_block(&current_block, &next_rich_block.block)?;
+            let height = rich_block.block.header.height;
+            if let Err(e) = self
+                .verify_sync_proofs(ctx.clone(), &rich_block.block, &proof, &last_proof, &synced)
+                .await
+            {
+                if err.is_none() {
+                    self.adapter
+                        .report_bad(ctx.clone(), PeerMisbehavior::InvalidSyncResponse);
+                }
+                err = Some(sync_failed(height, e));
+                break;
+            }
 
-            self.commit_block(ctx.clone(), next_rich_block, sync_status_agent.clone())
-                .await?;
+            match self
+                .exec_sync_block(ctx.clone(), &status_agent, &rich_block, proof.clone())
+                .await
+            {
+                Ok((block_hash, metadata, receipts)) => {
+                    synced.insert(height, (block_hash, metadata.clone()));
+                    executed.push((rich_block.block, rich_block.txs, receipts));
+                    last_proof = proof.clone();
+                    latest = Some((height, metadata, proof));
+                }
+                Err(e) => {
+                    err = Some(sync_failed(height, e));
+                    break;
+                }
+            }
+        }
 
-            current_height = next_height;
+        if let Some((height, metadata, proof)) = latest {
+            let tx_hashes = executed
+                .iter()
+                .flat_map(|(block, _, _)| block.ordered_tx_hashes.iter().cloned())
+                .collect::<Vec<_>>();
 
-            if current_height >= remote_height {
-                return Ok(());
+            if let Err(e) = self.adapter.save_blocks_data(ctx.clone(), executed).await {
+                status_agent.replace(snapshot);
+                return Err(e);
             }
+            self.adapter.save_proof(ctx.clone(), proof).await?;
+            self.adapter.set_args(
+                ctx.clone(),
+                metadata.timeout_gap,
+                metadata.cycles_limit,
+                metadata.max_tx_size,
+            );
+
+            // If there are transactions in the trasnaction pool that have been on chain
+            // after this execution, make sure they are cleaned up.
+            self.adapter.flush_mempool(ctx.clone(), &tx_hashes).await?;
+            self.adapter.expire_mempool(ctx.clone(), height).await?;
+        }
+
+        match err {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    // The longest prefix of `blocks` that links to the status and block by
+    // block, with transactions that check out, and the error of the block
+    // after it.
+    fn verify_blocks(
+        &self,
+        status: &CurrentConsensusStatus,
+        mut blocks: Vec<(RichBlock, Proof)>,
+    ) -> (Vec<(RichBlock, Proof)>, Option<ProtocolError>) {
+        let mut parent_height = status.current_height;
+        let mut parent_hash = status.current_hash.clone();
+        let mut linked = 0;
+        let mut err = None;
+
+        for (rich_block, proof) in blocks.iter() {
+            match self.verify_block(parent_height, &parent_hash, &rich_block.block, proof) {
+                Ok(block_hash) => {
+                    parent_height = rich_block.block.header.height;
+                    parent_hash = block_hash;
+                    linked += 1;
+                }
+                Err(e) => {
+                    err = Some(e);
+                    break;
+                }
+            }
+        }
+
+        if let Some(index) = blocks[..linked]
+            .par_iter()
+            .position_first(|(rich_block, _)| verify_txs(rich_block).is_err())
+        {
+            err = verify_txs(&blocks[index].0).err();
+            linked = index;
         }
+
+        blocks.truncate(linked);
+        (blocks, err)
     }
 
     // TODO(yejiayu):
-    // - Verify the proof
     // - Verify the block header
-    // - Verify the transaction list
-    fn verify_block(&self, current_block: &Block, next_block: &Block) -> ProtocolResult<()> {
-        let block_hash = Hash::digest(current_block.encode_fixed()?);
+    fn verify_block(
+        &self,
+        parent_height: u64,
+        parent_hash: &Hash,
+        block: &Block,
+        proof: &Proof,
+    ) -> ProtocolResult<Hash> {
+        let header = &block.header;
+
+        if parent_hash != &header.pre_hash {
+            return Err(ConsensusError::InvalidSyncBlock {
+                height: header.height,
+                expect: parent_hash.clone(),
+                actual: header.pre_hash.clone(),
+            }
+            .into());
+        }
+
+        // A block carries the proof of its parent, the genesis block has none
+        if header.height > 1 {
+            if header.proof.height > parent_height {
+                return Err(ConsensusError::MissingProof(parent_height).into());
+            }
+
+            // Blocks proposed right after a sync used to carry an older proof,
+            // it is checked against the committed block of its height along
+            // with its signature
+            if header.proof.height == parent_height && &header.proof.block_hash != parent_hash {
+                return Err(ConsensusError::InvalidSyncBlock {
+                    height: header.height,
+                    expect: parent_hash.clone(),
+                    actual: header.proof.block_hash.clone(),
+                }
+                .into());
+            }
+        }
+
+        // The proof of the block itself, unless it is not known yet and the
+        // one the block carries stands in
+        let block_hash = Hash::digest(block.encode_fixed()?);
+        if proof.height == header.height {
+            if proof.block_hash != block_hash {
+                return Err(ConsensusError::InvalidSyncBlock {
+                    height: header.height,
+                    expect: block_hash,
+                    actual: proof.block_hash.clone(),
+                }
+                .into());
+            }
+        } else if proof != &header.proof {
+            return Err(ConsensusError::MissingProof(header.height).into());
+        }
+
+        Ok(block_hash)
+    }
+
+    // The proof of a block is carried by the block after it. Without that
+    // block, fall back to the proof the block itself carries until the right
+    // one is pulled.
+    fn proof_of(&self, block: &Block, next: Option<&RichBlock>) -> ProtocolResult<Proof> {
+        if let Some(next) = next {
+            let proof = &next.block.header.proof;
+
+            if proof.height == block.header.height
+                && proof.block_hash == Hash::digest(block.encode_fixed()?)
+            {
+                return Ok(proof.clone());
+            }
+        }
+
+        Ok(block.header.proof.clone())
+    }
+
+    // Checks the signatures of the proof the block carries, unless it is the
+    // proof checked along with the parent, and of the proof of the block
+    // itself. A carried proof older than the parent must be the one of the
+    // committed block of its height.
+    async fn verify_sync_proofs(
+        &self,
+        ctx: Context,
+        block: &Block,
+        proof: &Proof,
+        last_proof: &Proof,
+        synced: &HashMap<u64, (Hash, Metadata)>,
+    ) -> ProtocolResult<()> {
+        let header = &block.header;
 
-        if block_hash != next_block.header.pre_hash {
-            return Err(ConsensusError::SyncBlockHashErr(next_block.header.height).into());
+        if header.height > 1 && &header.proof != last_proof {
+            let (block_hash, _) = self
+                .committed_block(ctx.clone(), header.proof.height, synced)
+                .await?;
+            if header.proof.block_hash != block_hash {
+                return Err(ConsensusError::InvalidSyncBlock {
+                    height: header.height,
+                    expect: block_hash,
+                    actual: header.proof.block_hash.clone(),
+                }
+                .into());
+            }
+            self.verify_proof(ctx.clone(), &header.proof, synced)
+                .await?;
+        }
+
+        if proof.height == header.height {
+            self.verify_proof(ctx, proof, synced).await?;
         }
         Ok(())
     }
 
-    async fn commit_block(
+    // The proof of a height is signed by the validators the metadata after
+    // the block before it names
+    async fn verify_proof(
         &self,
         ctx: Context,
-        rich_block: RichBlock,
-        status_agent: StatusAgent,
+        proof: &Proof,
+        synced: &HashMap<u64, (Hash, Metadata)>,
     ) -> ProtocolResult<()> {
+        if proof.height == 0 {
+            return Err(ConsensusError::InvalidProof {
+                height: 0,
+                reason: "genesis has no proof".to_owned(),
+            }
+            .into());
+        }
+
+        let (_, metadata) = self.committed_block(ctx, proof.height - 1, synced).await?;
+        verify_proof_signature(proof, &metadata.verifier_list, &common_ref_of(&metadata)?)?;
+        Ok(())
+    }
+
+    // The hash of the committed block of `height` and the metadata after it,
+    // from the blocks executed in this round or from storage
+    async fn committed_block(
+        &self,
+        ctx: Context,
+        height: u64,
+        synced: &HashMap<u64, (Hash, Metadata)>,
+    ) -> ProtocolResult<(Hash, Metadata)> {
+        if let Some(committed) = synced.get(&height) {
+            return Ok(committed.clone());
+        }
+
+        let block = self
+            .adapter
+            .get_block_by_height(ctx.clone(), height)
+            .await?;
+        let metadata = self.adapter.get_metadata(
+            ctx,
+            block.header.state_root.clone(),
+            block.header.height,
+            block.header.timestamp,
+        )?;
+        Ok((Hash::digest(block.encode_fixed()?), metadata))
+    }
+
+    // Executes a verified block and moves the status past it
+    async fn exec_sync_block(
+        &self,
+        ctx: Context,
+        status_agent: &StatusAgent,
+        rich_block: &RichBlock,
+        proof: Proof,
+    ) -> ProtocolResult<(Hash, Metadata, Vec<Receipt>)> {
         let executor_resp = self
-            .exec_block(ctx.clone(), rich_block.clone(), status_agent.clone())
+            .exec_block(ctx.clone(), rich_block, status_agent.clone())
             .await?;
 
         let block = &rich_block.block;
         let block_hash = Hash::digest(block.encode_fixed()?);
 
         let metadata = self.adapter.get_metadata(
-            ctx.clone(),
+            ctx,
             block.header.state_root.clone(),
             block.header.height,
             block.header.timestamp,
         )?;
 
-        self.adapter.set_args(
-            ctx.clone(),
-            metadata.timeout_gap,
-            metadata.cycles_limit,
-            metadata.max_tx_size,
-        );
+        status_agent.update_by_commited(metadata.clone(), block.clone(), block_hash.clone(), proof);
 
-        status_agent.update_by_commited(
-            metadata,
-            block.clone(),
-            block_hash,
-            // TODO(@yejiayu): need to get proof for the next block
-            block.header.proof.clone(),
-        );
+        Ok((block_hash, metadata, executor_resp.receipts))
+    }
 
-        self.save_chain_data(
-            ctx.clone(),
-            rich_block.txs.clone(),
-            executor_resp.receipts.clone(),
-            rich_block.block.clone(),
-        )
-        .await?;
+    // Synced blocks take their proofs from the blocks after them, only the
+    // proof of the last one has to be pulled.
+    async fn sync_latest_proof(
+        &self,
+        ctx: Context,
+        status_agent: StatusAgent,
+    ) -> ProtocolResult<()> {
+        let status = status_agent.to_inner();
+        if status.current_proof.height == status.current_height {
+            return Ok(());
+        }
 
-        // If there are transactions in the trasnaction pool that have been on chain
-        // after this execution, make sure they are cleaned up.
-        self.adapter
-            .flush_mempool(ctx.clone(), &rich_block.block.ordered_tx_hashes)
+        let proof = self
+            .adapter
+            .get_proof_from_remote(ctx.clone(), status.current_height)
             .await?;
 
-        Ok(())
+        if proof.height != status.current_height {
+            return Err(ConsensusError::MissingProof(status.current_height).into());
+        }
+        if proof.block_hash != status.current_hash {
+            self.adapter
+                .report_bad(ctx, PeerMisbehavior::InvalidSyncResponse);
+            return Err(ConsensusError::InvalidSyncBlock {
+                height: status.current_height,
+                expect: status.current_hash,
+                actual: proof.block_hash,
+            }
+            .into());
+        }
+        if let Err(e) = self
+            .verify_proof(ctx.clone(), &proof, &HashMap::new())
+            .await
+        {
+            self.adapter
+                .report_bad(ctx, PeerMisbehavior::InvalidSyncResponse);
+            return Err(e);
+        }
+
+        status_agent.update_proof(proof.clone());
+        self.adapter.save_proof(ctx, proof).await
     }
 
     async fn get_rich_block_from_remote(
@@ -260,26 +609,10 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             .await
     }
 
-    async fn save_chain_data(
-        &self,
-        ctx: Context,
-        txs: Vec<SignedTransaction>,
-        receipts: Vec<Receipt>,
-        block: Block,
-    ) -> ProtocolResult<()> {
-        self.adapter.save_signed_txs(ctx.clone(), txs).await?;
-        self.adapter.save_receipts(ctx.clone(), receipts).await?;
-        self.adapter
-            .save_proof(ctx.clone(), block.header.proof.clone())
-            .await?;
-        self.adapter.save_block(ctx.clone(), block).await?;
-        Ok(())
-    }
-
     pub async fn exec_block(
         &self,
         ctx: Context,
-        rich_block: RichBlock,
+        rich_block: &RichBlock,
         status_agent: StatusAgent,
     ) -> ProtocolResult<ExecutorResp> {
         let current_status = status_agent.to_inner();
@@ -295,7 +628,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
 
         status_agent.update_by_executed(ExecutedInfo::new(
             rich_block.block.header.height,
-            rich_block.block.header.order_root,
+            rich_block.block.header.order_root.clone(),
             resp.clone(),
         ));
 
@@ -353,3 +686,47 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         Ok(true)
     }
 }
+
+fn sync_failed(height: u64, e: ProtocolError) -> ProtocolError {
+    ConsensusError::SyncFailed {
+        height,
+        reason: e.to_string(),
+    }
+    .into()
+}
+
+// The transactions of a block must be the ones it orders, each signed by its
+// sender
+fn verify_txs(rich_block: &RichBlock) -> ProtocolResult<()> {
+    let height = rich_block.block.header.height;
+    let tx_hashes = rich_block
+        .txs
+        .iter()
+        .map(|tx| tx.tx_hash.clone())
+        .collect::<Vec<_>>();
+    if tx_hashes != rich_block.block.ordered_tx_hashes {
+        return Err(sync_failed(
+            height,
+            ConsensusError::Other("transactions mismatch the ordered hashes".to_string()).into(),
+        ));
+    }
+
+    rich_block.txs.par_iter().try_for_each(|tx| {
+        let tx_hash = Hash::digest(tx.raw.encode_fixed()?);
+        let signed = Secp256k1::verify_signature(
+            tx.tx_hash.as_bytes().as_ref(),
+            tx.signature.as_ref(),
+            tx.pubkey.as_ref(),
+        )
+        .is_ok();
+
+        if tx_hash != tx.tx_hash || !signed {
+            return Err(ConsensusError::InvalidSyncTransaction {
+                height,
+                tx_hash: tx.tx_hash.clone(),
+            }
+            .into());
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::lock::Mutex;
+use overlord::types::{AggregatedSignature, Commit, Proof as OverlordProof};
+use overlord::{Codec, Consensus as Engine};
+use parking_lot::RwLock;
+
+use common_crypto::BlsPrivateKey;
+use common_merkle::Merkle;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{CommonConsensusAdapter, Context, NodeInfo, PeerMisbehavior};
+use protocol::types::{Address, Block, Hash, Pill};
+use protocol::Bytes;
+
+use crate::engine::ConsensusEngine;
+use crate::fixed_types::FixedPill;
+use crate::metrics::AtomicConsensusMetrics;
+use crate::status::StatusAgent;
+use crate::stop::Stopper;
+use crate::timestamp::TimestampRules;
+use crate::util::OverlordCrypto;
+use crate::wal::SignedTxsWAL;
+
+use super::synchronization::{
+    gen_remote_block_hashmap, gen_remote_tx_hashmap, genesis_status, mock_chained_rich_block,
+    MockCommonConsensusAdapter,
+};
+
+const PRIV_KEY: &str =
+    "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970";
+const TXS_WAL_PATH: &str = "./free-space/engine/txs";
+
+// The same commit delivered twice is committed once.
+#[test]
+fn test_commit_twice() {
+    let (engine, adapter, status_agent, blocks) = mock_engine();
+    let commit = mock_commit(&blocks[1]);
+
+    let status = block_on(engine.commit(Context::new(), 1, commit.clone())).unwrap();
+    assert_eq!(status.height, 2);
+
+    let status = block_on(engine.commit(Context::new(), 1, commit)).unwrap();
+    assert_eq!(status.height, 2);
+    assert_eq!(status_agent.to_inner().current_height, 1);
+    assert_eq!(
+        block_on(CommonConsensusAdapter::get_current_height(
+            &*adapter,
+            Context::new()
+        ))
+        .unwrap(),
+        1
+    );
+}
+
+// Another block committed at the committed height is rejected, the committed
+// one stays.
+#[test]
+fn test_commit_conflicting() {
+    let (engine, adapter, status_agent, blocks) = mock_engine();
+    block_on(engine.commit(Context::new(), 1, mock_commit(&blocks[1]))).unwrap();
+    let committed = status_agent.to_inner().current_hash;
+
+    let mut block = blocks[1].clone();
+    block.header.timestamp += 1;
+    let err = block_on(engine.commit(Context::new(), 1, mock_commit(&block))).unwrap_err();
+    assert!(err.to_string().contains("conflicts with the committed"));
+
+    let status = status_agent.to_inner();
+    assert_eq!(status.current_height, 1);
+    assert_eq!(status.current_hash, committed);
+    assert_eq!(
+        block_on(CommonConsensusAdapter::get_current_height(
+            &*adapter,
+            Context::new()
+        ))
+        .unwrap(),
+        1
+    );
+}
+
+// The proposal of a height is built once, and again once its transactions
+// leave the mempool or the height is committed.
+#[test]
+fn test_get_block_cached() {
+    let (engine, adapter, status_agent, blocks) = mock_engine();
+    adapter.set_mempool_txs(blocks[1].ordered_tx_hashes.clone());
+
+    let (pill, hash) = block_on(engine.get_block(Context::new(), 1)).unwrap();
+    thread::sleep(Duration::from_millis(10));
+    let (cached_pill, cached_hash) = block_on(engine.get_block(Context::new(), 1)).unwrap();
+    assert_eq!(cached_hash, hash);
+    assert_eq!(cached_pill.encode().unwrap(), pill.encode().unwrap());
+
+    adapter.set_mempool_txs(vec![]);
+    let (rebuilt_pill, rebuilt_hash) = block_on(engine.get_block(Context::new(), 1)).unwrap();
+    assert_ne!(rebuilt_hash, hash);
+    assert!(rebuilt_pill.inner.block.ordered_tx_hashes.is_empty());
+
+    block_on(engine.commit(Context::new(), 1, mock_commit(&blocks[1]))).unwrap();
+    let (next_pill, next_hash) = block_on(engine.get_block(Context::new(), 2)).unwrap();
+    assert_ne!(next_hash, rebuilt_hash);
+    assert_eq!(next_pill.inner.block.header.height, 2);
+    assert_eq!(
+        next_pill.inner.block.header.pre_hash,
+        status_agent.to_inner().current_hash
+    );
+}
+
+// Transactions the pool packaged over the limits of the status are left out
+// of the proposal, and out of its order root.
+#[test]
+fn test_get_block_within_limits() {
+    let (engine, adapter, status_agent, blocks) = mock_engine();
+    let tx_hashes = blocks[1].ordered_tx_hashes.clone();
+    adapter.set_mempool_txs(tx_hashes.clone());
+
+    // Every mock transaction has a cycles limit of 1
+    let mut status = status_agent.to_inner();
+    status.cycles_limit = 7;
+    status_agent.replace(status);
+
+    let (pill, _) = block_on(engine.get_block(Context::new(), 1)).unwrap();
+    let block = &pill.inner.block;
+    assert_eq!(block.ordered_tx_hashes, tx_hashes[..7].to_vec());
+    assert_eq!(
+        block.header.order_root,
+        Merkle::ordered_root(&tx_hashes[..7])
+    );
+
+    let (engine, adapter, status_agent, _) = mock_engine();
+    adapter.set_mempool_txs(tx_hashes.clone());
+    let mut status = status_agent.to_inner();
+    status.tx_num_limit = 4;
+    status_agent.replace(status);
+
+    let (pill, _) = block_on(engine.get_block(Context::new(), 1)).unwrap();
+    assert_eq!(pill.inner.block.ordered_tx_hashes, tx_hashes[..4].to_vec());
+}
+
+// A proposal holding more transactions than the status allows is rejected
+// before any of them are fetched.
+#[test]
+fn test_check_block_tx_num() {
+    let (engine, _adapter, status_agent, blocks) = mock_engine();
+    let mut status = status_agent.to_inner();
+    status.tx_num_limit = blocks[1].ordered_tx_hashes.len() as u64 - 1;
+    status_agent.replace(status);
+
+    let err = block_on(engine.check_block(
+        Context::new(),
+        1,
+        block_hash(&blocks[1]),
+        mock_pill(&blocks[1]),
+    ))
+    .unwrap_err();
+    assert!(err.to_string().contains("transaction number"));
+}
+
+// The execution roots of a valid proposal run ahead of a node whose executor
+// lags, the proposer isn't reported for that.
+#[test]
+fn test_check_block_lagging_not_reported() {
+    let (engine, adapter, _status_agent, blocks) = mock_engine();
+    let mut block = blocks[1].clone();
+    block.header.state_root = Hash::digest(Bytes::from("not executed here yet"));
+
+    let err =
+        block_on(engine.check_block(Context::new(), 1, block_hash(&block), mock_pill(&block)))
+            .unwrap_err();
+    assert!(err.to_string().contains("InvalidStatusVec"));
+    assert!(adapter.reports.read().is_empty());
+}
+
+#[test]
+fn test_check_block_wrong_parent_reported() {
+    let (engine, adapter, _status_agent, blocks) = mock_engine();
+    let mut block = blocks[1].clone();
+    block.header.pre_hash = Hash::digest(Bytes::from("not the parent"));
+
+    let err =
+        block_on(engine.check_block(Context::new(), 1, block_hash(&block), mock_pill(&block)))
+            .unwrap_err();
+    assert!(err.to_string().contains("InvalidPrevhash"));
+    assert_eq!(*adapter.reports.read(), vec![
+        PeerMisbehavior::InvalidProposal
+    ]);
+}
+
+fn block_hash(block: &Block) -> Bytes {
+    Hash::digest(block.encode_fixed().unwrap()).as_bytes()
+}
+
+fn mock_pill(block: &Block) -> FixedPill {
+    FixedPill {
+        inner: Pill {
+            block:          block.clone(),
+            propose_hashes: vec![],
+        },
+    }
+}
+
+fn mock_engine() -> (
+    ConsensusEngine<MockCommonConsensusAdapter>,
+    Arc<MockCommonConsensusAdapter>,
+    StatusAgent,
+    Vec<Block>,
+) {
+    let list_rich_block = mock_chained_rich_block(5, 1);
+    let genesis_block = list_rich_block[0].block.clone();
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        gen_remote_block_hashmap(list_rich_block.clone()),
+        Arc::new(RwLock::new(HashMap::new())),
+        gen_remote_tx_hashmap(list_rich_block.clone()),
+    ));
+    let status_agent = StatusAgent::new(genesis_status(&genesis_block));
+
+    let priv_key = BlsPrivateKey::try_from(hex::decode(PRIV_KEY).unwrap().as_ref()).unwrap();
+    let crypto = OverlordCrypto::new(priv_key, HashMap::new(), "muta".into());
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    let engine = ConsensusEngine::new(
+        status_agent.clone(),
+        NodeInfo {
+            chain_id:     genesis_block.header.chain_id.clone(),
+            self_address: Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83").unwrap(),
+        },
+        Arc::new(SignedTxsWAL::new(TXS_WAL_PATH)),
+        Arc::clone(&adapter),
+        Arc::new(crypto),
+        lock,
+        stopper,
+        TimestampRules::default(),
+        Arc::new(AtomicConsensusMetrics::default()),
+        None,
+    );
+
+    let blocks = list_rich_block
+        .into_iter()
+        .map(|rich_block| rich_block.block)
+        .collect();
+    (engine, adapter, status_agent, blocks)
+}
+
+fn mock_commit(block: &Block) -> Commit<FixedPill> {
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+    Commit {
+        height:  block.header.height,
+        content: FixedPill {
+            inner: Pill {
+                block:          block.clone(),
+                propose_hashes: vec![],
+            },
+        },
+        proof:   OverlordProof {
+            height:     block.header.height,
+            round:      0,
+            block_hash: block_hash.as_bytes(),
+            signature:  AggregatedSignature {
+                signature:      Bytes::new(),
+                address_bitmap: Bytes::new(),
+            },
+        },
+    }
+}
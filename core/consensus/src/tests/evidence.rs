@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use overlord::Crypto;
+
+use common_crypto::{BlsPrivateKey, BlsPublicKey};
+use protocol::types::Hash;
+use protocol::Bytes;
+
+use crate::evidence::{EvidencePool, SignedKind, SignedMessage};
+use crate::util::OverlordCrypto;
+
+const PRIV_KEY: &str =
+    "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970";
+const PUB_KEY: &str = "041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612";
+
+fn mock_crypto(signer: &Bytes) -> OverlordCrypto {
+    let priv_key = BlsPrivateKey::try_from(hex::decode(PRIV_KEY).unwrap().as_ref()).unwrap();
+    let pub_key = BlsPublicKey::try_from(hex::decode(PUB_KEY).unwrap().as_ref()).unwrap();
+    let mut addr_pubkey = HashMap::new();
+    addr_pubkey.insert(signer.clone(), pub_key);
+    OverlordCrypto::new(priv_key, addr_pubkey, "muta".into())
+}
+
+fn mock_signed(crypto: &OverlordCrypto, signer: &Bytes, block: &str) -> SignedMessage {
+    let block_hash = Hash::digest(Bytes::from(block.to_owned())).as_bytes();
+    let hash = crypto.hash(block_hash.clone());
+    SignedMessage {
+        kind: SignedKind::Prevote,
+        height: 10,
+        round: 0,
+        signer: signer.clone(),
+        block_hash,
+        signature: crypto.sign(hash.clone()).unwrap(),
+        hash,
+    }
+}
+
+fn verify(crypto: &OverlordCrypto) -> impl Fn(&SignedMessage) -> bool + '_ {
+    move |message| {
+        crypto
+            .verify_signature(
+                message.signature.clone(),
+                message.hash.clone(),
+                message.signer.clone(),
+            )
+            .is_ok()
+    }
+}
+
+#[test]
+fn test_double_sign_evidence() {
+    let signer = Bytes::from(vec![1u8; 20]);
+    let crypto = mock_crypto(&signer);
+    let pool = EvidencePool::new(16, 16);
+    let authority = vec![signer.clone()];
+
+    let first = mock_signed(&crypto, &signer, "block a");
+    let second = mock_signed(&crypto, &signer, "block b");
+    let third = mock_signed(&crypto, &signer, "block c");
+
+    assert!(pool
+        .observe(first.clone(), &authority, verify(&crypto))
+        .is_none());
+    assert!(pool
+        .observe(first.clone(), &authority, verify(&crypto))
+        .is_none());
+
+    let evidence = pool
+        .observe(second.clone(), &authority, verify(&crypto))
+        .unwrap();
+    assert_eq!(evidence.first, first);
+    assert_eq!(evidence.second, second);
+
+    // One piece of evidence per slot
+    assert!(pool.observe(third, &authority, verify(&crypto)).is_none());
+    assert_eq!(pool.pending_evidence().len(), 1);
+}
+
+#[test]
+fn test_forged_message_is_no_evidence() {
+    let signer = Bytes::from(vec![1u8; 20]);
+    let crypto = mock_crypto(&signer);
+    let pool = EvidencePool::new(16, 16);
+    let authority = vec![signer.clone()];
+
+    let first = mock_signed(&crypto, &signer, "block a");
+    let mut forged = mock_signed(&crypto, &signer, "block b");
+    forged.signature = first.signature.clone();
+
+    pool.observe(first, &authority, verify(&crypto));
+    assert!(pool.observe(forged, &authority, verify(&crypto)).is_none());
+    assert!(pool.pending_evidence().is_empty());
+}
+
+#[test]
+fn test_signer_outside_authority() {
+    let signer = Bytes::from(vec![1u8; 20]);
+    let crypto = mock_crypto(&signer);
+    let pool = EvidencePool::new(16, 16);
+    let authority = vec![Bytes::from(vec![2u8; 20])];
+
+    // Signed twice, but by no validator of the current height
+    let first = mock_signed(&crypto, &signer, "block a");
+    let second = mock_signed(&crypto, &signer, "block b");
+    assert!(pool.observe(first, &authority, verify(&crypto)).is_none());
+    assert!(pool.observe(second, &authority, verify(&crypto)).is_none());
+    assert!(pool.is_empty());
+    assert!(pool.pending_evidence().is_empty());
+}
+
+#[test]
+fn test_restore_evidence() {
+    let signer = Bytes::from(vec![1u8; 20]);
+    let crypto = mock_crypto(&signer);
+    let pool = EvidencePool::new(16, 16);
+    let authority = vec![signer.clone()];
+
+    let first = mock_signed(&crypto, &signer, "block a");
+    let second = mock_signed(&crypto, &signer, "block b");
+    pool.observe(first, &authority, verify(&crypto));
+    let evidence = pool.observe(second, &authority, verify(&crypto)).unwrap();
+
+    // Nothing was saved before the first evidence
+    let restarted = EvidencePool::new(16, 16);
+    restarted.restore(&[]).unwrap();
+    assert!(restarted.pending_evidence().is_empty());
+
+    restarted.restore(&pool.encode().unwrap()).unwrap();
+    assert_eq!(restarted.pending_evidence(), vec![evidence]);
+    assert!(restarted.restore(b"corrupted").is_err());
+}
+
+#[test]
+fn test_prune_committed_heights() {
+    let signer = Bytes::from(vec![1u8; 20]);
+    let crypto = mock_crypto(&signer);
+    let pool = EvidencePool::new(16, 16);
+    let authority = vec![signer.clone()];
+
+    pool.observe(
+        mock_signed(&crypto, &signer, "block a"),
+        &authority,
+        verify(&crypto),
+    );
+    assert_eq!(pool.len(), 1);
+
+    pool.prune(9);
+    assert_eq!(pool.len(), 1);
+    pool.prune(10);
+    assert!(pool.is_empty());
+}
+
+fn fake_signed(height: u64, signer: u32) -> SignedMessage {
+    SignedMessage {
+        kind: SignedKind::Prevote,
+        height,
+        round: 0,
+        signer: Bytes::from(signer.to_be_bytes().to_vec()),
+        block_hash: Bytes::new(),
+        hash: Bytes::new(),
+        signature: Bytes::new(),
+    }
+}
+
+#[test]
+fn test_window_bounds() {
+    let pool = EvidencePool::new(1000, 100);
+    let authority = (0..5000u32)
+        .map(|i| fake_signed(0, i).signer)
+        .collect::<Vec<_>>();
+
+    // A flood at scattered heights ahead stays within the caps
+    for i in 0..5000u32 {
+        pool.observe(
+            fake_signed(10 + u64::from(i % 37) * 1000, i),
+            &authority,
+            |_| false,
+        );
+        assert!(pool.len() <= 1000);
+    }
+    assert_eq!(pool.len(), 1000);
+
+    // The heights farthest ahead make room for the one being decided
+    for i in 0..200u32 {
+        pool.observe(fake_signed(1, i), &authority, |_| false);
+    }
+    assert_eq!(pool.len(), 1000);
+    pool.prune(1);
+    assert_eq!(pool.len(), 900);
+
+    // Committed heights are cleared
+    pool.prune(10);
+    assert_eq!(pool.len(), 800);
+    pool.prune(u64::max_value() - 1);
+    assert!(pool.is_empty());
+}
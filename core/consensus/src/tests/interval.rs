@@ -0,0 +1,45 @@
+use crate::interval::AdaptiveInterval;
+
+const BASE_INTERVAL: u64 = 3000;
+
+fn adaptive() -> AdaptiveInterval {
+    AdaptiveInterval::new(500, 6000, 1000).unwrap()
+}
+
+#[test]
+fn test_interval_follows_blocks() {
+    let adaptive = adaptive();
+
+    // Full blocks shrink the next interval, empty ones relax it
+    assert_eq!(adaptive.next(BASE_INTERVAL, 1000), 2250);
+    assert_eq!(adaptive.next(BASE_INTERVAL, 5000), 2250);
+    assert_eq!(adaptive.next(BASE_INTERVAL, 0), 3750);
+
+    // A block below the busy mark keeps the interval of the metadata
+    assert_eq!(adaptive.next(BASE_INTERVAL, 10), BASE_INTERVAL);
+}
+
+#[test]
+fn test_interval_stays_in_bounds() {
+    let adaptive = adaptive();
+    for base in [0, 100, 600, 3000, 5000, 100_000].iter() {
+        for txs in [0, 10, 1000].iter() {
+            let interval = adaptive.next(*base, *txs);
+            assert!(interval >= adaptive.min_interval && interval <= adaptive.max_interval);
+        }
+    }
+
+    // A base interval out of bounds is pulled in
+    assert_eq!(adaptive.next(100, 10), adaptive.min_interval);
+    assert_eq!(adaptive.next(100_000, 10), adaptive.max_interval);
+
+    // A zero minimum is a bound like any other
+    let adaptive = AdaptiveInterval::new(0, 6000, 1000).unwrap();
+    assert_eq!(adaptive.next(BASE_INTERVAL, 1000), 2250);
+}
+
+#[test]
+fn test_interval_bounds_checked() {
+    assert!(AdaptiveInterval::new(6000, 500, 1000).is_err());
+    assert!(AdaptiveInterval::new(500, 500, 1000).is_ok());
+}
@@ -0,0 +1,137 @@
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use overlord::types::Node;
+use parking_lot::Mutex;
+
+use common_crypto::{Crypto, Secp256k1, Secp256k1PrivateKey};
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::Context;
+use protocol::types::{Address, Hash};
+
+use crate::liveness::{JailHook, JailValidatorHook, LivenessTracker};
+use crate::status::StatusAgent;
+
+use super::synchronization::{genesis_status, mock_chained_rich_block};
+
+const ADMIN_PRIV_KEY: &str = "5ec982173d54d830b6789cbbbe43eaa2853a5ff752d1ebc1b266cf9790314f8a";
+
+#[derive(Default)]
+struct MockJailHook {
+    jailed: Mutex<Vec<Address>>,
+}
+
+impl JailHook for MockJailHook {
+    fn jail(&self, _ctx: Context, address: Address) {
+        self.jailed.lock().push(address);
+    }
+}
+
+fn mock_authority() -> Vec<Node> {
+    let mut authority = (1..=4u8)
+        .map(|i| Node {
+            address:        Address::from_bytes(vec![i; 20].into()).unwrap().as_bytes(),
+            propose_weight: 1,
+            vote_weight:    1,
+        })
+        .collect::<Vec<_>>();
+    authority.sort();
+    authority
+}
+
+#[test]
+fn test_jail_once_at_threshold() {
+    let hook = Arc::new(MockJailHook::default());
+    let tracker = LivenessTracker::new(3, Arc::clone(&hook) as Arc<dyn JailHook>);
+    let authority = mock_authority();
+
+    // The third validator never signs
+    for _ in 0..3 {
+        tracker.observe(Context::new(), &authority, &[0b1101_0000]);
+    }
+    assert_eq!(tracker.missed(&authority[2].address), 3);
+    assert!(hook.jailed.lock().is_empty());
+
+    tracker.observe(Context::new(), &authority, &[0b1101_0000]);
+    let jailed = hook.jailed.lock().clone();
+    assert_eq!(jailed.len(), 1);
+    assert_eq!(jailed[0].as_bytes(), authority[2].address);
+
+    for _ in 0..10 {
+        tracker.observe(Context::new(), &authority, &[0b1101_0000]);
+    }
+    assert_eq!(hook.jailed.lock().len(), 1);
+}
+
+#[test]
+fn test_signing_resets_missed() {
+    let hook = Arc::new(MockJailHook::default());
+    let tracker = LivenessTracker::new(3, Arc::clone(&hook) as Arc<dyn JailHook>);
+    let authority = mock_authority();
+
+    for _ in 0..3 {
+        tracker.observe(Context::new(), &authority, &[0b1110_0000]);
+    }
+    tracker.observe(Context::new(), &authority, &[0b1111_0000]);
+    assert_eq!(tracker.missed(&authority[3].address), 0);
+
+    for _ in 0..3 {
+        tracker.observe(Context::new(), &authority, &[0b1110_0000]);
+    }
+    assert!(hook.jailed.lock().is_empty());
+}
+
+#[test]
+fn test_restore_missed() {
+    let hook = Arc::new(MockJailHook::default());
+    let tracker = LivenessTracker::new(3, Arc::clone(&hook) as Arc<dyn JailHook>);
+    let authority = mock_authority();
+
+    for _ in 0..3 {
+        tracker.observe(Context::new(), &authority, &[0b1101_0000]);
+    }
+    let encoded = tracker.encode().unwrap();
+
+    let restarted = LivenessTracker::new(3, Arc::clone(&hook) as Arc<dyn JailHook>);
+    restarted.restore(&[]).unwrap();
+    assert_eq!(restarted.missed(&authority[2].address), 0);
+    restarted.restore(&encoded).unwrap();
+    assert_eq!(restarted.missed(&authority[2].address), 3);
+
+    // The restored count still reaches the threshold
+    restarted.observe(Context::new(), &authority, &[0b1101_0000]);
+    assert_eq!(hook.jailed.lock().len(), 1);
+}
+
+#[test]
+fn test_jail_validator_tx() {
+    let genesis_block = mock_chained_rich_block(1, 0)[0].block.clone();
+    let status_agent = StatusAgent::new(genesis_status(&genesis_block));
+    let priv_key =
+        Secp256k1PrivateKey::try_from(hex::decode(ADMIN_PRIV_KEY).unwrap().as_ref()).unwrap();
+    let hook = JailValidatorHook::new(
+        Arc::new(()),
+        status_agent.clone(),
+        Hash::from_empty(),
+        20,
+        priv_key,
+    );
+    let address = Address::from_bytes(vec![3u8; 20].into()).unwrap();
+
+    let stx = hook.jail_validator_tx(&address).unwrap();
+    let height = status_agent.to_inner().current_height;
+    assert_eq!(stx.raw.request.service_name, "metadata");
+    assert_eq!(stx.raw.request.method, "jail_validator");
+    assert_eq!(
+        stx.raw.request.payload,
+        format!("{{\"address\":\"{}\"}}", address.as_hex())
+    );
+    assert_eq!(stx.raw.timeout, height + 20);
+    assert_eq!(stx.tx_hash, Hash::digest(stx.raw.encode_fixed().unwrap()));
+    assert!(Secp256k1::verify_signature(
+        stx.tx_hash.as_bytes().as_ref(),
+        stx.signature.as_ref(),
+        stx.pubkey.as_ref()
+    )
+    .is_ok());
+}
@@ -0,0 +1,40 @@
+use std::time::Duration;
+
+use crate::metrics::{AtomicConsensusMetrics, CheckRejection, ConsensusMetrics};
+
+#[test]
+fn test_commit_metrics() {
+    let metrics = AtomicConsensusMetrics::default();
+
+    metrics.on_commit(1, 0, 10, Some(Duration::from_millis(300)));
+    metrics.on_commit(2, 2, 4, Some(Duration::from_millis(900)));
+    // Committed without building or checking a block of the height
+    metrics.on_commit(3, 0, 0, None);
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.committed_heights, 3);
+    assert_eq!(snapshot.latest_height, 3);
+    assert_eq!(snapshot.rounds, 5);
+    assert_eq!(snapshot.committed_txs, 14);
+    assert_eq!(snapshot.latest_block_txs, 0);
+    assert_eq!(snapshot.commit_latency_ms, 1200);
+    assert_eq!(snapshot.latest_commit_latency_ms, 900);
+}
+
+#[test]
+fn test_check_block_metrics() {
+    let metrics = AtomicConsensusMetrics::default();
+
+    metrics.on_check_block(Duration::from_millis(20));
+    metrics.on_check_block(Duration::from_millis(30));
+    metrics.on_rejection(CheckRejection::Roots);
+    metrics.on_rejection(CheckRejection::Txs);
+    metrics.on_rejection(CheckRejection::Txs);
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.checked_blocks, 2);
+    assert_eq!(snapshot.check_block_ms, 50);
+    assert_eq!(snapshot.rejected_roots, 1);
+    assert_eq!(snapshot.rejected_timestamp, 0);
+    assert_eq!(snapshot.rejected_txs, 2);
+}
@@ -1 +1,9 @@
+mod engine;
+mod evidence;
+mod interval;
+mod liveness;
+mod metrics;
+mod status;
+mod stop;
 mod synchronization;
+mod timestamp;
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use futures::executor::block_on;
+
+use core_storage::adapter::memory::MemoryAdapter;
+use core_storage::ImplStorage;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::Storage;
+use protocol::types::{Block, Hash};
+
+use crate::status::restore_latest_proof;
+
+use super::synchronization::mock_chained_rich_block;
+
+// The proof kept in storage is restored if it is the proof of the latest
+// block, the proof in its header otherwise.
+#[test]
+fn test_restore_latest_proof() {
+    let blocks = mock_chained_rich_block(3, 1)
+        .into_iter()
+        .map(|rich_block| rich_block.block)
+        .collect::<Vec<Block>>();
+    let latest_block = &blocks[3];
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    // Nothing kept yet
+    let proof = block_on(restore_latest_proof(&storage, latest_block)).unwrap();
+    assert_eq!(proof, latest_block.header.proof);
+
+    // The proof of the parent, as before the latest block committed
+    block_on(storage.update_latest_proof(blocks[2].header.proof.clone())).unwrap();
+    let proof = block_on(restore_latest_proof(&storage, latest_block)).unwrap();
+    assert_eq!(proof, latest_block.header.proof);
+
+    // The proof of the latest block
+    let mut latest_proof = latest_block.header.proof.clone();
+    latest_proof.height = latest_block.header.height;
+    latest_proof.block_hash = Hash::digest(latest_block.encode_fixed().unwrap());
+    block_on(storage.update_latest_proof(latest_proof.clone())).unwrap();
+    let proof = block_on(restore_latest_proof(&storage, latest_block)).unwrap();
+    assert_eq!(proof, latest_proof);
+
+    // A proof of the same height for another block
+    let mut forked_proof = latest_proof;
+    forked_proof.block_hash = Hash::digest(blocks[2].encode_fixed().unwrap());
+    block_on(storage.update_latest_proof(forked_proof)).unwrap();
+    let proof = block_on(restore_latest_proof(&storage, latest_block)).unwrap();
+    assert_eq!(proof, latest_block.header.proof);
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::lock::Mutex;
+
+use crate::stop::Stopper;
+
+#[test]
+fn test_stop_waits_for_height_in_progress() {
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    assert!(stopper.is_running());
+
+    // A commit holds the lock past the timeout
+    let in_progress = block_on(lock.lock());
+    assert!(!block_on(stopper.stop(Duration::from_millis(100))));
+    assert!(!stopper.is_running());
+
+    drop(in_progress);
+    assert!(block_on(stopper.stop(Duration::from_millis(100))));
+    assert!(!stopper.is_running());
+
+    stopper.resume();
+    assert!(stopper.is_running());
+}
@@ -1,23 +1,44 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use futures::executor::block_on;
 use futures::lock::Mutex;
+use overlord::types::{Vote, VoteType};
 use parking_lot::RwLock;
 
+use common_crypto::{
+    BlsPrivateKey, Crypto, HashValue, PrivateKey, PublicKey, Secp256k1, Secp256k1PrivateKey,
+    Signature, ToPublicKey,
+};
 use common_merkle::Merkle;
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{CommonConsensusAdapter, Synchronization, SynchronizationAdapter};
-use protocol::traits::{Context, ExecutorParams, ExecutorResp, ServiceResponse};
+use protocol::traits::{
+    CommonConsensusAdapter, ConsensusAdapter, Synchronization, SynchronizationAdapter,
+};
+use protocol::traits::{
+    Context, ExecutorParams, ExecutorResp, MessageTarget, MixedTxHashes, PeerMisbehavior,
+    ServiceResponse,
+};
 use protocol::types::{
     Address, Block, BlockHeader, Bytes, Hash, Hex, MerkleRoot, Metadata, Proof, RawTransaction,
     Receipt, ReceiptResponse, SignedTransaction, TransactionRequest, Validator, ValidatorExtend,
 };
 use protocol::ProtocolResult;
 
+use crate::interval::AdaptiveInterval;
 use crate::status::{CurrentConsensusStatus, StatusAgent};
+use crate::stop::Stopper;
 use crate::synchronization::{OverlordSynchronization, RichBlock};
+use crate::validators::LastValidators;
+use crate::ConsensusError;
+
+const TX_PRIV_KEY: &str = "5ec982173d54d830b6789cbbbe43eaa2853a5ff752d1ebc1b266cf9790314f8a";
+// The only validator, signing every proof
+const BLS_PRIV_KEY: &str = "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970";
+const BLS_PUB_KEY: &str = "0x041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612";
 
 // Test the blocks gap from 1 to 10.
 #[test]
@@ -45,33 +66,16 @@ fn sync_gap_test() {
             local_transactions,
             remote_transactions,
         ));
-        let block_hash = Hash::digest(genesis_block.encode_fixed().unwrap());
-        let status = CurrentConsensusStatus {
-            cycles_price:               1,
-            cycles_limit:               300_000_000,
-            current_height:             genesis_block.header.height,
-            exec_height:                genesis_block.header.exec_height,
-            current_hash:               block_hash,
-            list_logs_bloom:            vec![],
-            list_confirm_root:          vec![],
-            latest_commited_state_root: genesis_block.header.state_root.clone(),
-            list_state_root:            vec![],
-            list_receipt_root:          vec![],
-            list_cycles_used:           vec![],
-            current_proof:              genesis_block.header.proof,
-            validators:                 genesis_block.header.validators,
-            consensus_interval:         3000,
-            propose_ratio:              15,
-            prevote_ratio:              10,
-            precommit_ratio:            10,
-            brake_ratio:                3,
-            tx_num_limit:               20000,
-            max_tx_size:                1_073_741_824,
-        };
-        let status_agent = StatusAgent::new(status);
+        let status_agent = StatusAgent::new(genesis_status(&genesis_block));
         let lock = Arc::new(Mutex::new(()));
-        let sync =
-            OverlordSynchronization::new(5000, Arc::clone(&adapter), status_agent.clone(), lock);
+        let stopper = Stopper::new(Arc::clone(&lock));
+        let sync = OverlordSynchronization::new(
+            5000,
+            Arc::clone(&adapter),
+            status_agent.clone(),
+            lock,
+            stopper,
+        );
         block_on(sync.receive_remote_block(Context::new(), max_height / 2)).unwrap();
 
         let status = status_agent.to_inner();
@@ -87,6 +91,404 @@ fn sync_gap_test() {
     }
 }
 
+// A block that does not link to its parent stops the sync right before it.
+#[test]
+fn sync_corrupted_block_test() {
+    let status = sync_corrupted_block(|block| block.header.pre_hash = Hash::digest(Bytes::new()));
+    assert_eq!(status.current_height, 24);
+
+    let status = sync_corrupted_block(|block| block.header.proof.height = 25);
+    assert_eq!(status.current_height, 24);
+
+    let status = sync_corrupted_block(|block| block.header.proof.block_hash = Hash::from_empty());
+    assert_eq!(status.current_height, 24);
+}
+
+// Blocks carrying the proof of their grandparent are on chain already, they
+// still sync.
+#[test]
+fn sync_stale_proof_test() {
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    list_rich_block[25].block.header.proof = list_rich_block[24].block.header.proof.clone();
+    relink_rich_blocks(&mut list_rich_block);
+
+    let (status, adapter) = sync_rich_blocks(list_rich_block, 50);
+    assert_eq!(status.current_height, 50);
+    assert_eq!(status.current_proof.height, 50);
+    assert!(adapter.reports.read().is_empty());
+}
+
+// An older proof carried by a block must be signed for the committed block of
+// its height, or the sync stops right before the block.
+#[test]
+fn sync_forged_stale_proof_test() {
+    let forge = |proof: &mut Proof| {
+        proof.signature = mock_proof(proof.height, Hash::digest(Bytes::from("forged"))).signature
+    };
+    let status = sync_stale_proof_block(forge);
+    assert_eq!(status.current_height, 24);
+
+    let unsigned = |proof: &mut Proof| proof.bitmap = Bytes::new();
+    let status = sync_stale_proof_block(unsigned);
+    assert_eq!(status.current_height, 24);
+
+    let forked =
+        |proof: &mut Proof| *proof = mock_proof(proof.height, Hash::digest(Bytes::from("forked")));
+    let status = sync_stale_proof_block(forked);
+    assert_eq!(status.current_height, 24);
+
+    // Neither can a block carry the genesis proof
+    let genesis = |proof: &mut Proof| {
+        *proof = Proof {
+            height:     0,
+            round:      0,
+            block_hash: Hash::from_empty(),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::new(),
+        }
+    };
+    let status = sync_stale_proof_block(genesis);
+    assert_eq!(status.current_height, 24);
+}
+
+// A proof not signed by the validators of its height leaves the block it
+// proves out of the sync.
+#[test]
+fn sync_unsigned_proof_test() {
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    list_rich_block[25].block.header.proof.signature =
+        list_rich_block[24].block.header.proof.signature.clone();
+
+    let (status, adapter) = sync_rich_blocks(list_rich_block, 50);
+    assert_eq!(status.current_height, 23);
+    assert_eq!(status.current_proof.height, 23);
+    assert_eq!(*adapter.reports.read(), vec![
+        PeerMisbehavior::InvalidSyncResponse
+    ]);
+}
+
+// Every synced block gets its own proof, taken from the block after it, the
+// proof of the last one of a batch is saved and the proof of the tip pulled.
+#[test]
+fn sync_proof_test() {
+    let list_rich_block = mock_chained_rich_block(50, 1);
+    let (status, adapter) = sync_rich_blocks(list_rich_block, 50);
+    assert_eq!(status.current_height, 50);
+    assert_eq!(status.current_proof.height, 50);
+    assert_eq!(status.current_proof.block_hash, status.current_hash);
+
+    let proofs = adapter.proofs.read();
+    assert_eq!(proofs.last(), Some(&status.current_proof));
+    assert!(proofs.len() > 2);
+    for proof in proofs.iter() {
+        let block = block_on(adapter.get_block_by_height(Context::new(), proof.height)).unwrap();
+        assert_eq!(
+            proof.block_hash,
+            Hash::digest(block.encode_fixed().unwrap())
+        );
+    }
+}
+
+// A stopped node syncs nothing, once resumed it continues from its height.
+#[test]
+fn sync_stop_test() {
+    let list_rich_block = mock_chained_rich_block(50, 1);
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        remote_blocks,
+        Arc::new(RwLock::new(HashMap::new())),
+        gen_remote_tx_hashmap(list_rich_block),
+    ));
+    let status_agent = StatusAgent::new(genesis_status(&genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        lock,
+        stopper.clone(),
+    );
+
+    block_on(sync.receive_remote_block(Context::new(), 25)).unwrap();
+    assert_eq!(status_agent.to_inner().current_height, 25);
+
+    assert!(block_on(stopper.stop(Duration::from_millis(100))));
+    block_on(sync.receive_remote_block(Context::new(), 50)).unwrap();
+    assert_eq!(status_agent.to_inner().current_height, 25);
+    assert_eq!(*adapter.latest_height.read(), 25);
+
+    stopper.resume();
+    block_on(sync.receive_remote_block(Context::new(), 50)).unwrap();
+    let status = status_agent.to_inner();
+    assert_eq!(status.current_height, 50);
+    let block = block_on(adapter.get_block_by_height(Context::new(), 50)).unwrap();
+    assert_sync(status, block);
+}
+
+// Synced nodes adapt the interval to the last synced block like the others.
+#[test]
+fn sync_adaptive_interval_test() {
+    let list_rich_block = mock_chained_rich_block(20, 1);
+    let genesis_block = list_rich_block[0].block.clone();
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        gen_remote_block_hashmap(list_rich_block.clone()),
+        Arc::new(RwLock::new(HashMap::new())),
+        gen_remote_tx_hashmap(list_rich_block),
+    ));
+    let mut status = genesis_status(&genesis_block);
+    status.adaptive_interval = Some(AdaptiveInterval::new(500, 6000, 10).unwrap());
+    let status_agent = StatusAgent::new(status);
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        lock,
+        stopper,
+    );
+    block_on(sync.receive_remote_block(Context::new(), 20)).unwrap();
+
+    // Every mock block holds 10 transactions, the metadata interval is 3000
+    let status = status_agent.to_inner();
+    assert_eq!(status.current_height, 20);
+    assert_eq!(status.consensus_interval, 2250);
+}
+
+// Validators come from the stored block, from the metadata past the latest
+// block, and from the ones read before for the same height while storage
+// fails.
+#[test]
+fn last_validators_test() {
+    let list_rich_block = mock_chained_rich_block(10, 1);
+    let (_, adapter) = sync_rich_blocks(list_rich_block, 5);
+    adapter
+        .loacl_blocks
+        .write()
+        .get_mut(&5)
+        .unwrap()
+        .header
+        .validators[0]
+        .propose_weight = 1;
+
+    let last_validators = LastValidators::new();
+    let stored = block_on(last_validators.get(&*adapter, Context::new(), 5)).unwrap();
+    assert_eq!(stored[0].propose_weight, 1);
+
+    let unstored = block_on(last_validators.get(&*adapter, Context::new(), 6)).unwrap();
+    assert_eq!(unstored[0].propose_weight, 0);
+
+    *adapter.fail_storage.write() = true;
+    let cached = block_on(last_validators.get(&*adapter, Context::new(), 5)).unwrap();
+    assert_eq!(cached, stored);
+    assert!(block_on(last_validators.get(&*adapter, Context::new(), 4)).is_err());
+}
+
+// A batch of 50 blocks is inserted at once, each block with its own proof.
+#[test]
+fn insert_sync_blocks_test() {
+    let list_rich_block = mock_chained_rich_block(50, 1);
+    let (status, adapter) = insert_sync_blocks(list_rich_block, true);
+    assert_eq!(status.current_height, 50);
+    assert_eq!(status.current_proof.height, 50);
+    assert_eq!(status.current_proof.block_hash, status.current_hash);
+    assert_eq!(*adapter.latest_height.read(), 50);
+    assert_eq!(adapter.local_transactions.read().len(), 500);
+    assert_eq!(adapter.proofs.read().len(), 1);
+    assert!(adapter.reports.read().is_empty());
+
+    let block = block_on(adapter.get_block_by_height(Context::new(), 50)).unwrap();
+    assert_sync(status, block);
+}
+
+// A corrupted block in the middle of a batch leaves the blocks before it
+// inserted.
+#[test]
+fn insert_sync_blocks_corrupted_test() {
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    list_rich_block[25].txs[3].signature = list_rich_block[25].txs[4].signature.clone();
+    let (status, adapter) = insert_sync_blocks(list_rich_block.clone(), false);
+    assert_eq!(status.current_height, 24);
+    assert_eq!(*adapter.latest_height.read(), 24);
+    assert_eq!(adapter.local_transactions.read().len(), 240);
+    assert_eq!(*adapter.reports.read(), vec![
+        PeerMisbehavior::InvalidSyncResponse
+    ]);
+
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    list_rich_block[25].block.header.pre_hash = Hash::digest(Bytes::new());
+    let (status, adapter) = insert_sync_blocks(list_rich_block, false);
+    assert_eq!(status.current_height, 24);
+    assert_eq!(*adapter.latest_height.read(), 24);
+    assert_eq!(*adapter.reports.read(), vec![
+        PeerMisbehavior::InvalidSyncResponse
+    ]);
+
+    let block = block_on(adapter.get_block_by_height(Context::new(), 24)).unwrap();
+    assert_sync(status, block);
+}
+
+// Inserts the blocks after genesis in one batch, proofs taken from the block
+// after each
+fn insert_sync_blocks(
+    list_rich_block: Vec<RichBlock>,
+    ok: bool,
+) -> (CurrentConsensusStatus, Arc<MockCommonConsensusAdapter>) {
+    let genesis_block = list_rich_block[0].block.clone();
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        gen_remote_block_hashmap(list_rich_block.clone()),
+        Arc::new(RwLock::new(HashMap::new())),
+        gen_remote_tx_hashmap(list_rich_block.clone()),
+    ));
+    let status_agent = StatusAgent::new(genesis_status(&genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        lock,
+        stopper,
+    );
+
+    let mut blocks = vec![];
+    for i in 1..list_rich_block.len() {
+        let rich_block = list_rich_block[i].clone();
+        let proof = match list_rich_block.get(i + 1) {
+            Some(next) => next.block.header.proof.clone(),
+            None => block_on(adapter.get_proof_from_remote(Context::new(), i as u64)).unwrap(),
+        };
+        blocks.push((rich_block.block, rich_block.txs, proof));
+    }
+
+    let res = block_on(sync.insert_sync_blocks(Context::new(), status_agent.clone(), blocks));
+    assert_eq!(res.is_ok(), ok);
+    (status_agent.to_inner(), adapter)
+}
+
+// Syncs a 50 blocks chain whose 25th block goes through `corrupt`
+fn sync_corrupted_block(corrupt: fn(&mut Block)) -> CurrentConsensusStatus {
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    corrupt(&mut list_rich_block[25].block);
+
+    sync_rich_blocks(list_rich_block, 50).0
+}
+
+// Syncs a 50 blocks chain whose 25th block carries the proof of its
+// grandparent after it goes through `corrupt`
+fn sync_stale_proof_block(corrupt: fn(&mut Proof)) -> CurrentConsensusStatus {
+    let mut list_rich_block = mock_chained_rich_block(50, 1);
+    list_rich_block[25].block.header.proof = list_rich_block[24].block.header.proof.clone();
+    corrupt(&mut list_rich_block[25].block.header.proof);
+    relink_rich_blocks(&mut list_rich_block);
+
+    sync_rich_blocks(list_rich_block, 50).0
+}
+
+fn sync_rich_blocks(
+    list_rich_block: Vec<RichBlock>,
+    max_height: u64,
+) -> (CurrentConsensusStatus, Arc<MockCommonConsensusAdapter>) {
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let loacl_blocks = Arc::new(RwLock::new(HashMap::new()));
+    loacl_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        loacl_blocks,
+        remote_blocks,
+        Arc::new(RwLock::new(HashMap::new())),
+        gen_remote_tx_hashmap(list_rich_block),
+    ));
+    let status_agent = StatusAgent::new(genesis_status(&genesis_block));
+    let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
+    let sync = OverlordSynchronization::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        lock,
+        stopper,
+    );
+    block_on(sync.receive_remote_block(Context::new(), max_height)).unwrap();
+
+    let status = status_agent.to_inner();
+    let block =
+        block_on(adapter.get_block_by_height(Context::new(), status.current_height)).unwrap();
+    assert_sync(status.clone(), block);
+    (status, adapter)
+}
+
+// Links every block to its parent again after some of them changed
+fn relink_rich_blocks(list: &mut [RichBlock]) {
+    for i in 1..list.len() {
+        let parent = &list[i - 1].block;
+        let parent_hash = Hash::digest(parent.encode_fixed().unwrap());
+        let parent_height = parent.header.height;
+
+        let header = &mut list[i].block.header;
+        header.pre_hash = parent_hash.clone();
+        if header.proof.height == parent_height {
+            header.proof = mock_proof(parent_height, parent_hash);
+        }
+    }
+}
+
+pub fn genesis_status(genesis_block: &Block) -> CurrentConsensusStatus {
+    let block_hash = Hash::digest(genesis_block.encode_fixed().unwrap());
+    CurrentConsensusStatus {
+        cycles_price:               1,
+        cycles_limit:               300_000_000,
+        current_height:             genesis_block.header.height,
+        exec_height:                genesis_block.header.exec_height,
+        current_hash:               block_hash,
+        list_logs_bloom:            vec![],
+        list_confirm_root:          vec![],
+        latest_commited_state_root: genesis_block.header.state_root.clone(),
+        list_state_root:            vec![],
+        list_receipt_root:          vec![],
+        list_cycles_used:           vec![],
+        current_proof:              genesis_block.header.proof.clone(),
+        validators:                 genesis_block.header.validators.clone(),
+        consensus_interval:         3000,
+        propose_ratio:              15,
+        prevote_ratio:              10,
+        precommit_ratio:            10,
+        brake_ratio:                3,
+        tx_num_limit:               20000,
+        max_tx_size:                1_073_741_824,
+        adaptive_interval:          None,
+    }
+}
+
 pub type SafeHashMap<K, V> = Arc<RwLock<HashMap<K, V>>>;
 
 pub struct MockCommonConsensusAdapter {
@@ -95,6 +497,12 @@ pub struct MockCommonConsensusAdapter {
     remote_blocks:       SafeHashMap<u64, Block>,
     local_transactions:  SafeHashMap<Hash, SignedTransaction>,
     remote_transactions: SafeHashMap<Hash, SignedTransaction>,
+    proofs:              RwLock<Vec<Proof>>,
+    // Every storage read fails while set
+    fail_storage:        RwLock<bool>,
+    // Transactions the mock mempool packages
+    mempool_txs:         RwLock<Vec<Hash>>,
+    pub reports:         RwLock<Vec<PeerMisbehavior>>,
 }
 
 impl MockCommonConsensusAdapter {
@@ -111,8 +519,16 @@ impl MockCommonConsensusAdapter {
             remote_blocks,
             local_transactions,
             remote_transactions,
+            proofs: RwLock::new(vec![]),
+            fail_storage: RwLock::new(false),
+            mempool_txs: RwLock::new(vec![]),
+            reports: RwLock::new(vec![]),
         }
     }
+
+    pub fn set_mempool_txs(&self, tx_hashes: Vec<Hash>) {
+        *self.mempool_txs.write() = tx_hashes;
+    }
 }
 
 #[async_trait]
@@ -162,6 +578,144 @@ impl SynchronizationAdapter for MockCommonConsensusAdapter {
 
         Ok(txs)
     }
+
+    async fn get_proof_from_remote(&self, _: Context, height: u64) -> ProtocolResult<Proof> {
+        let blocks = self.remote_blocks.read();
+        if let Some(next_block) = blocks.get(&(height + 1)) {
+            return Ok(next_block.header.proof.clone());
+        }
+
+        let block = blocks.get(&height).unwrap();
+        Ok(mock_proof(
+            height,
+            Hash::digest(block.encode_fixed().unwrap()),
+        ))
+    }
+}
+
+#[async_trait]
+impl ConsensusAdapter for MockCommonConsensusAdapter {
+    async fn get_txs_from_mempool(
+        &self,
+        _: Context,
+        _: u64,
+        _: u64,
+        _: u64,
+    ) -> ProtocolResult<MixedTxHashes> {
+        Ok(MixedTxHashes {
+            order_tx_hashes:   self.mempool_txs.read().clone(),
+            propose_tx_hashes: vec![],
+        })
+    }
+
+    async fn check_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn sync_txs(&self, _: Context, _: Vec<Hash>) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn get_full_txs(
+        &self,
+        _: Context,
+        order_txs: Vec<Hash>,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        let local = self.local_transactions.read();
+        let remote = self.remote_transactions.read();
+        order_txs
+            .iter()
+            .map(|hash| {
+                local
+                    .get(hash)
+                    .or_else(|| remote.get(hash))
+                    .cloned()
+                    .ok_or_else(|| ConsensusError::Other(format!("tx {:?} missing", hash)).into())
+            })
+            .collect()
+    }
+
+    fn contains_txs(&self, _: Context, tx_hashes: &[Hash]) -> bool {
+        let mempool_txs = self.mempool_txs.read();
+        tx_hashes
+            .iter()
+            .all(|tx_hash| mempool_txs.contains(tx_hash))
+    }
+
+    async fn transmit(
+        &self,
+        _: Context,
+        _: Vec<u8>,
+        _: &str,
+        _: MessageTarget,
+    ) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        _: Hash,
+        _: MerkleRoot,
+        _: u64,
+        _: u64,
+        _: Address,
+        _: Hash,
+        _: Vec<SignedTransaction>,
+        _: u64,
+        _: u64,
+    ) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn get_last_validators(
+        &self,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Validator>> {
+        let block = self.get_block_by_height(ctx, height).await?;
+        Ok(block.header.validators)
+    }
+
+    async fn get_current_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        CommonConsensusAdapter::get_current_height(self, ctx).await
+    }
+
+    async fn pull_block(&self, ctx: Context, height: u64, _: &str) -> ProtocolResult<Block> {
+        self.get_block_from_remote(ctx, height).await
+    }
+
+    async fn pull_txs(
+        &self,
+        ctx: Context,
+        hashes: Vec<Hash>,
+        _: &str,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.get_txs_from_remote(ctx, &hashes).await
+    }
+
+    async fn save_overlord_wal(&self, _: Context, _: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_overlord_wal(&self, _: Context) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    async fn save_evidence(&self, _: Context, _: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_evidence(&self, _: Context) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    async fn save_liveness(&self, _: Context, _: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_liveness(&self, _: Context) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
 }
 
 #[async_trait]
@@ -174,7 +728,8 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
         Ok(())
     }
 
-    async fn save_proof(&self, _: Context, _: Proof) -> ProtocolResult<()> {
+    async fn save_proof(&self, _: Context, proof: Proof) -> ProtocolResult<()> {
+        self.proofs.write().push(proof);
         Ok(())
     }
 
@@ -195,14 +750,59 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
         Ok(())
     }
 
+    async fn save_signed_txs_with_receipts(
+        &self,
+        ctx: Context,
+        signed_txs: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        self.save_signed_txs(ctx, signed_txs).await
+    }
+
+    async fn save_block_data(
+        &self,
+        ctx: Context,
+        block: Block,
+        signed_txs: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        self.save_signed_txs(ctx.clone(), signed_txs).await?;
+        self.save_block(ctx, block).await
+    }
+
+    async fn save_blocks_data(
+        &self,
+        ctx: Context,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        for (block, signed_txs, receipts) in blocks.into_iter() {
+            self.save_block_data(ctx.clone(), block, signed_txs, receipts)
+                .await?;
+        }
+        Ok(())
+    }
+
     /// Flush the given transactions in the mempool.
     async fn flush_mempool(&self, _: Context, _: &[Hash]) -> ProtocolResult<()> {
         Ok(())
     }
 
+    /// Drop the transactions in the mempool timed out at the given height.
+    async fn expire_mempool(&self, _: Context, _: u64) -> ProtocolResult<usize> {
+        Ok(0)
+    }
+
     /// Get a block corresponding to the given height.
     async fn get_block_by_height(&self, _: Context, height: u64) -> ProtocolResult<Block> {
-        Ok(self.loacl_blocks.read().get(&height).unwrap().clone())
+        if *self.fail_storage.read() {
+            return Err(ConsensusError::Other("storage fails".to_owned()).into());
+        }
+
+        self.loacl_blocks
+            .read()
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| ConsensusError::Other(format!("block {} not stored", height)).into())
     }
 
     /// Get the current height from storage.
@@ -239,24 +839,25 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
     ) -> ProtocolResult<Metadata> {
         Ok(Metadata {
             chain_id:        Hash::from_empty(),
-            common_ref:      Hex::from_string("0x703873635a6b51513451".to_string()).unwrap(),
+            // "muta"
+            common_ref:      Hex::from_string("0x6d757461".to_string()).unwrap(),
             timeout_gap:     20,
             cycles_limit:    9999,
             cycles_price:    1,
             interval:        3000,
             verifier_list:   vec![ValidatorExtend {
-                bls_pub_key: Hex::from_string("0x04188ef9488c19458a963cc57b567adde7db8f8b6bec392d5cb7b67b0abc1ed6cd966edc451f6ac2ef38079460eb965e890d1f576e4039a20467820237cda753f07a8b8febae1ec052190973a1bcf00690ea8fc0168b3fbbccd1c4e402eda5ef22".to_owned()).unwrap(),
+                bls_pub_key:    Hex::from_string(BLS_PUB_KEY.to_owned()).unwrap(),
                 address:        Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83")
                     .unwrap(),
                 propose_weight: 0,
-                vote_weight:    0,
+                vote_weight:    1,
             }],
             propose_ratio:   10,
             prevote_ratio:   10,
             precommit_ratio: 10,
             brake_ratio:     10,
-            tx_num_limit: 20000,
-            max_tx_size: 1_073_741_824,
+            tx_num_limit:    20000,
+            max_tx_size:     1_073_741_824,
         })
     }
 
@@ -268,9 +869,13 @@ impl CommonConsensusAdapter for MockCommonConsensusAdapter {
         _max_tx_size: u64,
     ) {
     }
+
+    fn report_bad(&self, _: Context, misbehavior: PeerMisbehavior) {
+        self.reports.write().push(misbehavior);
+    }
 }
 
-fn gen_remote_tx_hashmap(list: Vec<RichBlock>) -> SafeHashMap<Hash, SignedTransaction> {
+pub fn gen_remote_tx_hashmap(list: Vec<RichBlock>) -> SafeHashMap<Hash, SignedTransaction> {
     let mut remote_txs = HashMap::new();
 
     for rich_block in list.into_iter() {
@@ -282,7 +887,7 @@ fn gen_remote_tx_hashmap(list: Vec<RichBlock>) -> SafeHashMap<Hash, SignedTransa
     Arc::new(RwLock::new(remote_txs))
 }
 
-fn gen_remote_block_hashmap(list: Vec<RichBlock>) -> SafeHashMap<u64, Block> {
+pub fn gen_remote_block_hashmap(list: Vec<RichBlock>) -> SafeHashMap<u64, Block> {
     let mut remote_blocks = HashMap::new();
     for rich_block in list.into_iter() {
         remote_blocks.insert(rich_block.block.header.height, rich_block.block.clone());
@@ -291,7 +896,7 @@ fn gen_remote_block_hashmap(list: Vec<RichBlock>) -> SafeHashMap<u64, Block> {
     Arc::new(RwLock::new(remote_blocks))
 }
 
-fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
+pub fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
     let mut list = vec![];
 
     let genesis_rich_block = mock_genesis_rich_block();
@@ -316,7 +921,7 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
             chain_id: last_header.chain_id.clone(),
             height: current_height,
             exec_height: current_height,
-            pre_hash: last_block_hash,
+            pre_hash: last_block_hash.clone(),
             timestamp: 0,
             order_root,
             logs_bloom: vec![],
@@ -325,13 +930,7 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
             receipt_root: vec![],
             cycles_used: vec![],
             proposer: Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83").unwrap(),
-            proof: Proof {
-                height:     current_height,
-                round:      0,
-                block_hash: Hash::from_empty(),
-                signature:  Bytes::new(),
-                bitmap:     Bytes::new(),
-            },
+            proof: mock_proof(current_height - 1, last_block_hash),
             validator_version: 0,
             validators: vec![Validator {
                 address:        Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83")
@@ -382,6 +981,28 @@ fn mock_chained_rich_block(len: u64, gap: u64) -> Vec<RichBlock> {
     list
 }
 
+// The proof of the block, signed by the only validator
+pub fn mock_proof(height: u64, block_hash: Hash) -> Proof {
+    let vote = Vote {
+        height,
+        round: 0,
+        vote_type: VoteType::Precommit,
+        block_hash: block_hash.as_bytes(),
+    };
+    let hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+    let signature = BlsPrivateKey::try_from(hex::decode(BLS_PRIV_KEY).unwrap().as_ref())
+        .unwrap()
+        .sign_message(&HashValue::try_from(hash.as_bytes().as_ref()).unwrap());
+
+    Proof {
+        height,
+        round: 0,
+        block_hash,
+        signature: signature.to_bytes(),
+        bitmap: Bytes::from(vec![0b1000_0000]),
+    }
+}
+
 fn mock_genesis_rich_block() -> RichBlock {
     let header = BlockHeader {
         chain_id:          Hash::from_empty(),
@@ -442,6 +1063,8 @@ fn get_receipt(tx: &SignedTransaction, height: u64) -> Receipt {
 }
 
 fn mock_tx_list(num: usize, height: u64) -> Vec<SignedTransaction> {
+    let priv_key =
+        Secp256k1PrivateKey::try_from(hex::decode(TX_PRIV_KEY).unwrap().as_ref()).unwrap();
     let mut txs = vec![];
 
     for i in 0..num {
@@ -459,11 +1082,13 @@ fn mock_tx_list(num: usize, height: u64) -> Vec<SignedTransaction> {
         };
 
         let bytes = raw.encode_fixed().unwrap();
+        let tx_hash = Hash::digest(bytes);
+        let signature = Secp256k1::sign_message(&tx_hash.as_bytes(), &priv_key.to_bytes()).unwrap();
         let signed_tx = SignedTransaction {
             raw,
-            tx_hash: Hash::digest(bytes),
-            pubkey: Bytes::new(),
-            signature: Bytes::new(),
+            tx_hash,
+            pubkey: priv_key.pub_key().to_bytes(),
+            signature: signature.to_bytes(),
         };
 
         txs.push(signed_tx)
@@ -481,14 +1106,10 @@ fn exec_txs(height: u64, txs: &[SignedTransaction]) -> (ExecutorResp, MerkleRoot
         all_cycles_used += receipt.cycles_used;
         receipts.push(receipt);
     }
-    let receipt_root = Merkle::from_hashes(
-        receipts
-            .iter()
-            .map(|r| Hash::digest(r.to_owned().encode_fixed().unwrap()))
-            .collect::<Vec<_>>(),
-    )
-    .get_root_hash()
-    .unwrap_or_else(Hash::from_empty);
+    let receipt_root = Merkle::from_receipts(&receipts)
+        .unwrap()
+        .get_root_hash()
+        .unwrap_or_else(Hash::from_empty);
 
     (
         ExecutorResp {
@@ -0,0 +1,71 @@
+use crate::timestamp::TimestampRules;
+
+const INTERVAL: u64 = 3000;
+const NOW: u64 = 1_000_000;
+
+fn rules() -> TimestampRules {
+    TimestampRules {
+        max_clock_skew:    1000,
+        max_gap_intervals: Some(10),
+    }
+}
+
+#[test]
+fn test_timestamp_bounds() {
+    let rules = rules();
+    let parent = NOW - INTERVAL;
+    let check = |timestamp: u64| rules.check(timestamp, Some(parent), INTERVAL, NOW);
+
+    // After the parent
+    assert!(check(parent).is_err());
+    assert!(check(parent + 1).is_ok());
+
+    // Within the clock skew
+    assert!(check(NOW + 1000).is_ok());
+    let err = check(NOW + 1001).unwrap_err().to_string();
+    assert!(err.contains("InvalidTimestamp"));
+
+    // Within ten intervals of the parent
+    let parent = NOW - 20 * INTERVAL;
+    assert!(rules
+        .check(parent + 10 * INTERVAL, Some(parent), INTERVAL, NOW)
+        .is_ok());
+    assert!(rules
+        .check(parent + 10 * INTERVAL + 1, Some(parent), INTERVAL, NOW)
+        .is_err());
+
+    // Unbounded by default, a halted chain can resume
+    let parent = NOW - 1000 * INTERVAL;
+    assert!(TimestampRules::default()
+        .check(NOW, Some(parent), INTERVAL, NOW)
+        .is_ok());
+}
+
+#[test]
+fn test_timestamp_after_genesis() {
+    let rules = rules();
+
+    // The genesis timestamp says nothing, only the clock skew applies
+    assert!(rules.check(1, None, INTERVAL, NOW).is_ok());
+    assert!(rules.check(NOW + 1001, None, INTERVAL, NOW).is_err());
+}
+
+#[test]
+fn test_timestamp_drift_ahead() {
+    let rules = rules();
+
+    // Each block stamped just under an interval ahead used to pass, keeping
+    // the chain clock an interval ahead of every node's
+    let mut now = NOW;
+    let mut parent = NOW - INTERVAL;
+    for _ in 0..100 {
+        assert!(rules
+            .check(now + INTERVAL - 1, Some(parent), INTERVAL, now)
+            .is_err());
+
+        let stamped = now + rules.max_clock_skew;
+        rules.check(stamped, Some(parent), INTERVAL, now).unwrap();
+        parent = stamped;
+        now += INTERVAL;
+    }
+}
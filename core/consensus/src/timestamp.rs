@@ -0,0 +1,69 @@
+//! Bounds on the timestamp a proposer stamps on its block.
+
+use protocol::ProtocolResult;
+
+use crate::ConsensusError;
+
+/// Milliseconds a proposer's clock may run ahead of ours.
+pub const DEFAULT_MAX_CLOCK_SKEW: u64 = 3_000;
+
+/// A block must be later than its parent and no later than `now` plus
+/// `max_clock_skew`. With `max_gap_intervals` set, it may also follow its
+/// parent by at most that many consensus intervals. That bound is off by
+/// default since a chain halted for longer could never resume.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimestampRules {
+    pub max_clock_skew:    u64,
+    pub max_gap_intervals: Option<u64>,
+}
+
+impl Default for TimestampRules {
+    fn default() -> Self {
+        TimestampRules {
+            max_clock_skew:    DEFAULT_MAX_CLOCK_SKEW,
+            max_gap_intervals: None,
+        }
+    }
+}
+
+impl TimestampRules {
+    /// `parent_timestamp` is `None` for the block right after genesis, whose
+    /// timestamp bears no relation to the chain's clock.
+    pub fn check(
+        &self,
+        timestamp: u64,
+        parent_timestamp: Option<u64>,
+        interval: u64,
+        now: u64,
+    ) -> ProtocolResult<()> {
+        if timestamp > now.saturating_add(self.max_clock_skew) {
+            return Err(invalid(timestamp, format!("ahead of local time {}", now)));
+        }
+
+        let parent_timestamp = match parent_timestamp {
+            Some(parent_timestamp) => parent_timestamp,
+            None => return Ok(()),
+        };
+        if timestamp <= parent_timestamp {
+            return Err(invalid(
+                timestamp,
+                format!("not after parent {}", parent_timestamp),
+            ));
+        }
+        if let Some(max_gap_intervals) = self.max_gap_intervals {
+            let max_gap = interval.saturating_mul(max_gap_intervals);
+            if timestamp - parent_timestamp > max_gap {
+                return Err(invalid(
+                    timestamp,
+                    format!("more than {} after parent {}", max_gap, parent_timestamp),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid(timestamp: u64, reason: String) -> protocol::ProtocolError {
+    ConsensusError::InvalidTimestamp { timestamp, reason }.into()
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error::Error;
 
+use overlord::types::{Vote, VoteType};
 use overlord::Crypto;
 use parking_lot::RwLock;
 
@@ -10,7 +11,9 @@ use common_crypto::{
     BlsCommonReference, BlsPrivateKey, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue,
     PrivateKey, Signature,
 };
-use protocol::types::{Address, Hash, MerkleRoot, SignedTransaction};
+use protocol::types::{
+    Address, Hash, MerkleRoot, Metadata, Proof, SignedTransaction, Validator, ValidatorExtend,
+};
 use protocol::{Bytes, ProtocolError};
 
 pub struct OverlordCrypto {
@@ -159,25 +162,190 @@ pub fn check_list_roots<T: Eq>(cache_roots: &[T], block_roots: &[T]) -> bool {
             .all(|(c_root, e_root)| c_root == e_root)
 }
 
+/// Checks the number of a block's transactions against the limit of the
+/// status. It only takes the hashes, so it runs before they are fetched.
+pub fn check_tx_num(tx_num: usize, tx_num_limit: u64) -> Result<(), ConsensusError> {
+    if tx_num as u64 > tx_num_limit {
+        return Err(ConsensusError::ExceedBlockLimit {
+            name:  "transaction number",
+            value: tx_num as u64,
+            limit: tx_num_limit,
+        });
+    }
+    Ok(())
+}
+
+/// Checks a block's transactions, given by their cycles limits, against the
+/// limits of the status.
+pub fn check_block_limits(
+    txs_cycles: &[u64],
+    cycles_limit: u64,
+    tx_num_limit: u64,
+) -> Result<(), ConsensusError> {
+    check_tx_num(txs_cycles.len(), tx_num_limit)?;
+
+    let cycles = txs_cycles
+        .iter()
+        .fold(0u64, |sum, cycles| sum.saturating_add(*cycles));
+    if cycles > cycles_limit {
+        return Err(ConsensusError::ExceedBlockLimit {
+            name:  "cycles",
+            value: cycles,
+            limit: cycles_limit,
+        });
+    }
+    Ok(())
+}
+
+/// Counts the leading transactions, given by their cycles limits, that fit the
+/// limits of the status together.
+pub fn fit_block_limits(txs_cycles: &[u64], cycles_limit: u64, tx_num_limit: u64) -> usize {
+    let mut cycles = 0u64;
+    txs_cycles
+        .iter()
+        .take(tx_num_limit as usize)
+        .take_while(|tx_cycles| {
+            cycles = cycles.saturating_add(**tx_cycles);
+            cycles <= cycles_limit
+        })
+        .count()
+}
+
+/// Checks that a block of `height` comes from one of the validators and
+/// carries the proof of the committed block, `current_hash`. The block after
+/// genesis carries the empty genesis proof.
+pub fn check_proposal_origin(
+    height: u64,
+    proposer: &Address,
+    proof: &Proof,
+    validators: &[Validator],
+    current_hash: &Hash,
+) -> Result<(), ConsensusError> {
+    if !validators.iter().any(|v| &v.address == proposer) {
+        return Err(ConsensusError::InvalidProposal(format!(
+            "proposer {} not in authority list",
+            proposer.as_hex()
+        )));
+    }
+
+    if height > 1 && (proof.height != height - 1 || &proof.block_hash != current_hash) {
+        return Err(ConsensusError::InvalidProposal(format!(
+            "proof of height {} block {:?} is not of the committed block",
+            proof.height, proof.block_hash
+        )));
+    }
+    Ok(())
+}
+
+/// The BLS common reference of the chain, hex encoded in the metadata.
+pub fn common_ref_of(metadata: &Metadata) -> Result<BlsCommonReference, ConsensusError> {
+    let common_ref = hex::decode(metadata.common_ref.as_string_trim0x())
+        .map_err(|e| ConsensusError::Other(format!("hex decode common ref error {:?}", e)))?;
+    let common_ref = std::str::from_utf8(&common_ref)
+        .map_err(|e| ConsensusError::Other(format!("common ref is not utf8 {:?}", e)))?;
+    Ok(common_ref.into())
+}
+
+/// Checks that the aggregated precommit signature of `proof` comes from the
+/// `verifiers` of its height marked in the bitmap, holding more than two
+/// thirds of the vote weight.
+pub fn verify_proof_signature(
+    proof: &Proof,
+    verifiers: &[ValidatorExtend],
+    common_ref: &BlsCommonReference,
+) -> Result<(), ConsensusError> {
+    let invalid = |reason: String| ConsensusError::InvalidProof {
+        height: proof.height,
+        reason,
+    };
+
+    // The bitmap follows the authority list overlord sorts by address
+    let mut verifiers = verifiers.iter().collect::<Vec<_>>();
+    verifiers.sort_by_key(|v| v.address.as_bytes());
+
+    let total_weight = verifiers
+        .iter()
+        .map(|v| u64::from(v.vote_weight))
+        .sum::<u64>();
+    let mut signed_weight = 0u64;
+    let mut pub_keys = Vec::new();
+    for (index, verifier) in verifiers.iter().enumerate() {
+        if !is_signed(&proof.bitmap, index) {
+            continue;
+        }
+
+        let pub_key = hex::decode(verifier.bls_pub_key.as_string_trim0x())
+            .map_err(|e| invalid(format!("hex decode bls pubkey error {:?}", e)))?;
+        let pub_key = BlsPublicKey::try_from(pub_key.as_ref())
+            .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+        signed_weight += u64::from(verifier.vote_weight);
+        pub_keys.push(pub_key);
+    }
+    if signed_weight * 3 <= total_weight * 2 {
+        return Err(invalid(format!(
+            "signed vote weight {} of {}",
+            signed_weight, total_weight
+        )));
+    }
+
+    let vote = Vote {
+        height:     proof.height,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: proof.block_hash.as_bytes(),
+    };
+    let hash = Hash::digest(Bytes::from(rlp::encode(&vote))).as_bytes();
+    let hash = HashValue::try_from(hash.as_ref())
+        .map_err(|_| invalid("failed to convert hash value".to_owned()))?;
+    let signature = BlsSignature::try_from(proof.signature.as_ref())
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+
+    signature
+        .verify(
+            &hash,
+            &BlsPublicKey::aggregate(pub_keys.iter().collect()),
+            common_ref,
+        )
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))
+}
+
+// Overlord sets the bit of the n-th authority at the n-th bit counted from
+// the most significant one.
+pub fn is_signed(bitmap: &[u8], index: usize) -> bool {
+    bitmap
+        .get(index / 8)
+        .map_or(false, |byte| byte & (0x80 >> (index % 8)) != 0)
+}
+
 #[cfg(test)]
 mod test {
+    use protocol::types::Hex;
+
     use super::*;
 
+    const BLS_PRIV_KEYS: [&str; 4] = [
+        "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+        "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+        "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+        "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+    ];
+    const BLS_PUB_KEYS: [&str; 4] = [
+        "041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612",
+        "040c15c82ed07dc866ab7c3af3a070eb4340ac0439bf12bb49cbed5797d52707e009f7c17414777b0213b9a55c8a5c08290ce40c366d59322db418b7ff41277090bd25614174763c9fd725ede1f65f3e61ca9acdb35f59e33d556e738add14d536",
+        "040b3118acefdfbb11ded262a7f3c90dfca4fbc0200a92b4f6bb80210ab85e39f79458f7d47f7cb06864df0571e7591a4e0858df0b52a4c3ae19ae3adc32e1da0ec4cbdca108365ee433becdb1ccebb1b339647788dfad94ebae1cbd770fcfa4e5",
+        "040709f204e3ec5b8bdd9f2bb6edc9cb1704fc1e4952661ba7532ea8e37f3b159b8d41987ee6707d32bdf494e2deb00b7f049a4670a5ce1ad8e429fcacc5bbc69cb03b71a7f1d831d0b47dda5e62642d420ff0a545950cb1db19d42fe04e2c91d2",
+    ];
+
     #[test]
     fn test_bls_amcl() {
-        let private_keys = vec![
-            hex::decode("000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970").unwrap(),
-            hex::decode("00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817").unwrap(),
-            hex::decode("000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc").unwrap(),
-            hex::decode("00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415").unwrap(),
-        ];
-
-        let public_keys = vec![
-            hex::decode("041054fe9a65be0891094ed37fb3655e3ffb12353bc0a1b4f8673b52ad65d1ca481780cf7e988eb8dcdc05d8352f03605b0d11afb2525b3f1b55ec694509248bcfead39cbb292725d710e2a509c77ed051d1d49e15e429cf6d12b9be7c02179612").unwrap(),
-            hex::decode("040c15c82ed07dc866ab7c3af3a070eb4340ac0439bf12bb49cbed5797d52707e009f7c17414777b0213b9a55c8a5c08290ce40c366d59322db418b7ff41277090bd25614174763c9fd725ede1f65f3e61ca9acdb35f59e33d556e738add14d536").unwrap(),
-            hex::decode("040b3118acefdfbb11ded262a7f3c90dfca4fbc0200a92b4f6bb80210ab85e39f79458f7d47f7cb06864df0571e7591a4e0858df0b52a4c3ae19ae3adc32e1da0ec4cbdca108365ee433becdb1ccebb1b339647788dfad94ebae1cbd770fcfa4e5").unwrap(),
-            hex::decode("040709f204e3ec5b8bdd9f2bb6edc9cb1704fc1e4952661ba7532ea8e37f3b159b8d41987ee6707d32bdf494e2deb00b7f049a4670a5ce1ad8e429fcacc5bbc69cb03b71a7f1d831d0b47dda5e62642d420ff0a545950cb1db19d42fe04e2c91d2").unwrap(),
-        ];
+        let private_keys = BLS_PRIV_KEYS
+            .iter()
+            .map(|key| hex::decode(key).unwrap())
+            .collect::<Vec<_>>();
+        let public_keys = BLS_PUB_KEYS
+            .iter()
+            .map(|key| hex::decode(key).unwrap())
+            .collect::<Vec<_>>();
 
         let msg = Hash::digest(Bytes::from("muta-consensus"));
         let hash = HashValue::try_from(msg.as_bytes().as_ref()).unwrap();
@@ -212,4 +380,170 @@ mod test {
         assert!(!check_list_roots(&roots_4, &roots_2));
         assert!(!check_list_roots(&roots_5, &roots_2));
     }
+
+    #[test]
+    fn test_block_limits() {
+        assert!(check_block_limits(&[100, 200], 300, 2).is_ok());
+        assert!(check_block_limits(&[], 300, 2).is_ok());
+
+        match check_block_limits(&[100, 200, 1], 300, 3) {
+            Err(ConsensusError::ExceedBlockLimit { value, .. }) => assert_eq!(value, 301),
+            _ => panic!("cycles over the limit should be rejected"),
+        }
+        match check_block_limits(&[1, 1, 1], 300, 2) {
+            Err(ConsensusError::ExceedBlockLimit { value, .. }) => assert_eq!(value, 3),
+            _ => panic!("transactions over the limit should be rejected"),
+        }
+        // Overflowing the sum still counts as over the limit
+        assert!(check_block_limits(&[u64::max_value(), 1], u64::max_value() - 1, 2).is_err());
+
+        assert!(check_tx_num(2, 2).is_ok());
+        match check_tx_num(3, 2) {
+            Err(ConsensusError::ExceedBlockLimit { value, limit, .. }) => {
+                assert_eq!((value, limit), (3, 2))
+            }
+            _ => panic!("transactions over the limit should be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_fit_block_limits() {
+        assert_eq!(fit_block_limits(&[], 300, 2), 0);
+        assert_eq!(fit_block_limits(&[100, 200], 300, 2), 2);
+        assert_eq!(fit_block_limits(&[100, 200, 1], 300, 3), 2);
+        assert_eq!(fit_block_limits(&[1, 1, 1], 300, 2), 2);
+        // A cheaper transaction after one over the limit is dropped too
+        assert_eq!(fit_block_limits(&[100, 300, 1], 300, 3), 1);
+        assert_eq!(
+            fit_block_limits(&[u64::max_value(), 1], u64::max_value() - 1, 2),
+            0
+        );
+    }
+
+    #[test]
+    fn test_proposal_origin() {
+        let validator = Address::from_hex("0x1c9776983b2f251fa5c9cc562c1b667d1f05ff83").unwrap();
+        let stranger = Address::from_hex("0x0000000000000000000000000000000000000001").unwrap();
+        let validators = vec![Validator {
+            address:        validator.clone(),
+            propose_weight: 1,
+            vote_weight:    1,
+        }];
+        let current_hash = Hash::digest(Bytes::from("block 9"));
+        let proof = Proof {
+            height:     9,
+            round:      0,
+            block_hash: current_hash.clone(),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::new(),
+        };
+
+        assert!(check_proposal_origin(10, &validator, &proof, &validators, &current_hash).is_ok());
+        assert!(check_proposal_origin(10, &stranger, &proof, &validators, &current_hash).is_err());
+
+        let mut stale_proof = proof.clone();
+        stale_proof.height = 8;
+        assert!(
+            check_proposal_origin(10, &validator, &stale_proof, &validators, &current_hash)
+                .is_err()
+        );
+
+        let mut forked_proof = proof.clone();
+        forked_proof.block_hash = Hash::digest(Bytes::from("other block 9"));
+        assert!(
+            check_proposal_origin(10, &validator, &forked_proof, &validators, &current_hash)
+                .is_err()
+        );
+
+        // The genesis proof is empty
+        let genesis_proof = Proof {
+            height:     0,
+            round:      0,
+            block_hash: Hash::from_empty(),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::new(),
+        };
+        assert!(
+            check_proposal_origin(1, &validator, &genesis_proof, &validators, &current_hash)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_proof_signature() {
+        let verifiers = (0..4)
+            .map(|i| ValidatorExtend {
+                bls_pub_key:    Hex::from_string(format!("0x{}", BLS_PUB_KEYS[i])).unwrap(),
+                address:        Address::from_hex(&format!("0x{:040x}", i + 1)).unwrap(),
+                propose_weight: 1,
+                vote_weight:    1,
+            })
+            .collect::<Vec<_>>();
+        let common_ref: BlsCommonReference = "muta".into();
+
+        // Signed by the verifiers marked in the bitmap, in the sorted order
+        let sign = |block_hash: &Hash, signers: &[usize]| {
+            let vote = Vote {
+                height:     9,
+                round:      1,
+                vote_type:  VoteType::Precommit,
+                block_hash: block_hash.as_bytes(),
+            };
+            let hash = Hash::digest(Bytes::from(rlp::encode(&vote)));
+            let hash = HashValue::try_from(hash.as_bytes().as_ref()).unwrap();
+
+            let mut bitmap = 0u8;
+            let mut sigs_and_pub_keys = Vec::new();
+            for i in signers.iter() {
+                bitmap |= 0x80 >> i;
+                let private_key = hex::decode(BLS_PRIV_KEYS[*i]).unwrap();
+                let public_key = hex::decode(BLS_PUB_KEYS[*i]).unwrap();
+                sigs_and_pub_keys.push((
+                    BlsPrivateKey::try_from(private_key.as_ref())
+                        .unwrap()
+                        .sign_message(&hash),
+                    BlsPublicKey::try_from(public_key.as_ref()).unwrap(),
+                ));
+            }
+
+            Proof {
+                height:     9,
+                round:      1,
+                block_hash: block_hash.clone(),
+                signature:  BlsSignature::combine(sigs_and_pub_keys).to_bytes(),
+                bitmap:     Bytes::from(vec![bitmap]),
+            }
+        };
+        let block_hash = Hash::digest(Bytes::from("block 9"));
+
+        let proof = sign(&block_hash, &[0, 2, 3]);
+        assert!(verify_proof_signature(&proof, &verifiers, &common_ref).is_ok());
+
+        // Two of four is not more than two thirds of the weight
+        let proof = sign(&block_hash, &[0, 2]);
+        match verify_proof_signature(&proof, &verifiers, &common_ref) {
+            Err(ConsensusError::InvalidProof { height, .. }) => assert_eq!(height, 9),
+            _ => panic!("a proof short of votes should be rejected"),
+        }
+
+        // A bitmap naming a verifier who did not sign
+        let mut proof = sign(&block_hash, &[0, 2, 3]);
+        proof.bitmap = Bytes::from(vec![0b1110_0000]);
+        assert!(verify_proof_signature(&proof, &verifiers, &common_ref).is_err());
+
+        // Signed for another block
+        let mut proof = sign(&Hash::digest(Bytes::from("other block 9")), &[0, 1, 2, 3]);
+        proof.block_hash = block_hash;
+        assert!(verify_proof_signature(&proof, &verifiers, &common_ref).is_err());
+
+        // Nobody signs the empty genesis proof
+        let genesis_proof = Proof {
+            height:     0,
+            round:      0,
+            block_hash: Hash::from_empty(),
+            signature:  Bytes::new(),
+            bitmap:     Bytes::new(),
+        };
+        assert!(verify_proof_signature(&genesis_proof, &verifiers, &common_ref).is_err());
+    }
 }
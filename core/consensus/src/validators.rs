@@ -0,0 +1,85 @@
+//! Validators of the last block, answered from the metadata while the block
+//! is not stored yet.
+
+use std::collections::BTreeMap;
+
+use parking_lot::RwLock;
+
+use protocol::traits::{CommonConsensusAdapter, Context};
+use protocol::types::{Block, Validator};
+use protocol::ProtocolResult;
+
+// Heights whose validators are kept to answer storage errors
+const CACHED_HEIGHTS: usize = 16;
+
+/// Validators of the latest heights read, each answering storage errors for
+/// its own height only.
+#[derive(Debug, Default)]
+pub struct LastValidators {
+    cache: RwLock<BTreeMap<u64, Vec<Validator>>>,
+}
+
+impl LastValidators {
+    pub fn new() -> Self {
+        LastValidators::default()
+    }
+
+    /// Falls back to the metadata service while the block isn't stored yet,
+    /// as during initial sync, and to the validators read for the same
+    /// height on other storage errors.
+    pub async fn get<A: CommonConsensusAdapter + ?Sized>(
+        &self,
+        adapter: &A,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<Validator>> {
+        let validators = match adapter.get_block_by_height(ctx.clone(), height).await {
+            Ok(block) => block.header.validators,
+            Err(err) => match adapter.get_current_height(ctx.clone()).await {
+                Ok(latest_height) if latest_height < height => {
+                    log::warn!(
+                        "[consensus-adapter]: block {} not stored yet, validators from metadata",
+                        height
+                    );
+                    let latest = adapter
+                        .get_block_by_height(ctx.clone(), latest_height)
+                        .await?;
+                    metadata_validators(adapter, ctx, &latest)?
+                }
+                _ => return self.cache.read().get(&height).cloned().ok_or(err),
+            },
+        };
+
+        let mut cache = self.cache.write();
+        cache.insert(height, validators.clone());
+        if cache.len() > CACHED_HEIGHTS {
+            let lowest = *cache.keys().next().expect("cache is not empty");
+            cache.remove(&lowest);
+        }
+        Ok(validators)
+    }
+}
+
+fn metadata_validators<A: CommonConsensusAdapter + ?Sized>(
+    adapter: &A,
+    ctx: Context,
+    block: &Block,
+) -> ProtocolResult<Vec<Validator>> {
+    let metadata = adapter.get_metadata(
+        ctx,
+        block.header.state_root.clone(),
+        block.header.height,
+        block.header.timestamp,
+    )?;
+
+    let validators = metadata
+        .verifier_list
+        .into_iter()
+        .map(|v| Validator {
+            address:        v.address,
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect();
+    Ok(validators)
+}
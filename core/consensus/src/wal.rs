@@ -90,6 +90,21 @@ impl SignedTxsWAL {
         }
         Ok(())
     }
+
+    /// Writes the saved transactions through to the disk.
+    pub fn flush(&self) -> ProtocolResult<()> {
+        for entry in fs::read_dir(&self.path).map_err(ConsensusError::WALErr)? {
+            let folder = entry.map_err(ConsensusError::WALErr)?.path();
+
+            for file in fs::read_dir(&folder).map_err(ConsensusError::WALErr)? {
+                let file = file.map_err(ConsensusError::WALErr)?.path();
+                fs::File::open(file)
+                    .and_then(|file| file.sync_all())
+                    .map_err(ConsensusError::WALErr)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
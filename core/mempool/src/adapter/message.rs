@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use futures::future::{try_join_all, TryFutureExt};
+use futures::future::TryFutureExt;
 use protocol::{
     traits::{Context, MemPool, MessageHandler, Priority, Rpc},
     types::{Hash, SignedTransaction},
@@ -43,26 +43,15 @@ where
     async fn process(&self, ctx: Context, msg: Self::Message) {
         let ctx = ctx.mark_network_origin_new_txs();
 
-        let insert_stx = |stx| -> _ {
-            let mem_pool = Arc::clone(&self.mem_pool);
-            let ctx = ctx.clone();
-
-            tokio::spawn(async move { mem_pool.insert(ctx, stx).await })
-        };
-
-        // Concurrently insert them
-        if try_join_all(
-            msg.batch_stxs
-                .into_iter()
-                .map(insert_stx)
-                .collect::<Vec<_>>(),
-        )
-        .await
-        .map(|_| ())
-        .is_err()
-        {
-            log::error!("[core_mempool] mempool batch insert error");
-        }
+        // Signatures are checked in parallel, failures are normal here since
+        // the same txs arrive from many peers
+        let results = self.mem_pool.insert_batch(ctx, msg.batch_stxs).await;
+        let failed = results.iter().filter(|result| result.is_err()).count();
+        log::debug!(
+            "[core_mempool]: {:?} of {:?} gossiped txs not inserted",
+            failed,
+            results.len()
+        );
     }
 }
 
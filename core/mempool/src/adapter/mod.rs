@@ -1,6 +1,7 @@
 pub mod message;
 
 use std::{
+    collections::{HashMap, HashSet},
     error::Error,
     marker::PhantomData,
     sync::atomic::{AtomicU64, Ordering},
@@ -11,20 +12,25 @@ use std::{
 use async_trait::async_trait;
 use derive_more::Display;
 use futures::{
-    channel::mpsc::{
-        channel, unbounded, Receiver, Sender, TrySendError, UnboundedReceiver, UnboundedSender,
+    channel::{
+        mpsc::{
+            channel, unbounded, Receiver, Sender, TrySendError, UnboundedReceiver, UnboundedSender,
+        },
+        oneshot,
     },
     lock::Mutex,
-    select,
-    stream::StreamExt,
+    pin_mut, select,
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
 };
 use futures_timer::Delay;
 use log::{debug, error};
+use rayon::prelude::*;
 
 use common_crypto::Crypto;
 use protocol::{
     fixed_codec::FixedCodec,
-    traits::{Context, Gossip, MemPoolAdapter, Priority, Rpc, Storage},
+    traits::{Context, Gossip, MemPoolAdapter, PeerMisbehavior, PeerTrust, Priority, Rpc, Storage},
     types::{Hash, SignedTransaction},
     ProtocolError, ProtocolErrorKind, ProtocolResult,
 };
@@ -37,6 +43,27 @@ use crate::MemPoolError;
 pub const DEFAULT_BROADCAST_TXS_SIZE: usize = 200;
 pub const DEFAULT_BROADCAST_TXS_INTERVAL: u64 = 200; // milliseconds
 
+/// How `pull_txs` chases the transactions of a proposal missing locally.
+#[derive(Clone, Debug)]
+pub struct PullTxsPolicy {
+    /// Wait for a single peer, the origin first.
+    pub peer_timeout:   Duration,
+    /// Other peers asked for what the origin didn't deliver.
+    pub fallback_peers: usize,
+    /// Bounds the whole pull, keep it below the round timeout.
+    pub deadline:       Duration,
+}
+
+impl Default for PullTxsPolicy {
+    fn default() -> Self {
+        PullTxsPolicy {
+            peer_timeout:   Duration::from_millis(800),
+            fallback_peers: 3,
+            deadline:       Duration::from_millis(2000),
+        }
+    }
+}
+
 struct IntervalTxsBroadcaster;
 
 impl IntervalTxsBroadcaster {
@@ -130,6 +157,48 @@ impl IntervalTxsBroadcaster {
     }
 }
 
+/// Pool changes waiting to be written, `removed` goes first.
+#[derive(Debug)]
+struct JournalUpdate {
+    inserted: Vec<SignedTransaction>,
+    removed:  Vec<Hash>,
+}
+
+impl JournalUpdate {
+    /// Appends a later update, an insert it removes is never written.
+    fn merge(&mut self, later: JournalUpdate) {
+        if !later.removed.is_empty() {
+            let removed = later.removed.iter().collect::<HashSet<_>>();
+            self.inserted.retain(|stx| !removed.contains(&stx.tx_hash));
+        }
+        self.removed.extend(later.removed);
+        self.inserted.extend(later.inserted);
+    }
+}
+
+struct TxsJournal;
+
+impl TxsJournal {
+    pub async fn write<S: Storage>(storage: Arc<S>, update_rx: UnboundedReceiver<JournalUpdate>) {
+        let mut update_rx = update_rx;
+
+        while let Some(mut update) = update_rx.next().await {
+            // Whatever queued up during the last write goes into this one
+            while let Ok(Some(later)) = update_rx.try_next() {
+                update.merge(later);
+            }
+
+            if let Err(err) = storage
+                .update_pool_transactions(update.inserted, update.removed)
+                .await
+            {
+                error!("mempool: journal txs failed {:?}", err);
+            }
+        }
+        debug!("mempool: default mempool adapter dropped")
+    }
+}
+
 pub struct DefaultMemPoolAdapter<C, N, S> {
     network: N,
     storage: Arc<S>,
@@ -141,14 +210,18 @@ pub struct DefaultMemPoolAdapter<C, N, S> {
     stx_tx: UnboundedSender<SignedTransaction>,
     err_rx: Mutex<UnboundedReceiver<ProtocolError>>,
 
+    journal_tx: UnboundedSender<JournalUpdate>,
+
+    pull_policy: PullTxsPolicy,
+
     pin_c: PhantomData<C>,
 }
 
 impl<C, N, S> DefaultMemPoolAdapter<C, N, S>
 where
     C: Crypto,
-    N: Rpc + Gossip + Clone + Unpin + 'static,
-    S: Storage,
+    N: Rpc + PeerTrust + Gossip + Clone + Unpin + 'static,
+    S: Storage + 'static,
 {
     pub fn new(
         network: N,
@@ -159,6 +232,7 @@ where
         let (stx_tx, stx_rx) = unbounded();
         let (err_tx, err_rx) = unbounded();
         let (signal_tx, interval_reached) = channel(1);
+        let (journal_tx, journal_rx) = unbounded();
 
         tokio::spawn(IntervalTxsBroadcaster::timer(
             signal_tx,
@@ -173,6 +247,8 @@ where
             err_tx,
         ));
 
+        tokio::spawn(TxsJournal::write(Arc::clone(&storage), journal_rx));
+
         DefaultMemPoolAdapter {
             network,
             storage,
@@ -184,16 +260,25 @@ where
             stx_tx,
             err_rx: Mutex::new(err_rx),
 
+            journal_tx,
+
+            pull_policy: PullTxsPolicy::default(),
+
             pin_c: PhantomData,
         }
     }
+
+    pub fn with_pull_txs_policy(mut self, policy: PullTxsPolicy) -> Self {
+        self.pull_policy = policy;
+        self
+    }
 }
 
 #[async_trait]
 impl<C, N, S> MemPoolAdapter for DefaultMemPoolAdapter<C, N, S>
 where
     C: Crypto + Send + Sync + 'static,
-    N: Rpc + Gossip + Clone + Unpin + 'static,
+    N: Rpc + PeerTrust + Gossip + Clone + Unpin + 'static,
     S: Storage + 'static,
 {
     async fn pull_txs(
@@ -201,14 +286,7 @@ where
         ctx: Context,
         tx_hashes: Vec<Hash>,
     ) -> ProtocolResult<Vec<SignedTransaction>> {
-        let pull_msg = MsgPullTxs { hashes: tx_hashes };
-
-        let resp_msg = self
-            .network
-            .call::<MsgPullTxs, MsgPushTxs>(ctx, RPC_PULL_TXS, pull_msg, Priority::High)
-            .await?;
-
-        Ok(resp_msg.sig_txs)
+        Ok(pull_txs_within::<C, N>(&self.network, ctx, tx_hashes, &self.pull_policy).await)
     }
 
     async fn broadcast_tx(&self, _ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
@@ -227,29 +305,41 @@ where
         Ok(())
     }
 
-    async fn check_signature(&self, _ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
-        let hash = tx.tx_hash.as_bytes();
-        let pub_key = tx.pubkey.as_ref();
-        let sig = tx.signature.as_ref();
+    async fn check_signature(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
+        let verified = verify_signature::<C>(&tx);
+        if verified.is_err() {
+            self.network
+                .report(ctx, PeerMisbehavior::InvalidTransaction);
+        }
+        verified
+    }
 
-        C::verify_signature(hash.as_ref(), sig, pub_key).map_err(|_| {
-            MemPoolError::CheckSig {
-                tx_hash: tx.tx_hash,
-            }
-            .into()
-        })
+    async fn check_signatures(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+    ) -> Vec<ProtocolResult<()>> {
+        let verified = verify_signatures::<C>(txs).await;
+        if verified.iter().any(Result::is_err) {
+            self.network
+                .report(ctx, PeerMisbehavior::InvalidTransaction);
+        }
+        verified
     }
 
     // TODO: Verify Fee?
     // TODO: Verify Nonce?
     // TODO: Cycle limit?
-    async fn check_transaction(&self, _ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
+    async fn check_transaction(&self, ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
         // Verify transaction hash
         let fixed_bytes = stx.raw.encode_fixed()?;
         let size = fixed_bytes.len() as u64;
         let tx_hash = Hash::digest(fixed_bytes);
 
         if tx_hash != stx.tx_hash {
+            self.network
+                .report(ctx, PeerMisbehavior::InvalidTransaction);
+
             let wrong_hash = MemPoolError::CheckHash {
                 expect: stx.tx_hash,
                 actual: tx_hash,
@@ -316,16 +406,15 @@ where
     }
 
     async fn check_storage_exist(&self, _ctx: Context, tx_hash: Hash) -> ProtocolResult<()> {
-        match self.storage.get_transaction_by_hash(tx_hash.clone()).await {
-            Ok(_) => Err(MemPoolError::CommittedTx { tx_hash }.into()),
-            Err(err) => {
-                // TODO: downcast to StorageError
-                if err.to_string().contains("GetNone") {
-                    Ok(())
-                } else {
-                    Err(err)
-                }
-            }
+        let unknown = self
+            .storage
+            .filter_unknown_transactions(vec![tx_hash.clone()])
+            .await?;
+
+        if unknown.is_empty() {
+            Err(MemPoolError::CommittedTx { tx_hash }.into())
+        } else {
+            Ok(())
         }
     }
 
@@ -334,6 +423,26 @@ where
         Ok(height)
     }
 
+    async fn persist_txs(
+        &self,
+        _ctx: Context,
+        inserted: Vec<SignedTransaction>,
+        removed: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        if inserted.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+
+        self.journal_tx
+            .unbounded_send(JournalUpdate { inserted, removed })
+            .map_err(|_| AdapterError::JournalDrop)?;
+        Ok(())
+    }
+
+    async fn load_persisted_txs(&self, _ctx: Context) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.storage.get_pool_transactions().await
+    }
+
     fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64) {
         self.timeout_gap.store(timeout_gap, Ordering::Relaxed);
         self.cycles_limit.store(cycles_limit, Ordering::Relaxed);
@@ -345,6 +454,12 @@ where
 pub enum AdapterError {
     #[display(fmt = "adapter: interval broadcaster drop")]
     IntervalBroadcasterDrop,
+
+    #[display(fmt = "adapter: txs journal drop")]
+    JournalDrop,
+
+    #[display(fmt = "adapter: pull txs timeout")]
+    PullTimeout,
 }
 
 impl Error for AdapterError {}
@@ -361,15 +476,204 @@ impl From<AdapterError> for ProtocolError {
     }
 }
 
+// The txs of `tx_hashes` that could be pulled before the policy's deadline,
+// in their order.
+async fn pull_txs_within<C: Crypto, N: Rpc + PeerTrust>(
+    network: &N,
+    ctx: Context,
+    tx_hashes: Vec<Hash>,
+    policy: &PullTxsPolicy,
+) -> Vec<SignedTransaction> {
+    let mut pulled = HashMap::new();
+    {
+        let pulling =
+            pull_with_fallback::<C, N>(network, ctx, &tx_hashes, policy, &mut pulled).fuse();
+        let deadline = Delay::new(policy.deadline).fuse();
+        pin_mut!(pulling, deadline);
+
+        select! {
+            () = pulling => (),
+            () = deadline => debug!("[core_mempool]: pull txs reached the deadline"),
+        }
+    }
+
+    tx_hashes
+        .iter()
+        .filter_map(|tx_hash| pulled.remove(tx_hash))
+        .collect()
+}
+
+// Asks the peer `ctx` targets for `tx_hashes`, then up to the policy's
+// fallback peers for whatever is still missing. Whatever arrives is
+// verified and deduplicated into `pulled`, peers answering with forged txs
+// are reported.
+async fn pull_with_fallback<C: Crypto, N: Rpc + PeerTrust>(
+    network: &N,
+    ctx: Context,
+    tx_hashes: &[Hash],
+    policy: &PullTxsPolicy,
+    pulled: &mut HashMap<Hash, SignedTransaction>,
+) {
+    let wanted: HashSet<Hash> = tx_hashes.iter().cloned().collect();
+    let resp = pull_from(
+        network,
+        ctx.clone(),
+        tx_hashes.to_vec(),
+        policy.peer_timeout,
+    )
+    .await;
+    if !admit_pulled::<C>(pulled, &wanted, resp) {
+        network.report(ctx.clone(), PeerMisbehavior::InvalidTransaction);
+    }
+
+    let missing: Vec<Hash> = tx_hashes
+        .iter()
+        .filter(|tx_hash| !pulled.contains_key(tx_hash))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let mut pulls = network
+        .peer_contexts(&ctx, policy.fallback_peers)
+        .into_iter()
+        .map(|peer_ctx| {
+            pull_from(
+                network,
+                peer_ctx.clone(),
+                missing.clone(),
+                policy.peer_timeout,
+            )
+            .map(move |resp| (peer_ctx, resp))
+        })
+        .collect::<FuturesUnordered<_>>();
+    while let Some((peer_ctx, resp)) = pulls.next().await {
+        if !admit_pulled::<C>(pulled, &wanted, resp) {
+            network.report(peer_ctx, PeerMisbehavior::InvalidTransaction);
+        }
+        if missing.iter().all(|tx_hash| pulled.contains_key(tx_hash)) {
+            break;
+        }
+    }
+}
+
+async fn pull_from<N: Rpc>(
+    network: &N,
+    ctx: Context,
+    hashes: Vec<Hash>,
+    timeout: Duration,
+) -> ProtocolResult<Vec<SignedTransaction>> {
+    let pull_msg = MsgPullTxs { hashes };
+    let call = network
+        .call::<MsgPullTxs, MsgPushTxs>(ctx, RPC_PULL_TXS, pull_msg, Priority::High)
+        .fuse();
+    let timeout = Delay::new(timeout).fuse();
+    pin_mut!(call, timeout);
+
+    select! {
+        resp = call => resp.map(|resp_msg| resp_msg.sig_txs),
+        () = timeout => Err(AdapterError::PullTimeout.into()),
+    }
+}
+
+// A peer may answer with txs nobody asked for or with forged ones, both are
+// skipped. Returns false if there is a forged one.
+fn admit_pulled<C: Crypto>(
+    pulled: &mut HashMap<Hash, SignedTransaction>,
+    wanted: &HashSet<Hash>,
+    resp: ProtocolResult<Vec<SignedTransaction>>,
+) -> bool {
+    let txs = match resp {
+        Ok(txs) => txs,
+        Err(err) => {
+            debug!("[core_mempool]: pull txs {}", err);
+            return true;
+        }
+    };
+
+    let mut verified = true;
+    for tx in txs.into_iter() {
+        if !wanted.contains(&tx.tx_hash) || pulled.contains_key(&tx.tx_hash) {
+            continue;
+        }
+        match verify_hash(&tx).and_then(|()| verify_signature::<C>(&tx)) {
+            Ok(()) => {
+                pulled.insert(tx.tx_hash.clone(), tx);
+            }
+            Err(err) => {
+                debug!("[core_mempool]: pulled tx {}", err);
+                verified = false;
+            }
+        }
+    }
+    verified
+}
+
+fn verify_hash(tx: &SignedTransaction) -> ProtocolResult<()> {
+    let tx_hash = Hash::digest(tx.raw.encode_fixed()?);
+    if tx_hash != tx.tx_hash {
+        return Err(MemPoolError::CheckHash {
+            expect: tx.tx_hash.clone(),
+            actual: tx_hash,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+// Verifies on the rayon pool, the executor thread only waits for the
+// results. They are in the order of `txs`.
+async fn verify_signatures<C: Crypto + 'static>(
+    txs: Vec<SignedTransaction>,
+) -> Vec<ProtocolResult<()>> {
+    let tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+    let (verified_tx, verified_rx) = oneshot::channel();
+
+    rayon::spawn(move || {
+        let verified = txs.par_iter().map(verify_signature::<C>).collect();
+        let _ = verified_tx.send(verified);
+    });
+
+    match verified_rx.await {
+        Ok(verified) => verified,
+        // The job went away without results, no signature counts as verified
+        Err(_) => tx_hashes
+            .into_iter()
+            .map(|tx_hash| Err(MemPoolError::CheckSig { tx_hash }.into()))
+            .collect(),
+    }
+}
+
+fn verify_signature<C: Crypto>(tx: &SignedTransaction) -> ProtocolResult<()> {
+    let hash = tx.tx_hash.as_bytes();
+    let pub_key = tx.pubkey.as_ref();
+    let sig = tx.signature.as_ref();
+
+    C::verify_signature(hash.as_ref(), sig, pub_key).map_err(|_| {
+        MemPoolError::CheckSig {
+            tx_hash: tx.tx_hash.clone(),
+        }
+        .into()
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::IntervalTxsBroadcaster;
+    use super::{
+        pull_txs_within, verify_signatures, IntervalTxsBroadcaster, JournalUpdate, PullTxsPolicy,
+    };
 
-    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs};
+    use crate::{
+        adapter::message::{MsgNewTxs, MsgPullTxs, MsgPushTxs},
+        tests::default_mock_txs,
+    };
 
+    use common_crypto::Secp256k1;
+    use futures_timer::Delay;
     use protocol::{
-        traits::{Context, Gossip, MessageCodec, Priority},
-        types::Address,
+        traits::{Context, Gossip, MessageCodec, PeerMisbehavior, PeerTrust, Priority, Rpc},
+        types::{Address, SignedTransaction},
         Bytes, ProtocolResult,
     };
 
@@ -438,6 +742,107 @@ mod tests {
         }
     }
 
+    const MOCK_PEER: &str = "mock_peer";
+
+    // Peer 0 is the origin, it and every other peer answers after its delay
+    // with the txs it holds
+    struct MockRpc {
+        peers:   Vec<(Duration, Vec<SignedTransaction>)>,
+        calls:   Mutex<Vec<usize>>,
+        reports: Mutex<Vec<usize>>,
+    }
+
+    impl MockRpc {
+        fn new(peers: Vec<(Duration, Vec<SignedTransaction>)>) -> Self {
+            MockRpc {
+                peers,
+                calls: Default::default(),
+                reports: Default::default(),
+            }
+        }
+    }
+
+    impl PeerTrust for MockRpc {
+        fn report(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+            assert_eq!(misbehavior, PeerMisbehavior::InvalidTransaction);
+
+            let peer = ctx.get::<usize>(MOCK_PEER).cloned().unwrap_or(0);
+            self.reports.lock().push(peer);
+        }
+    }
+
+    #[async_trait]
+    impl Rpc for MockRpc {
+        async fn call<M, R>(
+            &self,
+            ctx: Context,
+            _: &str,
+            mut msg: M,
+            _: Priority,
+        ) -> ProtocolResult<R>
+        where
+            M: MessageCodec,
+            R: MessageCodec,
+        {
+            let peer = ctx.get::<usize>(MOCK_PEER).cloned().unwrap_or(0);
+            self.calls.lock().push(peer);
+
+            let pull_msg = MsgPullTxs::decode(msg.encode().await?).await?;
+            let (delay, txs) = &self.peers[peer];
+            Delay::new(*delay).await;
+
+            let sig_txs = txs
+                .iter()
+                .filter(|tx| pull_msg.hashes.contains(&tx.tx_hash))
+                .cloned()
+                .collect();
+            R::decode(MsgPushTxs { sig_txs }.encode().await?).await
+        }
+
+        async fn response<M>(
+            &self,
+            _: Context,
+            _: &str,
+            _: ProtocolResult<M>,
+            _: Priority,
+        ) -> ProtocolResult<()>
+        where
+            M: MessageCodec,
+        {
+            unreachable!()
+        }
+
+        fn peer_contexts(&self, _: &Context, limit: usize) -> Vec<Context> {
+            (1..self.peers.len())
+                .take(limit)
+                .map(|peer| Context::new().with_value::<usize>(MOCK_PEER, peer))
+                .collect()
+        }
+    }
+
+    fn policy(peer_timeout: u64, deadline: u64) -> PullTxsPolicy {
+        PullTxsPolicy {
+            peer_timeout:   Duration::from_millis(peer_timeout),
+            fallback_peers: 3,
+            deadline:       Duration::from_millis(deadline),
+        }
+    }
+
+    async fn pull(
+        rpc: &MockRpc,
+        stxs: &[SignedTransaction],
+        policy: PullTxsPolicy,
+    ) -> Vec<SignedTransaction> {
+        let tx_hashes = stxs.iter().map(|stx| stx.tx_hash.clone()).collect();
+        pull_txs_within::<Secp256k1, _>(rpc, Context::new(), tx_hashes, &policy).await
+    }
+
+    fn sorted_calls(rpc: &MockRpc) -> Vec<usize> {
+        let mut calls = rpc.calls.lock().clone();
+        calls.sort();
+        calls
+    }
+
     macro_rules! pop_msg {
         ($msgs:expr) => {{
             let msg = $msgs.pop().expect("should have one message");
@@ -445,6 +850,90 @@ mod tests {
         }};
     }
 
+    #[test]
+    fn test_journal_update_merge() {
+        let stxs = default_mock_txs(3);
+        let hash = |i: usize| stxs[i].tx_hash.clone();
+
+        let mut update = JournalUpdate {
+            inserted: stxs[..2].to_vec(),
+            removed:  vec![],
+        };
+        // Removing a queued insert drops it, inserting again keeps it last
+        update.merge(JournalUpdate {
+            inserted: vec![],
+            removed:  vec![hash(0), hash(2)],
+        });
+        update.merge(JournalUpdate {
+            inserted: vec![stxs[2].clone()],
+            removed:  vec![],
+        });
+
+        let inserted = update.inserted.iter().map(|stx| stx.tx_hash.clone());
+        assert_eq!(inserted.collect::<Vec<_>>(), vec![hash(1), hash(2)]);
+        assert_eq!(update.removed, vec![hash(0), hash(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_pull_txs_fallback() {
+        let stxs = default_mock_txs(4);
+        let slow = Duration::from_secs(10);
+        let quick = Duration::from_millis(10);
+
+        // A forged copy of the third tx comes first, then the real one
+        let mut forged = stxs[2].clone();
+        forged.signature = stxs[3].signature.clone();
+        let rpc = MockRpc::new(vec![
+            (slow, stxs.clone()),
+            (quick, vec![stxs[0].clone(), stxs[1].clone(), forged]),
+            (quick * 2, stxs[1..].to_vec()),
+            (slow, stxs.clone()),
+        ]);
+
+        let now = Instant::now();
+        let pulled = pull(&rpc, &stxs, policy(100, 1000)).await;
+        assert!(now.elapsed() < Duration::from_millis(500));
+        assert_eq!(pulled, stxs);
+        assert_eq!(sorted_calls(&rpc), vec![0, 1, 2, 3]);
+        // Only the peer with the forged tx is reported
+        assert_eq!(*rpc.reports.lock(), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_signatures() {
+        let mut stxs = default_mock_txs(4);
+        stxs[2].signature = stxs[3].signature.clone();
+
+        let verified = verify_signatures::<Secp256k1>(stxs).await;
+        let signed = verified.iter().map(Result::is_ok).collect::<Vec<_>>();
+        assert_eq!(signed, vec![true, true, false, true]);
+    }
+
+    #[tokio::test]
+    async fn test_pull_txs_origin_answers() {
+        let stxs = default_mock_txs(2);
+        let rpc = MockRpc::new(vec![
+            (Duration::from_millis(10), stxs.clone()),
+            (Duration::from_millis(10), stxs.clone()),
+        ]);
+
+        let pulled = pull(&rpc, &stxs, policy(100, 1000)).await;
+        assert_eq!(pulled, stxs);
+        assert_eq!(sorted_calls(&rpc), vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_pull_txs_deadline() {
+        let stxs = default_mock_txs(2);
+        let slow = Duration::from_secs(10);
+        let rpc = MockRpc::new(vec![(slow, stxs.clone()), (slow, stxs.clone())]);
+
+        let now = Instant::now();
+        let pulled = pull(&rpc, &stxs, policy(1000, 200)).await;
+        assert!(now.elapsed() < Duration::from_millis(500));
+        assert!(pulled.is_empty());
+    }
+
     #[tokio::test]
     async fn test_interval_timer() {
         let (tx, mut rx) = channel(1);
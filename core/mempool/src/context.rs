@@ -3,10 +3,26 @@ use protocol::traits::Context;
 const TXS_ORIGINAL_KEY: &str = "txs_original";
 const NETWORK_TXS: usize = 1;
 
+/// Where a transaction entered the pool from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxOrigin {
+    /// Submitted through the API of this node.
+    Api,
+    /// Gossiped by a peer.
+    Peer,
+    /// Pulled in by propose sync.
+    Sync,
+    /// Persisted before a restart.
+    Recovered,
+}
+
 pub(crate) trait TxContext {
     fn mark_network_origin_new_txs(&self) -> Self;
 
     fn is_network_origin_txs(&self) -> bool;
+
+    /// Origin of the new transactions inserted with this context.
+    fn tx_origin(&self) -> TxOrigin;
 }
 
 impl TxContext for Context {
@@ -17,4 +33,12 @@ impl TxContext for Context {
     fn is_network_origin_txs(&self) -> bool {
         self.get::<usize>(TXS_ORIGINAL_KEY) == Some(&NETWORK_TXS)
     }
+
+    fn tx_origin(&self) -> TxOrigin {
+        if self.is_network_origin_txs() {
+            TxOrigin::Peer
+        } else {
+            TxOrigin::Api
+        }
+    }
 }
@@ -0,0 +1,50 @@
+//! Events published by the pool for every transaction it takes in.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use parking_lot::Mutex;
+
+use protocol::types::{Address, Hash};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NewTxEvent {
+    pub tx_hash:      Hash,
+    pub sender:       Address,
+    pub cycles_price: u64,
+}
+
+/// Hands new transaction events to every subscriber without ever waiting on
+/// one. A subscriber whose channel is full misses the event, which is
+/// counted in `dropped`.
+#[derive(Default)]
+pub struct NewTxPublisher {
+    subscribers: Mutex<Vec<Sender<NewTxEvent>>>,
+    dropped:     AtomicU64,
+}
+
+impl NewTxPublisher {
+    pub fn subscribe(&self, capacity: usize) -> Receiver<NewTxEvent> {
+        let (tx, rx) = channel(capacity);
+        self.subscribers.lock().push(tx);
+        rx
+    }
+
+    pub fn publish(&self, event: NewTxEvent) {
+        let mut subscribers = self.subscribers.lock();
+
+        // Dropped receivers unsubscribe
+        subscribers.retain(|subscriber| !subscriber.is_closed());
+        for subscriber in subscribers.iter_mut() {
+            if let Err(err) = subscriber.try_send(event.clone()) {
+                if err.is_full() {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
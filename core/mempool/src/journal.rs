@@ -0,0 +1,55 @@
+//! Recently rejected transactions, so a sender asking after a transaction
+//! that never made it into a block can be told why.
+
+use std::collections::VecDeque;
+
+use parking_lot::Mutex;
+
+use protocol::traits::RejectionRecord;
+use protocol::types::Hash;
+
+/// Keeps the latest `capacity` rejections, the oldest making room first.
+pub struct RejectionJournal {
+    capacity: usize,
+    records:  Mutex<VecDeque<RejectionRecord>>,
+}
+
+impl RejectionJournal {
+    pub fn new(capacity: usize) -> Self {
+        RejectionJournal {
+            capacity,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn record(&self, tx_hash: Hash, reason: String, timestamp: u64) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut records = self.records.lock();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(RejectionRecord {
+            tx_hash,
+            reason,
+            timestamp,
+        });
+    }
+
+    /// The latest rejection of `tx_hash` still kept.
+    pub fn get(&self, tx_hash: &Hash) -> Option<RejectionRecord> {
+        self.records
+            .lock()
+            .iter()
+            .rev()
+            .find(|record| &record.tx_hash == tx_hash)
+            .cloned()
+    }
+
+    /// Every kept rejection, oldest first.
+    pub fn records(&self) -> Vec<RejectionRecord> {
+        self.records.lock().iter().cloned().collect()
+    }
+}
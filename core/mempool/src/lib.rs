@@ -2,7 +2,10 @@
 
 mod adapter;
 mod context;
+mod events;
+mod journal;
 mod map;
+mod seen;
 #[cfg(test)]
 mod tests;
 mod tx_cache;
@@ -11,27 +14,40 @@ pub use adapter::message::{
     MsgPushTxs, NewTxsHandler, PullTxsHandler, END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
 };
 pub use adapter::DefaultMemPoolAdapter;
-pub use adapter::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+pub use adapter::{PullTxsPolicy, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+pub use context::TxOrigin;
+pub use events::NewTxEvent;
 
 use std::error::Error;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use async_trait::async_trait;
 use derive_more::Display;
+use futures::channel::mpsc::Receiver;
 use tokio::sync::RwLock;
 
-use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
-use protocol::types::{Hash, SignedTransaction};
+use protocol::traits::{
+    Context, MemPool, MemPoolAdapter, MixedTxHashes, PendingTx, PoolStats, RejectionRecord,
+};
+use protocol::types::{Address, Hash, SignedTransaction};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
-use crate::context::TxContext;
+use crate::context::{TxContext, TxOrigin};
+use crate::events::NewTxPublisher;
+use crate::journal::RejectionJournal;
 use crate::map::Map;
-use crate::tx_cache::TxCache;
+use crate::tx_cache::{now_millis, TxCache};
+
+/// Percentage by which a transaction must outbid the pending one with the
+/// same sender and nonce to replace it.
+pub const DEFAULT_REPLACE_PRICE_BUMP: u64 = 10;
+/// Pending transactions a single sender may have in the pool.
+pub const DEFAULT_SENDER_LIMIT: usize = 64;
+/// Rejected transactions remembered for `MemPool::rejection_reason`.
+pub const DEFAULT_REJECTION_JOURNAL_SIZE: usize = 1024;
 
 /// Memory pool for caching transactions.
 pub struct HashMemPool<Adapter: MemPoolAdapter> {
-    /// Pool size limit.
-    pool_size:      usize,
     /// A system param limits the life time of an off-chain transaction.
     timeout_gap:    AtomicU64,
     /// A structure for caching new transactions and responsible transactions of
@@ -43,6 +59,10 @@ pub struct HashMemPool<Adapter: MemPoolAdapter> {
     adapter:        Adapter,
     /// exclusive flush_memory and insert_tx to avoid repeat txs insertion.
     flush_lock:     RwLock<()>,
+    /// Tells subscribers about every inserted transaction.
+    new_tx_events:  NewTxPublisher,
+    /// Why recent transactions were turned away or dropped.
+    rejections:     RejectionJournal,
 }
 
 impl<Adapter> HashMemPool<Adapter>
@@ -51,15 +71,51 @@ where
 {
     pub fn new(pool_size: usize, adapter: Adapter) -> Self {
         HashMemPool {
-            pool_size,
             timeout_gap: AtomicU64::new(0),
-            tx_cache: TxCache::new(pool_size * 2),
+            tx_cache: TxCache::new(pool_size),
             callback_cache: Map::new(pool_size),
             adapter,
             flush_lock: RwLock::new(()),
+            new_tx_events: NewTxPublisher::default(),
+            rejections: RejectionJournal::new(DEFAULT_REJECTION_JOURNAL_SIZE),
         }
     }
 
+    pub fn with_replace_price_bump(mut self, percent: u64) -> Self {
+        self.tx_cache = self.tx_cache.with_replace_price_bump(percent);
+        self
+    }
+
+    pub fn with_sender_limit(mut self, sender_limit: usize) -> Self {
+        self.tx_cache = self.tx_cache.with_sender_limit(sender_limit);
+        self
+    }
+
+    /// Transactions calling one of `methods`, given as `(service_name,
+    /// method)`, are packaged before all others whatever they pay, and a full
+    /// pool evicts its cheapest transaction to take them.
+    pub fn with_priority_methods(mut self, methods: Vec<(String, String)>) -> Self {
+        self.tx_cache = self.tx_cache.with_priority_methods(methods);
+        self
+    }
+
+    /// Remembers the reasons of the latest `size` rejections, none at 0.
+    pub fn with_rejection_journal_size(mut self, size: usize) -> Self {
+        self.rejections = RejectionJournal::new(size);
+        self
+    }
+
+    /// Receives an event for every transaction inserted from now on, as
+    /// long as no more than `capacity` of them are left unread.
+    pub fn subscribe_new_txs(&self, capacity: usize) -> Receiver<NewTxEvent> {
+        self.new_tx_events.subscribe(capacity)
+    }
+
+    /// Events missed by subscribers that fell behind.
+    pub fn dropped_new_tx_events(&self) -> u64 {
+        self.new_tx_events.dropped()
+    }
+
     pub fn get_tx_cache(&self) -> &TxCache {
         &self.tx_cache
     }
@@ -72,6 +128,93 @@ where
         &self.adapter
     }
 
+    /// Puts the transactions persisted before a restart back into the pool,
+    /// except the ones that timed out, got committed or no longer pass the
+    /// checks meanwhile. Returns how many were recovered.
+    pub async fn recover(&self, ctx: Context) -> ProtocolResult<usize> {
+        let _lock = self.flush_lock.write().await;
+
+        let current_height = self.adapter.get_latest_height(ctx.clone()).await?;
+        let timeout = current_height + self.timeout_gap.load(Ordering::Relaxed);
+        let txs = self.adapter.load_persisted_txs(ctx.clone()).await?;
+
+        let mut recovered = 0;
+        let mut stale_hashes = Vec::new();
+        for tx in txs.into_iter() {
+            let tx_hash = tx.tx_hash.clone();
+            if tx.raw.timeout <= current_height || tx.raw.timeout > timeout {
+                stale_hashes.push(tx_hash);
+                continue;
+            }
+
+            match self.recover_tx(ctx.clone(), tx).await {
+                // Back in the pool, in place of a transaction recovered before
+                Ok(Some(evicted_hash)) => {
+                    recovered += 1;
+                    stale_hashes.push(evicted_hash);
+                }
+                Ok(None) => recovered += 1,
+                Err(_) => stale_hashes.push(tx_hash),
+            }
+        }
+
+        log::info!(
+            "[core_mempool]: recovered {:?} txs, dropped {:?}",
+            recovered,
+            stale_hashes.len()
+        );
+        self.adapter
+            .persist_txs(ctx, Vec::new(), stale_hashes)
+            .await?;
+        Ok(recovered)
+    }
+
+    async fn recover_tx(
+        &self,
+        ctx: Context,
+        tx: SignedTransaction,
+    ) -> ProtocolResult<Option<Hash>> {
+        self.tx_cache.check_reach_limit(&tx)?;
+        self.adapter
+            .check_signature(ctx.clone(), tx.clone())
+            .await?;
+        self.adapter
+            .check_transaction(ctx.clone(), tx.clone())
+            .await?;
+        self.adapter
+            .check_storage_exist(ctx, tx.tx_hash.clone())
+            .await?;
+        self.tx_cache.insert_new_tx(tx, TxOrigin::Recovered)
+    }
+
+    fn reject(&self, tx_hash: &Hash, origin: TxOrigin, err: &ProtocolError) {
+        log::debug!(
+            "[core_mempool]: tx {:?} from {:?} rejected, {}",
+            tx_hash,
+            origin,
+            err
+        );
+
+        // A dup of a pooled tx, the pool still has it
+        if !self.contains(tx_hash) {
+            self.rejections
+                .record(tx_hash.clone(), err.to_string(), now_millis());
+        }
+    }
+
+    // Drops by package and expire are logged in bulk by the callers
+    fn record_dropped(&self, dropped: Vec<(Hash, MemPoolError)>) -> Vec<Hash> {
+        let timestamp = now_millis();
+        dropped
+            .into_iter()
+            .map(|(tx_hash, err)| {
+                let reason = ProtocolError::from(err).to_string();
+                self.rejections.record(tx_hash.clone(), reason, timestamp);
+                tx_hash
+            })
+            .collect()
+    }
+
     fn show_unknown_txs(&self, tx_hashes: Vec<Hash>) -> Vec<Hash> {
         self.tx_cache
             .show_unknown(tx_hashes)
@@ -86,25 +229,98 @@ where
         tx: SignedTransaction,
         tx_type: TxType,
     ) -> ProtocolResult<()> {
+        // Repeats of a pooled transaction, common under gossip, turn back
+        // before waiting on any lock
+        self.tx_cache.check_seen(&tx.tx_hash)?;
         let _lock = self.flush_lock.read().await;
 
-        let tx_hash = &tx.tx_hash;
-        self.tx_cache.check_reach_limit(self.pool_size)?;
-        self.tx_cache.check_exist(tx_hash)?;
+        self.precheck_tx(&tx)?;
         self.adapter
             .check_signature(ctx.clone(), tx.clone())
             .await?;
+        self.admit_tx(ctx, tx, tx_type).await
+    }
+
+    /// Inserts `txs` with their signatures checked in parallel, a failing
+    /// transaction doesn't keep the others out.
+    async fn insert_txs(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+        tx_type: TxType,
+    ) -> Vec<ProtocolResult<()>> {
+        let seen: Vec<ProtocolResult<()>> = txs
+            .iter()
+            .map(|tx| self.tx_cache.check_seen(&tx.tx_hash))
+            .collect();
+        let _lock = self.flush_lock.read().await;
+
+        let mut results: Vec<ProtocolResult<()>> = txs
+            .iter()
+            .zip(seen.into_iter())
+            .map(|(tx, seen)| seen.and_then(|()| self.precheck_tx(tx)))
+            .collect();
+        let candidates: Vec<usize> = (0..txs.len()).filter(|i| results[*i].is_ok()).collect();
+        let candidate_txs = candidates.iter().map(|i| txs[*i].clone()).collect();
+        let sig_results = self
+            .adapter
+            .check_signatures(ctx.clone(), candidate_txs)
+            .await;
+
+        for (i, sig_result) in candidates.into_iter().zip(sig_results.into_iter()) {
+            results[i] = match sig_result {
+                Ok(()) => self.admit_tx(ctx.clone(), txs[i].clone(), tx_type).await,
+                Err(err) => Err(err),
+            };
+        }
+        results
+    }
+
+    // Cheap checks worth doing before verifying the signature
+    fn precheck_tx(&self, tx: &SignedTransaction) -> ProtocolResult<()> {
+        self.tx_cache.check_reach_limit(tx)?;
+        self.tx_cache.check_exist(&tx.tx_hash)
+    }
+
+    // Callers hold the flush lock and checked the signature
+    async fn admit_tx(
+        &self,
+        ctx: Context,
+        tx: SignedTransaction,
+        tx_type: TxType,
+    ) -> ProtocolResult<()> {
+        let tx_hash = &tx.tx_hash;
         self.adapter
             .check_transaction(ctx.clone(), tx.clone())
             .await?;
         self.adapter
             .check_storage_exist(ctx.clone(), tx_hash.clone())
             .await?;
-        match tx_type {
-            TxType::NewTx => self.tx_cache.insert_new_tx(tx.clone())?,
+        let displaced = match tx_type {
+            TxType::NewTx => self.tx_cache.insert_new_tx(tx.clone(), ctx.tx_origin())?,
             TxType::ProposeTx => self.tx_cache.insert_propose_tx(tx.clone())?,
+        };
+        // Forget a replaced or evicted transaction so that a late order sync
+        // can't bring it back
+        if let Some(displaced_hash) = displaced.as_ref() {
+            self.callback_cache.remove(displaced_hash);
         }
+        self.adapter
+            .persist_txs(
+                ctx.clone(),
+                vec![tx.clone()],
+                displaced.into_iter().collect(),
+            )
+            .await?;
 
+        self.new_tx_events.publish(NewTxEvent {
+            tx_hash:      tx_hash.clone(),
+            sender:       Address::from_pubkey_bytes(tx.pubkey.clone())?,
+            cycles_price: tx.raw.cycles_price,
+        });
+
+        // Only transactions submitted here are broadcast, a gossiped one is
+        // never sent on, back to its source peer included
         if !ctx.is_network_origin_txs() {
             self.adapter.broadcast_tx(ctx, tx).await?;
         }
@@ -119,7 +335,31 @@ where
     Adapter: MemPoolAdapter,
 {
     async fn insert(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
-        self.insert_tx(ctx, tx, TxType::NewTx).await
+        let origin = ctx.tx_origin();
+        let tx_hash = tx.tx_hash.clone();
+
+        let result = self.insert_tx(ctx, tx, TxType::NewTx).await;
+        if let Err(err) = result.as_ref() {
+            self.reject(&tx_hash, origin, err);
+        }
+        result
+    }
+
+    async fn insert_batch(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+    ) -> Vec<ProtocolResult<()>> {
+        let origin = ctx.tx_origin();
+        let tx_hashes: Vec<Hash> = txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+
+        let results = self.insert_txs(ctx, txs, TxType::NewTx).await;
+        for (tx_hash, result) in tx_hashes.iter().zip(results.iter()) {
+            if let Err(err) = result {
+                self.reject(tx_hash, origin, err);
+            }
+        }
+        results
     }
 
     async fn package(
@@ -134,32 +374,52 @@ where
             self.tx_cache.len(),
             self.tx_cache.queue_len(),
         );
-        self.tx_cache.package(
+        let (mixed_tx_hashes, dropped) = self.tx_cache.package(
             cycles_limit,
             tx_num_limit,
             current_height,
             current_height + self.timeout_gap.load(Ordering::Relaxed),
-        )
+        )?;
+        let dropped = self.record_dropped(dropped);
+        if !dropped.is_empty() {
+            self.adapter.persist_txs(ctx, Vec::new(), dropped).await?;
+        }
+
+        Ok(mixed_tx_hashes)
     }
 
     async fn flush(&self, ctx: Context, tx_hashes: Vec<Hash>) -> ProtocolResult<()> {
         let _lock = self.flush_lock.write().await;
 
-        let current_height = self.adapter.get_latest_height(ctx.clone()).await?;
         log::info!(
             "[core_mempool]: flush mempool with {:?} tx_hashes",
             tx_hashes.len(),
         );
-        self.tx_cache.flush(
-            &tx_hashes,
-            current_height,
-            current_height + self.timeout_gap.load(Ordering::Relaxed),
-        );
+        self.tx_cache.flush(&tx_hashes);
         self.callback_cache.clear();
+        self.adapter.persist_txs(ctx, Vec::new(), tx_hashes).await?;
 
         Ok(())
     }
 
+    async fn expire(&self, ctx: Context, current_height: u64) -> ProtocolResult<usize> {
+        let expired = self.record_dropped(self.tx_cache.expire(
+            current_height,
+            current_height + self.timeout_gap.load(Ordering::Relaxed),
+        ));
+        let count = expired.len();
+        if count != 0 {
+            log::info!(
+                "[core_mempool]: {:?} txs expired at height {:?}",
+                count,
+                current_height
+            );
+            self.adapter.persist_txs(ctx, Vec::new(), expired).await?;
+        }
+
+        Ok(count)
+    }
+
     async fn get_full_txs(
         &self,
         _ctx: Context,
@@ -204,6 +464,13 @@ where
                 }
                 .into());
             }
+            let sig_results = self
+                .adapter
+                .check_signatures(ctx.clone(), txs.clone())
+                .await;
+            for result in sig_results.into_iter() {
+                result?;
+            }
             txs.into_iter().for_each(|tx| {
                 self.callback_cache.insert(tx.tx_hash.clone(), tx);
             });
@@ -220,16 +487,45 @@ where
         let unknown_hashes = self.show_unknown_txs(propose_tx_hashes);
         if !unknown_hashes.is_empty() {
             let txs = self.adapter.pull_txs(ctx.clone(), unknown_hashes).await?;
-            // TODO: concurrently insert
-            for tx in txs.into_iter() {
-                // Should not handle error here, it is normal that transactions
-                // response here are exist in pool.
-                let _ = self.insert_tx(ctx.clone(), tx, TxType::ProposeTx).await;
-            }
+            // Should not handle error here, it is normal that transactions
+            // response here are exist in pool.
+            let _ = self.insert_txs(ctx, txs, TxType::ProposeTx).await;
         }
         Ok(())
     }
 
+    async fn get(
+        &self,
+        _ctx: Context,
+        tx_hash: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        let tx = self
+            .tx_cache
+            .get(tx_hash)
+            .or_else(|| self.callback_cache.get(tx_hash));
+        Ok(tx)
+    }
+
+    fn contains(&self, tx_hash: &Hash) -> bool {
+        self.tx_cache.contain(tx_hash) || self.callback_cache.contains_key(tx_hash)
+    }
+
+    fn stats(&self) -> PoolStats {
+        self.tx_cache.stats()
+    }
+
+    fn content(&self, limit: usize) -> Vec<PendingTx> {
+        self.tx_cache.content(limit)
+    }
+
+    fn rejection_reason(&self, tx_hash: &Hash) -> Option<RejectionRecord> {
+        self.rejections.get(tx_hash)
+    }
+
+    fn rejections(&self) -> Vec<RejectionRecord> {
+        self.rejections.records()
+    }
+
     fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64) {
         self.adapter
             .set_args(timeout_gap, cycles_limit, max_tx_size);
@@ -237,6 +533,7 @@ where
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum TxType {
     NewTx,
     ProposeTx,
@@ -303,6 +600,32 @@ pub enum MemPoolError {
 
     #[display(fmt = "Tx: {:?} invalid timeout", tx_hash)]
     InvalidTimeout { tx_hash: Hash },
+
+    #[display(
+        fmt = "Tx: {:?} can't replace {:?}, cycles price: {}, required: {}",
+        tx_hash,
+        pending,
+        cycles_price,
+        min_price
+    )]
+    ReplaceUnderpriced {
+        tx_hash:      Hash,
+        pending:      Hash,
+        cycles_price: u64,
+        min_price:    u64,
+    },
+
+    #[display(
+        fmt = "Tx: {:?} exceeds the {} pending txs of sender {:?}",
+        tx_hash,
+        sender_limit,
+        sender
+    )]
+    SenderLimit {
+        tx_hash:      Hash,
+        sender:       Address,
+        sender_limit: usize,
+    },
 }
 
 impl Error for MemPoolError {}
@@ -0,0 +1,157 @@
+//! Duplicate detection that runs before an insert takes any pool lock.
+//!
+//! A counting bloom filter over the pooled hashes answers most lookups with a
+//! few atomic loads. A hit is only trusted once an exact lru of recent hashes
+//! confirms it, so a false positive falls through to the full insert path
+//! instead of turning a new transaction away.
+
+use std::cmp;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use lru::LruCache;
+use parking_lot::Mutex;
+
+use protocol::types::Hash;
+
+// Filter counters per transaction the pool holds
+const SLOTS_PER_TX: usize = 8;
+// Counters each hash maps to
+const FILTER_HASHES: usize = 3;
+const RECENT_SHARDS: usize = 16;
+
+pub struct SeenTxs {
+    filter: Vec<AtomicU8>,
+    recent: Vec<Mutex<LruCache<Hash, ()>>>,
+}
+
+impl SeenTxs {
+    pub fn new(pool_size: usize) -> Self {
+        let slots = cmp::max(pool_size * SLOTS_PER_TX, 1).next_power_of_two();
+        let shard_size = cmp::max(pool_size / RECENT_SHARDS, 1);
+
+        SeenTxs {
+            filter: (0..slots).map(|_| AtomicU8::new(0)).collect(),
+            recent: (0..RECENT_SHARDS)
+                .map(|_| Mutex::new(LruCache::new(shard_size)))
+                .collect(),
+        }
+    }
+
+    /// Whether `tx_hash` is surely in the pool. `false` only means it has to
+    /// be looked up the usual way.
+    pub fn contains(&self, tx_hash: &Hash) -> bool {
+        let filtered_out = self
+            .slots(tx_hash)
+            .iter()
+            .any(|slot| self.filter[*slot].load(Ordering::Acquire) == 0);
+        if filtered_out {
+            return false;
+        }
+
+        self.shard(tx_hash).lock().contains(tx_hash)
+    }
+
+    pub fn insert(&self, tx_hash: &Hash) {
+        for slot in self.slots(tx_hash).iter() {
+            increment(&self.filter[*slot]);
+        }
+        self.shard(tx_hash).lock().put(tx_hash.clone(), ());
+    }
+
+    /// Callers remove each inserted hash once.
+    pub fn remove(&self, tx_hash: &Hash) {
+        for slot in self.slots(tx_hash).iter() {
+            decrement(&self.filter[*slot]);
+        }
+        self.shard(tx_hash).lock().pop(tx_hash);
+    }
+
+    // Transaction hashes are uniform already, their leading words do as the
+    // filter hashes
+    fn slots(&self, tx_hash: &Hash) -> [usize; FILTER_HASHES] {
+        let bytes = tx_hash.as_bytes();
+        let mask = self.filter.len() - 1;
+
+        let mut slots = [0; FILTER_HASHES];
+        for (i, slot) in slots.iter_mut().enumerate() {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[i * 8..(i + 1) * 8]);
+            *slot = u64::from_le_bytes(word) as usize & mask;
+        }
+        slots
+    }
+
+    fn shard(&self, tx_hash: &Hash) -> &Mutex<LruCache<Hash, ()>> {
+        let last = tx_hash.as_bytes()[31] as usize;
+        &self.recent[last % RECENT_SHARDS]
+    }
+}
+
+// A saturated counter stays put, it no longer knows how many hashes share it
+fn increment(counter: &AtomicU8) {
+    let mut current = counter.load(Ordering::Relaxed);
+    while current != u8::max_value() {
+        match counter.compare_exchange_weak(
+            current,
+            current + 1,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+fn decrement(counter: &AtomicU8) {
+    let mut current = counter.load(Ordering::Relaxed);
+    while current != 0 && current != u8::max_value() {
+        match counter.compare_exchange_weak(
+            current,
+            current - 1,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::types::Hash;
+    use protocol::Bytes;
+
+    use super::SeenTxs;
+
+    fn hash(i: u32) -> Hash {
+        Hash::digest(Bytes::from(i.to_be_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_seen_txs() {
+        let seen = SeenTxs::new(1024);
+
+        seen.insert(&hash(0));
+        assert!(seen.contains(&hash(0)));
+        assert!(!seen.contains(&hash(1)));
+
+        seen.remove(&hash(0));
+        assert!(!seen.contains(&hash(0)));
+    }
+
+    #[test]
+    fn test_filter_hit_without_exact_entry() {
+        // One exact entry per shard, so the shard forgets all but the latest
+        let seen = SeenTxs::new(1);
+        let hashes: Vec<Hash> = (0..64).map(hash).collect();
+        for tx_hash in hashes.iter() {
+            seen.insert(tx_hash);
+        }
+
+        let confirmed = hashes.iter().filter(|h| seen.contains(h)).count();
+        assert!(confirmed <= 16);
+        assert!(seen.contains(&hashes[63]));
+    }
+}
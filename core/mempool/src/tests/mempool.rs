@@ -1,10 +1,12 @@
 use std::sync::Arc;
+use std::thread;
 
 use test::Bencher;
 
-use protocol::types::Hash;
+use protocol::types::{Address, Hash};
 
 use super::*;
+use crate::context::TxContext;
 
 macro_rules! insert {
     (normal($pool_size: expr, $input: expr, $output: expr)) => {
@@ -40,6 +42,291 @@ fn test_insert() {
     insert!(invalid(80, 10, 80));
 }
 
+#[test]
+fn test_insert_rejections() {
+    let mempool = new_mempool(2, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE);
+    let txs = mock_txs(3, 1, TIMEOUT);
+    let insert = |tx: SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx));
+    let rejection = |tx: SignedTransaction| insert(tx).unwrap_err().to_string();
+
+    insert(txs[0].clone()).unwrap();
+    assert!(rejection(txs[0].clone()).contains("Dup"));
+    assert!(rejection(txs[3].clone()).contains("CheckSig"));
+
+    // The hash is recomputed from the raw transaction, not taken on trust
+    let mut forged = txs[1].clone();
+    forged.tx_hash = txs[2].tx_hash.clone();
+    assert!(rejection(forged).contains("CheckHash"));
+
+    insert(txs[1].clone()).unwrap();
+    assert!(rejection(txs[2].clone()).contains("ReachLimit"));
+    assert_eq!(mempool.get_tx_cache().len(), 2);
+}
+
+#[test]
+fn test_concurrent_dup_insert() {
+    let mempool = Arc::new(default_mempool());
+    let tx = default_mock_txs(1).remove(0);
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let mempool = Arc::clone(&mempool);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|_| executor::block_on(mempool.insert(Context::new(), tx.clone())))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let results: Vec<ProtocolResult<()>> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+    assert!(results
+        .iter()
+        .filter_map(|result| result.as_ref().err())
+        .all(|err| err.to_string().contains("Dup")));
+    assert_eq!(mempool.get_tx_cache().len(), 1);
+
+    // Flushing forgets the hash, so the transaction may come again
+    exec_flush(vec![tx.tx_hash.clone()], Arc::clone(&mempool));
+    executor::block_on(mempool.insert(Context::new(), tx)).unwrap();
+}
+
+#[test]
+fn test_gossiped_txs_not_broadcast() {
+    let mempool = default_mempool();
+    let txs = default_mock_txs(2);
+    let peer_ctx = Context::new().mark_network_origin_new_txs();
+
+    executor::block_on(mempool.insert(peer_ctx, txs[0].clone())).unwrap();
+    executor::block_on(mempool.insert(Context::new(), txs[1].clone())).unwrap();
+
+    let broadcast = &mempool.get_adapter().network_txs;
+    assert!(broadcast.get(&txs[0].tx_hash).is_none());
+    assert!(broadcast.get(&txs[1].tx_hash).is_some());
+
+    let stats = mempool.stats();
+    assert_eq!(stats.pending, 2);
+    assert_eq!(stats.from_peers, 1);
+}
+
+#[test]
+fn test_replace_by_fee() {
+    let mempool = &Arc::new(default_mempool());
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let raw = mock_raw_tx(TIMEOUT);
+    let priced = |cycles_price: u64| {
+        let mut raw = raw.clone();
+        raw.cycles_price = cycles_price;
+        sign_tx(&priv_key, &pub_key, raw, true)
+    };
+    let insert =
+        |tx: &SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx.clone()));
+
+    let pending = priced(100);
+    insert(&pending).unwrap();
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![pending
+        .tx_hash
+        .clone()]);
+
+    // Less than the default 10% more
+    let err = insert(&priced(109)).unwrap_err().to_string();
+    assert!(err.contains("ReplaceUnderpriced"));
+    assert!(mempool.get_tx_cache().contain(&pending.tx_hash));
+
+    // Replacing a packaged but uncommitted tx
+    mempool
+        .get_callback_cache()
+        .insert(pending.tx_hash.clone(), pending.clone());
+    let replacement = priced(110);
+    insert(&replacement).unwrap();
+    assert_eq!(mempool.get_tx_cache().len(), 1);
+    assert!(!mempool.get_tx_cache().contain(&pending.tx_hash));
+    assert!(!mempool.get_callback_cache().contains_key(&pending.tx_hash));
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, vec![replacement
+        .tx_hash
+        .clone()]);
+
+    // The replaced tx arriving again through gossip stays out
+    let err = insert(&pending).unwrap_err().to_string();
+    assert!(err.contains("ReplaceUnderpriced"));
+
+    // Another nonce of the same sender is a separate tx
+    let other = sign_tx(&priv_key, &pub_key, mock_raw_tx(TIMEOUT), true);
+    insert(&other).unwrap();
+    assert_eq!(mempool.get_tx_cache().len(), 2);
+}
+
+#[test]
+fn test_sender_limit() {
+    let mempool = &Arc::new(
+        new_mempool(POOL_SIZE, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE)
+            .with_sender_limit(DEFAULT_SENDER_LIMIT),
+    );
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let txs: Vec<SignedTransaction> = (0..=DEFAULT_SENDER_LIMIT)
+        .map(|_| sign_tx(&priv_key, &pub_key, mock_raw_tx(TIMEOUT), true))
+        .collect();
+    let insert = |tx: SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx));
+
+    let (pending, over_limit) = txs.split_at(DEFAULT_SENDER_LIMIT);
+    for tx in pending.iter() {
+        insert(tx.clone()).unwrap();
+    }
+    let rejection = insert(over_limit[0].clone()).unwrap_err().to_string();
+    assert!(rejection.contains("SenderLimit"));
+
+    // Other senders and replacements are still welcome
+    insert(default_mock_txs(1).remove(0)).unwrap();
+    let mut raw = pending[0].raw.clone();
+    raw.cycles_price *= 2;
+    insert(sign_tx(&priv_key, &pub_key, raw, true)).unwrap();
+
+    exec_flush(vec![pending[1].tx_hash.clone()], Arc::clone(mempool));
+    insert(over_limit[0].clone()).unwrap();
+    assert_eq!(mempool.get_tx_cache().len(), DEFAULT_SENDER_LIMIT + 1);
+}
+
+#[test]
+fn test_insert_batch() {
+    let mempool = default_mempool();
+    let txs = mock_txs(5, 3, TIMEOUT);
+    let (valid_txs, invalid_txs) = txs.split_at(5);
+
+    // Valid and invalid txs mixed, and one of them twice
+    let mut batch = Vec::new();
+    for (i, tx) in valid_txs.iter().enumerate() {
+        batch.push(tx.clone());
+        if let Some(invalid_tx) = invalid_txs.get(i) {
+            batch.push(invalid_tx.clone());
+        }
+    }
+    batch.push(valid_txs[0].clone());
+
+    let results = executor::block_on(mempool.insert_batch(Context::new(), batch.clone()));
+    assert_eq!(results.len(), batch.len());
+    for (tx, result) in batch[..batch.len() - 1].iter().zip(results.iter()) {
+        let is_valid = valid_txs.contains(tx);
+        assert_eq!(result.is_ok(), is_valid);
+        if !is_valid {
+            let err = result.as_ref().unwrap_err().to_string();
+            assert!(err.contains("CheckSig"));
+        }
+    }
+    assert!(results[batch.len() - 1]
+        .as_ref()
+        .unwrap_err()
+        .to_string()
+        .contains("Dup"));
+    assert_eq!(mempool.get_tx_cache().len(), valid_txs.len());
+}
+
+#[test]
+fn test_ensure_rejects_bad_signature() {
+    let mempool = &Arc::new(default_mempool());
+    let txs = mock_txs(3, 1, TIMEOUT);
+    concurrent_broadcast(txs.clone(), Arc::clone(mempool));
+
+    let tx_hashes: Vec<Hash> = txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+    let result = executor::block_on(mempool.ensure_order_txs(Context::new(), tx_hashes));
+    assert!(result.unwrap_err().to_string().contains("CheckSig"));
+    assert_eq!(mempool.get_callback_cache().len(), 0);
+}
+
+#[test]
+fn test_get_pending_tx() {
+    let mempool = default_mempool();
+    let txs = default_mock_txs(2);
+    executor::block_on(mempool.insert(Context::new(), txs[0].clone())).unwrap();
+
+    let get = |tx_hash: &Hash| executor::block_on(mempool.get(Context::new(), tx_hash)).unwrap();
+    assert_eq!(get(&txs[0].tx_hash), Some(txs[0].clone()));
+    assert!(mempool.contains(&txs[0].tx_hash));
+
+    assert_eq!(get(&txs[1].tx_hash), None);
+    assert!(!mempool.contains(&txs[1].tx_hash));
+}
+
+#[test]
+fn test_new_tx_events() {
+    let mempool = default_mempool();
+    let mut events = mempool.subscribe_new_txs(1);
+    let txs = mock_priced_txs(&[(3, 1), (4, 1), (5, 1)]);
+    let insert = |tx: SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx));
+
+    for tx in txs.iter() {
+        insert(tx.clone()).unwrap();
+    }
+
+    let event = events.try_next().unwrap().unwrap();
+    assert_eq!(event.tx_hash, txs[0].tx_hash);
+    assert_eq!(
+        event.sender,
+        Address::from_pubkey_bytes(txs[0].pubkey.clone()).unwrap()
+    );
+    assert_eq!(event.cycles_price, 3);
+
+    // The channel held two events, the third one was dropped
+    assert_eq!(events.try_next().unwrap().unwrap().tx_hash, txs[1].tx_hash);
+    assert!(events.try_next().is_err());
+    assert_eq!(mempool.dropped_new_tx_events(), 1);
+}
+
+#[test]
+fn test_replace_price_bump() {
+    let mempool = &Arc::new(default_mempool().with_replace_price_bump(50));
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let raw = mock_raw_tx(TIMEOUT);
+    let priced = |cycles_price: u64| {
+        let mut raw = raw.clone();
+        raw.cycles_price = cycles_price;
+        sign_tx(&priv_key, &pub_key, raw, true)
+    };
+    let insert = |tx: SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx));
+
+    insert(priced(10)).unwrap();
+    assert!(insert(priced(14)).is_err());
+    insert(priced(15)).unwrap();
+    assert_eq!(mempool.get_tx_cache().len(), 1);
+}
+
+#[test]
+fn test_evict_cheapest() {
+    let mempool = new_mempool(5, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE);
+    let txs = mock_priced_txs(&[(2, 1), (1, 1), (3, 1), (2, 1), (4, 1), (1, 1), (10, 1)]);
+    let insert = |tx: SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx));
+
+    for tx in txs[..5].iter() {
+        insert(tx.clone()).unwrap();
+    }
+
+    // Paying no more than the cheapest pending tx isn't enough
+    let rejection = insert(txs[5].clone()).unwrap_err().to_string();
+    assert!(rejection.contains("ReachLimit"));
+
+    insert(txs[6].clone()).unwrap();
+    let tx_cache = mempool.get_tx_cache();
+    assert_eq!(tx_cache.len(), 5);
+    assert!(!tx_cache.contain(&txs[1].tx_hash));
+    assert!([0, 2, 3, 4, 6]
+        .iter()
+        .all(|i| tx_cache.contain(&txs[*i].tx_hash)));
+
+    let storage = &mempool.get_adapter().storage;
+    let persisted = executor::block_on(storage.get_pool_transactions()).unwrap();
+    assert_eq!(persisted.len(), 5);
+    assert!(persisted.iter().all(|tx| tx.tx_hash != txs[1].tx_hash));
+}
+
 macro_rules! package {
     (normal($tx_num_limit: expr, $insert: expr, $expect_order: expr, $expect_propose: expr)) => {
         package!(inner(
@@ -113,6 +400,125 @@ fn test_package_order_consistent_with_insert_order() {
     assert!(check_order_consistant(&mixed_tx_hashes, reserve_txs));
 }
 
+#[test]
+fn test_package_by_price() {
+    let mempool = &Arc::new(default_mempool());
+    let txs = &mock_priced_txs(&[(1, 1), (5, 1), (3, 1), (5, 1), (2, 1)]);
+    let hashes = |indexes: &[usize]| -> Vec<Hash> {
+        indexes.iter().map(|i| txs[*i].tx_hash.clone()).collect()
+    };
+    txs.iter()
+        .for_each(|signed_tx| exec_insert(signed_tx, Arc::clone(mempool)));
+
+    // Equal prices keep their arrival order
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[1, 3, 2, 4, 0]));
+    assert!(mixed_tx_hashes.propose_tx_hashes.is_empty());
+
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, 2);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[1, 3]));
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, hashes(&[2, 4]));
+
+    // The index follows flushes without a rebuild
+    exec_flush(hashes(&[1, 2]), Arc::clone(mempool));
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[3, 4, 0]));
+}
+
+#[test]
+fn test_package_priority_first() {
+    let mempool = &Arc::new(
+        new_mempool(3, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE)
+            .with_priority_methods(vec![("metadata".to_owned(), "update_metadata".to_owned())]),
+    );
+    let transfers = mock_priced_txs(&[(100, 1), (100, 1), (90, 1)]);
+    for tx in transfers.iter() {
+        executor::block_on(mempool.insert(Context::new(), tx.clone())).unwrap();
+    }
+
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let mut raw = mock_raw_tx(TIMEOUT);
+    raw.cycles_price = 0;
+    raw.request.service_name = "metadata".to_owned();
+    raw.request.method = "update_metadata".to_owned();
+    let metadata_tx = sign_tx(&priv_key, &priv_key.pub_key(), raw, true);
+
+    // A full pool of better paying transfers still makes room for it
+    executor::block_on(mempool.insert(Context::new(), metadata_tx.clone())).unwrap();
+    assert!(!mempool.get_tx_cache().contain(&transfers[2].tx_hash));
+
+    let hash = |tx: &SignedTransaction| tx.tx_hash.clone();
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, 2);
+    let order = vec![hash(&metadata_tx), hash(&transfers[0])];
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, order);
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, vec![hash(&transfers[1])]);
+}
+
+#[test]
+fn test_package_within_cycles_limit() {
+    let mempool = &Arc::new(default_mempool());
+    let txs = &mock_priced_txs(&[(4, 5), (3, 8), (2, 3), (1, 2), (1, 1)]);
+    let hashes = |indexes: &[usize]| -> Vec<Hash> {
+        indexes.iter().map(|i| txs[*i].tx_hash.clone()).collect()
+    };
+    txs.iter()
+        .for_each(|signed_tx| exec_insert(signed_tx, Arc::clone(mempool)));
+
+    // The second tx doesn't fit in the 5 cycles left and is skipped, the
+    // cheaper ones still fill the budget
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), 10, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[0, 2, 3]));
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, hashes(&[1, 4]));
+}
+
+#[test]
+fn test_package_cut_off() {
+    let mempool = &Arc::new(default_mempool());
+    let txs = &mock_priced_txs(&[(5, 3), (4, 3), (3, 4), (2, 1), (1, 1)]);
+    let hashes = |indexes: &[usize]| -> Vec<Hash> {
+        indexes.iter().map(|i| txs[*i].tx_hash.clone()).collect()
+    };
+    txs.iter()
+        .for_each(|signed_tx| exec_insert(signed_tx, Arc::clone(mempool)));
+
+    // Count bound, the cycles would allow all of them
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), CYCLE_LIMIT, 2);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[0, 1]));
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, hashes(&[2, 3]));
+
+    // Cycles bound, the third tx uses up the last 4 cycles exactly
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), 10, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[0, 1, 2]));
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, hashes(&[3, 4]));
+
+    // One cycle less and it is skipped for the cheaper ones
+    let mixed_tx_hashes = exec_package(Arc::clone(mempool), 9, TX_NUM_LIMIT);
+    assert_eq!(mixed_tx_hashes.order_tx_hashes, hashes(&[0, 1, 3, 4]));
+    assert_eq!(mixed_tx_hashes.propose_tx_hashes, hashes(&[2]));
+}
+
+#[test]
+fn test_package_evicts_oversize() {
+    let mempool = &Arc::new(default_mempool());
+    let txs = &mock_priced_txs(&[(5, 11), (1, 1)]);
+    txs.iter()
+        .for_each(|signed_tx| exec_insert(signed_tx, Arc::clone(mempool)));
+
+    // Skipped by every package until the third one drops it
+    let fitting = vec![txs[1].tx_hash.clone()];
+    for _ in 0..3 {
+        assert!(mempool.get_tx_cache().contain(&txs[0].tx_hash));
+        let mixed_tx_hashes = exec_package(Arc::clone(mempool), 10, TX_NUM_LIMIT);
+        assert_eq!(mixed_tx_hashes.order_tx_hashes, fitting);
+        assert!(mixed_tx_hashes.propose_tx_hashes.is_empty());
+    }
+    assert!(!mempool.get_tx_cache().contain(&txs[0].tx_hash));
+
+    let storage = &mempool.get_adapter().storage;
+    let persisted = executor::block_on(storage.get_pool_transactions()).unwrap();
+    assert_eq!(persisted.len(), 1);
+}
+
 #[test]
 fn test_flush() {
     let mempool = Arc::new(default_mempool());
@@ -146,6 +552,137 @@ fn test_flush() {
     assert_eq!(mempool.get_tx_cache().queue_len(), 432);
 }
 
+#[test]
+fn test_expire() {
+    let mempool = Arc::new(new_mempool(20, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE));
+    let short_lived = mock_txs(10, 0, CURRENT_HEIGHT + 2);
+    let long_lived = mock_txs(10, 0, TIMEOUT);
+    concurrent_insert(short_lived.clone(), Arc::clone(&mempool));
+    concurrent_insert(long_lived.clone(), Arc::clone(&mempool));
+    assert_eq!(mempool.get_tx_cache().len(), 20);
+
+    let expire = |height: u64| executor::block_on(mempool.expire(Context::new(), height)).unwrap();
+    assert_eq!(expire(CURRENT_HEIGHT + 1), 0);
+    assert_eq!(expire(CURRENT_HEIGHT + 2), 10);
+    assert_eq!(mempool.get_tx_cache().len(), 10);
+    assert_eq!(mempool.get_tx_cache().queue_len(), 10);
+    assert!(short_lived
+        .iter()
+        .all(|tx| !mempool.get_tx_cache().contain(&tx.tx_hash)));
+
+    let storage = &mempool.get_adapter().storage;
+    let persisted = executor::block_on(storage.get_pool_transactions()).unwrap();
+    assert_eq!(persisted.len(), 10);
+
+    // The expired txs no longer take up room
+    concurrent_insert(mock_txs(10, 0, TIMEOUT), Arc::clone(&mempool));
+    assert_eq!(mempool.get_tx_cache().len(), 20);
+}
+
+#[test]
+fn test_rejection_journal() {
+    let mempool = &Arc::new(default_mempool());
+    let insert =
+        |tx: &SignedTransaction| executor::block_on(mempool.insert(Context::new(), tx.clone()));
+    let reason = |tx: &SignedTransaction| {
+        mempool
+            .rejection_reason(&tx.tx_hash)
+            .map(|record| record.reason)
+    };
+
+    let bad_sig = mock_txs(0, 1, TIMEOUT).remove(0);
+    insert(&bad_sig).unwrap_err();
+    assert!(reason(&bad_sig).unwrap().contains("CheckSig"));
+
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    let mut raw = mock_raw_tx(TIMEOUT);
+    raw.cycles_price = 100;
+    let pending = sign_tx(&priv_key, &pub_key, raw.clone(), true);
+    insert(&pending).unwrap();
+    raw.cycles_price = 109;
+    let underpriced = sign_tx(&priv_key, &pub_key, raw, true);
+    insert(&underpriced).unwrap_err();
+    assert!(reason(&underpriced).unwrap().contains("ReplaceUnderpriced"));
+
+    // The pool still holds a rejected dup
+    insert(&pending).unwrap_err();
+    assert_eq!(reason(&pending), None);
+
+    let oversize = mock_priced_txs(&[(1, 11)]).remove(0);
+    insert(&oversize).unwrap();
+    for _ in 0..3 {
+        exec_package(Arc::clone(mempool), 10, TX_NUM_LIMIT);
+    }
+    assert!(reason(&oversize).unwrap().contains("ExceedCyclesLimit"));
+
+    let short_lived = mock_txs(1, 0, CURRENT_HEIGHT + 2).remove(0);
+    insert(&short_lived).unwrap();
+    executor::block_on(mempool.expire(Context::new(), CURRENT_HEIGHT + 2)).unwrap();
+    assert!(reason(&short_lived).unwrap().contains("Timeout"));
+
+    let rejected: Vec<Hash> = mempool
+        .rejections()
+        .into_iter()
+        .map(|record| record.tx_hash)
+        .collect();
+    assert_eq!(rejected, vec![
+        bad_sig.tx_hash,
+        underpriced.tx_hash,
+        oversize.tx_hash,
+        short_lived.tx_hash
+    ]);
+}
+
+#[test]
+fn test_rejection_journal_size() {
+    let mempool = &Arc::new(default_mempool().with_rejection_journal_size(2));
+    let bad_sigs = mock_txs(0, 3, TIMEOUT);
+    for tx in bad_sigs.iter() {
+        executor::block_on(mempool.insert(Context::new(), tx.clone())).unwrap_err();
+    }
+
+    // The oldest one made room
+    assert_eq!(mempool.rejection_reason(&bad_sigs[0].tx_hash), None);
+    let rejected: Vec<Hash> = mempool
+        .rejections()
+        .into_iter()
+        .map(|record| record.tx_hash)
+        .collect();
+    assert_eq!(rejected, vec![
+        bad_sigs[1].tx_hash.clone(),
+        bad_sigs[2].tx_hash.clone()
+    ]);
+}
+
+#[test]
+fn test_recover() {
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+    let mempool = Arc::new(mempool_with_storage(Arc::clone(&storage)));
+
+    let txs = default_mock_txs(20);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool));
+    // Already timed out when the pool comes back
+    concurrent_insert(mock_txs(5, 0, CURRENT_HEIGHT), Arc::clone(&mempool));
+
+    let (committed_txs, pending_txs) = txs.split_at(5);
+    let committed_hashes: Vec<Hash> = committed_txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+    exec_flush(committed_hashes, Arc::clone(&mempool));
+
+    // Restart over the same storage
+    let mempool = mempool_with_storage(Arc::clone(&storage));
+    let recovered = executor::block_on(mempool.recover(Context::new())).unwrap();
+    assert_eq!(recovered, pending_txs.len());
+    assert_eq!(mempool.get_tx_cache().len(), pending_txs.len());
+    assert!(pending_txs
+        .iter()
+        .all(|tx| mempool.get_tx_cache().contain(&tx.tx_hash)));
+
+    // The timed out txs are gone from storage too
+    let persisted = executor::block_on(storage.get_pool_transactions()).unwrap();
+    assert_eq!(persisted.len(), pending_txs.len());
+}
+
 macro_rules! ensure_order_txs {
     ($in_pool: expr, $out_pool: expr) => {
         let mempool = &Arc::new(default_mempool());
@@ -200,6 +737,29 @@ fn bench_insert(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn bench_insert_dups(b: &mut Bencher) {
+    let mempool = &Arc::new(default_mempool());
+    let txs = default_mock_txs(100);
+    concurrent_insert(txs.clone(), Arc::clone(mempool));
+
+    // Every transaction arrives again from ten peers
+    let dups: Vec<SignedTransaction> = (0..10).flat_map(|_| txs.clone()).collect();
+    b.iter(|| {
+        concurrent_insert(dups.clone(), Arc::clone(mempool));
+    });
+}
+
+#[bench]
+fn bench_insert_batch(b: &mut Bencher) {
+    let mempool = &Arc::new(default_mempool());
+
+    b.iter(|| {
+        let txs = default_mock_txs(100);
+        executor::block_on(mempool.insert_batch(Context::new(), txs));
+    });
+}
+
 #[bench]
 fn bench_package(b: &mut Bencher) {
     let mempool = Arc::new(default_mempool());
@@ -210,6 +770,19 @@ fn bench_package(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn bench_package_mixed_prices(b: &mut Bencher) {
+    let mempool = Arc::new(default_mempool());
+    let prices: Vec<(u64, u64)> = (0..50_000)
+        .map(|_| (u64::from(random::<u8>()), TX_CYCLE))
+        .collect();
+    let txs = mock_priced_txs(&prices);
+    concurrent_insert(txs, Arc::clone(&mempool));
+    b.iter(|| {
+        exec_package(Arc::clone(&mempool), CYCLE_LIMIT, TX_NUM_LIMIT);
+    });
+}
+
 #[bench]
 fn bench_flush(b: &mut Bencher) {
     let mempool = &Arc::new(default_mempool());
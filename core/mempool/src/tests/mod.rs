@@ -17,12 +17,14 @@ use common_crypto::{
     Crypto, PrivateKey, PublicKey, Secp256k1, Secp256k1PrivateKey, Secp256k1PublicKey,
     Secp256k1Signature, Signature, ToPublicKey,
 };
+use core_storage::adapter::memory::MemoryAdapter;
+use core_storage::ImplStorage;
 use protocol::codec::ProtocolCodec;
-use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
+use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes, Storage};
 use protocol::types::{Hash, RawTransaction, SignedTransaction, TransactionRequest};
 use protocol::{Bytes, ProtocolResult};
 
-use crate::{HashMemPool, MemPoolError};
+use crate::{HashMemPool, MemPoolError, DEFAULT_SENDER_LIMIT};
 
 const CYCLE_LIMIT: u64 = 1_000_000;
 const TX_NUM_LIMIT: u64 = 10_000;
@@ -33,14 +35,22 @@ const TIMEOUT: u64 = 1000;
 const TIMEOUT_GAP: u64 = 100;
 const TX_CYCLE: u64 = 1;
 
+type MemoryStorage = ImplStorage<MemoryAdapter>;
+
 pub struct HashMemPoolAdapter {
     network_txs: CHashMap<Hash, SignedTransaction>,
+    storage:     Arc<MemoryStorage>,
 }
 
 impl HashMemPoolAdapter {
     fn new() -> HashMemPoolAdapter {
+        Self::with_storage(Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()))))
+    }
+
+    fn with_storage(storage: Arc<MemoryStorage>) -> HashMemPoolAdapter {
         HashMemPoolAdapter {
             network_txs: CHashMap::new(),
+            storage,
         }
     }
 }
@@ -71,6 +81,19 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         check_sig(&tx)
     }
 
+    async fn check_signatures(
+        &self,
+        _ctx: Context,
+        txs: Vec<SignedTransaction>,
+    ) -> Vec<ProtocolResult<()>> {
+        txs.par_iter()
+            .map(|tx| {
+                executor::block_on(check_hash(tx.clone()))?;
+                check_sig(tx)
+            })
+            .collect()
+    }
+
     async fn check_transaction(&self, _ctx: Context, _tx: SignedTransaction) -> ProtocolResult<()> {
         Ok(())
     }
@@ -83,6 +106,21 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(CURRENT_HEIGHT)
     }
 
+    async fn persist_txs(
+        &self,
+        _ctx: Context,
+        inserted: Vec<SignedTransaction>,
+        removed: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        self.storage
+            .update_pool_transactions(inserted, removed)
+            .await
+    }
+
+    async fn load_persisted_txs(&self, _ctx: Context) -> ProtocolResult<Vec<SignedTransaction>> {
+        self.storage.get_pool_transactions().await
+    }
+
     fn set_args(&self, _timeout_gap: u64, _cycles_limit: u64, _max_tx_size: u64) {}
 }
 
@@ -100,18 +138,42 @@ fn mock_txs(valid_size: usize, invalid_size: usize, timeout: u64) -> Vec<SignedT
     vec
 }
 
+// One transaction per `(cycles_price, cycles_limit)`, all from one sender
+fn mock_priced_txs(prices: &[(u64, u64)]) -> Vec<SignedTransaction> {
+    let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+    prices
+        .iter()
+        .map(|(cycles_price, cycles_limit)| {
+            let mut raw = mock_raw_tx(TIMEOUT);
+            raw.cycles_price = *cycles_price;
+            raw.cycles_limit = *cycles_limit;
+            sign_tx(&priv_key, &pub_key, raw, true)
+        })
+        .collect()
+}
+
 fn default_mempool() -> HashMemPool<HashMemPoolAdapter> {
     new_mempool(POOL_SIZE, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE)
 }
 
+// A pool persisting its transactions to `storage`
+fn mempool_with_storage(storage: Arc<MemoryStorage>) -> HashMemPool<HashMemPoolAdapter> {
+    let adapter = HashMemPoolAdapter::with_storage(storage);
+    let mempool = HashMemPool::new(POOL_SIZE, adapter).with_sender_limit(POOL_SIZE);
+    mempool.set_args(TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE);
+    mempool
+}
+
 fn new_mempool(
     pool_size: usize,
     timeout_gap: u64,
     cycles_limit: u64,
     max_tx_size: u64,
 ) -> HashMemPool<HashMemPoolAdapter> {
+    // Mock txs mostly come from one sender, so only the pool size limits them
     let adapter = HashMemPoolAdapter::new();
-    let mempool = HashMemPool::new(pool_size, adapter);
+    let mempool = HashMemPool::new(pool_size, adapter).with_sender_limit(pool_size);
     mempool.set_args(timeout_gap, cycles_limit, max_tx_size);
     mempool
 }
@@ -227,6 +289,10 @@ fn mock_signed_tx(
     timeout: u64,
     valid: bool,
 ) -> SignedTransaction {
+    sign_tx(priv_key, pub_key, mock_raw_tx(timeout), valid)
+}
+
+fn mock_raw_tx(timeout: u64) -> RawTransaction {
     let nonce = Hash::digest(Bytes::from(get_random_bytes(10)));
 
     let request = TransactionRequest {
@@ -234,15 +300,22 @@ fn mock_signed_tx(
         method:       "test".to_owned(),
         payload:      "test".to_owned(),
     };
-    let mut raw = RawTransaction {
+    RawTransaction {
         chain_id: nonce.clone(),
         nonce,
         timeout,
         cycles_limit: TX_CYCLE,
         cycles_price: 1,
         request,
-    };
+    }
+}
 
+fn sign_tx(
+    priv_key: &Secp256k1PrivateKey,
+    pub_key: &Secp256k1PublicKey,
+    mut raw: RawTransaction,
+    valid: bool,
+) -> SignedTransaction {
     let raw_bytes = executor::block_on(async { raw.encode().await.unwrap() });
     let tx_hash = Hash::digest(raw_bytes);
 
@@ -1,55 +1,95 @@
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::cmp::{self, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crossbeam_queue::ArrayQueue;
+use parking_lot::Mutex;
 
-use protocol::traits::MixedTxHashes;
-use protocol::types::{Hash, SignedTransaction};
+use protocol::traits::{MixedTxHashes, PendingTx, PoolStats};
+use protocol::types::{Address, Hash, SignedTransaction, TransactionRequest};
 use protocol::ProtocolResult;
 
+use crate::context::TxOrigin;
 use crate::map::Map;
-use crate::MemPoolError;
+use crate::seen::SeenTxs;
+use crate::{MemPoolError, DEFAULT_REPLACE_PRICE_BUMP, DEFAULT_SENDER_LIMIT};
+
+// Packages a transaction above the block cycles limit is passed over before
+// it's evicted
+const MAX_OVERSIZE_SKIPS: u64 = 3;
 
 /// Wrap `SignedTransaction` with two marks for mempool management.
 ///
 /// Each new transaction inserting into mempool will set `removed` false,
 /// while transaction from propose-transaction-sync will additionally set
 /// `proposed` true. When shared transaction in `TxCache` removed from map,
-/// it will set `removed` true. The `removed` and `proposed` marks will remind
-/// queue in `TxCache` to appropriately process elements while packaging
-/// transaction hashes for consensus.
+/// it will set `removed` true.
 pub struct TxWrapper {
     /// Content.
-    tx:       SignedTransaction,
+    tx:         SignedTransaction,
+    /// Address derived from the transaction's public key.
+    sender:     Address,
+    /// Arrival order in the cache, breaks ties between equal prices.
+    seq:        u64,
+    /// Arrival time, milliseconds since the unix epoch.
+    arrived_at: u64,
     /// While map removes a `shared_tx` during flush, it will mark `removed`
-    /// true. Afterwards, queue removes the transaction which marks
-    /// `removed` true during package.
-    removed:  AtomicBool,
+    /// true, so holders of the shared transaction know it is gone.
+    removed:    AtomicBool,
     /// The response transactions in propose-syncing will insert into `TxCache`
     /// marking `proposed` true.
     /// While collecting propose_tx_hashes during package,
     /// it will skips transactions which marks 'proposed` true.
-    proposed: AtomicBool,
+    proposed:   AtomicBool,
+    /// Calls an allowlisted method, queued ahead of the price ordered ones.
+    priority:   bool,
+    /// Where the transaction came from.
+    origin:     TxOrigin,
+    /// Packages that passed over it because it exceeds the block cycles
+    /// limit on its own.
+    skips:      AtomicU64,
 }
 
 impl TxWrapper {
-    #[allow(dead_code)]
-    pub(crate) fn new(tx: SignedTransaction) -> Self {
+    pub(crate) fn new(tx: SignedTransaction, sender: Address, seq: u64) -> Self {
         TxWrapper {
             tx,
+            sender,
+            seq,
+            arrived_at: now_millis(),
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(false),
+            priority: false,
+            origin: TxOrigin::Api,
+            skips: AtomicU64::new(0),
         }
     }
 
-    pub(crate) fn propose(tx: SignedTransaction) -> Self {
+    pub(crate) fn propose(tx: SignedTransaction, sender: Address, seq: u64) -> Self {
         TxWrapper {
             tx,
+            sender,
+            seq,
+            arrived_at: now_millis(),
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(true),
+            priority: false,
+            origin: TxOrigin::Sync,
+            skips: AtomicU64::new(0),
         }
     }
 
+    pub(crate) fn with_priority(mut self, priority: bool) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn with_origin(mut self, origin: TxOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
     pub(crate) fn set_removed(&self) {
         self.removed.store(true, Ordering::SeqCst);
     }
@@ -64,96 +104,291 @@ impl TxWrapper {
         self.proposed.load(Ordering::SeqCst)
     }
 
+    /// Counts one more skip and returns the total.
+    fn skip(&self) -> u64 {
+        self.skips.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
     #[inline]
     fn is_timeout(&self, current_height: u64, timeout: u64) -> bool {
         let tx_timeout = self.tx.raw.timeout;
         tx_timeout <= current_height || tx_timeout > timeout
     }
+
+    // Why a timed out tx is dropped, its timeout is either behind the chain
+    // or too far ahead after the timeout gap shrank
+    fn timeout_err(&self, current_height: u64) -> MemPoolError {
+        let tx_hash = self.tx.tx_hash.clone();
+        let timeout = self.tx.raw.timeout;
+        if timeout <= current_height {
+            MemPoolError::Timeout { tx_hash, timeout }
+        } else {
+            MemPoolError::InvalidTimeout { tx_hash }
+        }
+    }
+
+    /// Approximate memory held by the transaction.
+    fn size(&self) -> usize {
+        let raw = &self.tx.raw;
+        let request = &raw.request;
+        // chain id, nonce and hash plus timeout, cycles limit and price
+        3 * 32
+            + 3 * 8
+            + request.service_name.len()
+            + request.method.len()
+            + request.payload.len()
+            + self.tx.pubkey.len()
+            + self.tx.signature.len()
+    }
+
+    #[inline]
+    fn price_key(&self) -> PriceKey {
+        PriceKey {
+            price: Reverse(self.tx.raw.cycles_price),
+            seq:   self.seq,
+        }
+    }
 }
 
 /// Share `TxWrapper` for collections in `TxCache`.
 pub type SharedTx = Arc<TxWrapper>;
 
-/// An enum stands for package stage
-#[derive(PartialEq, Eq)]
-enum Stage {
-    /// Packing order_tx_hashes
-    OrderTxs,
-    /// Packing propose_tx_hashes
-    ProposeTxs,
-    /// Packing finished. Only insert transactions into temp queue.
-    Finished,
+/// Package position of a transaction, higher `cycles_price` first and the
+/// earlier arrival among equal prices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct PriceKey {
+    price: Reverse<u64>,
+    seq:   u64,
 }
 
-impl Stage {
-    fn next(&self) -> Self {
-        match self {
-            Stage::OrderTxs => Stage::ProposeTxs,
-            Stage::ProposeTxs => Stage::Finished,
-            Stage::Finished => panic!("There is no next stage after finished stage!"),
+/// Running totals over the indexed transactions.
+#[derive(Default)]
+struct Totals {
+    pending:    usize,
+    parked:     usize,
+    from_peers: usize,
+    bytes:      usize,
+}
+
+/// Secondary indices over the transactions of the `TxCache` map.
+#[derive(Default)]
+struct TxIndex {
+    /// Priority transactions by arrival, packaged first.
+    priority:  BTreeMap<u64, SharedTx>,
+    /// Package order of the others.
+    by_price:  BTreeMap<PriceKey, SharedTx>,
+    /// Pending transactions of each sender by nonce.
+    by_sender: HashMap<Address, HashMap<Hash, SharedTx>>,
+    /// Arrival time by arrival order.
+    arrivals:  BTreeMap<u64, u64>,
+    /// Updated along with the indices, so stats never scan the pool.
+    totals:    Totals,
+}
+
+impl TxIndex {
+    fn insert(&mut self, shared_tx: SharedTx) {
+        if shared_tx.is_proposed() {
+            self.totals.parked += 1;
+        } else {
+            self.totals.pending += 1;
         }
+        if shared_tx.origin == TxOrigin::Peer {
+            self.totals.from_peers += 1;
+        }
+        self.totals.bytes += shared_tx.size();
+        self.arrivals.insert(shared_tx.seq, shared_tx.arrived_at);
+
+        self.by_sender
+            .entry(shared_tx.sender.clone())
+            .or_default()
+            .insert(shared_tx.tx.raw.nonce.clone(), Arc::clone(&shared_tx));
+        if shared_tx.priority {
+            self.priority.insert(shared_tx.seq, shared_tx);
+        } else {
+            self.by_price.insert(shared_tx.price_key(), shared_tx);
+        }
+    }
+
+    fn remove(&mut self, shared_tx: &TxWrapper) {
+        let queued = if shared_tx.priority {
+            self.priority.remove(&shared_tx.seq)
+        } else {
+            self.by_price.remove(&shared_tx.price_key())
+        };
+        if queued.is_some() {
+            if shared_tx.is_proposed() {
+                self.totals.parked -= 1;
+            } else {
+                self.totals.pending -= 1;
+            }
+            if shared_tx.origin == TxOrigin::Peer {
+                self.totals.from_peers -= 1;
+            }
+            self.totals.bytes -= shared_tx.size();
+            self.arrivals.remove(&shared_tx.seq);
+        }
+
+        let nonce = &shared_tx.tx.raw.nonce;
+        if let Some(pending) = self.by_sender.get_mut(&shared_tx.sender) {
+            let same_tx = pending
+                .get(nonce)
+                .map(|tx| tx.tx.tx_hash == shared_tx.tx.tx_hash)
+                .unwrap_or(false);
+            if same_tx {
+                pending.remove(nonce);
+            }
+            if pending.is_empty() {
+                self.by_sender.remove(&shared_tx.sender);
+            }
+        }
+    }
+
+    fn pending(&self, sender: &Address, nonce: &Hash) -> Option<&SharedTx> {
+        self.by_sender
+            .get(sender)
+            .and_then(|pending| pending.get(nonce))
+    }
+
+    fn pending_count(&self, sender: &Address) -> usize {
+        self.by_sender.get(sender).map_or(0, HashMap::len)
+    }
+
+    /// Every transaction in package order.
+    fn queued(&self) -> impl Iterator<Item = &SharedTx> {
+        self.priority.values().chain(self.by_price.values())
+    }
+
+    fn queued_len(&self) -> usize {
+        self.priority.len() + self.by_price.len()
+    }
+
+    /// The lowest priced transaction, the latest arrival among equal prices.
+    /// Priority transactions are never the cheapest.
+    fn cheapest(&self) -> Option<&SharedTx> {
+        self.by_price.values().next_back()
     }
 }
 
-/// Queue role. Incumbent is for insertion and package.
-struct QueueRole<'a> {
-    incumbent: &'a ArrayQueue<SharedTx>,
-    candidate: &'a ArrayQueue<SharedTx>,
+/// Transaction hashes collected for one stage of a package.
+struct Stage {
+    tx_hashes:   Vec<Hash>,
+    tx_num_left: u64,
+    cycles_left: u64,
+}
+
+impl Stage {
+    fn new(cycles_limit: u64, tx_num_limit: u64) -> Self {
+        Stage {
+            tx_hashes:   Vec::new(),
+            tx_num_left: tx_num_limit,
+            cycles_left: cycles_limit,
+        }
+    }
+
+    /// Adds the transaction if it fits in what is left of both limits.
+    fn push(&mut self, tx: &SignedTransaction) -> bool {
+        if self.tx_num_left == 0 || tx.raw.cycles_limit > self.cycles_left {
+            return false;
+        }
+
+        self.tx_hashes.push(tx.tx_hash.clone());
+        self.tx_num_left -= 1;
+        self.cycles_left -= tx.raw.cycles_limit;
+        true
+    }
+
+    fn is_full(&self) -> bool {
+        self.tx_num_left == 0 || self.cycles_left == 0
+    }
 }
 
 /// This is the core structure for caching new transactions and
 /// feeding transactions in batch for consensus.
 ///
-/// The queues are served for packaging a batch of transactions in insertion
-/// order. The `map` is served for randomly search and removal.
-/// All these collections should support concurrent insertion.
-/// We set two queues, `queue_0` and `queue_1`, to make package concurrent with
-/// insertion. When `queue_0` served for insertion and package begins,
-/// transactions pop from `queue_0` and push into `queue_1` while new
-/// transactions still insert into `queue_0` concurrently. while `queue_0` pop
-/// out, `queue_1` switch to insertion queue.
+/// The `map` is served for randomly search and removal, the `index` keeps
+/// the same transactions in package order and by sender and nonce. Both are
+/// updated together under the index lock on insert and flush, so package
+/// only walks the index as far as the limits reach instead of sorting the
+/// whole pool.
 pub struct TxCache {
-    /// One queue.
-    queue_0:          ArrayQueue<SharedTx>,
-    /// Another queue.
-    queue_1:          ArrayQueue<SharedTx>,
+    /// Pool size limit.
+    pool_size:          usize,
     /// A map for randomly search and removal.
-    map:              Map<SharedTx>,
-    /// This is used to pick a queue for insertion,
-    /// If true selects `queue_0`, else `queue_1`.
-    is_zero:          AtomicBool,
-    /// This is an atomic state to solve concurrent insertion problem during
-    /// package. While switching insertion queues, some transactions may
-    /// still insert into the old queue. We use this state to make sure
-    /// switch insertions *happen-before* old queue re-pop.
-    concurrent_count: AtomicUsize,
+    map:                Map<SharedTx>,
+    /// Secondary indices over the transactions of `map`.
+    index:              Mutex<TxIndex>,
+    /// Arrival counter, the next transaction gets this sequence.
+    next_seq:           AtomicU64,
+    /// Percentage by which a transaction must outbid the pending one with
+    /// the same sender and nonce to replace it.
+    replace_price_bump: u64,
+    /// Pending transactions a single sender may have.
+    sender_limit:       usize,
+    /// Hashes of the transactions in `map`, readable without any lock.
+    seen:               SeenTxs,
+    /// `(service_name, method)` of the calls packaged ahead of the fee
+    /// market.
+    priority_methods:   Vec<(String, String)>,
 }
 
 impl TxCache {
     pub fn new(pool_size: usize) -> Self {
         TxCache {
-            queue_0:          ArrayQueue::new(pool_size * 2),
-            queue_1:          ArrayQueue::new(pool_size * 2),
-            map:              Map::new(pool_size * 2),
-            is_zero:          AtomicBool::new(true),
-            concurrent_count: AtomicUsize::new(0),
+            pool_size,
+            map: Map::new(pool_size * 2),
+            index: Mutex::new(TxIndex::default()),
+            next_seq: AtomicU64::new(0),
+            replace_price_bump: DEFAULT_REPLACE_PRICE_BUMP,
+            sender_limit: DEFAULT_SENDER_LIMIT,
+            seen: SeenTxs::new(pool_size),
+            priority_methods: Vec::new(),
         }
     }
 
+    pub fn with_priority_methods(mut self, methods: Vec<(String, String)>) -> Self {
+        self.priority_methods = methods;
+        self
+    }
+
+    pub fn with_replace_price_bump(mut self, percent: u64) -> Self {
+        self.replace_price_bump = percent;
+        self
+    }
+
+    pub fn with_sender_limit(mut self, sender_limit: usize) -> Self {
+        self.sender_limit = sender_limit;
+        self
+    }
+
     pub fn len(&self) -> usize {
         self.map.len()
     }
 
-    pub fn insert_new_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
+    /// Returns the hash of the pending transaction it replaced or evicted,
+    /// if any.
+    pub fn insert_new_tx(
+        &self,
+        signed_tx: SignedTransaction,
+        origin: TxOrigin,
+    ) -> ProtocolResult<Option<Hash>> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::new(signed_tx);
+        let sender = Address::from_pubkey_bytes(signed_tx.pubkey.clone())?;
+        let priority = self.is_priority(&signed_tx.raw.request);
+        let tx_wrapper = TxWrapper::new(signed_tx, sender, self.next_seq())
+            .with_priority(priority)
+            .with_origin(origin);
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx)
     }
 
-    pub fn insert_propose_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
+    /// Returns the hash of the pending transaction it replaced or evicted,
+    /// if any.
+    pub fn insert_propose_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<Option<Hash>> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::propose(signed_tx);
+        let sender = Address::from_pubkey_bytes(signed_tx.pubkey.clone())?;
+        let priority = self.is_priority(&signed_tx.raw.request);
+        let tx_wrapper =
+            TxWrapper::propose(signed_tx, sender, self.next_seq()).with_priority(priority);
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx)
     }
@@ -165,89 +400,99 @@ impl TxCache {
             .collect()
     }
 
-    pub fn flush(&self, tx_hashes: &[Hash], current_height: u64, timeout: u64) {
+    pub fn flush(&self, tx_hashes: &[Hash]) {
+        let mut index = self.index.lock();
+
         for tx_hash in tx_hashes {
             let opt = self.map.get(tx_hash);
             if let Some(shared_tx) = opt {
                 shared_tx.set_removed();
+                index.remove(&shared_tx);
+                self.seen.remove(tx_hash);
             }
         }
-        // Dividing set removed and remove into two loops is to avoid lock competition.
         self.map.deletes(tx_hashes);
-        self.flush_incumbent_queue(current_height, timeout);
     }
 
+    /// Drops every transaction that can no longer be packaged at
+    /// `current_height` and returns their hashes with the reason.
+    pub fn expire(&self, current_height: u64, timeout: u64) -> Vec<(Hash, MemPoolError)> {
+        let mut index = self.index.lock();
+
+        let timeout_txs: Vec<(SharedTx, MemPoolError)> = index
+            .queued()
+            .filter(|shared_tx| shared_tx.is_timeout(current_height, timeout))
+            .map(|shared_tx| (Arc::clone(shared_tx), shared_tx.timeout_err(current_height)))
+            .collect();
+        self.drop_txs(&mut index, timeout_txs)
+    }
+
+    /// Collects up to `tx_num_limit` transactions within `cycles_limit` for
+    /// the order hashes and as many again for the propose hashes, walking
+    /// the priority transactions first and then the others by descending
+    /// `cycles_price`. A stage ends once either limit is used up. A
+    /// transaction that doesn't fit in what is left of a stage's cycles is
+    /// skipped, not the end of it.
+    ///
+    /// Timed out transactions met on the way are dropped, and so are the
+    /// ones exceeding `cycles_limit` on their own after being skipped
+    /// `MAX_OVERSIZE_SKIPS` times. Their hashes are returned alongside with
+    /// the reason.
     pub fn package(
         &self,
-        _cycles_limit: u64,
+        cycles_limit: u64,
         tx_num_limit: u64,
         current_height: u64,
         timeout: u64,
-    ) -> ProtocolResult<MixedTxHashes> {
-        let queue_role = self.get_queue_role();
-
-        let mut order_tx_hashes = Vec::new();
-        let mut propose_tx_hashes = Vec::new();
-        let mut timeout_tx_hashes = Vec::new();
+    ) -> ProtocolResult<(MixedTxHashes, Vec<(Hash, MemPoolError)>)> {
+        let mut index = self.index.lock();
 
-        let mut tx_count: u64 = 0;
-        let mut stage = Stage::OrderTxs;
+        let mut order = Stage::new(cycles_limit, tx_num_limit);
+        let mut propose = Stage::new(cycles_limit, tx_num_limit);
+        let mut dropped_txs = Vec::new();
 
-        loop {
-            if let Ok(shared_tx) = queue_role.incumbent.pop() {
-                let tx_hash = &shared_tx.tx.tx_hash;
-
-                if shared_tx.is_removed() {
-                    continue;
-                }
-                if shared_tx.is_timeout(current_height, timeout) {
-                    timeout_tx_hashes.push(tx_hash.clone());
-                    continue;
-                }
-                // After previous filter, tx are valid and should cache in temp_queue.
-                if queue_role
-                    .candidate
-                    .push(Arc::<TxWrapper>::clone(&shared_tx))
-                    .is_err()
-                {
-                    log::error!(
-                        "[core_mempool]: candidate queue is full while package, delete {:?}",
-                        &shared_tx.tx.tx_hash
+        for shared_tx in index.queued() {
+            if order.is_full() && propose.is_full() {
+                break;
+            }
+            if shared_tx.is_removed() {
+                continue;
+            }
+            if shared_tx.is_timeout(current_height, timeout) {
+                let err = shared_tx.timeout_err(current_height);
+                dropped_txs.push((Arc::clone(shared_tx), err));
+                continue;
+            }
+            // No block takes it, as happens after the cycles limit shrinks
+            if shared_tx.tx.raw.cycles_limit > cycles_limit {
+                if shared_tx.skip() >= MAX_OVERSIZE_SKIPS {
+                    log::info!(
+                        "[core_mempool]: tx {:?} evicted, its {:?} cycles exceed the limit {:?}",
+                        shared_tx.tx.tx_hash,
+                        shared_tx.tx.raw.cycles_limit,
+                        cycles_limit
                     );
-                    self.map.remove(&shared_tx.tx.tx_hash);
-                }
-
-                if stage == Stage::Finished
-                    || (stage == Stage::ProposeTxs && shared_tx.is_proposed())
-                {
-                    continue;
-                }
-                tx_count += 1;
-                if tx_count > tx_num_limit {
-                    stage = stage.next();
-                    tx_count = 1;
+                    let err = MemPoolError::ExceedCyclesLimit {
+                        tx_hash:             shared_tx.tx.tx_hash.clone(),
+                        cycles_limit_config: cycles_limit,
+                        cycles_limit_tx:     shared_tx.tx.raw.cycles_limit,
+                    };
+                    dropped_txs.push((Arc::clone(shared_tx), err));
                 }
+                continue;
+            }
 
-                match stage {
-                    Stage::OrderTxs => order_tx_hashes.push(tx_hash.clone()),
-                    Stage::ProposeTxs => propose_tx_hashes.push(tx_hash.clone()),
-                    Stage::Finished => {}
-                }
-            } else {
-                // Switch queue_roles
-                let new_role = self.switch_queue_role();
-                // Transactions may insert into previous incumbent queue during role switch.
-                self.process_omission_txs(new_role);
-                break;
+            if !order.push(&shared_tx.tx) && !shared_tx.is_proposed() {
+                propose.push(&shared_tx.tx);
             }
         }
-        // Remove timeout tx in map
-        self.map.deletes(&timeout_tx_hashes);
+        let dropped = self.drop_txs(&mut index, dropped_txs);
 
-        Ok(MixedTxHashes {
-            order_tx_hashes,
-            propose_tx_hashes,
-        })
+        let mixed_tx_hashes = MixedTxHashes {
+            order_tx_hashes:   order.tx_hashes,
+            propose_tx_hashes: propose.tx_hashes,
+        };
+        Ok((mixed_tx_hashes, dropped))
     }
 
     #[inline]
@@ -261,14 +506,38 @@ impl TxCache {
         Ok(())
     }
 
+    /// Turns away a transaction surely in the pool without taking a lock.
     #[inline]
-    pub fn check_reach_limit(&self, pool_size: usize) -> ProtocolResult<()> {
-        if self.len() >= pool_size {
-            return Err(MemPoolError::ReachLimit { pool_size }.into());
+    pub fn check_seen(&self, tx_hash: &Hash) -> ProtocolResult<()> {
+        if self.seen.contains(tx_hash) {
+            return Err(MemPoolError::Dup {
+                tx_hash: tx_hash.clone(),
+            }
+            .into());
         }
         Ok(())
     }
 
+    #[inline]
+    pub fn check_reach_limit(&self, tx: &SignedTransaction) -> ProtocolResult<()> {
+        if self.len() < self.pool_size {
+            return Ok(());
+        }
+
+        // A full pool still takes a priority transaction or one paying more
+        // than its cheapest
+        let priority = self.is_priority(&tx.raw.request);
+        match self.index.lock().cheapest() {
+            Some(cheapest) if priority || cheapest.tx.raw.cycles_price < tx.raw.cycles_price => {
+                Ok(())
+            }
+            _ => Err(MemPoolError::ReachLimit {
+                pool_size: self.pool_size,
+            }
+            .into()),
+        }
+    }
+
     #[inline]
     pub fn contain(&self, tx_hash: &Hash) -> bool {
         self.map.contains_key(tx_hash)
@@ -279,18 +548,99 @@ impl TxCache {
         self.map.get(tx_hash).map(|shared_tx| shared_tx.tx.clone())
     }
 
-    #[allow(dead_code)]
+    /// Number of transactions waiting in package order.
     pub fn queue_len(&self) -> usize {
-        if self.is_zero.load(Ordering::Relaxed) {
-            self.queue_0.len()
-        } else {
-            self.queue_1.len()
+        self.index.lock().queued_len()
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let index = self.index.lock();
+        // Prices of the fee market, priority transactions don't take part
+        let price = |shared_tx: &SharedTx| shared_tx.tx.raw.cycles_price;
+
+        PoolStats {
+            pending:          index.totals.pending,
+            parked:           index.totals.parked,
+            from_peers:       index.totals.from_peers,
+            capacity:         self.pool_size,
+            min_price:        index.cheapest().map(price),
+            max_price:        index.by_price.values().next().map(price),
+            oldest_timestamp: index.arrivals.values().next().cloned(),
+            bytes:            index.totals.bytes,
         }
     }
 
-    fn insert(&self, tx_hash: Hash, shared_tx: SharedTx) -> ProtocolResult<()> {
+    /// Up to `limit` transactions not from propose sync, in package order.
+    pub fn content(&self, limit: usize) -> Vec<PendingTx> {
+        self.index
+            .lock()
+            .queued()
+            .filter(|shared_tx| !shared_tx.is_proposed())
+            .take(limit)
+            .map(|shared_tx| PendingTx {
+                tx_hash:      shared_tx.tx.tx_hash.clone(),
+                sender:       shared_tx.sender.clone(),
+                nonce:        shared_tx.tx.raw.nonce.clone(),
+                cycles_price: shared_tx.tx.raw.cycles_price,
+            })
+            .collect()
+    }
+
+    fn insert(&self, tx_hash: Hash, shared_tx: SharedTx) -> ProtocolResult<Option<Hash>> {
+        let mut index = self.index.lock();
+
+        if self.contain(&tx_hash) {
+            return Err(MemPoolError::Dup { tx_hash }.into());
+        }
+
+        // A pending transaction with the same sender and nonce only makes
+        // way for one paying enough more
+        let replaced = match index.pending(&shared_tx.sender, &shared_tx.tx.raw.nonce) {
+            Some(pending) => {
+                let min_price = replace_price(pending.tx.raw.cycles_price, self.replace_price_bump);
+                if shared_tx.tx.raw.cycles_price < min_price {
+                    return Err(MemPoolError::ReplaceUnderpriced {
+                        tx_hash,
+                        pending: pending.tx.tx_hash.clone(),
+                        cycles_price: shared_tx.tx.raw.cycles_price,
+                        min_price,
+                    }
+                    .into());
+                }
+                Some(Arc::clone(pending))
+            }
+            None => None,
+        };
+
+        // A replacement leaves the sender's pending count as it is
+        if replaced.is_none() && index.pending_count(&shared_tx.sender) >= self.sender_limit {
+            return Err(MemPoolError::SenderLimit {
+                tx_hash,
+                sender: shared_tx.sender.clone(),
+                sender_limit: self.sender_limit,
+            }
+            .into());
+        }
+
+        // Otherwise a full pool makes room by evicting its cheapest
+        // transaction, as long as that one pays less or the new one has
+        // priority
+        let outbids = |cheapest: &SharedTx| {
+            shared_tx.priority || cheapest.tx.raw.cycles_price < shared_tx.tx.raw.cycles_price
+        };
+        let evicted = match index.cheapest() {
+            _ if replaced.is_some() || self.len() < self.pool_size => None,
+            Some(cheapest) if outbids(cheapest) => Some(Arc::clone(cheapest)),
+            _ => {
+                return Err(MemPoolError::ReachLimit {
+                    pool_size: self.pool_size,
+                }
+                .into())
+            }
+        };
+
         // If multiple transactions exactly the same insert concurrently,
-        // this will prevent them to be both insert successfully into queue.
+        // this will prevent them to be both insert successfully.
         if self
             .map
             .insert(tx_hash.clone(), Arc::<TxWrapper>::clone(&shared_tx))
@@ -299,109 +649,92 @@ impl TxCache {
             return Err(MemPoolError::Dup { tx_hash }.into());
         }
 
-        self.concurrent_count.fetch_add(1, Ordering::SeqCst);
-        let rst = self
-            .get_queue_role()
-            .incumbent
-            .push(Arc::<TxWrapper>::clone(&shared_tx));
-        self.concurrent_count.fetch_sub(1, Ordering::SeqCst);
-
-        // If queue inserts into queue failed, removes from map.
-        if rst.is_err() {
-            // If tx_hash exists, it will panic. So repeat check must do before insertion.
-            self.map.remove(&tx_hash);
-            Err(MemPoolError::Insert { tx_hash }.into())
-        } else {
-            Ok(())
+        if let Some(pending) = replaced.as_ref() {
+            log::info!(
+                "[core_mempool]: tx {:?} replaced by {:?}",
+                pending.tx.tx_hash,
+                tx_hash
+            );
         }
-    }
-
-    // Process transactions insert into previous incumbent queue during role switch.
-    fn process_omission_txs(&self, queue_role: QueueRole) {
-        'outer: loop {
-            // When there are no transaction insertions processing,
-            // pop off previous incumbent queue and push them into current incumbent queue.
-            if self.concurrent_count.load(Ordering::SeqCst) == 0 {
-                while let Ok(shared_tx) = queue_role.candidate.pop() {
-                    if queue_role
-                        .incumbent
-                        .push(Arc::<TxWrapper>::clone(&shared_tx))
-                        .is_err()
-                    {
-                        log::error!(
-                            "[core_mempool]: incumbent queue is full while process_omission_txs, delete {:?}",
-                            &shared_tx.tx.tx_hash
-                        );
-                        self.map.remove(&shared_tx.tx.tx_hash);
-                    }
-                }
-                break 'outer;
-            }
+        if let Some(cheapest) = evicted.as_ref() {
+            log::info!(
+                "[core_mempool]: pool full, tx {:?} evicted by {:?}",
+                cheapest.tx.tx_hash,
+                tx_hash
+            );
         }
-    }
 
-    fn flush_incumbent_queue(&self, current_height: u64, timeout: u64) {
-        let queue_role = self.get_queue_role();
-        let mut timeout_tx_hashes = Vec::new();
+        let displaced_hash = replaced.or(evicted).map(|displaced| {
+            displaced.set_removed();
+            index.remove(&displaced);
+            self.map.remove(&displaced.tx.tx_hash);
+            self.seen.remove(&displaced.tx.tx_hash);
+            displaced.tx.tx_hash.clone()
+        });
+        self.seen.insert(&tx_hash);
+        index.insert(shared_tx);
+        Ok(displaced_hash)
+    }
 
-        loop {
-            if let Ok(shared_tx) = queue_role.incumbent.pop() {
-                let tx_hash = &shared_tx.tx.tx_hash;
+    fn remove_txs(&self, index: &mut TxIndex, txs: Vec<SharedTx>) -> Vec<Hash> {
+        let tx_hashes: Vec<Hash> = txs
+            .into_iter()
+            .map(|shared_tx| {
+                shared_tx.set_removed();
+                index.remove(&shared_tx);
+                self.seen.remove(&shared_tx.tx.tx_hash);
+                shared_tx.tx.tx_hash.clone()
+            })
+            .collect();
+        self.map.deletes(&tx_hashes);
+        tx_hashes
+    }
 
-                if shared_tx.is_removed() {
-                    continue;
-                }
-                if shared_tx.is_timeout(current_height, timeout) {
-                    timeout_tx_hashes.push(tx_hash.clone());
-                    continue;
-                }
-                // After previous filter, tx are valid and should cache in temp_queue.
-                if queue_role
-                    .candidate
-                    .push(Arc::<TxWrapper>::clone(&shared_tx))
-                    .is_err()
-                {
-                    log::error!(
-                        "[core_mempool]: candidate queue is full while flush_incumbent_queue, delete {:?}",
-                        &shared_tx.tx.tx_hash
-                    );
-                    self.map.remove(&shared_tx.tx.tx_hash);
-                }
-            } else {
-                // Switch queue_roles
-                let new_role = self.switch_queue_role();
-                // Transactions may insert into previous incumbent queue during role switch.
-                self.process_omission_txs(new_role);
-                break;
-            }
-        }
-        // Remove timeout tx in map
-        self.map.deletes(&timeout_tx_hashes);
+    fn drop_txs(
+        &self,
+        index: &mut TxIndex,
+        txs: Vec<(SharedTx, MemPoolError)>,
+    ) -> Vec<(Hash, MemPoolError)> {
+        let (txs, reasons): (Vec<SharedTx>, Vec<MemPoolError>) = txs.into_iter().unzip();
+        self.remove_txs(index, txs)
+            .into_iter()
+            .zip(reasons)
+            .collect()
     }
 
-    fn switch_queue_role(&self) -> QueueRole {
-        self.is_zero.fetch_xor(true, Ordering::SeqCst);
-        self.get_queue_role()
+    // A handful of methods at most, a scan beats hashing the strings
+    fn is_priority(&self, request: &TransactionRequest) -> bool {
+        self.priority_methods.iter().any(|(service_name, method)| {
+            service_name == &request.service_name && method == &request.method
+        })
     }
 
     #[inline]
-    fn get_queue_role(&self) -> QueueRole {
-        let (incumbent, candidate) = if self.is_zero.load(Ordering::SeqCst) {
-            (&self.queue_0, &self.queue_1)
-        } else {
-            (&self.queue_1, &self.queue_0)
-        };
-        QueueRole {
-            incumbent,
-            candidate,
-        }
+    fn next_seq(&self) -> u64 {
+        self.next_seq.fetch_add(1, Ordering::SeqCst)
     }
 }
 
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// The lowest price outbidding `cycles_price` by `bump` percent, and at least
+// by one.
+fn replace_price(cycles_price: u64, bump: u64) -> u64 {
+    let bumped = (u128::from(cycles_price) * (100 + u128::from(bump)) + 99) / 100;
+    let bumped = cmp::min(bumped, u128::from(u64::max_value())) as u64;
+    cmp::max(bumped, cycles_price.saturating_add(1))
+}
+
 #[cfg(test)]
 mod tests {
     extern crate test;
 
+    use std::collections::HashSet;
     use std::sync::Arc;
     use std::thread;
 
@@ -410,11 +743,13 @@ mod tests {
     use rayon::prelude::*;
     use test::Bencher;
 
-    use protocol::types::{Hash, RawTransaction, SignedTransaction, TransactionRequest};
+    use protocol::traits::PoolStats;
+    use protocol::types::{Address, Hash, RawTransaction, SignedTransaction, TransactionRequest};
     use protocol::Bytes;
 
+    use crate::context::TxOrigin;
     use crate::map::Map;
-    use crate::tx_cache::{TxCache, TxWrapper};
+    use crate::tx_cache::{SharedTx, TxCache, TxWrapper};
     use std::thread::JoinHandle;
 
     const POOL_SIZE: usize = 1000;
@@ -430,6 +765,11 @@ mod tests {
         (0..BYTES_LEN).map(|_| random::<u8>()).collect()
     }
 
+    // The mock txs all share one sender
+    fn new_tx_cache() -> TxCache {
+        TxCache::new(POOL_SIZE).with_sender_limit(POOL_SIZE)
+    }
+
     fn gen_signed_txs(n: usize) -> Vec<SignedTransaction> {
         let mut vec = Vec::new();
         for _ in 0..n {
@@ -468,9 +808,41 @@ mod tests {
         }
     }
 
+    fn mock_sender_tx(sender: u8, nonce: u8, cycles_price: u64, timeout: u64) -> SignedTransaction {
+        let mut tx = mock_signed_tx(gen_bytes());
+        tx.raw.nonce = Hash::digest(Bytes::from(vec![nonce]));
+        tx.raw.cycles_price = cycles_price;
+        tx.raw.timeout = timeout;
+        tx.pubkey = Bytes::from(vec![sender]);
+        tx
+    }
+
+    // What the stats should be, recomputed from every transaction still held
+    fn scan_stats(tx_cache: &TxCache, tx_hashes: &HashSet<Hash>) -> PoolStats {
+        let held: Vec<SharedTx> = tx_hashes
+            .iter()
+            .filter_map(|tx_hash| tx_cache.map.get(tx_hash))
+            .collect();
+        let prices = || held.iter().map(|shared_tx| shared_tx.tx.raw.cycles_price);
+
+        PoolStats {
+            pending:          held.iter().filter(|tx| !tx.is_proposed()).count(),
+            parked:           held.iter().filter(|tx| tx.is_proposed()).count(),
+            from_peers:       held.iter().filter(|tx| tx.origin == TxOrigin::Peer).count(),
+            capacity:         tx_cache.pool_size,
+            min_price:        prices().min(),
+            max_price:        prices().max(),
+            oldest_timestamp: held
+                .iter()
+                .min_by_key(|shared_tx| shared_tx.seq)
+                .map(|shared_tx| shared_tx.arrived_at),
+            bytes:            held.iter().map(|shared_tx| shared_tx.size()).sum(),
+        }
+    }
+
     fn concurrent_insert(txs: Vec<SignedTransaction>, tx_cache: &TxCache) {
         txs.par_iter().for_each(|signed_tx| {
-            let _ = tx_cache.insert_new_tx(signed_tx.clone());
+            let _ = tx_cache.insert_new_tx(signed_tx.clone(), TxOrigin::Peer);
         });
     }
 
@@ -482,7 +854,8 @@ mod tests {
         let tx_cache_clone = Arc::<TxCache>::clone(tx_cache);
 
         thread::spawn(move || {
-            tx_cache_clone.flush(&tx_hashes, height, height + TIMEOUT);
+            tx_cache_clone.flush(&tx_hashes);
+            tx_cache_clone.expire(height, height + TIMEOUT);
         })
     }
 
@@ -506,24 +879,80 @@ mod tests {
                     .collect::<Vec<SignedTransaction>>()
             })
             .collect();
-        let tx_cache = TxCache::new(POOL_SIZE);
+        let tx_cache = new_tx_cache();
         concurrent_insert(txs, &tx_cache);
         assert_eq!(tx_cache.len(), POOL_SIZE / 2);
     }
 
+    #[test]
+    fn test_stats_follow_operations() {
+        // Fewer slots than senders may fill, so inserts also evict
+        let tx_cache = TxCache::new(32).with_sender_limit(8);
+        let mut tx_hashes = HashSet::new();
+
+        for _ in 0..2000 {
+            let sender = random::<u8>() % 6;
+            let nonce = random::<u8>() % 8;
+            let cycles_price = 1 + random::<u64>() % 100;
+            let timeout = CURRENT_H + random::<u64>() % 60;
+
+            match random::<u8>() % 8 {
+                0..=4 => {
+                    let tx = mock_sender_tx(sender, nonce, cycles_price, timeout);
+                    let origin = if random::<bool>() {
+                        TxOrigin::Api
+                    } else {
+                        TxOrigin::Peer
+                    };
+                    if tx_cache.insert_new_tx(tx.clone(), origin).is_ok() {
+                        tx_hashes.insert(tx.tx_hash);
+                    }
+                }
+                5 => {
+                    let tx = mock_sender_tx(sender, nonce, cycles_price, timeout);
+                    if tx_cache.insert_propose_tx(tx.clone()).is_ok() {
+                        tx_hashes.insert(tx.tx_hash);
+                    }
+                }
+                6 => {
+                    let flushed: Vec<Hash> = tx_hashes
+                        .iter()
+                        .filter(|_| random::<u8>() % 4 == 0)
+                        .cloned()
+                        .collect();
+                    tx_cache.flush(&flushed);
+                }
+                _ => {
+                    let height = CURRENT_H + random::<u64>() % 20;
+                    tx_cache.expire(height, height + TIMEOUT);
+                }
+            }
+
+            let stats = tx_cache.stats();
+            assert_eq!(stats, scan_stats(&tx_cache, &tx_hashes));
+            assert_eq!(stats.pending + stats.parked, tx_cache.len());
+
+            let content = tx_cache.content(5);
+            assert_eq!(content.len(), stats.pending.min(5));
+            assert!(content
+                .windows(2)
+                .all(|pair| pair[0].cycles_price >= pair[1].cycles_price));
+        }
+    }
+
     #[test]
     fn test_insert_overlap() {
         let txs = gen_signed_txs(1);
         let tx = txs.get(0).unwrap();
         let map = Map::new(POOL_SIZE);
 
-        let tx_wrapper_0 = TxWrapper::new(tx.clone());
+        let tx_wrapper_0 = TxWrapper::new(tx.clone(), Address::default(), 0);
         tx_wrapper_0.set_removed();
         map.insert(tx.tx_hash.clone(), Arc::new(tx_wrapper_0));
         let shared_tx_0 = map.get(&tx.tx_hash).unwrap();
         assert!(shared_tx_0.is_removed());
 
-        let tx_wrapper_1 = TxWrapper::new(tx.clone());
+        let tx_wrapper_1 = TxWrapper::new(tx.clone(), Address::default(), 0);
         map.insert(tx.tx_hash.clone(), Arc::new(tx_wrapper_1));
         let shared_tx_1 = map.get(&tx.tx_hash).unwrap();
         assert!(shared_tx_1.is_removed());
@@ -540,7 +969,7 @@ mod tests {
     fn bench_insert(b: &mut Bencher) {
         let txs = gen_signed_txs(TX_NUM);
         b.iter(|| {
-            let tx_cache = TxCache::new(POOL_SIZE);
+            let tx_cache = new_tx_cache();
             concurrent_insert(txs.clone(), &tx_cache);
             assert_eq!(tx_cache.len(), TX_NUM);
             assert_eq!(tx_cache.queue_len(), TX_NUM);
@@ -555,11 +984,11 @@ mod tests {
             .map(|signed_tx| signed_tx.tx_hash.clone())
             .collect();
         b.iter(|| {
-            let tx_cache = TxCache::new(POOL_SIZE);
+            let tx_cache = new_tx_cache();
             concurrent_insert(txs.clone(), &tx_cache);
             assert_eq!(tx_cache.len(), TX_NUM);
             assert_eq!(tx_cache.queue_len(), TX_NUM);
-            tx_cache.flush(tx_hashes.as_slice(), CURRENT_H, CURRENT_H + TIMEOUT);
+            tx_cache.flush(tx_hashes.as_slice());
             assert_eq!(tx_cache.len(), 0);
             assert_eq!(tx_cache.queue_len(), 0);
         });
@@ -574,7 +1003,7 @@ mod tests {
             .map(|signed_tx| signed_tx.tx_hash.clone())
             .collect();
         b.iter(|| {
-            let tx_cache = Arc::new(TxCache::new(POOL_SIZE));
+            let tx_cache = Arc::new(new_tx_cache());
             concurrent_insert(txs_base.clone(), &tx_cache);
             let handle = concurrent_flush(&tx_cache, txs_flush.clone(), CURRENT_H);
             concurrent_insert(txs_insert.clone(), &tx_cache);
@@ -587,10 +1016,10 @@ mod tests {
     #[bench]
     fn bench_package(b: &mut Bencher) {
         let txs = gen_signed_txs(TX_NUM);
-        let tx_cache = TxCache::new(POOL_SIZE);
+        let tx_cache = new_tx_cache();
         concurrent_insert(txs, &tx_cache);
         b.iter(|| {
-            let mixed_tx_hashes = tx_cache
+            let (mixed_tx_hashes, _) = tx_cache
                 .package(TX_NUM_LIMIT, CYCLE_LIMIT, CURRENT_H, TIMEOUT)
                 .unwrap();
             assert_eq!(
@@ -605,7 +1034,7 @@ mod tests {
         let txs = gen_signed_txs(TX_NUM / 2);
         let txs_insert = gen_signed_txs(TX_NUM / 2);
         b.iter(|| {
-            let tx_cache = Arc::new(TxCache::new(POOL_SIZE));
+            let tx_cache = Arc::new(new_tx_cache());
             concurrent_insert(txs.clone(), &tx_cache);
             let handle = concurrent_package(&tx_cache);
             concurrent_insert(txs_insert.clone(), &tx_cache);
@@ -203,3 +203,9 @@ impl From<&Multiaddr> for ConnectedAddr {
         ConnectedAddr { host, port }
     }
 }
+
+impl ConnectedAddr {
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+}
@@ -17,7 +17,7 @@ use crate::{
     common::socket_to_multi_addr,
     connection::ConnectionConfig,
     error::NetworkError,
-    peer_manager::{ArcPeer, PeerManagerConfig, SharedSessionsConfig},
+    peer_manager::{ArcPeer, PeerManagerConfig, PeerScoreConfig, SharedSessionsConfig},
     selfcheck::SelfCheckConfig,
     traits::MultiaddrExt,
 };
@@ -44,6 +44,7 @@ pub const DEFAULT_PEER_DAT_FILE: &str = "./peers.dat";
 
 pub const DEFAULT_PING_INTERVAL: u64 = 15;
 pub const DEFAULT_PING_TIMEOUT: u64 = 30;
+pub const DEFAULT_PING_MAX_MISSED: usize = 3;
 pub const DEFAULT_DISCOVERY_SYNC_INTERVAL: u64 = 60 * 60; // 1 hour
 
 pub const DEFAULT_PEER_MANAGER_HEART_BEAT_INTERVAL: u64 = 30;
@@ -51,6 +52,11 @@ pub const DEFAULT_SELF_HEART_BEAT_INTERVAL: u64 = 35;
 
 pub const DEFAULT_RPC_TIMEOUT: u64 = 10;
 
+// Peer score
+pub const DEFAULT_BAN_THRESHOLD: u64 = 100;
+pub const DEFAULT_BAN_DURATION: u64 = 60 * 60; // 1 hour
+pub const DEFAULT_SCORE_HALF_LIFE: u64 = 10 * 60; // 10 minutes
+
 // Selfcheck
 pub const DEFAULT_SELF_CHECK_INTERVAL: u64 = 30;
 
@@ -118,6 +124,7 @@ pub struct NetworkConfig {
     // protocol
     pub ping_interval:           Duration,
     pub ping_timeout:            Duration,
+    pub ping_max_missed:         usize,
     pub discovery_sync_interval: Duration,
 
     // routine
@@ -127,6 +134,12 @@ pub struct NetworkConfig {
     // rpc
     pub rpc_timeout: Duration,
 
+    // peer score
+    pub ban_threshold:   u64,
+    pub ban_duration:    Duration,
+    pub score_half_life: Duration,
+    pub ban_host:        bool,
+
     // self check
     pub selfcheck_interval: Duration,
 }
@@ -158,6 +171,7 @@ impl NetworkConfig {
 
             ping_interval:           Duration::from_secs(DEFAULT_PING_INTERVAL),
             ping_timeout:            Duration::from_secs(DEFAULT_PING_TIMEOUT),
+            ping_max_missed:         DEFAULT_PING_MAX_MISSED,
             discovery_sync_interval: Duration::from_secs(DEFAULT_DISCOVERY_SYNC_INTERVAL),
 
             peer_manager_heart_beat_interval: peer_manager_hb_interval,
@@ -165,6 +179,11 @@ impl NetworkConfig {
 
             rpc_timeout: Duration::from_secs(DEFAULT_RPC_TIMEOUT),
 
+            ban_threshold:   DEFAULT_BAN_THRESHOLD,
+            ban_duration:    Duration::from_secs(DEFAULT_BAN_DURATION),
+            score_half_life: Duration::from_secs(DEFAULT_SCORE_HALF_LIFE),
+            ban_host:        false,
+
             selfcheck_interval: Duration::from_secs(DEFAULT_SELF_CHECK_INTERVAL),
         }
     }
@@ -292,14 +311,26 @@ impl NetworkConfig {
         }
     }
 
-    pub fn ping_interval(mut self, interval: u64) -> Self {
-        self.ping_interval = Duration::from_secs(interval);
+    pub fn ping_interval(mut self, interval: Option<u64>) -> Self {
+        if let Some(interval) = interval {
+            self.ping_interval = Duration::from_secs(interval);
+        }
 
         self
     }
 
-    pub fn ping_timeout(mut self, timeout: u64) -> Self {
-        self.ping_timeout = Duration::from_secs(timeout);
+    pub fn ping_timeout(mut self, timeout: Option<u64>) -> Self {
+        if let Some(timeout) = timeout {
+            self.ping_timeout = Duration::from_secs(timeout);
+        }
+
+        self
+    }
+
+    pub fn ping_max_missed(mut self, max: Option<usize>) -> Self {
+        if let Some(max) = max {
+            self.ping_max_missed = max;
+        }
 
         self
     }
@@ -338,6 +369,38 @@ impl NetworkConfig {
         self
     }
 
+    pub fn ban_threshold(mut self, threshold: Option<u64>) -> Self {
+        if let Some(threshold) = threshold {
+            self.ban_threshold = threshold;
+        }
+
+        self
+    }
+
+    pub fn ban_duration(mut self, duration: Option<u64>) -> Self {
+        if let Some(duration) = duration {
+            self.ban_duration = Duration::from_secs(duration);
+        }
+
+        self
+    }
+
+    pub fn score_half_life(mut self, half_life: Option<u64>) -> Self {
+        if let Some(half_life) = half_life {
+            self.score_half_life = Duration::from_secs(half_life);
+        }
+
+        self
+    }
+
+    pub fn ban_host(mut self, ban_host: Option<bool>) -> Self {
+        if let Some(ban_host) = ban_host {
+            self.ban_host = ban_host;
+        }
+
+        self
+    }
+
     fn parse_peer_addr(addr: PeerAddrStr) -> ProtocolResult<Multiaddr> {
         if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
             Ok(socket_to_multi_addr(socket_addr))
@@ -379,6 +442,13 @@ impl From<&NetworkConfig> for PeerManagerConfig {
             max_connections:          config.max_connections,
             routine_interval:         config.peer_manager_heart_beat_interval,
             peer_dat_file:            config.peer_dat_file.clone(),
+            ping_max_missed:          config.ping_max_missed,
+            peer_score:               PeerScoreConfig {
+                ban_threshold: config.ban_threshold,
+                ban_duration:  config.ban_duration,
+                half_life:     config.score_half_life,
+                ban_host:      config.ban_host,
+            },
         }
     }
 }
@@ -190,4 +190,8 @@ where
 
         Ok(())
     }
+
+    fn sendable_sessions(&self) -> Vec<SessionId> {
+        self.sessions.all_sendable()
+    }
 }
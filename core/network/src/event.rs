@@ -1,4 +1,4 @@
-use std::{error::Error, sync::Arc};
+use std::{error::Error, sync::Arc, time::Duration};
 
 use derive_more::Display;
 use protocol::types::Address;
@@ -80,6 +80,20 @@ pub enum MisbehaviorKind {
     // Maybe message codec or nonce incorrect
     #[display(fmt = "ping unexpect")]
     PingUnexpect,
+
+    // Message we cannot decode
+    #[display(fmt = "invalid message")]
+    InvalidMessage,
+
+    #[display(fmt = "invalid proposal")]
+    InvalidProposal,
+
+    #[display(fmt = "invalid transaction")]
+    InvalidTransaction,
+
+    // Block or transactions that don't match what we asked for
+    #[display(fmt = "invalid sync response")]
+    InvalidSyncResponse,
 }
 
 #[derive(Debug, Display, PartialEq, Eq)]
@@ -138,12 +152,18 @@ pub enum PeerManagerEvent {
         kind: SessionErrorKind,
     },
 
-    #[display(fmt = "peer {:?} alive", pid)]
-    PeerAlive { pid: PeerId },
+    #[display(fmt = "peer {:?} alive, rtt {:?}", pid, rtt)]
+    PeerAlive { pid: PeerId, rtt: Duration },
 
     #[display(fmt = "peer {:?} misbehave {}", pid, kind)]
     Misbehave { pid: PeerId, kind: MisbehaviorKind },
 
+    #[display(fmt = "session {} misbehave {}", sid, kind)]
+    SessionMisbehave {
+        sid:  SessionId,
+        kind: MisbehaviorKind,
+    },
+
     #[display(fmt = "whitelist peers by chain addresses {:?}", chain_addrs)]
     WhitelistPeersByChainAddr { chain_addrs: Vec<Address> },
 
@@ -19,5 +19,6 @@ mod test;
 mod traits;
 
 pub use config::NetworkConfig;
+pub use event::MisbehaviorKind;
 pub use message::{serde, serde_multi};
 pub use service::{NetworkService, NetworkServiceHandle};
@@ -139,4 +139,16 @@ where
 
         Ok(())
     }
+
+    fn peer_contexts(&self, cx: &Context, limit: usize) -> Vec<Context> {
+        let origin = cx.session_id().ok();
+
+        self.sender
+            .sendable_sessions()
+            .into_iter()
+            .filter(|sid| Some(*sid) != origin)
+            .take(limit)
+            .map(|sid| Context::new().set_session_id(sid))
+            .collect()
+    }
 }
@@ -4,6 +4,7 @@ mod ident;
 mod peer;
 mod retry;
 mod save_restore;
+mod score;
 mod shared;
 mod time;
 
@@ -11,10 +12,12 @@ use addr_set::PeerAddrSet;
 use peer::Peer;
 use retry::Retry;
 use save_restore::{NoPeerDatFile, PeerDatFile, SaveRestore};
+use score::PeerScore;
 
 pub use disc::DiscoveryAddrManager;
 pub use ident::IdentifyCallback;
 pub use peer::{ArcPeer, Connectedness};
+pub use score::PeerScoreConfig;
 pub use shared::{SharedSessions, SharedSessionsConfig};
 
 #[cfg(test)]
@@ -34,7 +37,7 @@ use std::{
     sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     sync::Arc,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use derive_more::Display;
@@ -314,6 +317,8 @@ struct Session {
     peer:           ArcPeer,
     blocked:        AtomicBool,
     connected_addr: ConnectedAddr,
+    ping_missed:    AtomicUsize,
+    rtt:            RwLock<Option<Duration>>,
 }
 
 #[derive(Debug, Clone)]
@@ -328,6 +333,8 @@ impl ArcSession {
             peer,
             blocked: AtomicBool::new(false),
             connected_addr,
+            ping_missed: AtomicUsize::new(0),
+            rtt: RwLock::new(None),
         };
 
         ArcSession(Arc::new(session))
@@ -344,6 +351,21 @@ impl ArcSession {
     pub fn unblock(&self) {
         self.blocked.store(false, Ordering::SeqCst);
     }
+
+    /// Round trip time of the latest ping answered
+    pub fn rtt(&self) -> Option<Duration> {
+        *self.rtt.read()
+    }
+
+    pub fn pong(&self, rtt: Duration) {
+        self.ping_missed.store(0, Ordering::SeqCst);
+        *self.rtt.write() = Some(rtt);
+    }
+
+    /// Returns ping timeouts in a row so far, including this one
+    pub fn ping_timeout(&self) -> usize {
+        self.ping_missed.fetch_add(1, Ordering::SeqCst) + 1
+    }
 }
 
 impl Borrow<SessionId> for ArcSession {
@@ -552,6 +574,12 @@ pub struct PeerManagerConfig {
 
     /// Peer dat file path
     pub peer_dat_file: PathBuf,
+
+    /// Ping timeouts in a row before a session is disconnected
+    pub ping_max_missed: usize,
+
+    /// Banning peers for misbehaviors
+    pub peer_score: PeerScoreConfig,
 }
 
 #[derive(Clone)]
@@ -602,6 +630,9 @@ pub struct PeerManager {
     // peers currently connecting
     connecting: HashSet<ConnectingAttempt>,
 
+    // misbehavior penalties and bans
+    score: PeerScore,
+
     event_rx: UnboundedReceiver<PeerManagerEvent>,
     conn_tx:  UnboundedSender<ConnectionEvent>,
 
@@ -626,6 +657,7 @@ impl PeerManager {
         let waker = Arc::new(AtomicWaker::new());
         let heart_beat = HeartBeat::new(Arc::clone(&waker), config.routine_interval);
         let peer_dat_file = Box::new(NoPeerDatFile);
+        let score = PeerScore::new(config.peer_score.clone());
 
         inner.whitelist_never_expired_peers_by_chain_addr(config.whitelist_by_chain_addrs.clone());
 
@@ -637,6 +669,8 @@ impl PeerManager {
 
             connecting: Default::default(),
 
+            score,
+
             event_rx,
             conn_tx,
 
@@ -724,6 +758,18 @@ impl PeerManager {
             }
         }
 
+        let remote_addr = ConnectedAddr::from(&ctx.address);
+        if self
+            .score
+            .is_banned(&remote_peer_id, Some(remote_addr.host()), Instant::now())
+        {
+            debug!("reject banned peer {:?}", remote_peer.id);
+
+            remote_peer.mark_disconnected();
+            self.disconnect_session(ctx.id);
+            return;
+        }
+
         if self.config.whitelist_peers_only && !self.inner.whitelisted(&remote_peer) {
             debug!("reject peer {:?} not in whitelist", remote_peer.id);
 
@@ -887,15 +933,18 @@ impl PeerManager {
         }
     }
 
-    fn update_peer_alive(&self, pid: &PeerId) {
+    fn update_peer_alive(&self, pid: &PeerId, rtt: Duration) {
         if let Some(peer) = self.inner.peer(pid) {
             peer.retry.reset(); // Just in case
             peer.update_alive();
+
+            if let Some(session) = self.inner.session(peer.session_id()) {
+                session.pong(rtt);
+            }
         }
     }
 
-    // TODO: score system
-    fn peer_misbehave(&self, pid: PeerId, kind: MisbehaviorKind) {
+    fn peer_misbehave(&mut self, pid: PeerId, kind: MisbehaviorKind) {
         use MisbehaviorKind::*;
 
         let peer = match self.inner.peer(&pid) {
@@ -906,6 +955,19 @@ impl PeerManager {
             }
         };
 
+        // Bad messages count against the peer until it crosses the threshold
+        if let Some(penalty) = score::penalty(&kind) {
+            let session = self.inner.session(peer.session_id());
+            let host = session.as_ref().map(|s| s.connected_addr.host());
+
+            if !self.score.report(&pid, host, penalty, Instant::now()) {
+                debug!("peer {:?} misbehave {}", pid, kind);
+                return;
+            }
+
+            warn!("ban peer {:?} for {}", pid, kind);
+        }
+
         let sid = peer.session_id();
         if sid == SessionId::new(0) {
             // Impossible, connected session always bigger than 0
@@ -913,6 +975,18 @@ impl PeerManager {
             return;
         }
 
+        // A single lost pong may be just a busy peer
+        if let PingTimeout = kind {
+            let missed = self.inner.session(sid).map(|s| s.ping_timeout());
+
+            if let Some(missed) = missed {
+                if missed < self.config.ping_max_missed {
+                    debug!("peer {:?} missed {} pings in a row", pid, missed);
+                    return;
+                }
+            }
+        }
+
         self.inner.remove_session(sid);
         peer.mark_disconnected();
         // Ensure we disconnect from this peer
@@ -921,6 +995,15 @@ impl PeerManager {
         match kind {
             PingTimeout => peer.retry.inc(),
             PingUnexpect | Discovery => peer.set_connectedness(Connectedness::Unconnectable), /* Give up this peer */
+            // Banned, no dial until the ban lapses
+            InvalidMessage | InvalidProposal | InvalidTransaction | InvalidSyncResponse => (),
+        }
+    }
+
+    fn session_misbehave(&mut self, sid: SessionId, kind: MisbehaviorKind) {
+        match self.inner.session(sid) {
+            Some(session) => self.peer_misbehave(session.peer.owned_id(), kind),
+            None => debug!("misbehave session {} not found", sid),
         }
     }
 
@@ -960,7 +1043,13 @@ impl PeerManager {
     }
 
     fn connect_peers(&mut self, peers: Vec<ArcPeer>) {
+        let now = Instant::now();
         let connectable = |p: ArcPeer| -> Option<ArcPeer> {
+            if self.score.is_banned(&p.id, None, now) {
+                debug!("filter banned peer {:?}", p.id);
+                return None;
+            }
+
             if self.config.whitelist_peers_only && !self.inner.whitelisted(&p) {
                 debug!("filter peer {:?} not in whitelist", p.id);
                 return None;
@@ -1074,8 +1163,9 @@ impl PeerManager {
             PeerManagerEvent::SessionBlocked { ctx, .. } => self.session_blocked(ctx),
             PeerManagerEvent::SessionClosed { sid, .. } => self.session_closed(sid),
             PeerManagerEvent::SessionFailed { sid, kind } => self.session_failed(sid, kind),
-            PeerManagerEvent::PeerAlive { pid } => self.update_peer_alive(&pid),
+            PeerManagerEvent::PeerAlive { pid, rtt } => self.update_peer_alive(&pid, rtt),
             PeerManagerEvent::Misbehave { pid, kind } => self.peer_misbehave(pid, kind),
+            PeerManagerEvent::SessionMisbehave { sid, kind } => self.session_misbehave(sid, kind),
             PeerManagerEvent::WhitelistPeersByChainAddr { chain_addrs } => {
                 self.inner.whitelist_peers_by_chain_addr(chain_addrs);
             }
@@ -1153,6 +1243,9 @@ impl Future for PeerManager {
         // Clean expired whitelisted peer
         self.inner.whitelist.write().retain(|p| !p.is_expired());
 
+        // Forget lapsed bans and decayed scores
+        self.score.prune(Instant::now());
+
         Poll::Pending
     }
 }
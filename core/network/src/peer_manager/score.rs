@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use tentacle::secio::PeerId;
+
+use crate::{
+    config::{DEFAULT_BAN_DURATION, DEFAULT_BAN_THRESHOLD, DEFAULT_SCORE_HALF_LIFE},
+    event::MisbehaviorKind,
+};
+
+// Scores decayed below this are forgotten
+const FORGOTTEN_SCORE: f64 = 1.0;
+
+#[derive(Debug, Clone)]
+pub struct PeerScoreConfig {
+    /// Score at which a peer is banned
+    pub ban_threshold: u64,
+    pub ban_duration:  Duration,
+    /// Time for a score to decay to half
+    pub half_life:     Duration,
+    /// Ban the host a banned peer connected from too. Nodes sharing a host
+    /// are all cut off with it, so it is off by default.
+    pub ban_host:      bool,
+}
+
+impl Default for PeerScoreConfig {
+    fn default() -> Self {
+        PeerScoreConfig {
+            ban_threshold: DEFAULT_BAN_THRESHOLD,
+            ban_duration:  Duration::from_secs(DEFAULT_BAN_DURATION),
+            half_life:     Duration::from_secs(DEFAULT_SCORE_HALF_LIFE),
+            ban_host:      false,
+        }
+    }
+}
+
+// Misbehaviors judged on the messages a peer sends. The others cut the
+// session at once.
+pub fn penalty(kind: &MisbehaviorKind) -> Option<u64> {
+    use MisbehaviorKind::*;
+
+    match kind {
+        InvalidMessage => Some(20),
+        InvalidTransaction => Some(10),
+        InvalidProposal | InvalidSyncResponse => Some(50),
+        Discovery | PingTimeout | PingUnexpect => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Score {
+    value:      f64,
+    updated_at: Instant,
+}
+
+impl Score {
+    fn decayed(&self, half_life: Duration, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        let half_lives = elapsed.as_secs_f64() / half_life.as_secs_f64().max(1.0);
+
+        self.value * 0.5f64.powf(half_lives)
+    }
+}
+
+/// Penalties of misbehaving peers, and the peers banned for them. Both
+/// outlive the sessions they were earned in. With `ban_host` a ban covers
+/// the host the peer connected from too, a new peer id doesn't lift it.
+#[derive(Debug)]
+pub struct PeerScore {
+    config:    PeerScoreConfig,
+    scores:    HashMap<PeerId, Score>,
+    bans:      HashMap<PeerId, Instant>,
+    host_bans: HashMap<String, Instant>,
+}
+
+impl PeerScore {
+    pub fn new(config: PeerScoreConfig) -> Self {
+        PeerScore {
+            config,
+            scores: HashMap::new(),
+            bans: HashMap::new(),
+            host_bans: HashMap::new(),
+        }
+    }
+
+    pub fn score(&self, pid: &PeerId, now: Instant) -> f64 {
+        self.scores
+            .get(pid)
+            .map(|s| s.decayed(self.config.half_life, now))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns true if the penalty brings the peer to the threshold, which
+    /// bans it, and the host it is connected from if configured.
+    pub fn report(&mut self, pid: &PeerId, host: Option<&str>, penalty: u64, now: Instant) -> bool {
+        let value = self.score(pid, now) + penalty as f64;
+
+        if value >= self.config.ban_threshold as f64 {
+            let until = now + self.config.ban_duration;
+
+            self.scores.remove(pid);
+            self.bans.insert(pid.to_owned(), until);
+            if let (true, Some(host)) = (self.config.ban_host, host) {
+                self.host_bans.insert(host.to_owned(), until);
+            }
+            return true;
+        }
+
+        let score = Score {
+            value,
+            updated_at: now,
+        };
+        self.scores.insert(pid.to_owned(), score);
+        false
+    }
+
+    pub fn is_banned(&self, pid: &PeerId, host: Option<&str>, now: Instant) -> bool {
+        let banned = |until: Option<&Instant>| until.map_or(false, |until| *until > now);
+        let host_ban = host.and_then(|host| self.host_bans.get(host));
+
+        banned(self.bans.get(pid)) || banned(host_ban)
+    }
+
+    pub fn prune(&mut self, now: Instant) {
+        let half_life = self.config.half_life;
+
+        self.bans.retain(|_, until| *until > now);
+        self.host_bans.retain(|_, until| *until > now);
+        self.scores
+            .retain(|_, s| s.decayed(half_life, now) >= FORGOTTEN_SCORE);
+    }
+}
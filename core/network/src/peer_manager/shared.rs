@@ -6,7 +6,7 @@ use parking_lot::RwLock;
 use protocol::types::Address;
 use tentacle::{secio::PeerId, SessionId};
 
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 pub struct SharedSessionsConfig {
     pub max_stream_window_size: usize,
@@ -134,4 +134,8 @@ impl SessionBook for SharedSessions {
             .map(|p| p.owned_chain_addr())
             .collect()
     }
+
+    fn rtt(&self, sid: SessionId) -> Option<Duration> {
+        self.sessions().read().get(&sid).and_then(|s| s.rtt())
+    }
 }
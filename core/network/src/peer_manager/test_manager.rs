@@ -16,6 +16,7 @@ use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     StreamExt,
 };
+use futures_timer::Delay;
 use tentacle::{
     multiaddr::Multiaddr,
     secio::{PeerId, PublicKey, SecioKeyPair},
@@ -119,6 +120,14 @@ impl Future for MockManager {
 fn make_manager(
     bootstrap_num: usize,
     max_connections: usize,
+) -> (MockManager, UnboundedReceiver<ConnectionEvent>) {
+    make_manager_with(bootstrap_num, max_connections, |_| ())
+}
+
+fn make_manager_with(
+    bootstrap_num: usize,
+    max_connections: usize,
+    configure: impl FnOnce(&mut PeerManagerConfig),
 ) -> (MockManager, UnboundedReceiver<ConnectionEvent>) {
     let manager_pubkey = make_pubkey();
     let manager_id = manager_pubkey.peer_id();
@@ -126,7 +135,7 @@ fn make_manager(
     let mut peer_dat_file = std::env::temp_dir();
     peer_dat_file.push("peer.dat");
 
-    let config = PeerManagerConfig {
+    let mut config = PeerManagerConfig {
         our_id: manager_id,
         pubkey: manager_pubkey,
         bootstraps,
@@ -135,7 +144,10 @@ fn make_manager(
         max_connections,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        ping_max_missed: 1,
+        peer_score: Default::default(),
     };
+    configure(&mut config);
 
     let (conn_tx, conn_rx) = unbounded();
     let (mgr_tx, mgr_rx) = unbounded();
@@ -1082,6 +1094,7 @@ async fn should_update_peer_alive_on_peer_alive() {
 
     let peer_alive = PeerManagerEvent::PeerAlive {
         pid: test_peer.owned_id(),
+        rtt: Duration::from_millis(20),
     };
     mgr.poll_event(peer_alive).await;
 
@@ -1105,6 +1118,7 @@ async fn should_reset_peer_retry_on_peer_alive() {
 
     let peer_alive = PeerManagerEvent::PeerAlive {
         pid: test_peer.owned_id(),
+        rtt: Duration::from_millis(20),
     };
     mgr.poll_event(peer_alive).await;
 
@@ -1154,6 +1168,200 @@ async fn should_increase_retry_for_ping_timeout_on_misbehave() {
     assert_eq!(test_peer.retry.count(), 1, "should increase retry");
 }
 
+#[tokio::test]
+async fn should_record_session_rtt_on_peer_alive() {
+    let (mut mgr, _conn_rx) = make_manager(0, 20);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let inner = mgr.core_inner();
+    let session = inner.session(test_peer.session_id()).expect("get session");
+    assert_eq!(session.rtt(), None, "should have no rtt before pong");
+
+    let peer_alive = PeerManagerEvent::PeerAlive {
+        pid: test_peer.owned_id(),
+        rtt: Duration::from_millis(20),
+    };
+    mgr.poll_event(peer_alive).await;
+
+    assert_eq!(
+        session.rtt(),
+        Some(Duration::from_millis(20)),
+        "should record rtt"
+    );
+}
+
+#[tokio::test]
+async fn should_disconnect_peer_only_after_ping_max_missed_in_a_row() {
+    let (mut mgr, _conn_rx) = make_manager_with(0, 20, |config| config.ping_max_missed = 3);
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let ping_timeout = || PeerManagerEvent::Misbehave {
+        pid:  test_peer.owned_id(),
+        kind: MisbehaviorKind::PingTimeout,
+    };
+    let inner = mgr.core_inner();
+
+    mgr.poll_event(ping_timeout()).await;
+    mgr.poll_event(ping_timeout()).await;
+    assert_eq!(inner.connected(), 1, "should keep session");
+
+    // A pong starts the count over
+    let peer_alive = PeerManagerEvent::PeerAlive {
+        pid: test_peer.owned_id(),
+        rtt: Duration::from_millis(20),
+    };
+    mgr.poll_event(peer_alive).await;
+    mgr.poll_event(ping_timeout()).await;
+    mgr.poll_event(ping_timeout()).await;
+    assert_eq!(inner.connected(), 1, "should keep session");
+
+    mgr.poll_event(ping_timeout()).await;
+    assert_eq!(inner.connected(), 0, "should disconnect session");
+    assert_eq!(test_peer.retry.count(), 1, "should increase retry");
+}
+
+#[tokio::test]
+async fn should_ban_peer_for_repeated_invalid_transactions() {
+    let (mut mgr, mut conn_rx) = make_manager_with(0, 20, |config| {
+        config.peer_score.ban_threshold = 50;
+        config.peer_score.ban_duration = Duration::from_secs(1);
+    });
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let expect_sid = test_peer.session_id();
+    let invalid_tx = || PeerManagerEvent::SessionMisbehave {
+        sid:  expect_sid,
+        kind: MisbehaviorKind::InvalidTransaction,
+    };
+    let inner = mgr.core_inner();
+
+    for _ in 0..4 {
+        mgr.poll_event(invalid_tx()).await;
+    }
+    assert_eq!(inner.connected(), 1, "should keep session under threshold");
+
+    mgr.poll_event(invalid_tx()).await;
+    mgr.poll_event(invalid_tx()).await;
+    assert_eq!(inner.connected(), 0, "should disconnect session");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => {
+            assert_eq!(sid, expect_sid, "should disconnect session")
+        }
+        _ => panic!("should be disconnect event"),
+    }
+
+    // Reconnect during the ban
+    let remote_pubkey = test_peer.owned_pubkey().expect("peer pubkey");
+    let reconnect = |sid: usize| {
+        let remote_addr = make_multiaddr(5000, Some(test_peer.owned_id()));
+        let sess_ctx = SessionContext::make(
+            SessionId::new(sid),
+            remote_addr,
+            SessionType::Inbound,
+            remote_pubkey.clone(),
+        );
+
+        PeerManagerEvent::NewSession {
+            pid:    test_peer.owned_id(),
+            pubkey: remote_pubkey.clone(),
+            ctx:    sess_ctx.arced(),
+        }
+    };
+    mgr.poll_event(reconnect(2)).await;
+    assert_eq!(inner.connected(), 0, "should reject banned peer");
+
+    let conn_event = conn_rx.next().await.expect("should have disconnect event");
+    match conn_event {
+        ConnectionEvent::Disconnect(sid) => {
+            assert_eq!(sid, SessionId::new(2), "should disconnect banned session")
+        }
+        _ => panic!("should be disconnect event"),
+    }
+
+    // Reconnect after the ban lapses
+    Delay::new(Duration::from_millis(1100)).await;
+    mgr.poll_event(reconnect(3)).await;
+    assert_eq!(inner.connected(), 1, "should accept peer after ban");
+}
+
+#[tokio::test]
+async fn should_ban_host_of_banned_peer() {
+    let (mut mgr, _conn_rx) = make_manager_with(0, 20, |config| {
+        config.peer_score.ban_threshold = 50;
+        config.peer_score.ban_host = true;
+    });
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let invalid_sync = PeerManagerEvent::SessionMisbehave {
+        sid:  test_peer.session_id(),
+        kind: MisbehaviorKind::InvalidSyncResponse,
+    };
+    let inner = mgr.core_inner();
+
+    mgr.poll_event(invalid_sync).await;
+    assert_eq!(inner.connected(), 0, "should disconnect session");
+
+    // A fresh peer id doesn't lift the ban on the host
+    mgr.poll_event(make_inbound_from("127.0.0.1", 2)).await;
+    assert_eq!(inner.connected(), 0, "should reject peer from banned host");
+
+    mgr.poll_event(make_inbound_from("10.0.0.1", 3)).await;
+    assert_eq!(inner.connected(), 1, "should accept peer from other host");
+}
+
+#[tokio::test]
+async fn should_not_ban_host_by_default() {
+    let (mut mgr, _conn_rx) = make_manager_with(0, 20, |config| {
+        config.peer_score.ban_threshold = 50;
+    });
+    let remote_peers = make_sessions(&mut mgr, 1, 5000).await;
+
+    let test_peer = remote_peers.first().expect("get first peer");
+    let invalid_sync = PeerManagerEvent::SessionMisbehave {
+        sid:  test_peer.session_id(),
+        kind: MisbehaviorKind::InvalidSyncResponse,
+    };
+    let inner = mgr.core_inner();
+
+    mgr.poll_event(invalid_sync).await;
+    assert_eq!(inner.connected(), 0, "should disconnect session");
+
+    // Another node on the same host
+    mgr.poll_event(make_inbound_from("127.0.0.1", 2)).await;
+    assert_eq!(
+        inner.connected(),
+        1,
+        "should accept other peer from the host"
+    );
+}
+
+fn make_inbound_from(host: &str, sid: usize) -> PeerManagerEvent {
+    let remote_pubkey = make_pubkey();
+    let remote_pid = remote_pubkey.peer_id();
+    let mut remote_addr = format!("/ip4/{}/tcp/6000", host)
+        .parse::<Multiaddr>()
+        .expect("peer multiaddr");
+    remote_addr.push_id(remote_pid.clone());
+
+    let sess_ctx = SessionContext::make(
+        SessionId::new(sid),
+        remote_addr,
+        SessionType::Inbound,
+        remote_pubkey.clone(),
+    );
+    PeerManagerEvent::NewSession {
+        pid:    remote_pid,
+        pubkey: remote_pubkey,
+        ctx:    sess_ctx.arced(),
+    }
+}
+
 #[tokio::test]
 async fn should_give_up_peer_for_ping_unexpect_on_misbehave() {
     let (mut mgr, _conn_rx) = make_manager(0, 20);
@@ -1863,6 +2071,8 @@ async fn should_never_expire_peers_from_config_whitelist() {
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        ping_max_missed: 1,
+        peer_score: Default::default(),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -1911,6 +2121,8 @@ async fn should_not_refresh_never_expired_peers_from_config_whitelist() {
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        ping_max_missed: 1,
+        peer_score: Default::default(),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -1970,6 +2182,8 @@ async fn should_only_connect_peers_in_whitelist_if_whitelist_only_enabled() {
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        ping_max_missed: 1,
+        peer_score: Default::default(),
     };
 
     let (conn_tx, mut conn_rx) = unbounded();
@@ -2029,6 +2243,8 @@ async fn should_only_allow_incoming_peers_in_whitelist_if_whitelist_only_enabled
         max_connections: 10,
         routine_interval: Duration::from_secs(10),
         peer_dat_file,
+        ping_max_missed: 1,
+        peer_score: Default::default(),
     };
 
     let (conn_tx, _conn_rx) = unbounded();
@@ -54,7 +54,10 @@ impl Future for EventTranslator {
 
             let mgr_event = match event {
                 PingEvent::Ping(ref _pid) => continue,
-                PingEvent::Pong(ref pid, _) => PeerManagerEvent::PeerAlive { pid: pid.clone() },
+                PingEvent::Pong(ref pid, rtt) => PeerManagerEvent::PeerAlive {
+                    pid: pid.clone(),
+                    rtt,
+                },
                 PingEvent::Timeout(ref pid) => {
                     let kind = MisbehaviorKind::PingTimeout;
 
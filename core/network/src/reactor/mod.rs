@@ -11,15 +11,22 @@ use std::{
 };
 
 use async_trait::async_trait;
-use futures::{channel::mpsc::UnboundedReceiver, future::TryFutureExt, pin_mut, stream::Stream};
+use futures::{
+    channel::mpsc::{UnboundedReceiver, UnboundedSender},
+    future::TryFutureExt,
+    pin_mut,
+    stream::Stream,
+};
 use log::warn;
 use protocol::{
     traits::{Context, MessageCodec, MessageHandler},
     Bytes, ProtocolError,
 };
+use tentacle::SessionId;
 
 use crate::{
     endpoint::{Endpoint, EndpointScheme, RpcEndpoint},
+    event::{MisbehaviorKind, PeerManagerEvent},
     message::SessionMessage,
     rpc::RpcResponse,
     rpc_map::RpcMap,
@@ -30,6 +37,7 @@ pub struct Reactor<M> {
     smsg_rx: UnboundedReceiver<SessionMessage>,
     handler: Arc<Box<dyn MessageHandler<Message = M>>>,
     rpc_map: Arc<RpcMap>,
+    mgr_tx:  UnboundedSender<PeerManagerEvent>,
 }
 
 impl<M> Reactor<M>
@@ -40,25 +48,33 @@ where
         smsg_rx: UnboundedReceiver<SessionMessage>,
         boxed_handler: Box<dyn MessageHandler<Message = M>>,
         rpc_map: Arc<RpcMap>,
+        mgr_tx: UnboundedSender<PeerManagerEvent>,
     ) -> Self {
         Reactor {
             smsg_rx,
             handler: Arc::new(boxed_handler),
             rpc_map,
+            mgr_tx,
         }
     }
 
-    pub fn rpc_resp(smsg_rx: UnboundedReceiver<SessionMessage>, rpc_map: Arc<RpcMap>) -> Self {
+    pub fn rpc_resp(
+        smsg_rx: UnboundedReceiver<SessionMessage>,
+        rpc_map: Arc<RpcMap>,
+        mgr_tx: UnboundedSender<PeerManagerEvent>,
+    ) -> Self {
         Reactor {
             smsg_rx,
             handler: Arc::new(Box::new(DummyHandler::new())),
             rpc_map,
+            mgr_tx,
         }
     }
 
     pub fn react(&self, smsg: SessionMessage) -> impl Future<Output = ()> {
         let handler = Arc::clone(&self.handler);
         let rpc_map = Arc::clone(&self.rpc_map);
+        let mgr_tx = self.mgr_tx.clone();
 
         let SessionMessage {
             sid,
@@ -79,18 +95,21 @@ where
 
             match endpoint.scheme() {
                 EndpointScheme::Gossip => {
-                    let content = M::decode(Bytes::from(net_msg.content)).await?;
+                    let content = M::decode(Bytes::from(net_msg.content)).await;
+                    let content = content.map_err(|err| report_invalid(&mgr_tx, sid, err))?;
                     handler.process(ctx, content).await
                 }
                 EndpointScheme::RpcCall => {
-                    let content = M::decode(Bytes::from(net_msg.content)).await?;
+                    let content = M::decode(Bytes::from(net_msg.content)).await;
+                    let content = content.map_err(|err| report_invalid(&mgr_tx, sid, err))?;
                     let rpc_endpoint = RpcEndpoint::try_from(endpoint)?;
 
                     let ctx = ctx.set_rpc_id(rpc_endpoint.rpc_id().value());
                     handler.process(ctx, content).await
                 }
                 EndpointScheme::RpcResponse => {
-                    let content = RpcResponse::decode(Bytes::from(net_msg.content)).await?;
+                    let content = RpcResponse::decode(Bytes::from(net_msg.content)).await;
+                    let content = content.map_err(|err| report_invalid(&mgr_tx, sid, err))?;
                     let rpc_endpoint = RpcEndpoint::try_from(endpoint)?;
                     let rpc_id = rpc_endpoint.rpc_id().value();
 
@@ -121,6 +140,24 @@ where
     }
 }
 
+// Counts an undecodable message against its session, passing the error on
+fn report_invalid(
+    mgr_tx: &UnboundedSender<PeerManagerEvent>,
+    sid: SessionId,
+    err: ProtocolError,
+) -> ProtocolError {
+    let kind = MisbehaviorKind::InvalidMessage;
+
+    if mgr_tx
+        .unbounded_send(PeerManagerEvent::SessionMisbehave { sid, kind })
+        .is_err()
+    {
+        warn!("network: reactor: peer manager offline");
+    }
+
+    err
+}
+
 impl<M> Future for Reactor<M>
 where
     M: MessageCodec,
@@ -15,7 +15,9 @@ use futures::{
 };
 use log::{debug, error, info};
 use protocol::{
-    traits::{Context, Gossip, MessageCodec, MessageHandler, Priority, Rpc},
+    traits::{
+        Context, Gossip, MessageCodec, MessageHandler, PeerMisbehavior, PeerTrust, Priority, Rpc,
+    },
     types::Address,
     ProtocolResult,
 };
@@ -28,7 +30,7 @@ use crate::{
     },
     endpoint::{Endpoint, EndpointScheme},
     error::NetworkError,
-    event::{ConnectionEvent, PeerManagerEvent},
+    event::{ConnectionEvent, MisbehaviorKind, PeerManagerEvent},
     message::RawSessionMessage,
     outbound::{NetworkGossip, NetworkRpc},
     peer_manager::{
@@ -38,6 +40,7 @@ use crate::{
     reactor::{MessageRouter, Reactor},
     rpc_map::RpcMap,
     selfcheck::SelfCheck,
+    traits::NetworkContext,
     NetworkConfig,
 };
 
@@ -45,6 +48,28 @@ use crate::{
 pub struct NetworkServiceHandle {
     gossip: NetworkGossip<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
     rpc:    NetworkRpc<ConnectionServiceControl<CoreProtocol, SharedSessions>, Snappy>,
+    mgr_tx: UnboundedSender<PeerManagerEvent>,
+}
+
+impl PeerTrust for NetworkServiceHandle {
+    /// Reports the peer of the session a message came in on, once the
+    /// message fails verification. Enough reports get the peer banned.
+    fn report(&self, ctx: Context, misbehavior: PeerMisbehavior) {
+        let sid = match ctx.session_id() {
+            Ok(sid) => sid,
+            Err(_) => return, // Not from remote
+        };
+
+        let kind = match misbehavior {
+            PeerMisbehavior::InvalidTransaction => MisbehaviorKind::InvalidTransaction,
+            PeerMisbehavior::InvalidProposal => MisbehaviorKind::InvalidProposal,
+            PeerMisbehavior::InvalidSyncResponse => MisbehaviorKind::InvalidSyncResponse,
+        };
+        let misbehave = PeerManagerEvent::SessionMisbehave { sid, kind };
+        if self.mgr_tx.unbounded_send(misbehave).is_err() {
+            error!("network: peer manager offline");
+        }
+    }
 }
 
 #[async_trait]
@@ -93,6 +118,10 @@ impl Rpc for NetworkServiceHandle {
     {
         self.rpc.response(cx, end, msg, p).await
     }
+
+    fn peer_contexts(&self, cx: &Context, limit: usize) -> Vec<Context> {
+        self.rpc.peer_contexts(cx, limit)
+    }
 }
 
 enum NetworkConnectionService {
@@ -226,7 +255,8 @@ impl NetworkService {
         if let Some(router) = &mut self.router {
             router.register_reactor(endpoint, msg_tx);
 
-            let reactor = Reactor::new(msg_rx, handler, Arc::clone(&self.rpc_map));
+            let rpc_map = Arc::clone(&self.rpc_map);
+            let reactor = Reactor::new(msg_rx, handler, rpc_map, self.mgr_tx.clone());
             tokio::spawn(reactor);
         }
 
@@ -249,7 +279,8 @@ impl NetworkService {
         if let Some(router) = &mut self.router {
             router.register_reactor(endpoint, msg_tx);
 
-            let reactor = Reactor::<M>::rpc_resp(msg_rx, Arc::clone(&self.rpc_map));
+            let rpc_map = Arc::clone(&self.rpc_map);
+            let reactor = Reactor::<M>::rpc_resp(msg_rx, rpc_map, self.mgr_tx.clone());
             tokio::spawn(reactor);
         }
 
@@ -260,6 +291,7 @@ impl NetworkService {
         NetworkServiceHandle {
             gossip: self.gossip.clone(),
             rpc:    self.rpc.clone(),
+            mgr_tx: self.mgr_tx.clone(),
         }
     }
 
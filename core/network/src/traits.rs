@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 use async_trait::async_trait;
 use protocol::{
@@ -32,6 +32,7 @@ pub trait NetworkProtocol {
 pub trait MessageSender {
     fn send(&self, tar: TargetSession, msg: Bytes, pri: Priority) -> Result<(), NetworkError>;
     async fn users_send(&self, users: Vec<Address>, msg: Bytes, pri: Priority) -> Result<(), NetworkError>;
+    fn sendable_sessions(&self) -> Vec<SessionId>;
 }
 
 pub trait Compression {
@@ -67,6 +68,7 @@ pub trait SessionBook {
     fn connected_addr(&self, sid: SessionId) -> Option<ConnectedAddr>;
     fn pending_data_size(&self, sid: SessionId) -> usize;
     fn whitelist(&self) -> Vec<Address>;
+    fn rtt(&self, sid: SessionId) -> Option<Duration>;
 }
 
 pub trait MultiaddrExt {
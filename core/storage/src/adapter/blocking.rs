@@ -0,0 +1,81 @@
+//! Fixed set of threads running the blocking calls of `RocksAdapter`, so
+//! they never stall the threads of the async executor.
+
+use std::fmt;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub(crate) struct BlockingPool {
+    // Taken on drop, which lets the workers run out of jobs and stop
+    sender:  Mutex<Option<Sender<Job>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads)
+            .map(|i| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("rocksdb-io-{}", i))
+                    .spawn(move || loop {
+                        // The lock is released before the job runs
+                        let job = receiver.lock().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                    .expect("spawn rocksdb io thread")
+            })
+            .collect();
+
+        BlockingPool {
+            sender: Mutex::new(Some(sender)),
+            workers,
+        }
+    }
+
+    /// Runs `f` on one of the threads, `None` if the pool is shutting down.
+    pub async fn run<T, F>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(f());
+        });
+
+        {
+            let sender = self.sender.lock();
+            sender.as_ref()?.send(job).ok()?;
+        }
+        rx.await.ok()
+    }
+}
+
+impl Drop for BlockingPool {
+    // Jobs already queued still run before the workers stop
+    fn drop(&mut self) {
+        self.sender.lock().take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for BlockingPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BlockingPool {{ threads: {} }}", self.workers.len())
+    }
+}
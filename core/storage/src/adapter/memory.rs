@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::error::Error;
+use std::ops::Bound;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -7,28 +8,78 @@ use derive_more::{Display, From};
 use parking_lot::RwLock;
 
 use protocol::codec::ProtocolCodec;
-use protocol::traits::{StorageAdapter, StorageBatchModify, StorageSchema};
+use protocol::traits::{
+    StorageAdapter, StorageBatch, StorageBatchModify, StorageCategory, StorageSchema,
+    StorageSnapshot,
+};
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+use crate::adapter::ttl::Expiry;
+
+const CATEGORIES: [StorageCategory; 8] = [
+    StorageCategory::Block,
+    StorageCategory::Receipt,
+    StorageCategory::SignedTransaction,
+    StorageCategory::Wal,
+    StorageCategory::EventIndex,
+    StorageCategory::TransactionPool,
+    StorageCategory::Evidence,
+    StorageCategory::Liveness,
+];
+
+// Keys start with their category, the same key can then be stored in
+// several categories like it can in the column families of rocksdb.
 #[derive(Debug)]
 pub struct MemoryAdapter {
-    db: Arc<RwLock<HashMap<Vec<u8>, Vec<u8>>>>,
+    db:        Arc<RwLock<BTreeMap<Vec<u8>, Vec<u8>>>>,
+    read_only: bool,
+    expiry:    Expiry,
 }
 
 impl MemoryAdapter {
     pub fn new() -> Self {
         MemoryAdapter {
-            db: Arc::new(RwLock::new(HashMap::new())),
+            db:        Arc::new(RwLock::new(BTreeMap::new())),
+            read_only: false,
+            expiry:    Expiry::default(),
+        }
+    }
+
+    /// Values of the categories with a ttl in `expiry` read as missing once
+    /// it passed.
+    pub fn with_expiry(mut self, expiry: Expiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// A handle on the same data whose writes fail with
+    /// `MemoryAdapterError::ReadOnly`, like `RocksAdapter::open_read_only`.
+    pub fn read_only(&self) -> Self {
+        MemoryAdapter {
+            db:        Arc::clone(&self.db),
+            read_only: true,
+            expiry:    self.expiry.clone(),
+        }
+    }
+
+    fn read(&self, category: StorageCategory, key: &[u8]) -> Option<Bytes> {
+        let bytes = self.db.read().get(key).cloned()?;
+        self.expiry.open(category, Bytes::from(bytes))
+    }
+
+    fn check_writable(&self) -> Result<(), MemoryAdapterError> {
+        if self.read_only {
+            Err(MemoryAdapterError::ReadOnly)
+        } else {
+            Ok(())
         }
     }
 }
 
 impl Default for MemoryAdapter {
     fn default() -> Self {
-        MemoryAdapter {
-            db: Arc::new(RwLock::new(HashMap::new())),
-        }
+        MemoryAdapter::new()
     }
 }
 
@@ -39,10 +90,11 @@ impl StorageAdapter for MemoryAdapter {
         mut key: <S as StorageSchema>::Key,
         mut val: <S as StorageSchema>::Value,
     ) -> ProtocolResult<()> {
-        let key = key.encode().await?.to_vec();
-        let val = val.encode().await?.to_vec();
+        self.check_writable()?;
+        let key = category_key(S::category(), &key.encode().await?);
+        let val = self.expiry.seal(S::category(), val.encode().await?);
 
-        self.db.write().insert(key, val);
+        self.db.write().insert(key, val.to_vec());
 
         Ok(())
     }
@@ -51,9 +103,9 @@ impl StorageAdapter for MemoryAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
-        let key = key.encode().await?;
+        let key = category_key(S::category(), &key.encode().await?);
 
-        let opt_bytes = self.db.read().get(&key.to_vec()).cloned();
+        let opt_bytes = self.read(S::category(), &key);
 
         if let Some(bytes) = opt_bytes {
             let val = <_>::decode(bytes).await?;
@@ -68,7 +120,8 @@ impl StorageAdapter for MemoryAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<()> {
-        let key = key.encode().await?.to_vec();
+        self.check_writable()?;
+        let key = category_key(S::category(), &key.encode().await?);
 
         self.db.write().remove(&key);
 
@@ -79,9 +132,29 @@ impl StorageAdapter for MemoryAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<bool> {
-        let key = key.encode().await?.to_vec();
+        let key = category_key(S::category(), &key.encode().await?);
 
-        Ok(self.db.read().get(&key).is_some())
+        Ok(self.read(S::category(), &key).is_some())
+    }
+
+    async fn contains_batch<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<bool>> {
+        let mut category_keys = Vec::with_capacity(keys.len());
+        for mut key in keys.into_iter() {
+            category_keys.push(category_key(S::category(), &key.encode().await?));
+        }
+
+        let db = self.db.read();
+        let found = category_keys
+            .iter()
+            .map(|key| match db.get(key) {
+                Some(value) => !self.expiry.is_expired(S::category(), value),
+                None => false,
+            })
+            .collect();
+        Ok(found)
     }
 
     async fn batch_modify<S: StorageSchema>(
@@ -89,17 +162,20 @@ impl StorageAdapter for MemoryAdapter {
         keys: Vec<<S as StorageSchema>::Key>,
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()> {
+        self.check_writable()?;
         if keys.len() != vals.len() {
             return Err(MemoryAdapterError::BatchLengthMismatch.into());
         }
 
-        let mut pairs: Vec<(Bytes, Option<Bytes>)> = Vec::with_capacity(keys.len());
+        let mut pairs: Vec<(Vec<u8>, Option<Bytes>)> = Vec::with_capacity(keys.len());
 
         for (mut key, value) in keys.into_iter().zip(vals.into_iter()) {
-            let key = key.encode().await?;
+            let key = category_key(S::category(), &key.encode().await?);
 
             let value = match value {
-                StorageBatchModify::Insert(mut value) => Some(value.encode().await?),
+                StorageBatchModify::Insert(mut value) => {
+                    Some(self.expiry.seal(S::category(), value.encode().await?))
+                }
                 StorageBatchModify::Remove => None,
             };
 
@@ -108,19 +184,123 @@ impl StorageAdapter for MemoryAdapter {
 
         for (key, value) in pairs.into_iter() {
             match value {
-                Some(value) => self.db.write().insert(key.to_vec(), value.to_vec()),
-                None => self.db.write().remove(&key.to_vec()),
+                Some(value) => self.db.write().insert(key, value.to_vec()),
+                None => self.db.write().remove(&key),
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        self.check_writable()?;
+        let mut db = self.db.write();
+
+        for (category, key, value) in batch.into_entries().into_iter() {
+            let key = category_key(category, &key);
+            match value {
+                Some(value) => db.insert(key, self.expiry.seal(category, value).to_vec()),
+                None => db.remove(&key),
             };
         }
 
         Ok(())
     }
+
+    async fn iter_prefix(
+        &self,
+        category: StorageCategory,
+        prefix: &[u8],
+        start_after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> ProtocolResult<Vec<(Bytes, Bytes)>> {
+        let prefix = category_key(category, prefix);
+        let lower = match start_after {
+            Some(after) if after.as_slice() >= &prefix[1..] => {
+                Bound::Excluded(category_key(category, &after))
+            }
+            _ => Bound::Included(prefix.clone()),
+        };
+
+        let pairs = self
+            .db
+            .read()
+            .range((lower, Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(key, value)| {
+                let value = self.expiry.open(category, Bytes::from(value.clone()))?;
+                Some((Bytes::from(key[1..].to_vec()), value))
+            })
+            .take(limit)
+            .collect();
+        Ok(pairs)
+    }
+
+    async fn approximate_sizes(&self) -> ProtocolResult<Vec<(StorageCategory, u64)>> {
+        let mut sizes = CATEGORIES.iter().map(|c| (*c, 0)).collect::<Vec<_>>();
+
+        for (key, value) in self.db.read().iter() {
+            sizes[key[0] as usize].1 += (key.len() - 1 + value.len()) as u64;
+        }
+        Ok(sizes)
+    }
+
+    async fn purge_expired(&self, category: StorageCategory) -> ProtocolResult<u64> {
+        self.check_writable()?;
+        if !self.expiry.has_ttl(category) {
+            return Ok(0);
+        }
+
+        let mut db = self.db.write();
+        let expired = db
+            .range(vec![category as u8]..)
+            .take_while(|(key, _)| key[0] == category as u8)
+            .filter(|(_, value)| self.expiry.is_expired(category, value))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+
+        for key in expired.iter() {
+            db.remove(key);
+        }
+        Ok(expired.len() as u64)
+    }
+
+    fn snapshot(&self) -> ProtocolResult<Box<dyn StorageSnapshot + '_>> {
+        Ok(Box::new(MemorySnapshot {
+            db:     self.db.read().clone(),
+            expiry: &self.expiry,
+        }))
+    }
+}
+
+// A copy of the whole map, fine for the sizes tests work with
+struct MemorySnapshot<'a> {
+    db:     BTreeMap<Vec<u8>, Vec<u8>>,
+    expiry: &'a Expiry,
+}
+
+impl StorageSnapshot for MemorySnapshot<'_> {
+    fn get(&self, category: StorageCategory, key: &[u8]) -> ProtocolResult<Option<Bytes>> {
+        let key = category_key(category, key);
+        let bytes = self.db.get(&key).map(|value| Bytes::from(value.clone()));
+        Ok(bytes.and_then(|bytes| self.expiry.open(category, bytes)))
+    }
+}
+
+fn category_key(category: StorageCategory, key: &[u8]) -> Vec<u8> {
+    let mut category_key = Vec::with_capacity(key.len() + 1);
+    category_key.push(category as u8);
+    category_key.extend_from_slice(key);
+    category_key
 }
 
 #[derive(Debug, Display, From)]
 pub enum MemoryAdapterError {
     #[display(fmt = "batch length dont match")]
     BatchLengthMismatch,
+
+    #[display(fmt = "database is opened read only")]
+    ReadOnly,
 }
 
 impl Error for MemoryAdapterError {}
@@ -1,2 +1,4 @@
+mod blocking;
 pub mod memory;
 pub mod rocks;
+pub mod ttl;
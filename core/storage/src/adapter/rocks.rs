@@ -1,39 +1,334 @@
 use std::error::Error;
-use std::path::Path;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use derive_more::{Display, From};
-use rocksdb::{ColumnFamily, Options, WriteBatch, DB};
+use rocksdb::backup::{BackupEngine, BackupEngineOptions, RestoreOptions};
+use rocksdb::{
+    BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, Direction,
+    IteratorMode, Options, Snapshot, WriteBatch, DB,
+};
 
 use protocol::codec::ProtocolCodec;
-use protocol::traits::{StorageAdapter, StorageBatchModify, StorageCategory, StorageSchema};
+use protocol::traits::{
+    StorageAdapter, StorageBatch, StorageBatchModify, StorageCategory, StorageSchema,
+    StorageSnapshot,
+};
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+use crate::adapter::blocking::BlockingPool;
+use crate::adapter::ttl::{Expiry, SystemClock};
+
+/// How long the persisted transaction pool entries live by default, far
+/// past any transaction timeout.
+pub const DEFAULT_POOL_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Debug)]
 pub struct RocksAdapter {
-    db: Arc<DB>,
+    db:        Arc<DB>,
+    read_only: bool,
+    expiry:    Expiry,
+    // Runs every call into rocksdb, they block
+    pool:      BlockingPool,
+}
+
+/// Tuning of the rocksdb instance behind a `RocksAdapter`.
+#[derive(Clone, Debug)]
+pub struct RocksConfig {
+    pub max_open_files:      i32,
+    /// Bytes of the lru block cache of each column family
+    pub block_cache_size:    usize,
+    /// Bytes of a single memtable
+    pub write_buffer_size:   usize,
+    /// Rocksdb's own default when unset
+    pub compression:         Option<RocksCompression>,
+    pub max_background_jobs: i32,
+    pub overrides:           Vec<(StorageCategory, RocksCategoryConfig)>,
+    /// Categories whose values expire, the transaction pool by default
+    pub ttls:                Vec<(StorageCategory, Duration)>,
+    /// Threads the blocking rocksdb calls run on
+    pub io_threads:          usize,
+}
+
+impl Default for RocksConfig {
+    fn default() -> Self {
+        // The rocksdb defaults
+        RocksConfig {
+            max_open_files:      64,
+            block_cache_size:    8 * 1024 * 1024,
+            write_buffer_size:   64 * 1024 * 1024,
+            compression:         None,
+            max_background_jobs: 2,
+            overrides:           Vec::new(),
+            ttls:                vec![(StorageCategory::TransactionPool, DEFAULT_POOL_TTL)],
+            io_threads:          4,
+        }
+    }
+}
+
+/// Settings replacing the ones of `RocksConfig` for a single category.
+#[derive(Clone, Debug, Default)]
+pub struct RocksCategoryConfig {
+    pub write_buffer_size: Option<usize>,
+    pub compression:       Option<RocksCompression>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RocksCompression {
+    None,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl FromStr for RocksCompression {
+    type Err = RocksAdapterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(RocksCompression::None),
+            "snappy" => Ok(RocksCompression::Snappy),
+            "lz4" => Ok(RocksCompression::Lz4),
+            "zstd" => Ok(RocksCompression::Zstd),
+            _ => Err(RocksAdapterError::InvalidConfig(format!(
+                "unknown compression {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl From<RocksCompression> for DBCompressionType {
+    fn from(compression: RocksCompression) -> Self {
+        match compression {
+            RocksCompression::None => DBCompressionType::None,
+            RocksCompression::Snappy => DBCompressionType::Snappy,
+            RocksCompression::Lz4 => DBCompressionType::Lz4,
+            RocksCompression::Zstd => DBCompressionType::Zstd,
+        }
+    }
+}
+
+impl RocksConfig {
+    fn validate(&self) -> Result<(), RocksAdapterError> {
+        let invalid = |reason: &str| Err(RocksAdapterError::InvalidConfig(reason.to_owned()));
+
+        if self.max_open_files == 0 {
+            return invalid("max_open_files must not be 0, use -1 for no limit");
+        }
+        if self.block_cache_size == 0 {
+            return invalid("block_cache_size must be positive");
+        }
+        if self.write_buffer_size == 0 {
+            return invalid("write_buffer_size must be positive");
+        }
+        if self.max_background_jobs <= 0 {
+            return invalid("max_background_jobs must be positive");
+        }
+        if self.io_threads == 0 {
+            return invalid("io_threads must be positive");
+        }
+        if self
+            .ttls
+            .iter()
+            .any(|(_, ttl)| *ttl == Duration::from_secs(0))
+        {
+            return invalid("ttls must be positive");
+        }
+        for (category, config) in self.overrides.iter() {
+            if config.write_buffer_size == Some(0) {
+                return Err(RocksAdapterError::InvalidConfig(format!(
+                    "write_buffer_size of {} must be positive",
+                    category
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn expiry(&self) -> Expiry {
+        Expiry::new(self.ttls.clone(), Arc::new(SystemClock))
+    }
+
+    fn column_options(&self, category: StorageCategory) -> Options {
+        let overrides = self
+            .overrides
+            .iter()
+            .find(|(c, _)| *c == category)
+            .map(|(_, config)| config.clone())
+            .unwrap_or_default();
+
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_lru_cache(self.block_cache_size);
+
+        let mut opts = Options::default();
+        opts.set_block_based_table_factory(&block_opts);
+        opts.set_write_buffer_size(
+            overrides
+                .write_buffer_size
+                .unwrap_or(self.write_buffer_size),
+        );
+        if let Some(compression) = overrides.compression.or(self.compression) {
+            opts.set_compression_type(compression.into());
+        }
+        opts
+    }
 }
 
 impl RocksAdapter {
     pub fn new<P: AsRef<Path>>(path: P, max_open_files: i32) -> ProtocolResult<Self> {
+        Self::new_with_config(path, RocksConfig {
+            max_open_files,
+            ..RocksConfig::default()
+        })
+    }
+
+    pub fn new_with_config<P: AsRef<Path>>(path: P, config: RocksConfig) -> ProtocolResult<Self> {
+        config.validate()?;
+
         let mut opts = Options::default();
         opts.create_if_missing(true);
         opts.create_missing_column_families(true);
-        opts.set_max_open_files(max_open_files);
+        opts.set_max_open_files(config.max_open_files);
+        opts.increase_parallelism(config.max_background_jobs);
+
+        let columns = CATEGORIES
+            .iter()
+            .map(|c| ColumnFamilyDescriptor::new(map_category(*c), config.column_options(*c)))
+            .collect::<Vec<_>>();
+
+        let db = DB::open_cf_descriptors(&opts, path, columns).map_err(RocksAdapterError::from)?;
+
+        Ok(RocksAdapter {
+            db:        Arc::new(db),
+            read_only: false,
+            expiry:    config.expiry(),
+            pool:      BlockingPool::new(config.io_threads),
+        })
+    }
 
-        let categories = [
-            map_category(StorageCategory::Block),
-            map_category(StorageCategory::Receipt),
-            map_category(StorageCategory::SignedTransaction),
-            map_category(StorageCategory::Wal),
-        ];
+    /// Opens the database of a possibly running node without taking its lock.
+    /// Reads see the state on disk when opened, every write fails with
+    /// `RocksAdapterError::ReadOnly`.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> ProtocolResult<Self> {
+        Self::open_read_only_with_config(path, RocksConfig::default())
+    }
+
+    /// Like `open_read_only`, with the ttls of `config` applied to reads.
+    pub fn open_read_only_with_config<P: AsRef<Path>>(
+        path: P,
+        config: RocksConfig,
+    ) -> ProtocolResult<Self> {
+        let opts = Options::default();
+        let path = path.as_ref();
+
+        // A database written by an older version lacks the newer categories.
+        // Only the ones it has are opened, using the others fails with
+        // `CategoryNotFound`.
+        let existing = DB::list_cf(&opts, path).map_err(RocksAdapterError::from)?;
+        let categories = CATEGORIES
+            .iter()
+            .map(|c| map_category(*c))
+            .filter(|name| existing.iter().any(|cf| cf == name));
+
+        let db = DB::open_cf_for_read_only(&opts, path, categories, false)
+            .map_err(RocksAdapterError::from)?;
+
+        Ok(RocksAdapter {
+            db:        Arc::new(db),
+            read_only: true,
+            expiry:    config.expiry(),
+            pool:      BlockingPool::new(config.io_threads),
+        })
+    }
+
+    /// Adds a backup of the current state to `backup_dir`. Files already
+    /// backed up there are shared, so only what changed since gets copied.
+    pub fn create_backup<P: AsRef<Path>>(&self, backup_dir: P) -> ProtocolResult<()> {
+        let mut engine = open_backup_engine(backup_dir)?;
+        engine
+            .create_new_backup(&self.db)
+            .map_err(RocksAdapterError::from)?;
+        Ok(())
+    }
+
+    /// Restores the latest backup of `backup_dir` into `target_dir`, which
+    /// must be missing or empty.
+    pub fn restore_from_backup<P: AsRef<Path>, Q: AsRef<Path>>(
+        backup_dir: P,
+        target_dir: Q,
+    ) -> ProtocolResult<()> {
+        let target_dir = target_dir.as_ref();
+        if let Ok(mut entries) = fs::read_dir(target_dir) {
+            if entries.next().is_some() {
+                return Err(
+                    RocksAdapterError::RestoreTargetNotEmpty(target_dir.to_path_buf()).into(),
+                );
+            }
+        }
 
-        let db = DB::open_cf(&opts, path, categories.iter()).map_err(RocksAdapterError::from)?;
+        let mut engine = open_backup_engine(backup_dir)?;
+        engine
+            .restore_from_latest_backup(target_dir, target_dir, &RestoreOptions::default())
+            .map_err(RocksAdapterError::from)?;
+        Ok(())
+    }
 
-        Ok(RocksAdapter { db: Arc::new(db) })
+    /// Backups in `backup_dir`, oldest first.
+    pub fn list_backups<P: AsRef<Path>>(backup_dir: P) -> ProtocolResult<Vec<BackupInfo>> {
+        let engine = open_backup_engine(backup_dir)?;
+
+        let backups = engine
+            .get_backup_info()
+            .into_iter()
+            .map(|info| BackupInfo {
+                id:        info.backup_id,
+                size:      info.size,
+                timestamp: info.timestamp,
+            })
+            .collect();
+        Ok(backups)
     }
+
+    async fn blocking<T, F>(&self, f: F) -> ProtocolResult<T>
+    where
+        F: FnOnce(&DB) -> Result<T, RocksAdapterError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+
+        match self.pool.run(move || f(&db)).await {
+            Some(res) => Ok(res?),
+            None => Err(RocksAdapterError::PoolClosed.into()),
+        }
+    }
+
+    fn check_writable(&self) -> Result<(), RocksAdapterError> {
+        if self.read_only {
+            Err(RocksAdapterError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A backup taken by `RocksAdapter::create_backup`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub id:        u32,
+    /// Bytes of the files of this backup, shared ones included
+    pub size:      u64,
+    /// Seconds since the unix epoch
+    pub timestamp: i64,
+}
+
+fn open_backup_engine<P: AsRef<Path>>(backup_dir: P) -> Result<BackupEngine, RocksAdapterError> {
+    BackupEngine::open(&BackupEngineOptions::default(), backup_dir).map_err(RocksAdapterError::from)
 }
 
 macro_rules! db {
@@ -53,24 +348,32 @@ impl StorageAdapter for RocksAdapter {
         mut key: <S as StorageSchema>::Key,
         mut val: <S as StorageSchema>::Value,
     ) -> ProtocolResult<()> {
-        let column = get_column::<S>(&self.db)?;
-        let key = key.encode().await?.to_vec();
-        let val = val.encode().await?.to_vec();
-
-        db!(self.db, put_cf, column, key, val)?;
+        self.check_writable()?;
+        let category = S::category();
+        let key = key.encode().await?;
+        let val = self.expiry.seal(category, val.encode().await?);
 
-        Ok(())
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+            db!(db, put_cf, column, key, val)
+        })
+        .await
     }
 
     async fn get<S: StorageSchema>(
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
-        let column = get_column::<S>(&self.db)?;
+        let category = S::category();
         let key = key.encode().await?;
 
-        let opt_bytes =
-            { db!(self.db, get_cf, column, key)?.map(|db_vec| Bytes::from(db_vec.to_vec())) };
+        let opt_bytes = self
+            .blocking(move |db| {
+                let column = get_column_by_category(db, category)?;
+                Ok(db!(db, get_cf, column, key)?.map(|db_vec| Bytes::from(db_vec.to_vec())))
+            })
+            .await?
+            .and_then(|bytes| self.expiry.open(category, bytes));
 
         if let Some(bytes) = opt_bytes {
             let val = <_>::decode(bytes).await?;
@@ -85,23 +388,49 @@ impl StorageAdapter for RocksAdapter {
         &self,
         mut key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<()> {
-        let column = get_column::<S>(&self.db)?;
-        let key = key.encode().await?.to_vec();
-
-        db!(self.db, delete_cf, column, key)?;
+        self.check_writable()?;
+        let category = S::category();
+        let key = key.encode().await?;
 
-        Ok(())
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+            db!(db, delete_cf, column, key)
+        })
+        .await
     }
 
     async fn contains<S: StorageSchema>(
         &self,
-        mut key: <S as StorageSchema>::Key,
+        key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<bool> {
-        let column = get_column::<S>(&self.db)?;
-        let key = key.encode().await?.to_vec();
-        let val = db!(self.db, get_cf, column, key)?;
+        let found = self.contains_batch::<S>(vec![key]).await?;
+        Ok(found[0])
+    }
+
+    // rocksdb 0.12 has no multi get, the keys are looked up back to back
+    // in a single job instead.
+    async fn contains_batch<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<bool>> {
+        let category = S::category();
+        let mut encoded = Vec::with_capacity(keys.len());
+        for mut key in keys.into_iter() {
+            encoded.push(key.encode().await?);
+        }
 
-        Ok(val.is_some())
+        let expiry = self.expiry.clone();
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+
+            let mut found = Vec::with_capacity(encoded.len());
+            for key in encoded.into_iter() {
+                let val = db!(db, get_cf, column, key)?;
+                found.push(val.map_or(false, |val| !expiry.is_expired(category, &val)));
+            }
+            Ok(found)
+        })
+        .await
     }
 
     async fn batch_modify<S: StorageSchema>(
@@ -109,34 +438,184 @@ impl StorageAdapter for RocksAdapter {
         keys: Vec<<S as StorageSchema>::Key>,
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()> {
+        self.check_writable()?;
         if keys.len() != vals.len() {
             return Err(RocksAdapterError::BatchLengthMismatch.into());
         }
 
-        let column = get_column::<S>(&self.db)?;
+        let category = S::category();
         let mut pairs: Vec<(Bytes, Option<Bytes>)> = Vec::with_capacity(keys.len());
 
         for (mut key, value) in keys.into_iter().zip(vals.into_iter()) {
             let key = key.encode().await?;
 
             let value = match value {
-                StorageBatchModify::Insert(mut value) => Some(value.encode().await?),
+                StorageBatchModify::Insert(mut value) => {
+                    Some(self.expiry.seal(category, value.encode().await?))
+                }
                 StorageBatchModify::Remove => None,
             };
 
             pairs.push((key, value))
         }
 
-        let mut batch = WriteBatch::default();
-        for (key, value) in pairs.into_iter() {
-            match value {
-                Some(value) => db!(batch, put_cf, column, key, value)?,
-                None => db!(batch, delete_cf, column, key)?,
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+
+            let mut batch = WriteBatch::default();
+            for (key, value) in pairs.into_iter() {
+                match value {
+                    Some(value) => db!(batch, put_cf, column, key, value)?,
+                    None => db!(batch, delete_cf, column, key)?,
+                }
+            }
+            db.write(batch).map_err(RocksAdapterError::from)
+        })
+        .await
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        self.check_writable()?;
+        let entries = batch
+            .into_entries()
+            .into_iter()
+            .map(|(category, key, value)| {
+                let value = value.map(|value| self.expiry.seal(category, value));
+                (category, key, value)
+            })
+            .collect::<Vec<_>>();
+
+        self.blocking(move |db| {
+            let mut write_batch = WriteBatch::default();
+            for (category, key, value) in entries.into_iter() {
+                let column = get_column_by_category(db, category)?;
+                match value {
+                    Some(value) => db!(write_batch, put_cf, column, key, value)?,
+                    None => db!(write_batch, delete_cf, column, key)?,
+                }
+            }
+            db.write(write_batch).map_err(RocksAdapterError::from)
+        })
+        .await
+    }
+
+    async fn iter_prefix(
+        &self,
+        category: StorageCategory,
+        prefix: &[u8],
+        start_after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> ProtocolResult<Vec<(Bytes, Bytes)>> {
+        let prefix = prefix.to_vec();
+        let expiry = self.expiry.clone();
+
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+            let from = match start_after.as_ref() {
+                Some(after) if after >= &prefix => after.as_slice(),
+                _ => prefix.as_slice(),
+            };
+            let iter = db
+                .iterator_cf(column, IteratorMode::From(from, Direction::Forward))
+                .map_err(RocksAdapterError::from)?;
+
+            let mut pairs = Vec::new();
+            for (key, value) in iter {
+                if pairs.len() == limit || !key.starts_with(&prefix) {
+                    break;
+                }
+                // The seek lands on `start_after` itself when it still exists
+                if start_after.as_ref().map(|after| after.as_slice()) == Some(&key[..]) {
+                    continue;
+                }
+                if let Some(value) = expiry.open(category, Bytes::from(value.to_vec())) {
+                    pairs.push((Bytes::from(key.to_vec()), value));
+                }
             }
+            Ok(pairs)
+        })
+        .await
+    }
+
+    async fn compact(&self, category: StorageCategory) -> ProtocolResult<()> {
+        self.check_writable()?;
+
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+            db.compact_range_cf(column, None::<&[u8]>, None::<&[u8]>);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn approximate_sizes(&self) -> ProtocolResult<Vec<(StorageCategory, u64)>> {
+        self.blocking(|db| {
+            let mut sizes = Vec::with_capacity(CATEGORIES.len());
+            for category in CATEGORIES.iter() {
+                let column = get_column_by_category(db, *category)?;
+                let size = db
+                    .property_int_value_cf(column, SST_FILES_SIZE)
+                    .map_err(RocksAdapterError::from)?
+                    .unwrap_or(0);
+                sizes.push((*category, size));
+            }
+            Ok(sizes)
+        })
+        .await
+    }
+
+    async fn purge_expired(&self, category: StorageCategory) -> ProtocolResult<u64> {
+        self.check_writable()?;
+        if !self.expiry.has_ttl(category) {
+            return Ok(0);
         }
 
-        self.db.write(batch).map_err(RocksAdapterError::from)?;
-        Ok(())
+        let expiry = self.expiry.clone();
+        self.blocking(move |db| {
+            let column = get_column_by_category(db, category)?;
+            let iter = db
+                .iterator_cf(column, IteratorMode::Start)
+                .map_err(RocksAdapterError::from)?;
+
+            let mut batch = WriteBatch::default();
+            let mut purged = 0;
+            for (key, value) in iter {
+                if expiry.is_expired(category, &value) {
+                    db!(batch, delete_cf, column, key)?;
+                    purged += 1;
+                }
+            }
+
+            db.write(batch).map_err(RocksAdapterError::from)?;
+            Ok(purged)
+        })
+        .await
+    }
+
+    fn snapshot(&self) -> ProtocolResult<Box<dyn StorageSnapshot + '_>> {
+        Ok(Box::new(RocksSnapshot {
+            db:       &self.db,
+            snapshot: self.db.snapshot(),
+            expiry:   &self.expiry,
+        }))
+    }
+}
+
+struct RocksSnapshot<'a> {
+    db:       &'a DB,
+    snapshot: Snapshot<'a>,
+    expiry:   &'a Expiry,
+}
+
+impl StorageSnapshot for RocksSnapshot<'_> {
+    fn get(&self, category: StorageCategory, key: &[u8]) -> ProtocolResult<Option<Bytes>> {
+        let column = get_column_by_category(self.db, category)?;
+        let opt = self
+            .snapshot
+            .get_cf(column, key)
+            .map_err(RocksAdapterError::from)?;
+
+        Ok(opt.and_then(|value| self.expiry.open(category, Bytes::from(value.to_vec()))))
     }
 }
 
@@ -153,6 +632,18 @@ pub enum RocksAdapterError {
 
     #[display(fmt = "batch length dont match")]
     BatchLengthMismatch,
+
+    #[display(fmt = "invalid config: {}", _0)]
+    InvalidConfig(String),
+
+    #[display(fmt = "database is opened read only")]
+    ReadOnly,
+
+    #[display(fmt = "restore target {:?} is not empty", _0)]
+    RestoreTargetNotEmpty(PathBuf),
+
+    #[display(fmt = "io pool is shut down")]
+    PoolClosed,
 }
 
 impl Error for RocksAdapterError {}
@@ -163,10 +654,28 @@ impl From<RocksAdapterError> for ProtocolError {
     }
 }
 
+const CATEGORIES: [StorageCategory; 8] = [
+    StorageCategory::Block,
+    StorageCategory::Receipt,
+    StorageCategory::SignedTransaction,
+    StorageCategory::Wal,
+    StorageCategory::EventIndex,
+    StorageCategory::TransactionPool,
+    StorageCategory::Evidence,
+    StorageCategory::Liveness,
+];
+
+// Bytes of the sst files of a column family, memtables not included
+const SST_FILES_SIZE: &str = "rocksdb.total-sst-files-size";
+
 const C_BLOCKS: &str = "c1";
 const C_SIGNED_TRANSACTIONS: &str = "c2";
 const C_RECEIPTS: &str = "c3";
 const C_WALS: &str = "c4";
+const C_EVENT_INDEX: &str = "c5";
+const C_TRANSACTION_POOL: &str = "c6";
+const C_EVIDENCE: &str = "c7";
+const C_LIVENESS: &str = "c8";
 
 fn map_category(c: StorageCategory) -> &'static str {
     match c {
@@ -174,11 +683,18 @@ fn map_category(c: StorageCategory) -> &'static str {
         StorageCategory::Receipt => C_RECEIPTS,
         StorageCategory::SignedTransaction => C_SIGNED_TRANSACTIONS,
         StorageCategory::Wal => C_WALS,
+        StorageCategory::EventIndex => C_EVENT_INDEX,
+        StorageCategory::TransactionPool => C_TRANSACTION_POOL,
+        StorageCategory::Evidence => C_EVIDENCE,
+        StorageCategory::Liveness => C_LIVENESS,
     }
 }
 
-fn get_column<S: StorageSchema>(db: &DB) -> Result<ColumnFamily, RocksAdapterError> {
-    let category = map_category(S::category());
+fn get_column_by_category(
+    db: &DB,
+    category: StorageCategory,
+) -> Result<ColumnFamily, RocksAdapterError> {
+    let category = map_category(category);
 
     let column = db
         .cf_handle(category)
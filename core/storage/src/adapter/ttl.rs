@@ -0,0 +1,120 @@
+//! Lazily enforced expiry of stored values.
+//!
+//! Values of a category with a ttl are stored as `tag | expires at | value`,
+//! the expiry being milliseconds since the unix epoch as u64 big endian.
+//! Values stored before the category had a ttl lack the tag, they read as
+//! they are and never expire. Reads treat expired values as missing,
+//! `StorageAdapter::purge_expired` deletes them.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use protocol::traits::StorageCategory;
+use protocol::Bytes;
+
+// Leads the values stored with their expiry, version 1 of the format. It
+// would start an rlp list longer than 2^40 bytes, which no stored value is.
+const EXPIRY_TAG: [u8; 2] = [0xfe, 0x01];
+const EXPIRY_LEN: usize = 8;
+const HEADER_LEN: usize = EXPIRY_TAG.len() + EXPIRY_LEN;
+
+pub trait Clock: Send + Sync {
+    /// Time since the unix epoch
+    fn now(&self) -> Duration;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+pub struct Expiry {
+    ttls:  Vec<(StorageCategory, Duration)>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Expiry {
+    pub fn new(ttls: Vec<(StorageCategory, Duration)>, clock: Arc<dyn Clock>) -> Self {
+        Expiry { ttls, clock }
+    }
+
+    pub(crate) fn has_ttl(&self, category: StorageCategory) -> bool {
+        self.ttl(category).is_some()
+    }
+
+    pub(crate) fn seal(&self, category: StorageCategory, value: Bytes) -> Bytes {
+        let ttl = match self.ttl(category) {
+            Some(ttl) => ttl,
+            None => return value,
+        };
+        let expires_at = (self.clock.now() + ttl).as_millis() as u64;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + value.len());
+        bytes.extend_from_slice(&EXPIRY_TAG);
+        bytes.extend_from_slice(&expires_at.to_be_bytes());
+        bytes.extend_from_slice(&value);
+        Bytes::from(bytes)
+    }
+
+    /// The value stored in `bytes`, `None` once it expired.
+    pub(crate) fn open(&self, category: StorageCategory, bytes: Bytes) -> Option<Bytes> {
+        if !self.has_ttl(category) {
+            return Some(bytes);
+        }
+
+        match expires_at(&bytes) {
+            Some(expires_at) if self.is_past(expires_at) => None,
+            Some(_) => Some(bytes.slice(HEADER_LEN..)),
+            None => Some(bytes),
+        }
+    }
+
+    pub(crate) fn is_expired(&self, category: StorageCategory, bytes: &[u8]) -> bool {
+        if !self.has_ttl(category) {
+            return false;
+        }
+        expires_at(bytes).map_or(false, |expires_at| self.is_past(expires_at))
+    }
+
+    fn is_past(&self, expires_at: u64) -> bool {
+        expires_at <= self.clock.now().as_millis() as u64
+    }
+
+    fn ttl(&self, category: StorageCategory) -> Option<Duration> {
+        self.ttls
+            .iter()
+            .find(|(c, _)| *c == category)
+            .map(|(_, ttl)| *ttl)
+    }
+}
+
+// The expiry of a tagged value, none for a value stored without one
+fn expires_at(bytes: &[u8]) -> Option<u64> {
+    if bytes.len() < HEADER_LEN || bytes[..EXPIRY_TAG.len()] != EXPIRY_TAG {
+        return None;
+    }
+
+    let mut expires_at = [0u8; EXPIRY_LEN];
+    expires_at.copy_from_slice(&bytes[EXPIRY_TAG.len()..HEADER_LEN]);
+    Some(u64::from_be_bytes(expires_at))
+}
+
+impl Default for Expiry {
+    fn default() -> Self {
+        Expiry::new(Vec::new(), Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for Expiry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expiry {{ ttls: {:?} }}", self.ttls)
+    }
+}
@@ -0,0 +1,50 @@
+use std::hash::Hash;
+
+use lru::LruCache;
+use parking_lot::Mutex;
+
+/// Lru cache in front of the adapter. A zero capacity turns it into a no-op,
+/// every lookup then goes to the database.
+pub struct Cache<K, V> {
+    inner: Option<Mutex<LruCache<K, V>>>,
+}
+
+impl<K: Hash + Eq, V: Clone> Cache<K, V> {
+    pub fn new(cap: usize) -> Self {
+        let inner = if cap == 0 {
+            None
+        } else {
+            Some(Mutex::new(LruCache::new(cap)))
+        };
+
+        Cache { inner }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.inner
+            .as_ref()
+            .and_then(|cache| cache.lock().get(key).cloned())
+    }
+
+    pub fn put(&self, key: K, val: V) {
+        if let Some(cache) = self.inner.as_ref() {
+            cache.lock().put(key, val);
+        }
+    }
+
+    pub fn remove(&self, key: &K) {
+        if let Some(cache) = self.inner.as_ref() {
+            cache.lock().pop(key);
+        }
+    }
+}
+
+impl<K, V> std::fmt::Debug for Cache<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Cache {{ enabled: {} }}", self.inner.is_some())
+    }
+}
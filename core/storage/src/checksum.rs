@@ -0,0 +1,83 @@
+//! Checksummed envelope for stored blocks and transactions.
+//!
+//! A sealed value is `SEAL_TAG | crc32 u32 big endian | payload`. No protobuf
+//! message starts with `SEAL_TAG`, its low bits would be wire type 6, so
+//! values written before the envelope existed still decode as they are.
+
+use std::fmt;
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::traits::StorageCategory;
+use protocol::{Bytes, ProtocolResult};
+
+use crate::StorageError;
+
+const SEAL_TAG: u8 = 0xce;
+const SEAL_HEADER_LEN: usize = 5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sealed<T> {
+    /// Stored without an envelope
+    Plain(T),
+    /// Stored with an envelope whose checksum matched
+    Checked(T),
+    /// The raw bytes of an envelope whose checksum didn't match
+    Corrupted(Bytes),
+}
+
+impl<T> Sealed<T> {
+    pub fn new(value: T, checksum: bool) -> Self {
+        if checksum {
+            Sealed::Checked(value)
+        } else {
+            Sealed::Plain(value)
+        }
+    }
+
+    pub fn open<K: fmt::Debug>(self, category: StorageCategory, key: &K) -> ProtocolResult<T> {
+        match self {
+            Sealed::Plain(value) | Sealed::Checked(value) => Ok(value),
+            Sealed::Corrupted(_) => Err(StorageError::Corrupted {
+                category,
+                key: format!("{:?}", key),
+            }
+            .into()),
+        }
+    }
+}
+
+impl<T: ProtocolCodecSync> ProtocolCodecSync for Sealed<T> {
+    fn encode_sync(&self) -> ProtocolResult<Bytes> {
+        match self {
+            Sealed::Plain(value) => value.encode_sync(),
+            Sealed::Checked(value) => {
+                let payload = value.encode_sync()?;
+
+                let mut bytes = Vec::with_capacity(SEAL_HEADER_LEN + payload.len());
+                bytes.push(SEAL_TAG);
+                bytes.extend_from_slice(&crc32fast::hash(&payload).to_be_bytes());
+                bytes.extend_from_slice(&payload);
+                Ok(Bytes::from(bytes))
+            }
+            Sealed::Corrupted(bytes) => Ok(bytes.clone()),
+        }
+    }
+
+    fn decode_sync(bytes: Bytes) -> ProtocolResult<Self> {
+        if bytes.first() != Some(&SEAL_TAG) {
+            return Ok(Sealed::Plain(T::decode_sync(bytes)?));
+        }
+        if bytes.len() < SEAL_HEADER_LEN {
+            return Ok(Sealed::Corrupted(bytes));
+        }
+
+        let mut checksum = [0u8; 4];
+        checksum.copy_from_slice(&bytes[1..SEAL_HEADER_LEN]);
+        let payload = bytes.slice(SEAL_HEADER_LEN..);
+        if crc32fast::hash(&payload) != u32::from_be_bytes(checksum) {
+            return Ok(Sealed::Corrupted(bytes));
+        }
+
+        Ok(Sealed::Checked(T::decode_sync(payload)?))
+    }
+}
@@ -0,0 +1,93 @@
+//! Index from a service and a height to the transactions whose receipts
+//! carry events of that service.
+
+use std::collections::HashMap;
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::traits::{StorageAdapter, StorageBatch};
+use protocol::types::{Hash, Receipt};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::{EventIndexSchema, ImplStorage};
+
+const HASH_LEN: usize = 32;
+
+/// Hashes of the transactions indexed under one key, in insertion order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventTxs(pub Vec<Hash>);
+
+impl ProtocolCodecSync for EventTxs {
+    fn encode_sync(&self) -> ProtocolResult<Bytes> {
+        let mut bytes = Vec::with_capacity(self.0.len() * HASH_LEN);
+        for hash in self.0.iter() {
+            bytes.extend_from_slice(&hash.as_bytes());
+        }
+        Ok(Bytes::from(bytes))
+    }
+
+    fn decode_sync(bytes: Bytes) -> ProtocolResult<Self> {
+        let hashes = bytes
+            .chunks(HASH_LEN)
+            .map(|chunk| Hash::from_bytes(Bytes::from(chunk.to_vec())))
+            .collect::<ProtocolResult<Vec<_>>>()?;
+        Ok(EventTxs(hashes))
+    }
+}
+
+// The service length comes first so that no service name is a prefix of
+// another one's keys.
+pub(crate) fn event_key(service: &str, height: u64) -> Bytes {
+    let mut key = Vec::with_capacity(4 + service.len() + 8);
+    key.extend_from_slice(&(service.len() as u32).to_be_bytes());
+    key.extend_from_slice(service.as_bytes());
+    key.extend_from_slice(&height.to_be_bytes());
+    Bytes::from(key)
+}
+
+impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
+    /// Adds index entries for the events of `receipts` to `batch`, merged
+    /// with the stored ones so writing the same receipts twice adds nothing.
+    ///
+    /// Callers hold the counters lock, which keeps two merges of the same
+    /// key from racing.
+    pub(crate) async fn index_events(
+        &self,
+        batch: &mut StorageBatch,
+        receipts: &[Receipt],
+    ) -> ProtocolResult<()> {
+        let mut keys: Vec<Bytes> = Vec::new();
+        let mut entries: HashMap<Bytes, Vec<Hash>> = HashMap::new();
+        for receipt in receipts.iter() {
+            for event in receipt.events.iter() {
+                let key = event_key(&event.service, receipt.height);
+                let hashes = entries.entry(key.clone()).or_insert_with(|| {
+                    keys.push(key);
+                    Vec::new()
+                });
+                if !hashes.contains(&receipt.tx_hash) {
+                    hashes.push(receipt.tx_hash.clone());
+                }
+            }
+        }
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let stored = self.db_get_batch::<EventIndexSchema>(keys.clone()).await?;
+        for (key, stored) in keys.into_iter().zip(stored.into_iter()) {
+            let mut merged = stored.unwrap_or_default().0;
+            let added = entries.remove(&key).unwrap_or_default();
+            let len = merged.len();
+
+            for hash in added.into_iter() {
+                if !merged.contains(&hash) {
+                    merged.push(hash);
+                }
+            }
+            if merged.len() != len {
+                batch.insert::<EventIndexSchema>(key, EventTxs(merged))?;
+            }
+        }
+        Ok(())
+    }
+}
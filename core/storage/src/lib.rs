@@ -4,8 +4,19 @@
 mod tests;
 
 pub mod adapter;
+mod cache;
+pub mod checksum;
+mod event_index;
+pub mod metrics;
+mod position;
+mod snapshot;
+mod snapshot_storage;
 
+use std::cmp;
+use std::collections::HashSet;
 use std::error::Error;
+use std::fmt;
+use std::hash;
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -13,36 +24,361 @@ use derive_more::{Display, From};
 use lazy_static::lazy_static;
 use tokio::sync::RwLock;
 
+use protocol::codec::ProtocolCodecSync;
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    Storage, StorageAdapter, StorageBatchModify, StorageCategory, StorageSchema,
+    ChainStats, EventRecord, Storage, StorageAdapter, StorageBatch, StorageBatchModify,
+    StorageCategory, StorageSchema, TransactionPosition, TransactionWithPosition,
 };
-use protocol::types::{Block, Hash, Proof, Receipt, SignedTransaction};
+use protocol::types::{Block, BlockHeader, Hash, Proof, Receipt, SignedTransaction};
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
+use crate::cache::Cache;
+use crate::checksum::Sealed;
+use crate::event_index::{event_key, EventTxs};
+use crate::metrics::{NoopMetrics, Recorder, StorageMetrics, StorageOp};
+use crate::position::{position_key, StoredPosition};
+
+pub use crate::snapshot_storage::SnapshotStorage;
+
+// Upper bound on the heights covered by one `get_blocks` call
+pub const MAX_BLOCKS_RANGE: u64 = 512;
+
+// Pool transactions read per `iter_prefix` call
+const POOL_PAGE_SIZE: usize = 1024;
+// What `prune_below` removes, each with its own marker
+const PRUNABLE_CATEGORIES: [StorageCategory; 3] = [
+    StorageCategory::Block,
+    StorageCategory::SignedTransaction,
+    StorageCategory::Receipt,
+];
+
 lazy_static! {
     pub static ref LATEST_BLOCK_KEY: Hash = Hash::digest(Bytes::from("latest_hash"));
     pub static ref LATEST_PROOF_KEY: Hash = Hash::digest(Bytes::from("latest_proof"));
     pub static ref OVERLORD_WAL_KEY: Hash = Hash::digest(Bytes::from("overlord_wal"));
+    pub static ref EVIDENCE_KEY: Hash = Hash::digest(Bytes::from("evidence"));
+    pub static ref LIVENESS_KEY: Hash = Hash::digest(Bytes::from("liveness"));
+    pub static ref TOTAL_BLOCKS_KEY: Hash = Hash::digest(Bytes::from("total_blocks"));
+    pub static ref TOTAL_TXS_KEY: Hash = Hash::digest(Bytes::from("total_txs"));
+    pub static ref TOTAL_RECEIPTS_KEY: Hash = Hash::digest(Bytes::from("total_receipts"));
 }
 
+const HEADER_KEY_PREFIX: &[u8] = b"header-";
+
 #[derive(Debug)]
 pub struct ImplStorage<Adapter> {
     adapter: Arc<Adapter>,
 
     latest_block: RwLock<Option<Block>>,
+    // Loaded on first use, the lock also orders counter updates
+    counters:     RwLock<Option<ChainCounters>>,
+    block_cache:  Cache<u64, Block>,
+    tx_cache:     Cache<Hash, SignedTransaction>,
+    metrics:      Recorder,
+    // Whether blocks and transactions are written with a checksum
+    checksum:     bool,
 }
 
 impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
     pub fn new(adapter: Arc<Adapter>) -> Self {
+        Self::new_with_cache(adapter, 0, 0)
+    }
+
+    /// Keeps up to `block_cap` blocks by height and `tx_cap` transactions by
+    /// hash in memory. A capacity of 0 disables that cache.
+    pub fn new_with_cache(adapter: Arc<Adapter>, block_cap: usize, tx_cap: usize) -> Self {
         Self {
             adapter,
             latest_block: RwLock::new(None),
+            counters: RwLock::new(None),
+            block_cache: Cache::new(block_cap),
+            tx_cache: Cache::new(tx_cap),
+            metrics: Recorder::new(Arc::new(NoopMetrics)),
+            checksum: false,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<dyn StorageMetrics>) -> Self {
+        self.metrics = Recorder::new(metrics);
+        self
+    }
+
+    /// Writes blocks and transactions with a crc32 checksum, which reads
+    /// verify. Values stored without one are read either way.
+    pub fn with_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Compacts `categories`, then returns the approximate size of every
+    /// category. An empty slice only reports the sizes.
+    pub async fn storage_maintenance(
+        &self,
+        categories: &[StorageCategory],
+    ) -> ProtocolResult<Vec<(StorageCategory, u64)>> {
+        for category in categories.iter() {
+            self.adapter.compact(*category).await?;
+        }
+        self.adapter.approximate_sizes().await
+    }
+
+    /// Runs `f` against a single snapshot of the database, so all its reads
+    /// see the same state however much is written meanwhile.
+    pub fn with_snapshot<T, F>(&self, f: F) -> ProtocolResult<T>
+    where
+        F: FnOnce(&SnapshotStorage<'_>) -> ProtocolResult<T>,
+    {
+        let snapshot = SnapshotStorage::new(self.adapter.snapshot()?);
+        f(&snapshot)
+    }
+
+    fn seal<T>(&self, value: T) -> Sealed<T> {
+        Sealed::new(value, self.checksum)
+    }
+
+    async fn current_counters(
+        &self,
+        slot: &mut Option<ChainCounters>,
+    ) -> ProtocolResult<ChainCounters> {
+        if let Some(counters) = slot {
+            return Ok(counters.clone());
+        }
+
+        let counters = match self
+            .db_get::<CounterSchema>(TOTAL_BLOCKS_KEY.clone())
+            .await?
+        {
+            Some(blocks) => ChainCounters {
+                blocks,
+                txs: self
+                    .db_get::<CounterSchema>(TOTAL_TXS_KEY.clone())
+                    .await?
+                    .unwrap_or(0),
+                receipts: self
+                    .db_get::<CounterSchema>(TOTAL_RECEIPTS_KEY.clone())
+                    .await?
+                    .unwrap_or(0),
+            },
+            None => self.backfill_counters().await?,
+        };
+        slot.replace(counters.clone());
+        Ok(counters)
+    }
+
+    // Databases written before the counters existed get them computed once
+    // from the stored blocks.
+    async fn backfill_counters(&self) -> ProtocolResult<ChainCounters> {
+        let mut counters = ChainCounters::default();
+        let latest = self
+            .db_get_sealed::<LatestBlockSchema, _>(LATEST_BLOCK_KEY.clone())
+            .await?;
+
+        if let Some(latest) = latest {
+            let end = latest.header.height + 1;
+            let mut start = 0;
+            while start < end {
+                let chunk_end = cmp::min(end, start + MAX_BLOCKS_RANGE);
+                let blocks = self
+                    .db_get_batch_sealed::<BlockSchema, _>((start..chunk_end).collect())
+                    .await?;
+
+                for block in blocks.into_iter().flatten() {
+                    counters.blocks += 1;
+                    counters.txs += block.ordered_tx_hashes.len() as u64;
+
+                    let receipts = self
+                        .db_get_batch::<ReceiptSchema>(block.ordered_tx_hashes)
+                        .await?;
+                    counters.receipts += receipts.iter().filter(|r| r.is_some()).count() as u64;
+                }
+                start = chunk_end;
+            }
+        }
+
+        let mut batch = StorageBatch::new();
+        counters.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        Ok(counters)
+    }
+
+    async fn db_get<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>>
+    where
+        <S as StorageSchema>::Value: ProtocolCodecSync,
+    {
+        let start = self.metrics.start();
+        let opt = self.adapter.get::<S>(key).await?;
+        self.metrics
+            .record(start, StorageOp::Get, S::category(), || {
+                opt.iter().map(encoded_len).sum()
+            });
+        Ok(opt)
+    }
+
+    async fn db_get_batch<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<Option<<S as StorageSchema>::Value>>>
+    where
+        <S as StorageSchema>::Value: ProtocolCodecSync,
+    {
+        let start = self.metrics.start();
+        let opts = self.adapter.get_batch::<S>(keys).await?;
+        self.metrics
+            .record(start, StorageOp::Get, S::category(), || {
+                opts.iter().flatten().map(encoded_len).sum()
+            });
+        Ok(opts)
+    }
+
+    async fn db_get_sealed<S, T>(&self, key: <S as StorageSchema>::Key) -> ProtocolResult<Option<T>>
+    where
+        S: StorageSchema<Value = Sealed<T>>,
+        <S as StorageSchema>::Key: Clone + fmt::Debug,
+        T: ProtocolCodecSync + 'static,
+    {
+        match self.db_get::<S>(key.clone()).await? {
+            Some(sealed) => Ok(Some(sealed.open(S::category(), &key)?)),
+            None => Ok(None),
         }
     }
+
+    async fn db_get_batch_sealed<S, T>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<Option<T>>>
+    where
+        S: StorageSchema<Value = Sealed<T>>,
+        <S as StorageSchema>::Key: Clone + fmt::Debug,
+        T: ProtocolCodecSync + 'static,
+    {
+        let opts = self.db_get_batch::<S>(keys.clone()).await?;
+
+        keys.iter()
+            .zip(opts.into_iter())
+            .map(|(key, opt)| match opt {
+                Some(sealed) => sealed.open(S::category(), key).map(Some),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    async fn db_contains_batch<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<bool>> {
+        let start = self.metrics.start();
+        let found = self.adapter.contains_batch::<S>(keys).await?;
+        self.metrics
+            .record(start, StorageOp::Get, S::category(), || 0);
+        Ok(found)
+    }
+
+    // Splits `keys`, each counted once, into how many are stored and how many
+    // aren't
+    async fn count_stored<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<(u64, u64)>
+    where
+        <S as StorageSchema>::Key: Clone + Eq + hash::Hash,
+    {
+        let mut seen = HashSet::with_capacity(keys.len());
+        let keys = keys
+            .into_iter()
+            .filter(|key| seen.insert(key.clone()))
+            .collect::<Vec<_>>();
+        let total = keys.len() as u64;
+        let stored = self.db_contains_batch::<S>(keys).await?;
+        let stored = stored.iter().filter(|found| **found).count() as u64;
+        Ok((stored, total - stored))
+    }
+
+    async fn db_insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()>
+    where
+        <S as StorageSchema>::Value: ProtocolCodecSync,
+    {
+        let start = self.metrics.start();
+        let byte_len = if start.is_some() {
+            encoded_len(&val)
+        } else {
+            0
+        };
+
+        self.adapter.insert::<S>(key, val).await?;
+        self.metrics
+            .record(start, StorageOp::Insert, S::category(), || byte_len);
+        Ok(())
+    }
+
+    async fn db_batch_modify<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+        vals: Vec<StorageBatchModify<S>>,
+    ) -> ProtocolResult<()>
+    where
+        <S as StorageSchema>::Value: ProtocolCodecSync,
+    {
+        let start = self.metrics.start();
+        let op = match vals.first() {
+            Some(StorageBatchModify::Remove) => StorageOp::Remove,
+            _ => StorageOp::Insert,
+        };
+        let byte_len = if start.is_some() {
+            vals.iter()
+                .map(|val| match val {
+                    StorageBatchModify::Insert(val) => encoded_len(val),
+                    StorageBatchModify::Remove => 0,
+                })
+                .sum()
+        } else {
+            0
+        };
+
+        self.adapter.batch_modify::<S>(keys, vals).await?;
+        self.metrics.record(start, op, S::category(), || byte_len);
+        Ok(())
+    }
+
+    // Reported once for every category and operation found in the batch
+    async fn db_write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        let start = self.metrics.start();
+        let mut records: Vec<(StorageOp, StorageCategory, usize)> = Vec::new();
+        if start.is_some() {
+            for (category, _, val) in batch.entries().iter() {
+                let (op, byte_len) = match val {
+                    Some(val) => (StorageOp::Insert, val.len()),
+                    None => (StorageOp::Remove, 0),
+                };
+                let pos = records
+                    .iter()
+                    .position(|(o, c, _)| *o == op && *c as usize == *category as usize);
+                match pos {
+                    Some(pos) => records[pos].2 += byte_len,
+                    None => records.push((op, *category, byte_len)),
+                }
+            }
+        }
+
+        self.adapter.write_batch(batch).await?;
+        for (op, category, byte_len) in records.into_iter() {
+            self.metrics.record(start, op, category, || byte_len);
+        }
+        Ok(())
+    }
 }
 
+type SealedBlock = Sealed<Block>;
+type SealedTransaction = Sealed<SignedTransaction>;
+
 macro_rules! impl_storage_schema_for {
     ($name: ident, $key: ident, $val: ident, $category: ident) => {
         pub struct $name;
@@ -61,15 +397,48 @@ macro_rules! impl_storage_schema_for {
 impl_storage_schema_for!(
     TransactionSchema,
     Hash,
-    SignedTransaction,
+    SealedTransaction,
     SignedTransaction
 );
 impl_storage_schema_for!(ReceiptSchema, Hash, Receipt, Receipt);
-impl_storage_schema_for!(BlockSchema, u64, Block, Block);
+impl_storage_schema_for!(BlockSchema, u64, SealedBlock, Block);
 impl_storage_schema_for!(HashBlockSchema, Hash, u64, Block);
-impl_storage_schema_for!(LatestBlockSchema, Hash, Block, Block);
+impl_storage_schema_for!(HeaderSchema, Bytes, BlockHeader, Block);
+impl_storage_schema_for!(LatestBlockSchema, Hash, SealedBlock, Block);
 impl_storage_schema_for!(LatestProofSchema, Hash, Proof, Block);
 impl_storage_schema_for!(OverlordWalSchema, Hash, Bytes, Wal);
+impl_storage_schema_for!(EvidenceSchema, Hash, Bytes, Evidence);
+impl_storage_schema_for!(LivenessSchema, Hash, Bytes, Liveness);
+impl_storage_schema_for!(PrunedHeightSchema, Hash, u64, Block);
+impl_storage_schema_for!(CounterSchema, Hash, u64, Block);
+impl_storage_schema_for!(EventIndexSchema, Bytes, EventTxs, EventIndex);
+impl_storage_schema_for!(PositionSchema, Bytes, StoredPosition, SignedTransaction);
+impl_storage_schema_for!(
+    PoolTransactionSchema,
+    Hash,
+    SignedTransaction,
+    TransactionPool
+);
+
+/// Totals of what is stored: blocks, transactions indexed by a stored block
+/// (one position each) and receipts. A write counts only the keys it adds and
+/// a removal, pruning included, only the keys that were there, so repeating
+/// either leaves the totals as they are.
+#[derive(Clone, Debug, Default)]
+struct ChainCounters {
+    blocks:   u64,
+    txs:      u64,
+    receipts: u64,
+}
+
+impl ChainCounters {
+    fn write_to(&self, batch: &mut StorageBatch) -> ProtocolResult<()> {
+        batch.insert::<CounterSchema>(TOTAL_BLOCKS_KEY.clone(), self.blocks)?;
+        batch.insert::<CounterSchema>(TOTAL_TXS_KEY.clone(), self.txs)?;
+        batch.insert::<CounterSchema>(TOTAL_RECEIPTS_KEY.clone(), self.receipts)?;
+        Ok(())
+    }
+}
 
 macro_rules! batch_insert {
     ($self_: ident,$vec: expr, $schema: ident) => {
@@ -81,97 +450,524 @@ macro_rules! batch_insert {
 
         let batch_insert = $vec
             .into_iter()
-            .map(StorageBatchModify::Insert)
+            .map(|item| StorageBatchModify::Insert($self_.seal(item)))
             .collect::<Vec<_>>();
 
         $self_
-            .adapter
-            .batch_modify::<$schema>(hashes, batch_insert)
+            .db_batch_modify::<$schema>(hashes, batch_insert)
             .await?;
     };
 }
 
 macro_rules! get_batch {
     ($self_: ident, $keys: expr, $schema: ident) => {{
-        let opt = $self_.adapter.get_batch::<$schema>($keys).await?;
-        opts_to_flat(opt)
+        let keys = $keys;
+        let opt = $self_.db_get_batch::<$schema>(keys.clone()).await?;
+        opts_to_flat($schema::category(), &keys, opt)?
+    }};
+}
+
+macro_rules! get_sealed {
+    ($self_: ident, $key: expr, $schema: ident) => {{
+        let key = $key;
+        let opt = $self_.db_get_sealed::<$schema, _>(key.clone()).await?;
+        opt.ok_or_else(|| not_found($schema::category(), &[key]))?
     }};
 }
 
 macro_rules! get {
     ($self_: ident, $key: expr, $schema: ident) => {{
-        let opt = $self_.adapter.get::<$schema>($key).await?;
-        check_none(opt)?
+        let key = $key;
+        let opt = $self_.db_get::<$schema>(key.clone()).await?;
+        opt.ok_or_else(|| not_found($schema::category(), &[key]))?
     }};
 }
 
 #[async_trait]
 impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
     async fn insert_transactions(&self, signed_txs: Vec<SignedTransaction>) -> ProtocolResult<()> {
+        let cached = if self.tx_cache.is_enabled() {
+            signed_txs.clone()
+        } else {
+            Vec::new()
+        };
+
         batch_insert!(self, signed_txs, TransactionSchema);
+
+        for stx in cached.into_iter() {
+            self.tx_cache.put(stx.tx_hash.clone(), stx);
+        }
         Ok(())
     }
 
     async fn insert_block(&self, block: Block) -> ProtocolResult<()> {
-        let height = block.header.height;
-        let block_hash = Hash::digest(block.encode_fixed()?);
+        self.insert_block_data(block, Vec::new(), Vec::new()).await
+    }
 
-        self.adapter
-            .insert::<BlockSchema>(height.clone(), block.clone())
-            .await?;
-        self.adapter
-            .insert::<HashBlockSchema>(block_hash, height)
+    async fn insert_block_data(
+        &self,
+        block: Block,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        self.insert_blocks_data(vec![(block, signed_txs, receipts)])
+            .await
+    }
+
+    async fn insert_blocks_data(
+        &self,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        let latest_block = match blocks.last() {
+            Some((block, _, _)) => block.clone(),
+            None => return Ok(()),
+        };
+
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+        let heights = blocks.iter().map(|(block, _, _)| block.header.height);
+        let (_, new_blocks) = self.count_stored::<BlockSchema>(heights.collect()).await?;
+        let positions = blocks
+            .iter()
+            .flat_map(|(block, _, _)| block.ordered_tx_hashes.iter().map(position_key));
+        let (_, new_txs) = self
+            .count_stored::<PositionSchema>(positions.collect())
             .await?;
-        self.adapter
-            .insert::<LatestBlockSchema>(LATEST_BLOCK_KEY.clone(), block.clone())
+        let receipt_hashes = blocks
+            .iter()
+            .flat_map(|(_, _, receipts)| receipts.iter().map(|r| r.tx_hash.clone()));
+        let (_, new_receipts) = self
+            .count_stored::<ReceiptSchema>(receipt_hashes.collect())
             .await?;
+        next.blocks += new_blocks;
+        next.txs += new_txs;
+        next.receipts += new_receipts;
+
+        let mut cached = Vec::new();
+        let mut written = Vec::with_capacity(blocks.len());
 
-        self.latest_block.write().await.replace(block);
+        // The hash index, latest pointer and counters must never refer to a
+        // block that wasn't written, so they all go down in one batch.
+        let mut batch = StorageBatch::new();
+        for (block, signed_txs, receipts) in blocks.into_iter() {
+            let height = block.header.height;
+            let block_hash = Hash::digest(block.encode_fixed()?);
+
+            if self.tx_cache.is_enabled() {
+                cached.extend(signed_txs.iter().cloned());
+            }
+
+            self.index_events(&mut batch, &receipts).await?;
+            for stx in signed_txs.into_iter() {
+                batch.insert::<TransactionSchema>(stx.tx_hash.clone(), self.seal(stx))?;
+            }
+            for receipt in receipts.into_iter() {
+                batch.insert::<ReceiptSchema>(receipt.tx_hash.clone(), receipt)?;
+            }
+            write_positions(&mut batch, &block, &block_hash)?;
+            batch.insert::<BlockSchema>(height, self.seal(block.clone()))?;
+            batch.insert::<HashBlockSchema>(block_hash, height)?;
+            batch.insert::<HeaderSchema>(header_key(height), block.header.clone())?;
+            written.push(block);
+        }
+        batch.insert::<LatestBlockSchema>(
+            LATEST_BLOCK_KEY.clone(),
+            self.seal(latest_block.clone()),
+        )?;
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+
+        for stx in cached.into_iter() {
+            self.tx_cache.put(stx.tx_hash.clone(), stx);
+        }
+        for block in written.into_iter() {
+            self.block_cache.put(block.header.height, block);
+        }
+        self.latest_block.write().await.replace(latest_block);
 
         Ok(())
     }
 
     async fn insert_receipts(&self, receipts: Vec<Receipt>) -> ProtocolResult<()> {
-        batch_insert!(self, receipts, ReceiptSchema);
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+        let hashes = receipts.iter().map(|r| r.tx_hash.clone()).collect();
+        let (_, new_receipts) = self.count_stored::<ReceiptSchema>(hashes).await?;
+        next.receipts += new_receipts;
+
+        let mut batch = StorageBatch::new();
+        self.index_events(&mut batch, &receipts).await?;
+        for receipt in receipts.into_iter() {
+            batch.insert::<ReceiptSchema>(receipt.tx_hash.clone(), receipt)?;
+        }
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+
+        Ok(())
+    }
+
+    async fn insert_transactions_with_receipts(
+        &self,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        let cached = if self.tx_cache.is_enabled() {
+            signed_txs.clone()
+        } else {
+            Vec::new()
+        };
+
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+        let hashes = receipts.iter().map(|r| r.tx_hash.clone()).collect();
+        let (_, new_receipts) = self.count_stored::<ReceiptSchema>(hashes).await?;
+        next.receipts += new_receipts;
+
+        let mut batch = StorageBatch::new();
+        self.index_events(&mut batch, &receipts).await?;
+        for stx in signed_txs.into_iter() {
+            batch.insert::<TransactionSchema>(stx.tx_hash.clone(), self.seal(stx))?;
+        }
+        for receipt in receipts.into_iter() {
+            batch.insert::<ReceiptSchema>(receipt.tx_hash.clone(), receipt)?;
+        }
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+
+        for stx in cached.into_iter() {
+            self.tx_cache.put(stx.tx_hash.clone(), stx);
+        }
         Ok(())
     }
 
     async fn update_latest_proof(&self, proof: Proof) -> ProtocolResult<()> {
-        self.adapter
-            .insert::<LatestProofSchema>(LATEST_PROOF_KEY.clone(), proof)
+        self.db_insert::<LatestProofSchema>(LATEST_PROOF_KEY.clone(), proof)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<()> {
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+
+        // A transaction leaves the totals with its position
+        let positions = hashes.iter().map(position_key).collect::<Vec<_>>();
+        let (indexed, _) = self
+            .count_stored::<PositionSchema>(positions.clone())
+            .await?;
+        next.txs = next.txs.saturating_sub(indexed);
+
+        let mut batch = StorageBatch::new();
+        for (tx_hash, position) in hashes.iter().zip(positions.into_iter()) {
+            batch.remove::<TransactionSchema>(tx_hash.clone())?;
+            batch.remove::<PositionSchema>(position)?;
+        }
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+
+        // Drop cached entries only once the database no longer has them, a
+        // concurrent read could otherwise cache them again.
+        for hash in hashes.iter() {
+            self.tx_cache.remove(hash);
+        }
+        Ok(())
+    }
+
+    async fn remove_receipts(&self, hashes: Vec<Hash>) -> ProtocolResult<()> {
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+
+        let (stored, _) = self.count_stored::<ReceiptSchema>(hashes.clone()).await?;
+        next.receipts = next.receipts.saturating_sub(stored);
+
+        let mut batch = StorageBatch::new();
+        for tx_hash in hashes.into_iter() {
+            batch.remove::<ReceiptSchema>(tx_hash)?;
+        }
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+        Ok(())
+    }
+
+    async fn remove_block(&self, height: u64, force: bool) -> ProtocolResult<()> {
+        let block = get_sealed!(self, height, BlockSchema);
+        let block_hash = Hash::digest(block.encode_fixed()?);
+
+        // Loaded before anything is removed, a backfill must still see the
+        // block
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+
+        let mut batch = StorageBatch::new();
+        let latest_height = self.get_latest_block().await?.header.height;
+        let mut latest_update = None;
+        if height == latest_height {
+            if !force {
+                return Err(StorageError::RemoveLatestBlock { height }.into());
+            }
+
+            // Point to the previous block, or to nothing if it is the first.
+            // The pointer goes down with the counters, a crash in between
+            // would leave them apart.
+            let previous = match height.checked_sub(1) {
+                Some(previous) => self.db_get_sealed::<BlockSchema, _>(previous).await?,
+                None => None,
+            };
+            match previous.as_ref() {
+                Some(previous) => batch.insert::<LatestBlockSchema>(
+                    LATEST_BLOCK_KEY.clone(),
+                    self.seal(previous.clone()),
+                )?,
+                None => batch.remove::<LatestBlockSchema>(LATEST_BLOCK_KEY.clone())?,
+            }
+            latest_update = Some((self.latest_block.write().await, previous));
+        }
+
+        let positions = block
+            .ordered_tx_hashes
+            .iter()
+            .map(position_key)
+            .collect::<Vec<_>>();
+        let (indexed, _) = self
+            .count_stored::<PositionSchema>(positions.clone())
             .await?;
+        next.blocks = next.blocks.saturating_sub(1);
+        next.txs = next.txs.saturating_sub(indexed);
+
+        for position in positions.into_iter() {
+            batch.remove::<PositionSchema>(position)?;
+        }
+        batch.remove::<HashBlockSchema>(block_hash)?;
+        batch.remove::<HeaderSchema>(header_key(height))?;
+        batch.remove::<BlockSchema>(height)?;
+        next.write_to(&mut batch)?;
+        self.db_write_batch(batch).await?;
+        counters.replace(next);
+
+        if let Some((mut latest_block, previous)) = latest_update {
+            *latest_block = previous;
+        }
+
+        self.block_cache.remove(&height);
+        Ok(())
+    }
+
+    async fn prune_below(&self, height: u64, categories: &[StorageCategory]) -> ProtocolResult<()> {
+        let latest_height = self.get_latest_block().await?.header.height;
+        let end = cmp::min(height, latest_height);
+
+        // Each category resumes from its own marker, so pruning one of them
+        // never skips the heights another still holds.
+        let mut markers = Vec::with_capacity(PRUNABLE_CATEGORIES.len());
+        for category in PRUNABLE_CATEGORIES.iter() {
+            if categories.contains(category) {
+                let pruned = self
+                    .db_get::<PrunedHeightSchema>(pruned_height_key(*category))
+                    .await?
+                    .unwrap_or(0);
+                markers.push((*category, pruned));
+            }
+        }
+        let due = |category: StorageCategory, height: u64| {
+            markers
+                .iter()
+                .any(|(c, pruned)| *c == category && height >= *pruned)
+        };
+        let prune_blocks = categories.contains(&StorageCategory::Block);
+
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+        let mut start = markers
+            .iter()
+            .map(|(_, pruned)| *pruned)
+            .min()
+            .unwrap_or(end);
+        while start < end {
+            let chunk_end = cmp::min(end, start + MAX_BLOCKS_RANGE);
+            let blocks = self
+                .db_get_batch_sealed::<BlockSchema, _>((start..chunk_end).collect())
+                .await?;
+
+            let mut batch = StorageBatch::new();
+            let mut pruned_txs = Vec::new();
+            let mut pruned_receipts = Vec::new();
+            for (height, block) in (start..chunk_end).zip(blocks.into_iter()) {
+                // Heights without a block have nothing left to prune
+                let block = match block {
+                    Some(block) => block,
+                    None => continue,
+                };
+
+                let prune_receipts = due(StorageCategory::Receipt, height);
+                let prune_txs = due(StorageCategory::SignedTransaction, height);
+                for tx_hash in block.ordered_tx_hashes.iter() {
+                    if prune_receipts {
+                        batch.remove::<ReceiptSchema>(tx_hash.clone())?;
+                        pruned_receipts.push(tx_hash.clone());
+                    }
+                    if prune_txs {
+                        batch.remove::<TransactionSchema>(tx_hash.clone())?;
+                        batch.remove::<PositionSchema>(position_key(tx_hash))?;
+                        pruned_txs.push(tx_hash.clone());
+                    }
+                }
+                if due(StorageCategory::Block, height) {
+                    next.blocks = next.blocks.saturating_sub(1);
+                    batch.remove::<HashBlockSchema>(Hash::digest(block.encode_fixed()?))?;
+                    batch.remove::<HeaderSchema>(header_key(height))?;
+                    batch.remove::<BlockSchema>(height)?;
+                }
+            }
+
+            let positions = pruned_txs.iter().map(position_key).collect();
+            let (indexed, _) = self.count_stored::<PositionSchema>(positions).await?;
+            next.txs = next.txs.saturating_sub(indexed);
+            let (stored, _) = self.count_stored::<ReceiptSchema>(pruned_receipts).await?;
+            next.receipts = next.receipts.saturating_sub(stored);
+            next.write_to(&mut batch)?;
+
+            // Moving the markers in the same batch lets an interrupted prune
+            // resume after the last finished chunk.
+            for (category, pruned) in markers.iter() {
+                if *pruned < chunk_end {
+                    batch.insert::<PrunedHeightSchema>(pruned_height_key(*category), chunk_end)?;
+                }
+            }
+            self.db_write_batch(batch).await?;
+            counters.replace(next.clone());
+
+            if prune_blocks {
+                for height in start..chunk_end {
+                    self.block_cache.remove(&height);
+                }
+            }
+            for tx_hash in pruned_txs.iter() {
+                self.tx_cache.remove(tx_hash);
+            }
+
+            start = chunk_end;
+        }
+
         Ok(())
     }
 
     async fn get_transaction_by_hash(&self, tx_hash: Hash) -> ProtocolResult<SignedTransaction> {
-        let stx = get!(self, tx_hash, TransactionSchema);
+        if let Some(stx) = self.tx_cache.get(&tx_hash) {
+            return Ok(stx);
+        }
+
+        let stx = get_sealed!(self, tx_hash.clone(), TransactionSchema);
+        self.tx_cache.put(tx_hash, stx.clone());
         Ok(stx)
     }
 
+    async fn get_transaction_with_position(
+        &self,
+        tx_hash: Hash,
+    ) -> ProtocolResult<TransactionWithPosition> {
+        let (transaction, position, latest) = futures::try_join!(
+            self.get_transaction_by_hash(tx_hash.clone()),
+            self.db_get::<PositionSchema>(position_key(&tx_hash)),
+            self.get_latest_block(),
+        )?;
+
+        Ok(TransactionWithPosition {
+            transaction,
+            position: position.map(|stored| stored.0),
+            latest_height: latest.header.height,
+        })
+    }
+
     async fn get_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
-        let stxs = get_batch!(self, hashes, TransactionSchema);
-        Ok(stxs)
+        let opts = self
+            .db_get_batch_sealed::<TransactionSchema, _>(hashes.clone())
+            .await?;
+        opts_to_flat(StorageCategory::SignedTransaction, &hashes, opts)
+    }
+
+    async fn filter_unknown_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        let known = self
+            .db_contains_batch::<TransactionSchema>(hashes.clone())
+            .await?;
+
+        let unknown = hashes
+            .into_iter()
+            .zip(known.into_iter())
+            .filter(|(_, known)| !known)
+            .map(|(hash, _)| hash)
+            .collect();
+        Ok(unknown)
     }
 
     async fn get_latest_block(&self) -> ProtocolResult<Block> {
         let opt_block = { self.latest_block.read().await.clone() };
 
         if let Some(block) = opt_block {
-            Ok(block)
-        } else {
-            Ok(get!(self, LATEST_BLOCK_KEY.clone(), LatestBlockSchema))
+            return Ok(block);
         }
+
+        // A fresh database has no latest block yet
+        Ok(get_sealed!(
+            self,
+            LATEST_BLOCK_KEY.clone(),
+            LatestBlockSchema
+        ))
     }
 
     async fn get_block_by_height(&self, height: u64) -> ProtocolResult<Block> {
-        let block = get!(self, height, BlockSchema);
+        if let Some(block) = self.block_cache.get(&height) {
+            return Ok(block);
+        }
+
+        let block = get_sealed!(self, height, BlockSchema);
+        self.block_cache.put(height, block.clone());
         Ok(block)
     }
 
     async fn get_block_by_hash(&self, block_hash: Hash) -> ProtocolResult<Block> {
         let height = get!(self, block_hash, HashBlockSchema);
-        let block = get!(self, height, BlockSchema);
-        Ok(block)
+        self.get_block_by_height(height).await
+    }
+
+    async fn get_header_by_height(&self, height: u64) -> ProtocolResult<BlockHeader> {
+        let opt = self.db_get::<HeaderSchema>(header_key(height)).await?;
+
+        // Databases written before headers were stored on their own only
+        // have the full block.
+        match opt {
+            Some(header) => Ok(header),
+            None => Ok(get_sealed!(self, height, BlockSchema).header),
+        }
+    }
+
+    async fn get_header_by_hash(&self, block_hash: Hash) -> ProtocolResult<BlockHeader> {
+        let height = get!(self, block_hash, HashBlockSchema);
+        self.get_header_by_height(height).await
+    }
+
+    async fn get_blocks(&self, start: u64, end: u64) -> ProtocolResult<Vec<Block>> {
+        if start >= end {
+            return Ok(vec![]);
+        }
+        if end - start > MAX_BLOCKS_RANGE {
+            return Err(StorageError::RangeTooLarge { start, end }.into());
+        }
+
+        let opts = self
+            .db_get_batch_sealed::<BlockSchema, _>((start..end).collect())
+            .await?;
+
+        let mut blocks = Vec::with_capacity(opts.len());
+        for (height, opt) in (start..end).zip(opts.into_iter()) {
+            let block = opt.ok_or_else(|| not_found(StorageCategory::Block, &[height]))?;
+            blocks.push(block);
+        }
+        Ok(blocks)
     }
 
     async fn get_receipt(&self, hash: Hash) -> ProtocolResult<Receipt> {
@@ -184,14 +980,27 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         Ok(receipts)
     }
 
+    async fn get_block_receipts(&self, height: u64) -> ProtocolResult<Vec<Receipt>> {
+        let block = get_sealed!(self, height, BlockSchema);
+
+        // Receipts are written after the block, a missing one is reported
+        // by its tx hash instead of being dropped.
+        let receipts = get_batch!(self, block.ordered_tx_hashes, ReceiptSchema);
+        Ok(receipts)
+    }
+
+    async fn get_block_receipts_by_hash(&self, block_hash: Hash) -> ProtocolResult<Vec<Receipt>> {
+        let height = get!(self, block_hash, HashBlockSchema);
+        self.get_block_receipts(height).await
+    }
+
     async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
-        let proof = get!(self, LATEST_PROOF_KEY.clone(), LatestProofSchema);
-        Ok(proof)
+        // Nothing is stored before the first commit
+        Ok(get!(self, LATEST_PROOF_KEY.clone(), LatestProofSchema))
     }
 
     async fn update_overlord_wal(&self, info: Bytes) -> ProtocolResult<()> {
-        self.adapter
-            .insert::<OverlordWalSchema>(OVERLORD_WAL_KEY.clone(), info)
+        self.db_insert::<OverlordWalSchema>(OVERLORD_WAL_KEY.clone(), info)
             .await?;
         Ok(())
     }
@@ -200,24 +1009,263 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         let wal_info = get!(self, OVERLORD_WAL_KEY.clone(), OverlordWalSchema);
         Ok(wal_info)
     }
+
+    async fn update_evidence(&self, evidence: Bytes) -> ProtocolResult<()> {
+        self.db_insert::<EvidenceSchema>(EVIDENCE_KEY.clone(), evidence)
+            .await?;
+        Ok(())
+    }
+
+    async fn load_evidence(&self) -> ProtocolResult<Bytes> {
+        let evidence = self.db_get::<EvidenceSchema>(EVIDENCE_KEY.clone()).await?;
+        Ok(evidence.unwrap_or_default())
+    }
+
+    async fn update_liveness(&self, missed: Bytes) -> ProtocolResult<()> {
+        self.db_insert::<LivenessSchema>(LIVENESS_KEY.clone(), missed)
+            .await?;
+        Ok(())
+    }
+
+    async fn load_liveness(&self) -> ProtocolResult<Bytes> {
+        let missed = self.db_get::<LivenessSchema>(LIVENESS_KEY.clone()).await?;
+        Ok(missed.unwrap_or_default())
+    }
+
+    async fn get_chain_stats(&self) -> ProtocolResult<ChainStats> {
+        let counters = {
+            let mut counters = self.counters.write().await;
+            self.current_counters(&mut counters).await?
+        };
+
+        let cached = { self.latest_block.read().await.clone() };
+        let latest = match cached {
+            Some(block) => Some(block),
+            None => {
+                self.db_get_sealed::<LatestBlockSchema, _>(LATEST_BLOCK_KEY.clone())
+                    .await?
+            }
+        };
+        let (height, latest_block_tx_count) = latest
+            .map(|block| {
+                let tx_count = block.ordered_tx_hashes.len() as u64;
+                (block.header.height, tx_count)
+            })
+            .unwrap_or((0, 0));
+
+        Ok(ChainStats {
+            height,
+            total_blocks: counters.blocks,
+            total_txs: counters.txs,
+            latest_block_tx_count,
+            total_receipts: counters.receipts,
+        })
+    }
+
+    async fn get_events(
+        &self,
+        service: &str,
+        from: u64,
+        to: u64,
+        limit: usize,
+    ) -> ProtocolResult<Vec<EventRecord>> {
+        if from >= to || limit == 0 {
+            return Ok(vec![]);
+        }
+        if to - from > MAX_BLOCKS_RANGE {
+            return Err(StorageError::RangeTooLarge {
+                start: from,
+                end:   to,
+            }
+            .into());
+        }
+
+        let keys = (from..to)
+            .map(|height| event_key(service, height))
+            .collect();
+        let tx_hashes = self
+            .db_get_batch::<EventIndexSchema>(keys)
+            .await?
+            .into_iter()
+            .flatten()
+            .flat_map(|txs| txs.0)
+            .collect::<Vec<_>>();
+
+        // Pruned receipts leave their index entries behind, they are skipped
+        let receipts = self.db_get_batch::<ReceiptSchema>(tx_hashes).await?;
+
+        let mut records = Vec::new();
+        for receipt in receipts.into_iter().flatten() {
+            for event in receipt.events.into_iter() {
+                if event.service != service {
+                    continue;
+                }
+
+                records.push(EventRecord {
+                    height: receipt.height,
+                    tx_hash: receipt.tx_hash.clone(),
+                    event,
+                });
+                if records.len() == limit {
+                    return Ok(records);
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    async fn update_pool_transactions(
+        &self,
+        inserted: Vec<SignedTransaction>,
+        removed: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        if inserted.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = StorageBatch::new();
+        for hash in removed.into_iter() {
+            batch.remove::<PoolTransactionSchema>(hash)?;
+        }
+        for stx in inserted.into_iter() {
+            batch.insert::<PoolTransactionSchema>(stx.tx_hash.clone(), stx)?;
+        }
+        self.db_write_batch(batch).await
+    }
+
+    async fn get_pool_transactions(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        let start = self.metrics.start();
+        let mut signed_txs = Vec::new();
+        let mut byte_len = 0;
+        let mut start_after = None;
+
+        loop {
+            let pairs = self
+                .adapter
+                .iter_prefix(
+                    StorageCategory::TransactionPool,
+                    &[],
+                    start_after.take(),
+                    POOL_PAGE_SIZE,
+                )
+                .await?;
+            let is_last_page = pairs.len() < POOL_PAGE_SIZE;
+
+            for (key, value) in pairs.into_iter() {
+                byte_len += value.len();
+                signed_txs.push(SignedTransaction::decode_sync(value)?);
+                start_after = Some(key.to_vec());
+            }
+            if is_last_page {
+                break;
+            }
+        }
+
+        self.metrics.record(
+            start,
+            StorageOp::Get,
+            StorageCategory::TransactionPool,
+            || byte_len,
+        );
+        Ok(signed_txs)
+    }
 }
 
-fn opts_to_flat<T>(values: Vec<Option<T>>) -> Vec<T> {
-    values
-        .into_iter()
-        .filter(Option::is_some)
-        .map(|v| v.expect("get value"))
-        .collect()
+fn encoded_len<V: ProtocolCodecSync>(val: &V) -> usize {
+    val.encode_sync().map(|bytes| bytes.len()).unwrap_or(0)
 }
 
-fn check_none<T>(opt: Option<T>) -> ProtocolResult<T> {
-    opt.ok_or_else(|| StorageError::GetNone.into())
+fn write_positions(
+    batch: &mut StorageBatch,
+    block: &Block,
+    block_hash: &Hash,
+) -> ProtocolResult<()> {
+    for (index, tx_hash) in block.ordered_tx_hashes.iter().enumerate() {
+        let position = TransactionPosition {
+            height:     block.header.height,
+            block_hash: block_hash.clone(),
+            index:      index as u32,
+        };
+        batch.insert::<PositionSchema>(position_key(tx_hash), StoredPosition(position))?;
+    }
+    Ok(())
+}
+
+// Databases written before the markers were per category kept a single one,
+// it is left unused and pruning starts over once.
+pub fn pruned_height_key(category: StorageCategory) -> Hash {
+    Hash::digest(Bytes::from(format!("pruned_height_{}", category)))
+}
+
+fn header_key(height: u64) -> Bytes {
+    let mut key = HEADER_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&height.to_be_bytes());
+    Bytes::from(key)
+}
+
+// Fails with every key whose value is missing, so callers never work on a
+// shorter list than they asked for.
+fn opts_to_flat<K: fmt::Debug, T>(
+    category: StorageCategory,
+    keys: &[K],
+    values: Vec<Option<T>>,
+) -> ProtocolResult<Vec<T>> {
+    let missing = keys
+        .iter()
+        .zip(values.iter())
+        .filter(|(_, value)| value.is_none())
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Ok(values.into_iter().flatten().collect())
+    } else {
+        Err(not_found(category, &missing).into())
+    }
+}
+
+fn not_found<K: fmt::Debug>(category: StorageCategory, keys: &[K]) -> StorageError {
+    let keys = keys.iter().map(|key| format!("{:?}", key)).collect();
+    StorageError::NotFound { category, keys }
 }
 
 #[derive(Debug, Display, From)]
 pub enum StorageError {
-    #[display(fmt = "get none")]
-    GetNone,
+    #[display(fmt = "{} not found {:?}", category, keys)]
+    NotFound {
+        category: StorageCategory,
+        keys:     Vec<String>,
+    },
+
+    #[display(fmt = "block {} is the latest block, removing it needs force", height)]
+    RemoveLatestBlock { height: u64 },
+
+    #[display(
+        fmt = "block range [{}, {}) is larger than {}",
+        start,
+        end,
+        MAX_BLOCKS_RANGE
+    )]
+    RangeTooLarge { start: u64, end: u64 },
+
+    #[display(fmt = "{:?}", _0)]
+    Io(std::io::Error),
+
+    #[display(fmt = "invalid snapshot: {}", _0)]
+    InvalidSnapshot(String),
+
+    #[display(fmt = "transaction position of {} bytes", _0)]
+    InvalidPosition(usize),
+
+    #[display(
+        fmt = "{} {} failed its checksum, the database may be damaged",
+        category,
+        key
+    )]
+    Corrupted {
+        category: StorageCategory,
+        key:      String,
+    },
 }
 
 impl Error for StorageError {}
@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use protocol::traits::StorageCategory;
+
+const CATEGORIES: [StorageCategory; 8] = [
+    StorageCategory::Block,
+    StorageCategory::Receipt,
+    StorageCategory::SignedTransaction,
+    StorageCategory::Wal,
+    StorageCategory::EventIndex,
+    StorageCategory::TransactionPool,
+    StorageCategory::Evidence,
+    StorageCategory::Liveness,
+];
+
+/// Upper bounds in microseconds of the latency buckets, anything slower
+/// falls into the last bucket.
+pub const LATENCY_BUCKETS: [u64; 4] = [100, 1_000, 10_000, 100_000];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StorageOp {
+    Get,
+    Insert,
+    Remove,
+}
+
+/// Receives every completed database operation of `ImplStorage`.
+pub trait StorageMetrics: Send + Sync {
+    fn record(&self, op: StorageOp, category: StorageCategory, byte_len: usize, time: Duration);
+
+    /// Storage skips timing and measuring values when this is false.
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NoopMetrics;
+
+impl StorageMetrics for NoopMetrics {
+    fn record(&self, _: StorageOp, _: StorageCategory, _: usize, _: Duration) {}
+
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Default)]
+struct OpCounters {
+    count:   AtomicU64,
+    bytes:   AtomicU64,
+    micros:  AtomicU64,
+    buckets: [AtomicU64; 5],
+}
+
+impl OpCounters {
+    fn add(&self, byte_len: usize, time: Duration) {
+        let micros = time.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| micros < *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(byte_len as u64, Ordering::Relaxed);
+        self.micros.fetch_add(micros, Ordering::Relaxed);
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> OpStats {
+        let mut buckets = [0; 5];
+        for (value, counter) in buckets.iter_mut().zip(self.buckets.iter()) {
+            *value = counter.load(Ordering::Relaxed);
+        }
+
+        OpStats {
+            count: self.count.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            micros: self.micros.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+/// Counts operations, bytes and latency per category with atomics.
+#[derive(Debug, Default)]
+pub struct CounterMetrics {
+    // Indexed by category, then by get, insert and remove
+    counters: [[OpCounters; 3]; 8],
+}
+
+impl CounterMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let categories = CATEGORIES
+            .iter()
+            .map(|category| {
+                let counters = &self.counters[*category as usize];
+                CategoryStats {
+                    category: *category,
+                    get:      counters[0].snapshot(),
+                    insert:   counters[1].snapshot(),
+                    remove:   counters[2].snapshot(),
+                }
+            })
+            .collect();
+
+        MetricsSnapshot { categories }
+    }
+}
+
+impl StorageMetrics for CounterMetrics {
+    fn record(&self, op: StorageOp, category: StorageCategory, byte_len: usize, time: Duration) {
+        let op = match op {
+            StorageOp::Get => 0,
+            StorageOp::Insert => 1,
+            StorageOp::Remove => 2,
+        };
+        self.counters[category as usize][op].add(byte_len, time);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpStats {
+    pub count:   u64,
+    pub bytes:   u64,
+    pub micros:  u64,
+    /// Operation counts per `LATENCY_BUCKETS` entry plus the overflow bucket
+    pub buckets: [u64; 5],
+}
+
+#[derive(Debug, Clone)]
+pub struct CategoryStats {
+    pub category: StorageCategory,
+    pub get:      OpStats,
+    pub insert:   OpStats,
+    pub remove:   OpStats,
+}
+
+#[derive(Debug, Clone)]
+pub struct MetricsSnapshot {
+    pub categories: Vec<CategoryStats>,
+}
+
+impl MetricsSnapshot {
+    pub fn get(&self, category: StorageCategory) -> &CategoryStats {
+        &self.categories[category as usize]
+    }
+}
+
+// Starts a timer only when the metrics actually want it, so the no-op
+// metrics cost a single virtual call per operation.
+pub(crate) struct Recorder {
+    metrics: Arc<dyn StorageMetrics>,
+}
+
+impl Recorder {
+    pub fn new(metrics: Arc<dyn StorageMetrics>) -> Self {
+        Recorder { metrics }
+    }
+
+    pub fn start(&self) -> Option<Instant> {
+        if self.metrics.enabled() {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    pub fn record<F: FnOnce() -> usize>(
+        &self,
+        start: Option<Instant>,
+        op: StorageOp,
+        category: StorageCategory,
+        byte_len: F,
+    ) {
+        if let Some(start) = start {
+            self.metrics
+                .record(op, category, byte_len(), start.elapsed());
+        }
+    }
+}
+
+impl std::fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Recorder {{ enabled: {} }}", self.metrics.enabled())
+    }
+}
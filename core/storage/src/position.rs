@@ -0,0 +1,53 @@
+//! Index from a transaction hash to the block it was committed in.
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::traits::TransactionPosition;
+use protocol::types::Hash;
+use protocol::{Bytes, ProtocolResult};
+
+use crate::StorageError;
+
+const POSITION_KEY_PREFIX: &[u8] = b"position-";
+
+// height u64 | index u32 | block hash, all integers big endian
+const POSITION_LEN: usize = 8 + 4 + 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredPosition(pub TransactionPosition);
+
+impl ProtocolCodecSync for StoredPosition {
+    fn encode_sync(&self) -> ProtocolResult<Bytes> {
+        let position = &self.0;
+
+        let mut bytes = Vec::with_capacity(POSITION_LEN);
+        bytes.extend_from_slice(&position.height.to_be_bytes());
+        bytes.extend_from_slice(&position.index.to_be_bytes());
+        bytes.extend_from_slice(&position.block_hash.as_bytes());
+        Ok(Bytes::from(bytes))
+    }
+
+    fn decode_sync(bytes: Bytes) -> ProtocolResult<Self> {
+        if bytes.len() != POSITION_LEN {
+            return Err(StorageError::InvalidPosition(bytes.len()).into());
+        }
+
+        let mut height = [0u8; 8];
+        height.copy_from_slice(&bytes[..8]);
+        let mut index = [0u8; 4];
+        index.copy_from_slice(&bytes[8..12]);
+
+        Ok(StoredPosition(TransactionPosition {
+            height:     u64::from_be_bytes(height),
+            block_hash: Hash::from_bytes(bytes.slice(12..))?,
+            index:      u32::from_be_bytes(index),
+        }))
+    }
+}
+
+// Kept apart from the transactions themselves, which are keyed by the bare
+// hash in the same category.
+pub(crate) fn position_key(tx_hash: &Hash) -> Bytes {
+    let mut key = POSITION_KEY_PREFIX.to_vec();
+    key.extend_from_slice(&tx_hash.as_bytes());
+    Bytes::from(key)
+}
@@ -0,0 +1,329 @@
+//! Chain snapshot files for bootstrapping a node without syncing.
+//!
+//! Layout, all integers big endian:
+//!
+//! ```text
+//! magic "MUTASNAP" | version u32 | chain id (32 bytes) | from u64 | to u64
+//! then for every height in from..=to:
+//!   frame(block) | tx count u32 | frame(tx)* | receipt count u32 | frame(receipt)*
+//! ```
+//!
+//! A frame is a u32 length followed by the protobuf encoded value. Blocks
+//! not executed yet go without receipts, the node restarting on the imported
+//! chain executes them again.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::thread;
+
+use futures::channel::oneshot;
+
+use common_merkle::Merkle;
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{Storage, StorageAdapter, StorageBatch};
+use protocol::types::{Block, Hash, Receipt, SignedTransaction};
+use protocol::{Bytes, ProtocolResult};
+
+use crate::position::position_key;
+use crate::{
+    header_key, write_positions, BlockSchema, HashBlockSchema, HeaderSchema, ImplStorage,
+    LatestBlockSchema, PositionSchema, ReceiptSchema, StorageError, TransactionSchema,
+    LATEST_BLOCK_KEY,
+};
+
+const MAGIC: &[u8; 8] = b"MUTASNAP";
+const VERSION: u32 = 1;
+
+// Guards against allocating whatever a corrupt length asks for
+const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
+    /// Writes blocks `from..=to` with their transactions and the receipts
+    /// stored for them to `path`.
+    pub async fn export_chain(&self, path: &Path, from: u64, to: u64) -> ProtocolResult<()> {
+        if from > to {
+            return Err(snapshot_err(format!("empty range {}..={}", from, to)));
+        }
+
+        let first = self.get_block_by_height(from).await?;
+        let mut writer = BufWriter::new(File::create(path).map_err(StorageError::Io)?);
+
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&VERSION.to_be_bytes());
+        header.extend_from_slice(&first.header.chain_id.as_bytes());
+        header.extend_from_slice(&from.to_be_bytes());
+        header.extend_from_slice(&to.to_be_bytes());
+        writer.write_all(&header).map_err(StorageError::Io)?;
+
+        for height in from..=to {
+            let block = self.get_block_by_height(height).await?;
+            let signed_txs = self
+                .get_transactions(block.ordered_tx_hashes.clone())
+                .await?;
+            let receipts = self
+                .db_get_batch::<ReceiptSchema>(block.ordered_tx_hashes.clone())
+                .await?
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+
+            write_frame(&mut writer, &block)?;
+            write_frames(&mut writer, &signed_txs)?;
+            write_frames(&mut writer, &receipts)?;
+        }
+
+        writer.flush().map_err(StorageError::Io)?;
+        Ok(())
+    }
+
+    /// Loads a file written by `export_chain`. The blocks must continue the
+    /// stored chain and hold exactly the transactions they order. Nothing is
+    /// written unless the whole file checks out, it all goes down in one
+    /// batch.
+    pub async fn import_chain(&self, path: &Path) -> ProtocolResult<()> {
+        let path = path.to_owned();
+        let snapshot = on_io_thread(move || read_snapshot(&path)).await?;
+
+        let mut prev_hash = match self
+            .db_get_sealed::<LatestBlockSchema, _>(LATEST_BLOCK_KEY.clone())
+            .await?
+        {
+            Some(latest) if latest.header.height + 1 != snapshot.from => {
+                return Err(snapshot_err(format!(
+                    "snapshot starts at {}, stored chain ends at {}",
+                    snapshot.from, latest.header.height
+                )))
+            }
+            Some(latest) => Some(Hash::digest(latest.encode_fixed()?)),
+            None => None,
+        };
+
+        let mut counters = self.counters.write().await;
+        let mut next = self.current_counters(&mut counters).await?;
+        let mut batch = StorageBatch::new();
+        let mut last_block = None;
+
+        let heights = snapshot.from..;
+        for (height, (block, signed_txs, receipts)) in heights.zip(snapshot.blocks.into_iter()) {
+            if block.header.height != height || block.header.chain_id != snapshot.chain_id {
+                return Err(snapshot_err(format!("unexpected block at {}", height)));
+            }
+            if let Some(prev_hash) = prev_hash.as_ref() {
+                if &block.header.pre_hash != prev_hash {
+                    return Err(snapshot_err(format!(
+                        "block {} doesn't follow the previous block",
+                        height
+                    )));
+                }
+            }
+            check_block_txs(&block, &signed_txs)?;
+            let block_hash = Hash::digest(block.encode_fixed()?);
+
+            let (_, new_blocks) = self.count_stored::<BlockSchema>(vec![height]).await?;
+            let positions = block.ordered_tx_hashes.iter().map(position_key).collect();
+            let (_, new_txs) = self.count_stored::<PositionSchema>(positions).await?;
+            let hashes = receipts.iter().map(|r| r.tx_hash.clone()).collect();
+            let (_, new_receipts) = self.count_stored::<ReceiptSchema>(hashes).await?;
+            next.blocks += new_blocks;
+            next.txs += new_txs;
+            next.receipts += new_receipts;
+
+            self.index_events(&mut batch, &receipts).await?;
+            for stx in signed_txs.into_iter() {
+                batch.insert::<TransactionSchema>(stx.tx_hash.clone(), self.seal(stx))?;
+            }
+            for receipt in receipts.into_iter() {
+                batch.insert::<ReceiptSchema>(receipt.tx_hash.clone(), receipt)?;
+            }
+            write_positions(&mut batch, &block, &block_hash)?;
+            batch.insert::<BlockSchema>(height, self.seal(block.clone()))?;
+            batch.insert::<HashBlockSchema>(block_hash.clone(), height)?;
+            batch.insert::<HeaderSchema>(header_key(height), block.header.clone())?;
+
+            prev_hash = Some(block_hash);
+            last_block = Some(block);
+        }
+
+        // The range isn't empty, so the last block is set
+        if let Some(last_block) = last_block {
+            batch.insert::<LatestBlockSchema>(
+                LATEST_BLOCK_KEY.clone(),
+                self.seal(last_block.clone()),
+            )?;
+            next.write_to(&mut batch)?;
+            self.db_write_batch(batch).await?;
+
+            counters.replace(next);
+            self.latest_block.write().await.replace(last_block);
+        }
+
+        Ok(())
+    }
+}
+
+type BlockData = (Block, Vec<SignedTransaction>, Vec<Receipt>);
+
+struct ChainSnapshot {
+    chain_id: Hash,
+    from:     u64,
+    blocks:   Vec<BlockData>,
+}
+
+fn read_snapshot(path: &Path) -> ProtocolResult<ChainSnapshot> {
+    let mut reader = BufReader::new(File::open(path).map_err(StorageError::Io)?);
+
+    let mut magic = [0u8; 8];
+    read_exact(&mut reader, &mut magic)?;
+    if &magic != MAGIC {
+        return Err(snapshot_err("not a chain snapshot".to_owned()));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        return Err(snapshot_err(format!("unsupported version {}", version)));
+    }
+    let mut chain_id = [0u8; 32];
+    read_exact(&mut reader, &mut chain_id)?;
+    let chain_id = Hash::from_bytes(Bytes::from(chain_id.to_vec()))?;
+    let from = read_u64(&mut reader)?;
+    let to = read_u64(&mut reader)?;
+    if from > to {
+        return Err(snapshot_err(format!("empty range {}..={}", from, to)));
+    }
+
+    let mut blocks = Vec::new();
+    for _ in from..=to {
+        let block: Block = read_frame(&mut reader)?;
+        let signed_txs: Vec<SignedTransaction> = read_frames(&mut reader)?;
+        let receipts: Vec<Receipt> = read_frames(&mut reader)?;
+        blocks.push((block, signed_txs, receipts));
+    }
+
+    let mut trailing = [0u8; 1];
+    if reader.read(&mut trailing).map_err(StorageError::Io)? != 0 {
+        return Err(snapshot_err(
+            "trailing data after the last block".to_owned(),
+        ));
+    }
+
+    Ok(ChainSnapshot {
+        chain_id,
+        from,
+        blocks,
+    })
+}
+
+// Runs the file IO on a thread of its own, off the executor's threads
+async fn on_io_thread<T, F>(f: F) -> ProtocolResult<T>
+where
+    F: FnOnce() -> ProtocolResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = oneshot::channel();
+    thread::Builder::new()
+        .name("snapshot-io".to_owned())
+        .spawn(move || {
+            let _ = tx.send(f());
+        })
+        .map_err(StorageError::Io)?;
+
+    rx.await
+        .map_err(|_| snapshot_err("reading the file was interrupted".to_owned()))?
+}
+
+// The transactions must be the ones the block orders, in its order, each
+// under the hash of its content
+fn check_block_txs(block: &Block, signed_txs: &[SignedTransaction]) -> ProtocolResult<()> {
+    let height = block.header.height;
+    if Merkle::ordered_root(&block.ordered_tx_hashes) != block.header.order_root {
+        return Err(snapshot_err(format!(
+            "order root of block {} doesn't match its transactions",
+            height
+        )));
+    }
+    if signed_txs.len() != block.ordered_tx_hashes.len() {
+        return Err(snapshot_err(format!(
+            "block {} orders {} transactions, the file holds {}",
+            height,
+            block.ordered_tx_hashes.len(),
+            signed_txs.len()
+        )));
+    }
+
+    for (stx, tx_hash) in signed_txs.iter().zip(block.ordered_tx_hashes.iter()) {
+        if &stx.tx_hash != tx_hash || Hash::digest(stx.raw.encode_fixed()?) != stx.tx_hash {
+            return Err(snapshot_err(format!(
+                "transaction {} of block {} doesn't match",
+                tx_hash.as_hex(),
+                height
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn write_frame<W: Write, T: ProtocolCodecSync>(writer: &mut W, value: &T) -> ProtocolResult<()> {
+    let bytes = value.encode_sync()?;
+    writer
+        .write_all(&(bytes.len() as u32).to_be_bytes())
+        .map_err(StorageError::Io)?;
+    writer.write_all(&bytes).map_err(StorageError::Io)?;
+    Ok(())
+}
+
+fn write_frames<W: Write, T: ProtocolCodecSync>(
+    writer: &mut W,
+    values: &[T],
+) -> ProtocolResult<()> {
+    writer
+        .write_all(&(values.len() as u32).to_be_bytes())
+        .map_err(StorageError::Io)?;
+    for value in values.iter() {
+        write_frame(writer, value)?;
+    }
+    Ok(())
+}
+
+fn read_frame<R: Read, T: ProtocolCodecSync>(reader: &mut R) -> ProtocolResult<T> {
+    let len = read_u32(reader)?;
+    if len > MAX_FRAME_LEN {
+        return Err(snapshot_err(format!("frame of {} bytes", len)));
+    }
+
+    let mut bytes = vec![0u8; len as usize];
+    read_exact(reader, &mut bytes)?;
+    T::decode_sync(Bytes::from(bytes))
+}
+
+fn read_frames<R: Read, T: ProtocolCodecSync>(reader: &mut R) -> ProtocolResult<Vec<T>> {
+    let count = read_u32(reader)?;
+    (0..count).map(|_| read_frame(reader)).collect()
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> ProtocolResult<u32> {
+    let mut buf = [0u8; 4];
+    read_exact(reader, &mut buf)?;
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> ProtocolResult<u64> {
+    let mut buf = [0u8; 8];
+    read_exact(reader, &mut buf)?;
+    Ok(u64::from_be_bytes(buf))
+}
+
+fn read_exact<R: Read>(reader: &mut R, buf: &mut [u8]) -> ProtocolResult<()> {
+    reader.read_exact(buf).map_err(|err| {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            snapshot_err("file is truncated".to_owned())
+        } else {
+            StorageError::Io(err).into()
+        }
+    })
+}
+
+fn snapshot_err(reason: String) -> protocol::ProtocolError {
+    StorageError::InvalidSnapshot(reason).into()
+}
@@ -0,0 +1,99 @@
+//! Reads against one point in time of the database.
+
+use std::fmt;
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::traits::{StorageCategory, StorageSchema, StorageSnapshot};
+use protocol::types::{Block, Hash, Receipt, SignedTransaction};
+use protocol::ProtocolResult;
+
+use crate::checksum::Sealed;
+use crate::{
+    not_found, opts_to_flat, BlockSchema, LatestBlockSchema, ReceiptSchema, TransactionSchema,
+    LATEST_BLOCK_KEY,
+};
+
+/// Read side of `ImplStorage` over a snapshot, handed out by
+/// `ImplStorage::with_snapshot`. The caches are bypassed, they follow the live
+/// database.
+pub struct SnapshotStorage<'a> {
+    snapshot: Box<dyn StorageSnapshot + 'a>,
+}
+
+impl<'a> SnapshotStorage<'a> {
+    pub(crate) fn new(snapshot: Box<dyn StorageSnapshot + 'a>) -> Self {
+        SnapshotStorage { snapshot }
+    }
+
+    pub fn get_latest_block(&self) -> ProtocolResult<Block> {
+        let key = LATEST_BLOCK_KEY.clone();
+        self.get_sealed::<LatestBlockSchema, _>(&key)?
+            .ok_or_else(|| not_found(StorageCategory::Block, &[key]).into())
+    }
+
+    pub fn get_block_by_height(&self, height: u64) -> ProtocolResult<Block> {
+        self.get_sealed::<BlockSchema, _>(&height)?
+            .ok_or_else(|| not_found(StorageCategory::Block, &[height]).into())
+    }
+
+    pub fn get_transactions(&self, hashes: &[Hash]) -> ProtocolResult<Vec<SignedTransaction>> {
+        let opts = self.get_batch::<TransactionSchema>(hashes)?;
+
+        let opts = hashes
+            .iter()
+            .zip(opts.into_iter())
+            .map(|(hash, opt)| match opt {
+                Some(sealed) => sealed
+                    .open(StorageCategory::SignedTransaction, hash)
+                    .map(Some),
+                None => Ok(None),
+            })
+            .collect::<ProtocolResult<Vec<_>>>()?;
+        opts_to_flat(StorageCategory::SignedTransaction, hashes, opts)
+    }
+
+    pub fn get_receipts(&self, hashes: &[Hash]) -> ProtocolResult<Vec<Receipt>> {
+        let opts = self.get_batch::<ReceiptSchema>(hashes)?;
+        opts_to_flat(StorageCategory::Receipt, hashes, opts)
+    }
+
+    pub fn get_block_receipts(&self, height: u64) -> ProtocolResult<Vec<Receipt>> {
+        let block = self.get_block_by_height(height)?;
+        self.get_receipts(&block.ordered_tx_hashes)
+    }
+
+    fn get_sealed<S, T>(&self, key: &<S as StorageSchema>::Key) -> ProtocolResult<Option<T>>
+    where
+        S: StorageSchema<Value = Sealed<T>>,
+        <S as StorageSchema>::Key: fmt::Debug,
+    {
+        let bytes = self.snapshot.get(S::category(), &key.encode_sync()?)?;
+
+        match bytes {
+            Some(bytes) => {
+                let sealed = <S as StorageSchema>::Value::decode_sync(bytes)?;
+                Ok(Some(sealed.open(S::category(), key)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_batch<S: StorageSchema>(
+        &self,
+        keys: &[<S as StorageSchema>::Key],
+    ) -> ProtocolResult<Vec<Option<<S as StorageSchema>::Value>>> {
+        let keys = keys
+            .iter()
+            .map(ProtocolCodecSync::encode_sync)
+            .collect::<ProtocolResult<Vec<_>>>()?;
+
+        self.snapshot
+            .get_batch(S::category(), &keys)?
+            .into_iter()
+            .map(|opt| {
+                opt.map(<S as StorageSchema>::Value::decode_sync)
+                    .transpose()
+            })
+            .collect()
+    }
+}
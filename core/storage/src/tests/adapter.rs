@@ -1,10 +1,27 @@
-use protocol::traits::{StorageAdapter, StorageBatchModify};
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::future::join_all;
+use test::Bencher;
+
+use protocol::traits::{
+    Storage, StorageAdapter, StorageBatch, StorageBatchModify, StorageCategory, StorageSchema,
+};
 use protocol::types::Hash;
+use protocol::Bytes;
 
 use crate::adapter::memory::MemoryAdapter;
-use crate::adapter::rocks::RocksAdapter;
-use crate::tests::{get_random_bytes, mock_signed_tx};
-use crate::TransactionSchema;
+use crate::adapter::rocks::{
+    RocksAdapter, RocksCategoryConfig, RocksCompression, RocksConfig, DEFAULT_POOL_TTL,
+};
+use crate::adapter::ttl::{Clock, Expiry};
+use crate::checksum::Sealed;
+use crate::tests::{get_random_bytes, mock_block, mock_receipt, mock_signed_tx};
+use crate::{ImplStorage, PoolTransactionSchema, ReceiptSchema, TransactionSchema};
 
 #[test]
 fn test_adapter_insert() {
@@ -26,14 +43,606 @@ fn test_adapter_remove() {
     adapter_remove_test(RocksAdapter::new("rocksdb/test_adapter_remove".to_string(), 64).unwrap())
 }
 
+#[test]
+fn test_rocks_adapter_restart() {
+    let path = "rocksdb/test_rocks_adapter_restart";
+    let _ = fs::remove_dir_all(path);
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let receipt_hash = Hash::digest(get_random_bytes(10));
+    let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    let stx = mock_signed_tx(tx_hash.clone());
+    let receipt = mock_receipt(receipt_hash.clone());
+    let wal = get_random_bytes(64);
+    let evidence = get_random_bytes(64);
+
+    {
+        let storage = ImplStorage::new(Arc::new(RocksAdapter::new(path, 64).unwrap()));
+        exec!(storage.insert_transactions(vec![stx.clone()]));
+        exec!(storage.insert_receipts(vec![receipt.clone()]));
+        exec!(storage.insert_block(block.clone()));
+        exec!(storage.update_overlord_wal(wal.clone()));
+        exec!(storage.update_evidence(evidence.clone()));
+    }
+
+    // Every category, the consensus wal and evidence included, is back after
+    // reopening
+    let storage = ImplStorage::new(Arc::new(RocksAdapter::new(path, 64).unwrap()));
+    assert_eq!(exec!(storage.get_latest_block()), block);
+    assert_eq!(exec!(storage.get_block_by_height(1)), block);
+    assert_eq!(exec!(storage.get_transaction_by_hash(tx_hash)), stx);
+    assert_eq!(exec!(storage.get_receipt(receipt_hash)), receipt);
+    assert_eq!(exec!(storage.load_overlord_wal()), wal);
+    assert_eq!(exec!(storage.load_evidence()), evidence);
+}
+
+#[test]
+fn test_rocks_adapter_config() {
+    let path = "rocksdb/test_rocks_adapter_config";
+    let _ = fs::remove_dir_all(path);
+
+    // A tiny memtable flushes on nearly every write
+    let config = RocksConfig {
+        block_cache_size: 1024 * 1024,
+        write_buffer_size: 64 * 1024,
+        compression: Some(RocksCompression::None),
+        overrides: vec![(StorageCategory::SignedTransaction, RocksCategoryConfig {
+            write_buffer_size: Some(32 * 1024),
+            compression:       Some(RocksCompression::Snappy),
+        })],
+        ..RocksConfig::default()
+    };
+    let storage = ImplStorage::new(Arc::new(
+        RocksAdapter::new_with_config(path, config).unwrap(),
+    ));
+
+    let mut stxs = Vec::new();
+    for _ in 0..100 {
+        stxs.push(mock_signed_tx(Hash::digest(get_random_bytes(10))));
+    }
+    exec!(storage.insert_transactions(stxs.clone()));
+    for stx in stxs.into_iter() {
+        assert_eq!(
+            exec!(storage.get_transaction_by_hash(stx.tx_hash.clone())),
+            stx
+        );
+    }
+
+    let zero_cache = RocksConfig {
+        block_cache_size: 0,
+        ..RocksConfig::default()
+    };
+    let err =
+        RocksAdapter::new_with_config("rocksdb/test_rocks_adapter_config_invalid", zero_cache)
+            .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("block_cache_size must be positive"));
+}
+
+#[test]
+fn test_adapter_read_only() {
+    let path = "rocksdb/test_adapter_read_only";
+    let _ = fs::remove_dir_all(path);
+
+    let writable = RocksAdapter::new(path, 64).unwrap();
+    read_only_test(writable, || RocksAdapter::open_read_only(path).unwrap());
+    let memory = MemoryAdapter::new();
+    let read_only = memory.read_only();
+    read_only_test(memory, || read_only);
+}
+
+// A database from before the pool, evidence and liveness categories opens
+// read only, with only those missing.
+#[test]
+fn test_rocks_read_only_missing_categories() {
+    let path = "rocksdb/test_rocks_read_only_missing_categories";
+    let _ = fs::remove_dir_all(path);
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let stx = mock_signed_tx(tx_hash.clone());
+    {
+        let storage = ImplStorage::new(Arc::new(RocksAdapter::new(path, 64).unwrap()));
+        exec!(storage.insert_transactions(vec![stx.clone()]));
+    }
+    {
+        let opts = rocksdb::Options::default();
+        let columns = rocksdb::DB::list_cf(&opts, path).unwrap();
+        let mut db = rocksdb::DB::open_cf(&opts, path, &columns).unwrap();
+        for column in ["c6", "c7", "c8"].iter() {
+            db.drop_cf(column).unwrap();
+        }
+    }
+
+    let adapter = Arc::new(RocksAdapter::open_read_only(path).unwrap());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    assert_eq!(exec!(storage.get_transaction_by_hash(tx_hash.clone())), stx);
+
+    let err = block_on(adapter.get::<PoolTransactionSchema>(tx_hash)).unwrap_err();
+    assert!(err.to_string().contains("category c6 not found"));
+    let err = block_on(storage.load_liveness()).unwrap_err();
+    assert!(err.to_string().contains("category c8 not found"));
+}
+
+fn read_only_test<W, R, F>(writable: W, open_read_only: F)
+where
+    W: StorageAdapter,
+    R: StorageAdapter,
+    F: FnOnce() -> R,
+{
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    let stx = mock_signed_tx(tx_hash.clone());
+
+    let storage = ImplStorage::new(Arc::new(writable));
+    exec!(storage.insert_transactions(vec![stx.clone()]));
+    exec!(storage.insert_block(block.clone()));
+
+    // The writable handle stays open, as it would in a running node
+    let _writable = storage;
+    let storage = ImplStorage::new(Arc::new(open_read_only()));
+    assert_eq!(exec!(storage.get_latest_block()), block);
+    assert_eq!(exec!(storage.get_transaction_by_hash(tx_hash)), stx);
+
+    let err = block_on(storage.insert_block(mock_block(2, Hash::digest(get_random_bytes(10)))))
+        .unwrap_err();
+    assert!(err.to_string().contains("ReadOnly"));
+    let err = block_on(
+        storage.insert_transactions(vec![mock_signed_tx(Hash::digest(get_random_bytes(10)))]),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("ReadOnly"));
+}
+
+#[test]
+fn test_storage_with_snapshot() {
+    let path = "rocksdb/test_storage_with_snapshot";
+    let _ = fs::remove_dir_all(path);
+
+    storage_with_snapshot_test(MemoryAdapter::new());
+    storage_with_snapshot_test(RocksAdapter::new(path, 64).unwrap());
+}
+
+fn storage_with_snapshot_test<Adapter: StorageAdapter>(adapter: Adapter) {
+    let storage = ImplStorage::new(Arc::new(adapter));
+
+    let tx_hashes = (0..3)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let stxs = tx_hashes
+        .iter()
+        .cloned()
+        .map(mock_signed_tx)
+        .collect::<Vec<_>>();
+    let receipts = tx_hashes
+        .iter()
+        .cloned()
+        .map(mock_receipt)
+        .collect::<Vec<_>>();
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes.clone();
+    exec!(storage.insert_block_data(block.clone(), stxs.clone(), receipts.clone()));
+
+    let next = mock_block(2, Hash::digest(get_random_bytes(10)));
+    storage
+        .with_snapshot(|snapshot| {
+            // Live writes made after the snapshot was taken
+            exec!(storage.insert_block(next.clone()));
+            exec!(storage.remove_receipts(tx_hashes.clone()));
+
+            assert_eq!(snapshot.get_latest_block()?, block);
+            assert_eq!(snapshot.get_block_by_height(1)?, block);
+            assert!(snapshot.get_block_by_height(2).is_err());
+            assert_eq!(snapshot.get_transactions(&tx_hashes)?, stxs);
+            assert_eq!(snapshot.get_block_receipts(1)?, receipts);
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(exec!(storage.get_latest_block()), next);
+    storage
+        .with_snapshot(|snapshot| {
+            assert_eq!(snapshot.get_latest_block()?, next);
+            assert!(snapshot.get_receipts(&tx_hashes).is_err());
+            Ok(())
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_rocks_adapter_backup_restore() {
+    let path = "rocksdb/test_rocks_adapter_backup_restore";
+    let backup_dir = "rocksdb/test_rocks_adapter_backup_restore_backups";
+    let restored = "rocksdb/test_rocks_adapter_backup_restore_restored";
+    for dir in [path, backup_dir, restored].iter() {
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    let first = mock_block(1, Hash::digest(get_random_bytes(10)));
+    let second = mock_block(2, Hash::digest(get_random_bytes(10)));
+    {
+        let adapter = Arc::new(RocksAdapter::new(path, 64).unwrap());
+        let storage = ImplStorage::new(Arc::clone(&adapter));
+
+        exec!(storage.insert_block(first.clone()));
+        adapter.create_backup(backup_dir).unwrap();
+        exec!(storage.insert_block(second.clone()));
+        adapter.create_backup(backup_dir).unwrap();
+        exec!(storage.remove_block(2, true));
+    }
+
+    let backups = RocksAdapter::list_backups(backup_dir).unwrap();
+    assert_eq!(backups.len(), 2);
+    assert!(backups[0].id < backups[1].id);
+    assert!(backups.iter().all(|backup| backup.size > 0));
+
+    // The source still holds files, restoring over it is refused
+    assert!(RocksAdapter::restore_from_backup(backup_dir, path).is_err());
+
+    RocksAdapter::restore_from_backup(backup_dir, restored).unwrap();
+    let storage = ImplStorage::new(Arc::new(RocksAdapter::new(restored, 64).unwrap()));
+    assert_eq!(exec!(storage.get_latest_block()), second);
+    assert_eq!(exec!(storage.get_block_by_height(1)), first);
+}
+
+#[test]
+fn test_adapter_contains_batch() {
+    let path = "rocksdb/test_adapter_contains_batch";
+    let _ = fs::remove_dir_all(path);
+
+    adapter_contains_batch_test(MemoryAdapter::new());
+    adapter_contains_batch_test(RocksAdapter::new(path, 64).unwrap());
+}
+
+fn adapter_contains_batch_test<Adapter: StorageAdapter>(adapter: Adapter) {
+    let storage = ImplStorage::new(Arc::new(adapter));
+
+    let hashes = (0..6)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let known = vec![hashes[1].clone(), hashes[2].clone(), hashes[5].clone()];
+    exec!(storage.insert_transactions(known.iter().cloned().map(mock_signed_tx).collect()));
+
+    let found = exec!(storage
+        .adapter
+        .contains_batch::<TransactionSchema>(hashes.clone()));
+    assert_eq!(found, vec![false, true, true, false, false, true]);
+
+    let unknown = exec!(storage.filter_unknown_transactions(hashes.clone()));
+    assert_eq!(unknown, vec![
+        hashes[0].clone(),
+        hashes[3].clone(),
+        hashes[4].clone()
+    ]);
+    assert!(exec!(storage.filter_unknown_transactions(known)).is_empty());
+}
+
+#[test]
+fn test_rocks_adapter_concurrent_gets() {
+    let path = "rocksdb/test_rocks_adapter_concurrent_gets";
+    let _ = fs::remove_dir_all(path);
+
+    let adapter = Arc::new(
+        RocksAdapter::new_with_config(path, RocksConfig {
+            io_threads: 2,
+            ..RocksConfig::default()
+        })
+        .unwrap(),
+    );
+    let stxs = (0..200)
+        .map(|_| mock_signed_tx(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+    let keys = stxs.iter().map(|stx| stx.tx_hash.clone()).collect();
+    let vals = stxs
+        .iter()
+        .map(|stx| StorageBatchModify::Insert(Sealed::Plain(stx.clone())))
+        .collect();
+    exec!(adapter.batch_modify::<TransactionSchema>(keys, vals));
+
+    // Every thread keeps far more gets in flight than there are io threads
+    let workers = stxs
+        .chunks(50)
+        .map(|chunk| {
+            let adapter = Arc::clone(&adapter);
+            let chunk = chunk.to_vec();
+            thread::spawn(move || {
+                let gets = chunk
+                    .iter()
+                    .map(|stx| adapter.get::<TransactionSchema>(stx.tx_hash.clone()));
+                let got = block_on(join_all(gets));
+
+                for (stx, got) in chunk.into_iter().zip(got.into_iter()) {
+                    assert_eq!(got.unwrap(), Some(Sealed::Plain(stx)));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for worker in workers.into_iter() {
+        worker.join().unwrap();
+    }
+}
+
+#[bench]
+fn bench_rocks_concurrent_gets_one_io_thread(b: &mut Bencher) {
+    bench_rocks_concurrent_gets(b, "rocksdb/bench_rocks_concurrent_gets_one", 1);
+}
+
+#[bench]
+fn bench_rocks_concurrent_gets_four_io_threads(b: &mut Bencher) {
+    bench_rocks_concurrent_gets(b, "rocksdb/bench_rocks_concurrent_gets_four", 4);
+}
+
+fn bench_rocks_concurrent_gets(b: &mut Bencher, path: &str, io_threads: usize) {
+    let _ = fs::remove_dir_all(path);
+    let adapter = Arc::new(
+        RocksAdapter::new_with_config(path, RocksConfig {
+            io_threads,
+            ..RocksConfig::default()
+        })
+        .unwrap(),
+    );
+    let hashes = (0..100)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let vals = hashes
+        .iter()
+        .map(|hash| StorageBatchModify::Insert(Sealed::Plain(mock_signed_tx(hash.clone()))))
+        .collect();
+    exec!(adapter.batch_modify::<TransactionSchema>(hashes.clone(), vals));
+
+    b.iter(|| {
+        let gets = hashes
+            .iter()
+            .map(|hash| adapter.get::<TransactionSchema>(hash.clone()));
+        let got = block_on(join_all(gets));
+        assert!(got.into_iter().all(|got| got.unwrap().is_some()));
+    });
+}
+
+#[derive(Default)]
+struct ManualClock {
+    millis: AtomicU64,
+}
+
+impl ManualClock {
+    fn advance(&self, millis: u64) {
+        self.millis.fetch_add(millis, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        Duration::from_millis(self.millis.load(Ordering::SeqCst))
+    }
+}
+
+#[test]
+fn test_memory_adapter_expiry() {
+    let clock = Arc::new(ManualClock::default());
+    let expiry = Expiry::new(
+        vec![(StorageCategory::SignedTransaction, Duration::from_secs(1))],
+        Arc::clone(&clock) as Arc<dyn Clock>,
+    );
+    let adapter = Arc::new(MemoryAdapter::new().with_expiry(expiry));
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let stx = mock_signed_tx(tx_hash.clone());
+    let receipt = mock_receipt(tx_hash.clone());
+    exec!(adapter.insert::<TransactionSchema>(tx_hash.clone(), Sealed::Plain(stx.clone())));
+    exec!(adapter.insert::<ReceiptSchema>(tx_hash.clone(), receipt.clone()));
+
+    clock.advance(500);
+    assert_eq!(
+        exec!(adapter.get::<TransactionSchema>(tx_hash.clone())),
+        Some(Sealed::Plain(stx))
+    );
+
+    clock.advance(600);
+    assert_eq!(
+        exec!(adapter.get::<TransactionSchema>(tx_hash.clone())),
+        None
+    );
+    assert!(!exec!(
+        adapter.contains::<TransactionSchema>(tx_hash.clone())
+    ));
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let err = block_on(storage.get_transaction_by_hash(tx_hash.clone())).unwrap_err();
+    assert!(err.to_string().contains("NotFound"));
+
+    // Only the category with a ttl expires
+    assert_eq!(exec!(storage.get_receipt(tx_hash)), receipt);
+
+    assert_eq!(
+        exec!(adapter.purge_expired(StorageCategory::SignedTransaction)),
+        1
+    );
+    assert_eq!(
+        exec!(adapter.purge_expired(StorageCategory::SignedTransaction)),
+        0
+    );
+    assert_eq!(exec!(adapter.purge_expired(StorageCategory::Receipt)), 0);
+    let sizes = exec!(adapter.approximate_sizes());
+    assert_eq!(sizes[StorageCategory::SignedTransaction as usize].1, 0);
+}
+
+// Values stored before their category had a ttl read as they are and never
+// expire, the ones stored after do.
+#[test]
+fn test_memory_adapter_expiry_untagged() {
+    let adapter = MemoryAdapter::new();
+    let old_hash = Hash::digest(get_random_bytes(10));
+    let old_receipt = mock_receipt(old_hash.clone());
+    exec!(adapter.insert::<ReceiptSchema>(old_hash.clone(), old_receipt.clone()));
+
+    let clock = Arc::new(ManualClock::default());
+    let expiry = Expiry::new(
+        vec![(StorageCategory::Receipt, Duration::from_secs(1))],
+        Arc::clone(&clock) as Arc<dyn Clock>,
+    );
+    let adapter = adapter.with_expiry(expiry);
+    let new_hash = Hash::digest(get_random_bytes(10));
+    exec!(adapter.insert::<ReceiptSchema>(new_hash.clone(), mock_receipt(new_hash.clone())));
+
+    clock.advance(1000);
+    assert_eq!(
+        exec!(adapter.get::<ReceiptSchema>(old_hash.clone())),
+        Some(old_receipt)
+    );
+    assert_eq!(exec!(adapter.get::<ReceiptSchema>(new_hash)), None);
+    assert_eq!(exec!(adapter.purge_expired(StorageCategory::Receipt)), 1);
+    assert!(exec!(adapter.contains::<ReceiptSchema>(old_hash)));
+}
+
+// The transaction pool expires by default, its entries stored without a ttl
+// before still load.
+#[test]
+fn test_rocks_adapter_pool_ttl() {
+    let path = "rocksdb/test_rocks_adapter_pool_ttl";
+    let _ = fs::remove_dir_all(path);
+    assert_eq!(RocksConfig::default().ttls, vec![(
+        StorageCategory::TransactionPool,
+        DEFAULT_POOL_TTL
+    )]);
+
+    let old_stx = mock_signed_tx(Hash::digest(get_random_bytes(10)));
+    {
+        let adapter = RocksAdapter::new_with_config(path, RocksConfig {
+            ttls: Vec::new(),
+            ..RocksConfig::default()
+        })
+        .unwrap();
+        let storage = ImplStorage::new(Arc::new(adapter));
+        exec!(storage.update_pool_transactions(vec![old_stx.clone()], vec![]));
+    }
+
+    let storage = ImplStorage::new(Arc::new(RocksAdapter::new(path, 64).unwrap()));
+    assert_eq!(
+        exec!(storage.get_pool_transactions()),
+        vec![old_stx.clone()]
+    );
+
+    let new_stx = mock_signed_tx(Hash::digest(get_random_bytes(10)));
+    exec!(storage.update_pool_transactions(vec![new_stx.clone()], vec![]));
+    let mut stxs = exec!(storage.get_pool_transactions());
+    stxs.sort_by_key(|stx| stx.tx_hash.clone());
+    let mut expected = vec![old_stx, new_stx];
+    expected.sort_by_key(|stx| stx.tx_hash.clone());
+    assert_eq!(stxs, expected);
+}
+
+#[test]
+fn test_memory_adapter_approximate_sizes() {
+    let db = MemoryAdapter::new();
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    exec!(db.insert::<TransactionSchema>(
+        tx_hash.clone(),
+        Sealed::Plain(mock_signed_tx(tx_hash.clone()))
+    ));
+
+    let sizes = exec!(db.approximate_sizes());
+    assert_eq!(sizes.len(), 8);
+    for (category, size) in sizes.into_iter() {
+        assert_eq!(size > 0, category == StorageCategory::SignedTransaction);
+    }
+
+    exec!(db.compact(StorageCategory::SignedTransaction));
+    exec!(db.remove::<TransactionSchema>(tx_hash));
+    assert!(exec!(db.approximate_sizes())
+        .iter()
+        .all(|(_, size)| *size == 0));
+}
+
+#[test]
+fn test_rocks_adapter_maintenance() {
+    let path = "rocksdb/test_rocks_adapter_maintenance";
+    let _ = fs::remove_dir_all(path);
+    let storage = ImplStorage::new(Arc::new(RocksAdapter::new(path, 64).unwrap()));
+
+    for height in 1..=10 {
+        exec!(storage.insert_block(mock_block(height, Hash::digest(get_random_bytes(10)))));
+    }
+    exec!(storage.prune_below(5, &[StorageCategory::Block]));
+
+    let sizes = exec!(storage.storage_maintenance(&[StorageCategory::Block]));
+    let categories = sizes.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+    assert_eq!(categories, vec![
+        StorageCategory::Block,
+        StorageCategory::Receipt,
+        StorageCategory::SignedTransaction,
+        StorageCategory::Wal,
+        StorageCategory::EventIndex,
+        StorageCategory::TransactionPool,
+        StorageCategory::Evidence,
+        StorageCategory::Liveness,
+    ]);
+
+    // Compaction wrote the remaining blocks to sst files
+    assert!(sizes[0].1 > 0);
+    assert_eq!(exec!(storage.get_latest_block()).header.height, 10);
+}
+
+#[test]
+fn test_adapter_iter_prefix() {
+    adapter_iter_prefix_test(MemoryAdapter::new());
+
+    let path = "rocksdb/test_adapter_iter_prefix";
+    let _ = fs::remove_dir_all(path);
+    adapter_iter_prefix_test(RocksAdapter::new(path, 64).unwrap())
+}
+
+fn adapter_iter_prefix_test(db: impl StorageAdapter) {
+    let mut batch = StorageBatch::new();
+    for key in ["a", "b1", "b2", "b3", "b4", "c"].iter() {
+        let key = Bytes::from(key.to_string());
+        batch.insert::<RawWalSchema>(key.clone(), key).unwrap();
+    }
+    // Same key in another category must stay out of the results
+    batch
+        .insert::<RawReceiptSchema>(Bytes::from("b0"), Bytes::from("b0"))
+        .unwrap();
+    exec!(db.write_batch(batch));
+
+    let keys = |pairs: Vec<(Bytes, Bytes)>| {
+        pairs
+            .into_iter()
+            .map(|(key, value)| {
+                assert_eq!(key, value);
+                String::from_utf8(key.to_vec()).unwrap()
+            })
+            .collect::<Vec<_>>()
+    };
+    let wal = StorageCategory::Wal;
+
+    let all = keys(exec!(db.iter_prefix(wal, b"", None, 100)));
+    assert_eq!(all, vec!["a", "b1", "b2", "b3", "b4", "c"]);
+
+    let page = keys(exec!(db.iter_prefix(wal, b"b", None, 2)));
+    assert_eq!(page, vec!["b1", "b2"]);
+
+    // A key inserted before the page doesn't shift the next one
+    exec!(db.insert::<RawWalSchema>(Bytes::from("b0"), Bytes::from("b0")));
+    let next = keys(exec!(db.iter_prefix(wal, b"b", Some(b"b2".to_vec()), 2)));
+    assert_eq!(next, vec!["b3", "b4"]);
+
+    let last = keys(exec!(db.iter_prefix(wal, b"b", Some(b"b4".to_vec()), 2)));
+    assert!(last.is_empty());
+
+    // Continuing from a key outside the prefix starts at the prefix
+    let from_a = keys(exec!(db.iter_prefix(wal, b"b", Some(b"a".to_vec()), 1)));
+    assert_eq!(from_a, vec!["b0"]);
+
+    assert!(exec!(db.iter_prefix(wal, b"d", None, 10)).is_empty());
+}
+
 fn adapter_insert_test(db: impl StorageAdapter) {
     let tx_hash = Hash::digest(get_random_bytes(10));
     let stx = mock_signed_tx(tx_hash.clone());
 
-    exec!(db.insert::<TransactionSchema>(tx_hash.clone(), stx.clone()));
-    let stx = exec!(db.get::<TransactionSchema>(tx_hash.clone())).unwrap();
+    exec!(db.insert::<TransactionSchema>(tx_hash.clone(), Sealed::Plain(stx.clone())));
+    let stored = exec!(db.get::<TransactionSchema>(tx_hash.clone())).unwrap();
 
-    assert_eq!(tx_hash, stx.tx_hash);
+    assert_eq!(stored, Sealed::Plain(stx));
 }
 
 fn adapter_batch_modify_test(db: impl StorageAdapter) {
@@ -46,7 +655,9 @@ fn adapter_batch_modify_test(db: impl StorageAdapter) {
         hashes.push(tx_hash.clone());
         let stx = mock_signed_tx(tx_hash.clone());
         stxs.push(stx.clone());
-        inserts.push(StorageBatchModify::Insert::<TransactionSchema>(stx));
+        inserts.push(StorageBatchModify::Insert::<TransactionSchema>(
+            Sealed::Plain(stx),
+        ));
     }
 
     exec!(db.batch_modify::<TransactionSchema>(hashes.clone(), inserts));
@@ -54,8 +665,8 @@ fn adapter_batch_modify_test(db: impl StorageAdapter) {
 
     for i in 0..10 {
         assert_eq!(
-            stxs.get(i).unwrap().tx_hash,
-            opt_stxs.get(i).unwrap().as_ref().unwrap().tx_hash
+            Sealed::Plain(stxs.get(i).unwrap().clone()),
+            opt_stxs.get(i).unwrap().clone().unwrap()
         );
     }
 }
@@ -66,7 +677,7 @@ fn adapter_remove_test(db: impl StorageAdapter) {
     assert!(!is_exist);
 
     let stx = &mock_signed_tx(tx_hash.clone());
-    exec!(db.insert::<TransactionSchema>(tx_hash.clone(), stx.clone()));
+    exec!(db.insert::<TransactionSchema>(tx_hash.clone(), Sealed::Plain(stx.clone())));
     let is_exist = exec!(db.contains::<TransactionSchema>(tx_hash.clone()));
     assert!(is_exist);
 
@@ -74,3 +685,25 @@ fn adapter_remove_test(db: impl StorageAdapter) {
     let is_exist = exec!(db.contains::<TransactionSchema>(tx_hash.clone()));
     assert!(!is_exist);
 }
+
+struct RawWalSchema;
+
+impl StorageSchema for RawWalSchema {
+    type Key = Bytes;
+    type Value = Bytes;
+
+    fn category() -> StorageCategory {
+        StorageCategory::Wal
+    }
+}
+
+struct RawReceiptSchema;
+
+impl StorageSchema for RawReceiptSchema {
+    type Key = Bytes;
+    type Value = Bytes;
+
+    fn category() -> StorageCategory {
+        StorageCategory::Receipt
+    }
+}
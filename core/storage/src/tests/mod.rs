@@ -7,6 +7,7 @@ macro_rules! exec {
 }
 
 mod adapter;
+mod snapshot;
 mod storage;
 
 use rand::random;
@@ -0,0 +1,214 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::executor::block_on;
+
+use common_merkle::Merkle;
+use protocol::fixed_codec::FixedCodec;
+use protocol::traits::{ChainStats, Storage};
+use protocol::types::{Block, Hash, SignedTransaction};
+
+use crate::adapter::memory::MemoryAdapter;
+use crate::tests::{get_random_bytes, mock_block, mock_receipt, mock_signed_tx};
+use crate::ImplStorage;
+
+#[test]
+fn test_snapshot_round_trip() {
+    let source = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let blocks = insert_chain(&source, 10);
+    let path = snapshot_path();
+
+    exec!(source.export_chain(&path, 1, 10));
+
+    let target = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    exec!(target.import_chain(&path));
+    fs::remove_file(&path).unwrap();
+
+    for block in blocks.iter() {
+        let height = block.header.height;
+        assert_eq!(&exec!(target.get_block_by_height(height)), block);
+
+        let hashes = block.ordered_tx_hashes.clone();
+        assert_eq!(
+            exec!(target.get_transactions(hashes.clone())),
+            exec!(source.get_transactions(hashes.clone()))
+        );
+        assert_eq!(
+            exec!(target.get_receipts(hashes.clone())),
+            exec!(source.get_receipts(hashes))
+        );
+    }
+    assert_eq!(exec!(target.get_latest_block()), blocks[9]);
+    assert_eq!(
+        exec!(target.get_chain_stats()),
+        exec!(source.get_chain_stats())
+    );
+}
+
+#[test]
+fn test_snapshot_truncated() {
+    let source = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    insert_chain(&source, 10);
+    let path = snapshot_path();
+
+    exec!(source.export_chain(&path, 1, 10));
+    let bytes = fs::read(&path).unwrap();
+    fs::write(&path, &bytes[..bytes.len() - 10]).unwrap();
+
+    let target = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let err = block_on(target.import_chain(&path))
+        .unwrap_err()
+        .to_string();
+    fs::remove_file(&path).unwrap();
+
+    assert!(err.contains("truncated"));
+    assert!(block_on(target.get_latest_block()).is_err());
+}
+
+#[test]
+fn test_snapshot_must_continue_chain() {
+    let source = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    insert_chain(&source, 10);
+    let path = snapshot_path();
+
+    exec!(source.export_chain(&path, 5, 10));
+
+    // The target ends at height 2, so height 5 doesn't follow
+    let target = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    insert_chain(&target, 2);
+    let err = block_on(target.import_chain(&path))
+        .unwrap_err()
+        .to_string();
+    fs::remove_file(&path).unwrap();
+
+    assert!(err.contains("InvalidSnapshot"));
+    assert_eq!(exec!(target.get_latest_block()).header.height, 2);
+}
+
+// The latest block isn't executed yet, it is exported without receipts
+#[test]
+fn test_snapshot_unexecuted_block() {
+    let source = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let blocks = insert_chain(&source, 3);
+
+    let txs = (0..2).map(|_| chain_tx()).collect::<Vec<_>>();
+    let tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+    let block = next_block(&blocks[2], tx_hashes.clone());
+    exec!(source.insert_transactions(txs));
+    exec!(source.insert_block(block.clone()));
+    let path = snapshot_path();
+
+    exec!(source.export_chain(&path, 1, 4));
+
+    let target = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    exec!(target.import_chain(&path));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(exec!(target.get_latest_block()), block);
+    assert_eq!(
+        exec!(target.get_transactions(tx_hashes.clone())),
+        exec!(source.get_transactions(tx_hashes.clone()))
+    );
+    assert!(block_on(target.get_receipts(tx_hashes)).is_err());
+    let hashes = blocks[2].ordered_tx_hashes.clone();
+    assert_eq!(
+        exec!(target.get_receipts(hashes.clone())),
+        exec!(source.get_receipts(hashes))
+    );
+    assert_eq!(
+        exec!(target.get_chain_stats()),
+        exec!(source.get_chain_stats())
+    );
+}
+
+// A block whose transactions don't match what it orders is refused, and none
+// of the blocks before it are written.
+#[test]
+fn test_snapshot_forged_txs() {
+    let forgeries: [fn(&mut Block, &mut Vec<SignedTransaction>); 2] = [
+        |_, txs| txs[0].raw.cycles_limit += 1,
+        |block, _| block.header.order_root = Hash::digest(get_random_bytes(10)),
+    ];
+
+    for forge in forgeries.iter() {
+        let source = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+        let blocks = insert_chain(&source, 3);
+
+        let mut txs = (0..2).map(|_| chain_tx()).collect::<Vec<_>>();
+        let mut block = next_block(
+            &blocks[2],
+            txs.iter().map(|tx| tx.tx_hash.clone()).collect(),
+        );
+        forge(&mut block, &mut txs);
+        exec!(source.insert_transactions(txs));
+        exec!(source.insert_block(block));
+        let path = snapshot_path();
+
+        exec!(source.export_chain(&path, 1, 4));
+
+        let target = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+        let err = block_on(target.import_chain(&path))
+            .unwrap_err()
+            .to_string();
+        fs::remove_file(&path).unwrap();
+
+        assert!(err.contains("InvalidSnapshot"));
+        assert!(block_on(target.get_latest_block()).is_err());
+        assert!(block_on(target.get_block_by_height(1)).is_err());
+        assert_eq!(exec!(target.get_chain_stats()), ChainStats::default());
+    }
+}
+
+// Blocks 1..=count, each linked to the previous one and carrying two
+// transactions with receipts
+fn insert_chain(storage: &ImplStorage<MemoryAdapter>, count: u64) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for height in 1..=count {
+        let txs = (0..2).map(|_| chain_tx()).collect::<Vec<_>>();
+        let tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+        let block = match blocks.last() {
+            Some(prev) => next_block(prev, tx_hashes.clone()),
+            None => {
+                let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+                block.header.order_root = Merkle::ordered_root(&tx_hashes);
+                block.ordered_tx_hashes = tx_hashes.clone();
+                block
+            }
+        };
+
+        exec!(storage.insert_transactions_with_receipts(
+            txs,
+            tx_hashes.into_iter().map(mock_receipt).collect(),
+        ));
+        exec!(storage.insert_block(block.clone()));
+        blocks.push(block);
+    }
+
+    blocks
+}
+
+fn next_block(prev: &Block, tx_hashes: Vec<Hash>) -> Block {
+    let mut block = mock_block(prev.header.height + 1, Hash::digest(get_random_bytes(10)));
+    block.header.pre_hash = Hash::digest(prev.encode_fixed().unwrap());
+    block.header.order_root = Merkle::ordered_root(&tx_hashes);
+    block.ordered_tx_hashes = tx_hashes;
+    block
+}
+
+// A transaction stored under the hash of its content
+fn chain_tx() -> SignedTransaction {
+    let mut tx = mock_signed_tx(Hash::from_empty());
+    tx.raw.nonce = Hash::digest(get_random_bytes(10));
+    tx.tx_hash = Hash::digest(tx.raw.encode_fixed().unwrap());
+    tx
+}
+
+fn snapshot_path() -> PathBuf {
+    let name = format!(
+        "muta-snapshot-{}",
+        Hash::digest(get_random_bytes(10)).as_hex()
+    );
+    std::env::temp_dir().join(name)
+}
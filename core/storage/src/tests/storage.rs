@@ -1,12 +1,28 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::thread;
+
+use async_trait::async_trait;
+use derive_more::Display;
+use futures::executor::block_on;
+use rand::seq::SliceRandom;
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::Storage;
-use protocol::types::Hash;
+use protocol::traits::{
+    ChainStats, Storage, StorageAdapter, StorageBatch, StorageBatchModify, StorageCategory,
+    StorageSchema, StorageSnapshot, TransactionPosition,
+};
+use protocol::types::{Event, Hash, SignedTransaction};
+use protocol::{Bytes, ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::adapter::memory::MemoryAdapter;
+use crate::checksum::Sealed;
+use crate::metrics::CounterMetrics;
 use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
-use crate::ImplStorage;
+use crate::{
+    pruned_height_key, BlockSchema, ImplStorage, PrunedHeightSchema, StorageError, MAX_BLOCKS_RANGE,
+};
 
 #[test]
 fn test_storage_block_insert() {
@@ -28,6 +44,63 @@ fn test_storage_block_insert() {
     assert_eq!(height, block.header.height);
 }
 
+#[test]
+fn test_storage_get_header() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let block_hash = Hash::digest(get_random_bytes(10));
+    let block = mock_block(1, block_hash);
+    let hash = Hash::digest(block.encode_fixed().unwrap());
+    exec!(storage.insert_block(block.clone()));
+
+    // Drop the body, the header path must not need it
+    exec!(storage.adapter.remove::<BlockSchema>(1));
+
+    assert_eq!(exec!(storage.get_header_by_height(1)), block.header);
+    assert_eq!(exec!(storage.get_header_by_hash(hash)), block.header);
+    assert!(block_on(storage.get_block_by_height(1)).is_err());
+}
+
+#[test]
+fn test_storage_get_header_without_header_key() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    // Blocks written by an older version have no separate header
+    let block = mock_block(2, Hash::digest(get_random_bytes(10)));
+    exec!(storage
+        .adapter
+        .insert::<BlockSchema>(2, Sealed::Plain(block.clone())));
+
+    assert_eq!(exec!(storage.get_header_by_height(2)), block.header);
+}
+
+#[test]
+fn test_storage_get_blocks() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    for height in (1..=10).chain(12..=12) {
+        let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        exec!(storage.insert_block(block));
+    }
+
+    let blocks = exec!(storage.get_blocks(1, 11));
+    let heights = blocks
+        .iter()
+        .map(|block| block.header.height)
+        .collect::<Vec<_>>();
+    assert_eq!(heights, (1..11).collect::<Vec<_>>());
+    assert!(exec!(storage.get_blocks(5, 5)).is_empty());
+
+    // Height 11 is missing
+    let err = block_on(storage.get_blocks(8, 13)).unwrap_err().to_string();
+    assert!(err.contains(r#"NotFound { category: Block, keys: ["11"] }"#));
+
+    let err = block_on(storage.get_blocks(1, 1 + MAX_BLOCKS_RANGE + 1))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("RangeTooLarge"));
+}
+
 #[test]
 fn test_storage_receipts_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -78,6 +151,258 @@ fn test_storage_transactions_insert() {
     }
 }
 
+#[test]
+fn test_storage_pool_transactions() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let transactions = (0..10)
+        .map(|_| mock_signed_tx(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+    let hashes = |txs: &[SignedTransaction]| {
+        let mut hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+        hashes.sort();
+        hashes
+    };
+
+    exec!(storage.update_pool_transactions(transactions[..8].to_vec(), vec![]));
+    assert_eq!(
+        hashes(&exec!(storage.get_pool_transactions())),
+        hashes(&transactions[..8])
+    );
+
+    // Removals go first, so a transaction removed and inserted again stays
+    let removed = hashes(&transactions[..3]);
+    let inserted = transactions[2..].to_vec();
+    exec!(storage.update_pool_transactions(inserted, removed));
+    assert_eq!(
+        hashes(&exec!(storage.get_pool_transactions())),
+        hashes(&transactions[2..])
+    );
+
+    // Kept apart from the committed transactions
+    assert!(block_on(storage.get_transaction_by_hash(transactions[5].tx_hash.clone())).is_err());
+}
+
+#[test]
+fn test_storage_not_found_names_key() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let err = block_on(storage.get_block_by_height(7))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(r#"NotFound { category: Block, keys: ["7"] }"#));
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let err = block_on(storage.get_receipt(tx_hash.clone()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("category: Receipt"));
+    assert!(err.contains(&tx_hash.as_hex()));
+
+    let err = StorageError::NotFound {
+        category: StorageCategory::SignedTransaction,
+        keys:     vec![tx_hash.as_hex()],
+    };
+    assert_eq!(
+        err.to_string(),
+        format!("SignedTransaction not found [\"{}\"]", tx_hash.as_hex())
+    );
+}
+
+#[test]
+fn test_storage_get_batch_order() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let tx_hashes = (0..20)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let receipt_hashes = (0..20)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    exec!(storage.insert_transactions(tx_hashes.iter().cloned().map(mock_signed_tx).collect()));
+    exec!(storage.insert_receipts(receipt_hashes.iter().cloned().map(mock_receipt).collect()));
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..10 {
+        let mut request = tx_hashes.clone();
+        request.shuffle(&mut rng);
+        let stxs = exec!(storage.get_transactions(request.clone()));
+        let got = stxs.into_iter().map(|stx| stx.tx_hash).collect::<Vec<_>>();
+        assert_eq!(got, request);
+
+        let mut request = receipt_hashes.clone();
+        request.shuffle(&mut rng);
+        let receipts = exec!(storage.get_receipts(request.clone()));
+        let got = receipts
+            .into_iter()
+            .map(|receipt| receipt.tx_hash)
+            .collect::<Vec<_>>();
+        assert_eq!(got, request);
+    }
+}
+
+#[test]
+fn test_storage_get_batch_duplicates() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let a = Hash::digest(get_random_bytes(10));
+    let b = Hash::digest(get_random_bytes(10));
+    exec!(storage.insert_transactions(vec![mock_signed_tx(a.clone()), mock_signed_tx(b.clone())]));
+
+    let request = vec![a.clone(), b.clone(), a.clone(), a.clone(), b];
+    let stxs = exec!(storage.get_transactions(request.clone()));
+    let got = stxs.into_iter().map(|stx| stx.tx_hash).collect::<Vec<_>>();
+    assert_eq!(got, request);
+}
+
+#[test]
+fn test_storage_get_batch_missing() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let hashes = (0..5)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let transactions = vec![
+        mock_signed_tx(hashes[0].clone()),
+        mock_signed_tx(hashes[2].clone()),
+        mock_signed_tx(hashes[4].clone()),
+    ];
+    exec!(storage.insert_transactions(transactions));
+
+    let present = vec![hashes[4].clone(), hashes[0].clone(), hashes[2].clone()];
+    let stxs = exec!(storage.get_transactions(present.clone()));
+    let stx_hashes = stxs.into_iter().map(|stx| stx.tx_hash).collect::<Vec<_>>();
+    assert_eq!(stx_hashes, present);
+
+    let err = block_on(storage.get_transactions(hashes.clone()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(&hashes[1].as_hex()));
+    assert!(err.contains(&hashes[3].as_hex()));
+    for hash in [&hashes[0], &hashes[2], &hashes[4]].iter() {
+        assert!(!err.contains(&hash.as_hex()));
+    }
+
+    // The memory adapter shares one key space, so receipts need their own
+    // hashes
+    let receipt_hashes = vec![
+        Hash::digest(get_random_bytes(10)),
+        Hash::digest(get_random_bytes(10)),
+    ];
+    exec!(storage.insert_receipts(vec![mock_receipt(receipt_hashes[1].clone())]));
+
+    let err = block_on(storage.get_receipts(receipt_hashes.clone()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(&receipt_hashes[0].as_hex()));
+    assert!(!err.contains(&receipt_hashes[1].as_hex()));
+}
+
+#[test]
+fn test_storage_remove_transactions_and_receipts() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let tx_hashes = (0..4)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let receipt_hashes = (0..4)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    exec!(storage.insert_transactions(tx_hashes.iter().cloned().map(mock_signed_tx).collect()));
+    exec!(storage.insert_receipts(receipt_hashes.iter().cloned().map(mock_receipt).collect()));
+
+    exec!(storage.remove_transactions(tx_hashes[..2].to_vec()));
+    exec!(storage.remove_receipts(receipt_hashes[2..].to_vec()));
+
+    for hash in tx_hashes[..2].iter() {
+        assert!(block_on(storage.get_transaction_by_hash(hash.clone())).is_err());
+    }
+    exec!(storage.get_transactions(tx_hashes[2..].to_vec()));
+
+    for hash in receipt_hashes[2..].iter() {
+        assert!(block_on(storage.get_receipt(hash.clone())).is_err());
+    }
+    exec!(storage.get_receipts(receipt_hashes[..2].to_vec()));
+}
+
+#[test]
+fn test_storage_remove_block() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let mut block_hashes = vec![];
+    for height in 1..=3 {
+        let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block_hashes.push(Hash::digest(block.encode_fixed().unwrap()));
+        exec!(storage.insert_block(block));
+    }
+
+    // The latest block is protected
+    assert!(block_on(storage.remove_block(3, false)).is_err());
+    assert_eq!(exec!(storage.get_block_by_height(3)).header.height, 3);
+
+    exec!(storage.remove_block(1, false));
+    assert!(block_on(storage.get_block_by_height(1)).is_err());
+    assert!(block_on(storage.get_block_by_hash(block_hashes[0].clone())).is_err());
+    assert_eq!(exec!(storage.get_latest_block()).header.height, 3);
+
+    exec!(storage.remove_block(3, true));
+    assert!(block_on(storage.get_block_by_height(3)).is_err());
+    assert!(block_on(storage.get_block_by_hash(block_hashes[2].clone())).is_err());
+    assert_eq!(exec!(storage.get_latest_block()).header.height, 2);
+
+    assert!(block_on(storage.remove_block(1, true)).is_err());
+}
+
+#[test]
+fn test_storage_get_block_receipts() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let tx_hashes = (0..5)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let receipts = tx_hashes
+        .iter()
+        .map(|hash| mock_receipt(hash.clone()))
+        .collect::<Vec<_>>();
+    exec!(storage.insert_receipts(receipts.clone()));
+
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes;
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+    exec!(storage.insert_block(block));
+
+    assert_eq!(exec!(storage.get_block_receipts(1)), receipts);
+    assert_eq!(
+        exec!(storage.get_block_receipts_by_hash(block_hash)),
+        receipts
+    );
+}
+
+#[test]
+fn test_storage_get_block_receipts_missing() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let tx_hashes = (0..3)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    // The last receipt hasn't been written yet
+    let receipts = tx_hashes[..2]
+        .iter()
+        .map(|hash| mock_receipt(hash.clone()))
+        .collect::<Vec<_>>();
+    exec!(storage.insert_receipts(receipts));
+
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes.clone();
+    exec!(storage.insert_block(block));
+
+    let err = block_on(storage.get_block_receipts(1))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(&format!("{:?}", tx_hashes[2])));
+    assert!(!err.contains(&format!("{:?}", tx_hashes[0])));
+}
+
 #[test]
 fn test_storage_latest_proof_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -91,6 +416,53 @@ fn test_storage_latest_proof_insert() {
     assert_eq!(proof.block_hash, proof_2.block_hash);
 }
 
+#[test]
+fn test_storage_latest_proof_restart() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let err = block_on(storage.get_latest_proof())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("NotFound"));
+    assert!(err.contains(&crate::LATEST_PROOF_KEY.as_hex()));
+
+    let proof = mock_proof(Hash::digest(get_random_bytes(10)));
+    exec!(storage.update_latest_proof(proof.clone()));
+
+    // A restarted node reads the proof back from the database
+    let storage = ImplStorage::new(adapter);
+    assert_eq!(exec!(storage.get_latest_proof()), proof);
+}
+
+#[test]
+fn test_storage_latest_block_and_hash_lookup() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let err = block_on(storage.get_latest_block())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("NotFound"));
+    assert!(err.contains(&crate::LATEST_BLOCK_KEY.as_hex()));
+
+    let missing_hash = Hash::digest(get_random_bytes(10));
+    let err = block_on(storage.get_block_by_hash(missing_hash.clone()))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("NotFound"));
+    assert!(err.contains(&missing_hash.as_hex()));
+
+    let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+    exec!(storage.insert_block(block.clone()));
+
+    // Both lookups must survive a restart, not only the in-memory latest
+    let storage = ImplStorage::new(adapter);
+    assert_eq!(exec!(storage.get_latest_block()), block);
+    assert_eq!(exec!(storage.get_block_by_hash(block_hash)), block);
+}
+
 #[test]
 fn test_storage_wal_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -100,3 +472,955 @@ fn test_storage_wal_insert() {
     let info_2 = exec!(storage.load_overlord_wal());
     assert_eq!(info, info_2);
 }
+
+#[test]
+fn test_storage_evidence() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    assert!(exec!(storage.load_evidence()).is_empty());
+
+    let evidence = get_random_bytes(64);
+    exec!(storage.update_evidence(evidence.clone()));
+    assert_eq!(exec!(storage.load_evidence()), evidence);
+
+    // The latest update replaces the evidence kept before
+    let evidence = get_random_bytes(32);
+    exec!(storage.update_evidence(evidence.clone()));
+    assert_eq!(exec!(storage.load_evidence()), evidence);
+}
+
+#[test]
+fn test_storage_liveness() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    assert!(exec!(storage.load_liveness()).is_empty());
+
+    // Kept apart from the evidence
+    let missed = get_random_bytes(64);
+    exec!(storage.update_evidence(get_random_bytes(32)));
+    exec!(storage.update_liveness(missed.clone()));
+    assert_eq!(exec!(storage.load_liveness()), missed);
+}
+
+#[test]
+fn test_storage_insert_block_atomic() {
+    // Let the adapter crash after each possible number of writes
+    for allowed_writes in 0..3 {
+        let adapter = Arc::new(FaultAdapter::new(allowed_writes));
+        let storage = ImplStorage::new(Arc::clone(&adapter));
+
+        let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+        let block_hash = Hash::digest(block.encode_fixed().unwrap());
+        let stored = block_on(storage.insert_block(block)).is_ok();
+
+        let by_height = block_on(storage.get_block_by_height(1)).is_ok();
+        let by_hash = block_on(storage.get_block_by_hash(block_hash)).is_ok();
+        let latest = block_on(
+            adapter
+                .inner
+                .get::<crate::LatestBlockSchema>(crate::LATEST_BLOCK_KEY.clone()),
+        )
+        .unwrap()
+        .is_some();
+
+        assert_eq!(by_height, stored);
+        assert_eq!(by_hash, stored);
+        assert_eq!(latest, stored);
+    }
+}
+
+#[test]
+fn test_storage_insert_transactions_with_receipts_atomic() {
+    for allowed_writes in 0..2 {
+        let storage = ImplStorage::new(Arc::new(FaultAdapter::new(allowed_writes)));
+
+        let tx_hashes = (0..3)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipt_hashes = (0..3)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let stored = block_on(storage.insert_transactions_with_receipts(
+            tx_hashes.iter().cloned().map(mock_signed_tx).collect(),
+            receipt_hashes.iter().cloned().map(mock_receipt).collect(),
+        ))
+        .is_ok();
+
+        for hash in tx_hashes.into_iter() {
+            assert_eq!(
+                block_on(storage.get_transaction_by_hash(hash)).is_ok(),
+                stored
+            );
+        }
+        for hash in receipt_hashes.into_iter() {
+            assert_eq!(block_on(storage.get_receipt(hash)).is_ok(), stored);
+        }
+    }
+}
+
+#[test]
+fn test_storage_insert_block_data_atomic() {
+    for allowed_writes in 0..2 {
+        let storage = ImplStorage::new(Arc::new(FaultAdapter::new(allowed_writes)));
+
+        let tx_hashes = (0..3)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipt_hashes = (0..3)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = tx_hashes.clone();
+
+        let stored = block_on(storage.insert_block_data(
+            block,
+            tx_hashes.iter().cloned().map(mock_signed_tx).collect(),
+            receipt_hashes.iter().cloned().map(mock_receipt).collect(),
+        ))
+        .is_ok();
+
+        assert_eq!(block_on(storage.get_block_by_height(1)).is_ok(), stored);
+        assert_eq!(block_on(storage.get_latest_block()).is_ok(), stored);
+        for hash in tx_hashes.into_iter() {
+            assert_eq!(
+                block_on(storage.get_transaction_by_hash(hash)).is_ok(),
+                stored
+            );
+        }
+        for hash in receipt_hashes.into_iter() {
+            assert_eq!(block_on(storage.get_receipt(hash)).is_ok(), stored);
+        }
+    }
+}
+
+#[test]
+fn test_storage_insert_blocks_data_atomic() {
+    for allowed_writes in 0..2 {
+        let storage = ImplStorage::new(Arc::new(FaultAdapter::new(allowed_writes)));
+
+        let blocks = (1..=3)
+            .map(|height| {
+                let tx_hash = Hash::digest(get_random_bytes(10));
+                let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+                block.ordered_tx_hashes = vec![tx_hash.clone()];
+                (block, vec![mock_signed_tx(tx_hash.clone())], vec![
+                    mock_receipt(tx_hash),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let stored = block_on(storage.insert_blocks_data(blocks.clone())).is_ok();
+
+        for (block, _, receipts) in blocks.into_iter() {
+            let height = block.header.height;
+            let tx_hash = block.ordered_tx_hashes[0].clone();
+
+            assert_eq!(
+                block_on(storage.get_block_by_height(height)).is_ok(),
+                stored
+            );
+            assert_eq!(
+                block_on(storage.get_transaction_by_hash(tx_hash)).is_ok(),
+                stored
+            );
+            assert_eq!(
+                block_on(storage.get_receipt(receipts[0].tx_hash.clone())).is_ok(),
+                stored
+            );
+        }
+
+        if stored {
+            assert_eq!(exec!(storage.get_latest_block()).header.height, 3);
+            let stats = exec!(storage.get_chain_stats());
+            assert_eq!(stats.total_blocks, 3);
+            assert_eq!(stats.total_txs, 3);
+            assert_eq!(stats.total_receipts, 3);
+        } else {
+            assert!(block_on(storage.get_latest_block()).is_err());
+        }
+    }
+}
+
+#[test]
+fn test_storage_checksum_detects_corruption() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter)).with_checksum(true);
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = vec![tx_hash.clone()];
+    exec!(storage.insert_block_data(block.clone(), vec![mock_signed_tx(tx_hash.clone())], vec![]));
+
+    // Read back uncached by a second instance
+    let reopened = ImplStorage::new(Arc::clone(&adapter));
+    assert_eq!(exec!(reopened.get_block_by_height(1)), block);
+
+    flip_last_byte::<RawBlockSchema>(&adapter, 1);
+    flip_last_byte::<RawTransactionSchema>(&adapter, tx_hash.clone());
+
+    match block_on(reopened.get_block_by_height(1)) {
+        Err(e) => assert!(e
+            .to_string()
+            .contains("Corrupted { category: Block, key: \"1\" }")),
+        Ok(_) => panic!("corrupted block was read"),
+    }
+    match block_on(reopened.get_transaction_by_hash(tx_hash.clone())) {
+        Err(e) => {
+            let expect = format!(
+                "Corrupted {{ category: SignedTransaction, key: \"{:?}\" }}",
+                tx_hash
+            );
+            assert!(e.to_string().contains(&expect));
+        }
+        Ok(_) => panic!("corrupted transaction was read"),
+    }
+}
+
+#[test]
+fn test_storage_checksum_reads_legacy_values() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let legacy = ImplStorage::new(Arc::clone(&adapter));
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    let stx = mock_signed_tx(tx_hash.clone());
+    exec!(legacy.insert_block(block.clone()));
+    exec!(legacy.insert_transactions(vec![stx.clone()]));
+
+    let storage = ImplStorage::new(Arc::clone(&adapter)).with_checksum(true);
+    assert_eq!(exec!(storage.get_block_by_height(1)), block);
+    assert_eq!(exec!(storage.get_latest_block()), block);
+    assert_eq!(exec!(storage.get_transaction_by_hash(tx_hash)), stx);
+}
+
+#[test]
+fn test_storage_insert_block_data_never_torn() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let writer = Arc::new(ImplStorage::new(Arc::clone(&adapter)));
+    // Reads the database directly, without the latest block kept in memory
+    let reader = ImplStorage::new(Arc::clone(&adapter));
+
+    let handle = {
+        let writer = Arc::clone(&writer);
+        thread::spawn(move || {
+            for height in 1..=200 {
+                let tx_hashes = (0..4)
+                    .map(|_| Hash::digest(get_random_bytes(10)))
+                    .collect::<Vec<_>>();
+                let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+                block.ordered_tx_hashes = tx_hashes.clone();
+
+                exec!(writer.insert_block_data(
+                    block,
+                    tx_hashes.iter().cloned().map(mock_signed_tx).collect(),
+                    tx_hashes.into_iter().map(mock_receipt).collect(),
+                ));
+            }
+        })
+    };
+
+    let mut seen = 0;
+    while seen < 200 {
+        let latest = match block_on(reader.get_latest_block()) {
+            Ok(latest) => latest,
+            Err(_) => continue,
+        };
+        let height = latest.header.height;
+        assert!(height >= seen);
+        seen = height;
+
+        assert_eq!(exec!(reader.get_block_by_height(height)), latest);
+        let hashes = latest.ordered_tx_hashes;
+        assert_eq!(exec!(reader.get_transactions(hashes.clone())).len(), 4);
+        assert_eq!(exec!(reader.get_receipts(hashes)).len(), 4);
+    }
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_storage_get_transaction_with_position() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let tx_hashes = (0..3)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes.clone();
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+    let stxs = tx_hashes
+        .iter()
+        .cloned()
+        .map(mock_signed_tx)
+        .collect::<Vec<_>>();
+    exec!(storage.insert_block_data(block, stxs.clone(), vec![]));
+    exec!(storage.insert_block(mock_block(2, Hash::digest(get_random_bytes(10)))));
+
+    let found = exec!(storage.get_transaction_with_position(tx_hashes[2].clone()));
+    assert_eq!(found.transaction, stxs[2]);
+    assert_eq!(
+        found.position,
+        Some(TransactionPosition {
+            height: 1,
+            block_hash,
+            index: 2,
+        })
+    );
+    assert_eq!(found.latest_height, 2);
+
+    // Stored but not yet in a block
+    let pending_hash = Hash::digest(get_random_bytes(10));
+    exec!(storage.insert_transactions(vec![mock_signed_tx(pending_hash.clone())]));
+    let pending = exec!(storage.get_transaction_with_position(pending_hash));
+    assert_eq!(pending.position, None);
+    assert_eq!(pending.latest_height, 2);
+
+    let missing_hash = Hash::digest(get_random_bytes(10));
+    match block_on(storage.get_transaction_with_position(missing_hash.clone())) {
+        Err(e) => {
+            let expect = format!("SignedTransaction, keys: [\"{:?}\"]", missing_hash);
+            assert!(e.to_string().contains(&expect), "{}", e);
+        }
+        Ok(_) => panic!("missing transaction was found"),
+    }
+}
+
+#[test]
+fn test_storage_get_events() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let receipt = |height: u64, services: &[&str]| {
+        let mut receipt = mock_receipt(Hash::digest(get_random_bytes(10)));
+        receipt.height = height;
+        receipt.events = services
+            .iter()
+            .map(|service| Event {
+                service: service.to_string(),
+                data:    format!("{}-{}", service, height),
+            })
+            .collect();
+        receipt
+    };
+    let receipts = vec![
+        receipt(1, &["asset", "metadata"]),
+        receipt(2, &["asset"]),
+        receipt(2, &["metadata"]),
+        receipt(3, &["asset", "asset"]),
+        receipt(5, &[]),
+    ];
+    exec!(storage.insert_receipts(receipts.clone()));
+
+    let asset = exec!(storage.get_events("asset", 1, 6, 10));
+    assert_eq!(
+        asset
+            .iter()
+            .map(|record| (record.height, record.tx_hash.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (1, receipts[0].tx_hash.clone()),
+            (2, receipts[1].tx_hash.clone()),
+            (3, receipts[3].tx_hash.clone()),
+            (3, receipts[3].tx_hash.clone()),
+        ]
+    );
+    assert!(asset.iter().all(|record| record.event.service == "asset"));
+
+    let metadata = exec!(storage.get_events("metadata", 1, 6, 10));
+    assert_eq!(metadata.len(), 2);
+    assert_eq!(metadata[1].event, receipts[2].events[0]);
+
+    // Heights outside [from, to) are left out
+    let filtered = exec!(storage.get_events("asset", 2, 3, 10));
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].height, 2);
+
+    assert_eq!(exec!(storage.get_events("asset", 1, 6, 2)).len(), 2);
+    assert!(exec!(storage.get_events("unknown", 1, 6, 10)).is_empty());
+
+    // Writing the same receipts again doesn't duplicate the index
+    exec!(storage.insert_receipts(receipts));
+    assert_eq!(exec!(storage.get_events("asset", 1, 6, 10)), asset);
+
+    match block_on(storage.get_events("asset", 0, MAX_BLOCKS_RANGE + 1, 10)) {
+        Err(e) => assert!(e.to_string().contains("RangeTooLarge")),
+        Ok(_) => panic!("range too large should fail"),
+    }
+}
+
+#[test]
+fn test_storage_prune_below() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let mut tx_hashes = Vec::new();
+    for height in 1..=5 {
+        let tx_hash = Hash::digest(get_random_bytes(10));
+        let receipt = mock_receipt(tx_hash.clone());
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = vec![tx_hash.clone()];
+
+        exec!(storage.insert_receipts(vec![receipt]));
+        exec!(storage.insert_block(block));
+        tx_hashes.push(tx_hash);
+    }
+
+    exec!(storage.prune_below(3, &[StorageCategory::Receipt]));
+
+    for (tx_hash, height) in tx_hashes.iter().zip(1..) {
+        assert_eq!(
+            block_on(storage.get_receipt(tx_hash.clone())).is_ok(),
+            height >= 3
+        );
+        exec!(storage.get_block_by_height(height));
+    }
+
+    // A restarted node continues from the persisted marker
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let marker =
+        exec!(adapter.get::<PrunedHeightSchema>(pruned_height_key(StorageCategory::Receipt)));
+    assert_eq!(marker, Some(3));
+
+    exec!(storage.prune_below(10, &[StorageCategory::Receipt]));
+    // The latest block keeps its receipts
+    assert!(block_on(storage.get_receipt(tx_hashes[3].clone())).is_err());
+    exec!(storage.get_receipt(tx_hashes[4].clone()));
+    let marker =
+        exec!(adapter.get::<PrunedHeightSchema>(pruned_height_key(StorageCategory::Receipt)));
+    assert_eq!(marker, Some(5));
+}
+
+// Pruning the receipts first doesn't keep the blocks of the same heights from
+// being pruned later.
+#[test]
+fn test_storage_prune_receipts_then_blocks() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let mut tx_hashes = Vec::new();
+    for height in 1..=5 {
+        let tx_hash = Hash::digest(get_random_bytes(10));
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = vec![tx_hash.clone()];
+
+        exec!(storage.insert_receipts(vec![mock_receipt(tx_hash.clone())]));
+        exec!(storage.insert_block(block));
+        tx_hashes.push(tx_hash);
+    }
+
+    exec!(storage.prune_below(3, &[StorageCategory::Receipt]));
+    exec!(storage.prune_below(4, &[StorageCategory::Block]));
+
+    for (tx_hash, height) in tx_hashes.iter().zip(1..) {
+        assert_eq!(
+            block_on(storage.get_receipt(tx_hash.clone())).is_ok(),
+            height >= 3
+        );
+        assert_eq!(
+            block_on(storage.get_block_by_height(height)).is_ok(),
+            height >= 4
+        );
+    }
+
+    let marker = |category| exec!(adapter.get::<PrunedHeightSchema>(pruned_height_key(category)));
+    assert_eq!(marker(StorageCategory::Receipt), Some(3));
+    assert_eq!(marker(StorageCategory::Block), Some(4));
+    assert_eq!(marker(StorageCategory::SignedTransaction), None);
+}
+
+#[test]
+fn test_storage_chain_stats() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    assert_eq!(exec!(storage.get_chain_stats()), ChainStats::default());
+
+    for height in 1..=4 {
+        let tx_hashes = (0..height)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipts = tx_hashes.iter().cloned().map(mock_receipt).collect();
+
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = tx_hashes;
+        exec!(storage.insert_block(block));
+        exec!(storage.insert_receipts(receipts));
+    }
+
+    let stats = exec!(storage.get_chain_stats());
+    assert_eq!(stats, ChainStats {
+        height:                4,
+        total_blocks:          4,
+        total_txs:             10,
+        latest_block_tx_count: 4,
+        total_receipts:        10,
+    });
+}
+
+// Removed transactions and receipts leave the totals once, whichever goes
+// first, and the latest pointer moves in the same batch.
+#[test]
+fn test_storage_chain_stats_after_remove() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let mut blocks = vec![];
+    for height in 1..=4 {
+        let tx_hashes = (0..height)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipts = tx_hashes.iter().cloned().map(mock_receipt).collect();
+
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = tx_hashes;
+        exec!(storage.insert_block(block.clone()));
+        exec!(storage.insert_receipts(receipts));
+        blocks.push(block);
+    }
+
+    let latest_hashes = blocks[3].ordered_tx_hashes.clone();
+    exec!(storage.remove_receipts(latest_hashes.clone()));
+    exec!(storage.remove_receipts(latest_hashes.clone()));
+    exec!(storage.remove_transactions(latest_hashes));
+    exec!(storage.remove_block(4, true));
+
+    // Block 1 goes before its transactions
+    exec!(storage.remove_block(1, false));
+    exec!(storage.remove_transactions(blocks[0].ordered_tx_hashes.clone()));
+
+    let stats = exec!(storage.get_chain_stats());
+    assert_eq!(stats, ChainStats {
+        height:                3,
+        total_blocks:          2,
+        total_txs:             5,
+        latest_block_tx_count: 3,
+        total_receipts:        6,
+    });
+
+    let restarted = ImplStorage::new(adapter);
+    assert_eq!(exec!(restarted.get_latest_block()), blocks[2]);
+    assert_eq!(exec!(restarted.get_chain_stats()), stats);
+}
+
+// Writing the same data again leaves the totals as they are, pruning takes
+// out what it removes.
+#[test]
+fn test_storage_chain_stats_reinsert_and_prune() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let mut blocks = vec![];
+    for height in 1..=4 {
+        let tx_hashes = (0..2)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipts = tx_hashes
+            .iter()
+            .cloned()
+            .map(mock_receipt)
+            .collect::<Vec<_>>();
+
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = tx_hashes;
+        exec!(storage.insert_block_data(block.clone(), vec![], receipts.clone()));
+        blocks.push((block, receipts));
+    }
+
+    let (block, receipts) = blocks[3].clone();
+    exec!(storage.insert_block_data(block.clone(), vec![], receipts.clone()));
+    exec!(storage.insert_receipts(receipts.clone()));
+    exec!(storage.insert_transactions_with_receipts(vec![], receipts.clone()));
+    exec!(storage.insert_blocks_data(vec![
+        (block.clone(), vec![], receipts.clone()),
+        (block, vec![], receipts),
+    ]));
+
+    let stats = exec!(storage.get_chain_stats());
+    assert_eq!(
+        (stats.total_blocks, stats.total_txs, stats.total_receipts),
+        (4, 8, 8)
+    );
+
+    exec!(storage.prune_below(2, &[StorageCategory::Receipt]));
+    exec!(storage.prune_below(3, &[
+        StorageCategory::Block,
+        StorageCategory::SignedTransaction,
+    ]));
+    let stats = exec!(storage.get_chain_stats());
+    assert_eq!(
+        (stats.total_blocks, stats.total_txs, stats.total_receipts),
+        (2, 4, 6)
+    );
+}
+
+#[test]
+fn test_storage_chain_stats_backfill() {
+    let adapter = Arc::new(MemoryAdapter::new());
+
+    // Written the way an older version did, without any counters
+    for height in 1..=3 {
+        let tx_hashes = (0..2)
+            .map(|_| Hash::digest(get_random_bytes(10)))
+            .collect::<Vec<_>>();
+        let receipts = tx_hashes
+            .iter()
+            .cloned()
+            .map(mock_receipt)
+            .collect::<Vec<_>>();
+
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        block.ordered_tx_hashes = tx_hashes;
+        exec!(adapter.insert::<BlockSchema>(height, Sealed::Plain(block.clone())));
+        exec!(adapter.insert::<crate::LatestBlockSchema>(
+            crate::LATEST_BLOCK_KEY.clone(),
+            Sealed::Plain(block)
+        ));
+        let keys = receipts
+            .iter()
+            .map(|receipt| receipt.tx_hash.clone())
+            .collect();
+        let vals = receipts
+            .into_iter()
+            .map(StorageBatchModify::Insert)
+            .collect();
+        exec!(adapter.batch_modify::<crate::ReceiptSchema>(keys, vals));
+    }
+
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    exec!(storage.insert_block(mock_block(4, Hash::digest(get_random_bytes(10)))));
+
+    let stats = exec!(storage.get_chain_stats());
+    assert_eq!(stats.total_blocks, 4);
+    assert_eq!(stats.total_txs, 6);
+    assert_eq!(stats.total_receipts, 6);
+    assert_eq!(stats.latest_block_tx_count, 0);
+
+    // Persisted for the next start
+    let restarted = ImplStorage::new(adapter);
+    assert_eq!(exec!(restarted.get_chain_stats()), stats);
+}
+
+#[test]
+fn test_storage_block_cache() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = ImplStorage::new_with_cache(Arc::clone(&adapter), 16, 16);
+
+    let block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    exec!(adapter
+        .inner
+        .insert::<BlockSchema>(1, Sealed::Plain(block.clone())));
+
+    assert_eq!(exec!(storage.get_block_by_height(1)), block);
+    let reads = adapter.reads();
+    assert_eq!(exec!(storage.get_block_by_height(1)), block);
+    assert_eq!(adapter.reads(), reads);
+
+    // Removing a block must not leave it behind in the cache
+    exec!(storage.insert_block(mock_block(2, Hash::digest(get_random_bytes(10)))));
+    exec!(storage.remove_block(1, false));
+    assert!(block_on(storage.get_block_by_height(1)).is_err());
+}
+
+#[test]
+fn test_storage_transaction_cache() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = ImplStorage::new_with_cache(Arc::clone(&adapter), 16, 16);
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    exec!(storage.insert_transactions(vec![mock_signed_tx(tx_hash.clone())]));
+
+    let reads = adapter.reads();
+    exec!(storage.get_transaction_by_hash(tx_hash.clone()));
+    assert_eq!(adapter.reads(), reads);
+
+    exec!(storage.remove_transactions(vec![tx_hash.clone()]));
+    assert!(block_on(storage.get_transaction_by_hash(tx_hash)).is_err());
+}
+
+#[test]
+fn test_storage_cache_disabled() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = ImplStorage::new_with_cache(Arc::clone(&adapter), 0, 0);
+
+    exec!(storage.insert_block(mock_block(1, Hash::digest(get_random_bytes(10)))));
+
+    let reads = adapter.reads();
+    exec!(storage.get_block_by_height(1));
+    exec!(storage.get_block_by_height(1));
+    assert_eq!(adapter.reads(), reads + 2);
+}
+
+#[test]
+fn test_storage_metrics() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let metrics = Arc::new(CounterMetrics::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter)).with_metrics(metrics.clone());
+
+    let ops = || {
+        metrics
+            .snapshot()
+            .categories
+            .iter()
+            .map(|stats| stats.get.count + stats.insert.count + stats.remove.count)
+            .sum::<u64>()
+    };
+    macro_rules! counted {
+        ($func: expr) => {{
+            let before = ops();
+            let ret = exec!($func);
+            assert!(ops() > before, stringify!($func));
+            ret
+        }};
+    }
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let receipt_hash = Hash::digest(get_random_bytes(10));
+    let mut block = mock_block(1, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = vec![receipt_hash.clone()];
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+
+    counted!(storage.insert_transactions(vec![mock_signed_tx(tx_hash.clone())]));
+    counted!(storage.insert_receipts(vec![mock_receipt(receipt_hash.clone())]));
+    counted!(storage.insert_transactions_with_receipts(
+        vec![mock_signed_tx(Hash::digest(get_random_bytes(10)))],
+        vec![mock_receipt(Hash::digest(get_random_bytes(10)))],
+    ));
+    counted!(storage.insert_block(block));
+    counted!(storage.update_latest_proof(mock_proof(block_hash.clone())));
+    counted!(storage.update_overlord_wal(get_random_bytes(10)));
+
+    counted!(storage.get_transaction_by_hash(tx_hash.clone()));
+    counted!(storage.get_transactions(vec![tx_hash.clone()]));
+    counted!(storage.get_block_by_height(1));
+    counted!(storage.get_block_by_hash(block_hash.clone()));
+    counted!(storage.get_header_by_height(1));
+    counted!(storage.get_header_by_hash(block_hash.clone()));
+    counted!(storage.get_blocks(1, 2));
+    counted!(storage.get_receipt(receipt_hash.clone()));
+    counted!(storage.get_receipts(vec![receipt_hash.clone()]));
+    counted!(storage.get_block_receipts(1));
+    counted!(storage.get_block_receipts_by_hash(block_hash));
+    counted!(storage.get_latest_proof());
+    counted!(storage.load_overlord_wal());
+
+    // The latest block is only read from the database on a cold start
+    let restarted = ImplStorage::new(adapter).with_metrics(metrics.clone());
+    counted!(restarted.get_latest_block());
+
+    counted!(storage.remove_transactions(vec![tx_hash]));
+    counted!(storage.remove_receipts(vec![receipt_hash]));
+    counted!(storage.remove_block(1, true));
+
+    let block_stats = metrics.snapshot().get(StorageCategory::Block).clone();
+    assert!(block_stats.insert.bytes > 0);
+    assert!(block_stats.get.bytes > 0);
+    assert_eq!(
+        block_stats.get.buckets.iter().sum::<u64>(),
+        block_stats.get.count
+    );
+}
+
+// Memory adapter that counts the reads reaching it
+struct CountingAdapter {
+    inner: MemoryAdapter,
+    reads: AtomicUsize,
+}
+
+impl CountingAdapter {
+    fn new() -> Self {
+        CountingAdapter {
+            inner: MemoryAdapter::new(),
+            reads: AtomicUsize::new(0),
+        }
+    }
+
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for CountingAdapter {
+    async fn insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        self.inner.insert::<S>(key, val).await
+    }
+
+    async fn get<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.get::<S>(key).await
+    }
+
+    async fn remove<S: StorageSchema>(&self, key: <S as StorageSchema>::Key) -> ProtocolResult<()> {
+        self.inner.remove::<S>(key).await
+    }
+
+    async fn contains<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<bool> {
+        self.inner.contains::<S>(key).await
+    }
+
+    async fn batch_modify<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+        vals: Vec<StorageBatchModify<S>>,
+    ) -> ProtocolResult<()> {
+        self.inner.batch_modify::<S>(keys, vals).await
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        self.inner.write_batch(batch).await
+    }
+
+    async fn iter_prefix(
+        &self,
+        category: StorageCategory,
+        prefix: &[u8],
+        start_after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> ProtocolResult<Vec<(Bytes, Bytes)>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner
+            .iter_prefix(category, prefix, start_after, limit)
+            .await
+    }
+
+    fn snapshot(&self) -> ProtocolResult<Box<dyn StorageSnapshot + '_>> {
+        self.inner.snapshot()
+    }
+}
+
+// Memory adapter that fails every write once `allowed_writes` are used up,
+// as if the node crashed at that point.
+struct FaultAdapter {
+    inner:          MemoryAdapter,
+    allowed_writes: AtomicUsize,
+}
+
+impl FaultAdapter {
+    fn new(allowed_writes: usize) -> Self {
+        FaultAdapter {
+            inner:          MemoryAdapter::new(),
+            allowed_writes: AtomicUsize::new(allowed_writes),
+        }
+    }
+
+    fn write(&self) -> ProtocolResult<()> {
+        let remaining = self.allowed_writes.load(Ordering::SeqCst);
+        if remaining == 0 {
+            return Err(ProtocolError::new(
+                ProtocolErrorKind::Storage,
+                Box::new(FaultError::Crashed),
+            ));
+        }
+
+        self.allowed_writes.store(remaining - 1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display)]
+enum FaultError {
+    #[display(fmt = "crashed")]
+    Crashed,
+}
+
+impl Error for FaultError {}
+
+#[async_trait]
+impl StorageAdapter for FaultAdapter {
+    async fn insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        self.write()?;
+        self.inner.insert::<S>(key, val).await
+    }
+
+    async fn get<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        self.inner.get::<S>(key).await
+    }
+
+    async fn remove<S: StorageSchema>(&self, key: <S as StorageSchema>::Key) -> ProtocolResult<()> {
+        self.write()?;
+        self.inner.remove::<S>(key).await
+    }
+
+    async fn contains<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<bool> {
+        self.inner.contains::<S>(key).await
+    }
+
+    async fn batch_modify<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+        vals: Vec<StorageBatchModify<S>>,
+    ) -> ProtocolResult<()> {
+        self.write()?;
+        self.inner.batch_modify::<S>(keys, vals).await
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        self.write()?;
+        self.inner.write_batch(batch).await
+    }
+
+    async fn iter_prefix(
+        &self,
+        category: StorageCategory,
+        prefix: &[u8],
+        start_after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> ProtocolResult<Vec<(Bytes, Bytes)>> {
+        self.inner
+            .iter_prefix(category, prefix, start_after, limit)
+            .await
+    }
+
+    fn snapshot(&self) -> ProtocolResult<Box<dyn StorageSnapshot + '_>> {
+        self.inner.snapshot()
+    }
+}
+
+// Same keys as the block and transaction schemas, without the envelope
+struct RawBlockSchema;
+
+impl StorageSchema for RawBlockSchema {
+    type Key = u64;
+    type Value = Bytes;
+
+    fn category() -> StorageCategory {
+        StorageCategory::Block
+    }
+}
+
+struct RawTransactionSchema;
+
+impl StorageSchema for RawTransactionSchema {
+    type Key = Hash;
+    type Value = Bytes;
+
+    fn category() -> StorageCategory {
+        StorageCategory::SignedTransaction
+    }
+}
+
+fn flip_last_byte<S: StorageSchema<Value = Bytes>>(
+    adapter: &MemoryAdapter,
+    key: <S as StorageSchema>::Key,
+) where
+    <S as StorageSchema>::Key: Clone,
+{
+    let bytes = exec!(adapter.get::<S>(key.clone())).unwrap();
+    let mut bytes = bytes.to_vec();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    exec!(adapter.insert::<S>(key, Bytes::from(bytes)));
+}
@@ -1,4 +1,5 @@
 use asset::AssetService;
+use clap::{App, Arg, SubCommand};
 use derive_more::{Display, From};
 use metadata::MetadataService;
 use muta::MutaBuilder;
@@ -49,7 +50,71 @@ fn main() {
     let builer = builder.service_mapping(DefaultServiceMapping {});
 
     let muta = builer.build().expect("build");
-    muta.run().expect("run");
+
+    let matches = App::new("muta-chain")
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export blocks to a snapshot file")
+                .arg(Arg::with_name("path").required(true))
+                .arg(Arg::with_name("from").required(true))
+                .arg(Arg::with_name("to").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Import blocks from a snapshot file")
+                .arg(Arg::with_name("path").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("backup")
+                .about("Back up the block database, incrementally")
+                .arg(Arg::with_name("dir").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("restore")
+                .about("Restore the latest backup into an empty data path")
+                .arg(Arg::with_name("dir").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("list-backups")
+                .about("List the backups of a backup directory")
+                .arg(Arg::with_name("dir").required(true)),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("export", Some(args)) => {
+            let height = |name: &str| {
+                args.value_of(name)
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .expect("height must be a number")
+            };
+            let path = args.value_of("path").expect("path");
+            muta.export_chain(path, height("from"), height("to"))
+                .expect("export");
+        }
+        ("import", Some(args)) => {
+            let path = args.value_of("path").expect("path");
+            muta.import_chain(path).expect("import");
+        }
+        ("backup", Some(args)) => {
+            let dir = args.value_of("dir").expect("dir");
+            muta.backup(dir).expect("backup");
+        }
+        ("restore", Some(args)) => {
+            let dir = args.value_of("dir").expect("dir");
+            muta.restore(dir).expect("restore");
+        }
+        ("list-backups", Some(args)) => {
+            let dir = args.value_of("dir").expect("dir");
+            for backup in muta.list_backups(dir).expect("list backups") {
+                println!(
+                    "id {} size {} timestamp {}",
+                    backup.id, backup.size, backup.timestamp
+                );
+            }
+        }
+        _ => muta.run().expect("run"),
+    }
 }
 
 #[derive(Debug, Display, From)]
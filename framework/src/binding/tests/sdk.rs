@@ -6,7 +6,10 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use cita_trie::MemoryDB;
 
-use protocol::traits::{NoopDispatcher, ServiceResponse, ServiceSDK, Storage};
+use protocol::traits::{
+    ChainStats, EventRecord, NoopDispatcher, ServiceResponse, ServiceSDK, Storage, StorageCategory,
+    TransactionWithPosition,
+};
 use protocol::types::{
     Address, Block, BlockHeader, Event, Hash, MerkleRoot, Proof, RawTransaction, Receipt,
     ReceiptResponse, SignedTransaction, TransactionRequest, Validator,
@@ -116,18 +119,69 @@ impl Storage for MockStorage {
         Ok(())
     }
 
+    async fn insert_transactions_with_receipts(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_block_data(
+        &self,
+        _: Block,
+        _: Vec<SignedTransaction>,
+        _: Vec<Receipt>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn insert_blocks_data(
+        &self,
+        _: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn update_latest_proof(&self, _proof: Proof) -> ProtocolResult<()> {
         Ok(())
     }
 
+    async fn remove_transactions(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_receipts(&self, _: Vec<Hash>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove_block(&self, _: u64, _: bool) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn prune_below(&self, _: u64, _: &[StorageCategory]) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
     async fn get_transaction_by_hash(&self, _tx_hash: Hash) -> ProtocolResult<SignedTransaction> {
         Ok(mock_signed_tx())
     }
 
+    async fn get_transaction_with_position(
+        &self,
+        _: Hash,
+    ) -> ProtocolResult<TransactionWithPosition> {
+        unimplemented!()
+    }
+
     async fn get_transactions(&self, _hashes: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>> {
         Err(StoreError::GetNone.into())
     }
 
+    async fn filter_unknown_transactions(&self, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn get_latest_block(&self) -> ProtocolResult<Block> {
         Ok(mock_block(1))
     }
@@ -140,6 +194,18 @@ impl Storage for MockStorage {
         Err(StoreError::GetNone.into())
     }
 
+    async fn get_header_by_height(&self, _: u64) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_header_by_hash(&self, _: Hash) -> ProtocolResult<BlockHeader> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(&self, _: u64, _: u64) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn get_receipt(&self, _hash: Hash) -> ProtocolResult<Receipt> {
         Ok(mock_receipt())
     }
@@ -148,6 +214,14 @@ impl Storage for MockStorage {
         Err(StoreError::GetNone.into())
     }
 
+    async fn get_block_receipts(&self, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
+    async fn get_block_receipts_by_hash(&self, _: Hash) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self) -> ProtocolResult<Proof> {
         Err(StoreError::GetNone.into())
     }
@@ -159,6 +233,48 @@ impl Storage for MockStorage {
     async fn load_overlord_wal(&self) -> ProtocolResult<Bytes> {
         Err(StoreError::GetNone.into())
     }
+
+    async fn update_evidence(&self, _evidence: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_evidence(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    async fn update_liveness(&self, _missed: Bytes) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    async fn load_liveness(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::new())
+    }
+
+    async fn get_chain_stats(&self) -> ProtocolResult<ChainStats> {
+        unimplemented!()
+    }
+
+    async fn get_events(
+        &self,
+        _: &str,
+        _: u64,
+        _: u64,
+        _: usize,
+    ) -> ProtocolResult<Vec<EventRecord>> {
+        unimplemented!()
+    }
+
+    async fn update_pool_transactions(
+        &self,
+        _: Vec<SignedTransaction>,
+        _: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn get_pool_transactions(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        unimplemented!()
+    }
 }
 
 // #####################
@@ -1,6 +1,8 @@
 use async_trait::async_trait;
 
-use crate::traits::{Context, ServiceResponse};
+use crate::traits::{
+    Context, PendingTx, PoolStats, RejectionRecord, ServiceResponse, TransactionWithPosition,
+};
 use crate::types::{Address, Block, Hash, Receipt, SignedTransaction};
 use crate::ProtocolResult;
 
@@ -23,6 +25,32 @@ pub trait APIAdapter: Send + Sync {
         tx_hash: Hash,
     ) -> ProtocolResult<SignedTransaction>;
 
+    async fn get_transaction_with_position(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<TransactionWithPosition>;
+
+    /// A transaction still waiting in the mempool, not yet in storage.
+    async fn get_pending_transaction(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>>;
+
+    async fn get_pool_stats(&self, ctx: Context) -> ProtocolResult<PoolStats>;
+
+    async fn get_pool_content(&self, ctx: Context, limit: usize) -> ProtocolResult<Vec<PendingTx>>;
+
+    /// Why the mempool recently turned `tx_hash` away, if it did.
+    async fn get_rejection(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<RejectionRecord>>;
+
+    async fn get_rejections(&self, ctx: Context) -> ProtocolResult<Vec<RejectionRecord>>;
+
     async fn query_service(
         &self,
         ctx: Context,
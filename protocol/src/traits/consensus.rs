@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use creep::Context;
 
-use crate::traits::{ExecutorParams, ExecutorResp};
+use crate::traits::{ExecutorParams, ExecutorResp, PeerMisbehavior};
 use crate::types::{
     Address, Block, Bytes, Hash, MerkleRoot, Metadata, Proof, Receipt, SignedTransaction, Validator,
 };
@@ -70,6 +70,9 @@ pub trait SynchronizationAdapter: CommonConsensusAdapter + Send + Sync {
         ctx: Context,
         hashes: &[Hash],
     ) -> ProtocolResult<Vec<SignedTransaction>>;
+
+    /// Pull the proof of the block at the given height from other nodes.
+    async fn get_proof_from_remote(&self, ctx: Context, height: u64) -> ProtocolResult<Proof>;
 }
 
 #[async_trait]
@@ -88,9 +91,37 @@ pub trait CommonConsensusAdapter: Send + Sync {
 
     async fn save_receipts(&self, ctx: Context, receipts: Vec<Receipt>) -> ProtocolResult<()>;
 
+    /// Save a block's transactions and receipts in one atomic write.
+    async fn save_signed_txs_with_receipts(
+        &self,
+        ctx: Context,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()>;
+
+    /// Save a block with its transactions and receipts in one atomic write.
+    async fn save_block_data(
+        &self,
+        ctx: Context,
+        block: Block,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()>;
+
+    /// Save consecutive blocks, each with its transactions and receipts, in
+    /// one atomic write.
+    async fn save_blocks_data(
+        &self,
+        ctx: Context,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()>;
+
     /// Flush the given transactions in the mempool.
     async fn flush_mempool(&self, ctx: Context, ordered_tx_hashes: &[Hash]) -> ProtocolResult<()>;
 
+    /// Drop the transactions in the mempool timed out at the given height.
+    async fn expire_mempool(&self, ctx: Context, height: u64) -> ProtocolResult<usize>;
+
     /// Get a block corresponding to the given height.
     async fn get_block_by_height(&self, ctx: Context, height: u64) -> ProtocolResult<Block>;
 
@@ -115,6 +146,9 @@ pub trait CommonConsensusAdapter: Send + Sync {
     ) -> ProtocolResult<Metadata>;
 
     fn set_args(&self, context: Context, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
+
+    /// Report the peer that sent a message failing verification.
+    fn report_bad(&self, ctx: Context, misbehavior: PeerMisbehavior);
 }
 
 #[async_trait]
@@ -143,6 +177,9 @@ pub trait ConsensusAdapter: CommonConsensusAdapter + Send + Sync {
         order_txs: Vec<Hash>,
     ) -> ProtocolResult<Vec<SignedTransaction>>;
 
+    /// Whether the mempool of this node holds all the given transactions.
+    fn contains_txs(&self, ctx: Context, tx_hashes: &[Hash]) -> bool;
+
     /// Consensus transmit a message to the given target.
     async fn transmit(
         &self,
@@ -193,4 +230,16 @@ pub trait ConsensusAdapter: CommonConsensusAdapter + Send + Sync {
 
     /// Load latest overlord wal info.
     async fn load_overlord_wal(&self, ctx: Context) -> ProtocolResult<Bytes>;
+
+    /// Save the double signing evidence found so far.
+    async fn save_evidence(&self, ctx: Context, evidence: Bytes) -> ProtocolResult<()>;
+
+    /// Load the evidence saved before, empty if there is none.
+    async fn load_evidence(&self, ctx: Context) -> ProtocolResult<Bytes>;
+
+    /// Save the heights in a row each validator missed signing.
+    async fn save_liveness(&self, ctx: Context, missed: Bytes) -> ProtocolResult<()>;
+
+    /// Load the counts saved before, empty if there are none.
+    async fn load_liveness(&self, ctx: Context) -> ProtocolResult<Bytes>;
 }
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use creep::Context;
 
-use crate::types::{Hash, SignedTransaction};
+use crate::types::{Address, Hash, SignedTransaction};
 use crate::ProtocolResult;
 
 #[allow(dead_code)]
@@ -16,10 +16,55 @@ impl MixedTxHashes {
     }
 }
 
+/// Snapshot of what the pool holds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Transactions waiting to be packaged.
+    pub pending:          usize,
+    /// Transactions pulled in by propose sync, only packaged as order txs.
+    pub parked:           usize,
+    /// Pending transactions gossiped by peers rather than submitted to this
+    /// node.
+    pub from_peers:       usize,
+    pub capacity:         usize,
+    pub min_price:        Option<u64>,
+    pub max_price:        Option<u64>,
+    /// Arrival of the longest waiting transaction, milliseconds since the
+    /// unix epoch.
+    pub oldest_timestamp: Option<u64>,
+    /// Approximate size of the held transactions.
+    pub bytes:            usize,
+}
+
+/// A pending transaction as listed by `MemPool::content`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PendingTx {
+    pub tx_hash:      Hash,
+    pub sender:       Address,
+    pub nonce:        Hash,
+    pub cycles_price: u64,
+}
+
+/// Why the pool turned a transaction away or dropped it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectionRecord {
+    pub tx_hash:   Hash,
+    pub reason:    String,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: u64,
+}
+
 #[async_trait]
 pub trait MemPool: Send + Sync {
     async fn insert(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()>;
 
+    /// Inserts every transaction of `txs` on its own, one result for each.
+    async fn insert_batch(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+    ) -> Vec<ProtocolResult<()>>;
+
     async fn package(
         &self,
         ctx: Context,
@@ -29,12 +74,32 @@ pub trait MemPool: Send + Sync {
 
     async fn flush(&self, ctx: Context, tx_hashes: Vec<Hash>) -> ProtocolResult<()>;
 
+    /// Drops the transactions that can no longer be packaged after
+    /// `current_height`, returns how many there were.
+    async fn expire(&self, ctx: Context, current_height: u64) -> ProtocolResult<usize>;
+
     async fn get_full_txs(
         &self,
         ctx: Context,
         tx_hashes: Vec<Hash>,
     ) -> ProtocolResult<Vec<SignedTransaction>>;
 
+    /// The transaction with `tx_hash` if the pool holds it.
+    async fn get(&self, ctx: Context, tx_hash: &Hash) -> ProtocolResult<Option<SignedTransaction>>;
+
+    fn contains(&self, tx_hash: &Hash) -> bool;
+
+    fn stats(&self) -> PoolStats;
+
+    /// Up to `limit` pending transactions in package order.
+    fn content(&self, limit: usize) -> Vec<PendingTx>;
+
+    /// The latest recorded rejection of `tx_hash`, recent ones only.
+    fn rejection_reason(&self, tx_hash: &Hash) -> Option<RejectionRecord>;
+
+    /// The recorded rejections, oldest first.
+    fn rejections(&self) -> Vec<RejectionRecord>;
+
     async fn ensure_order_txs(
         &self,
         ctx: Context,
@@ -62,11 +127,30 @@ pub trait MemPoolAdapter: Send + Sync {
 
     async fn check_signature(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()>;
 
+    /// Checks the signatures of `txs` in parallel, one result for each in
+    /// the same order.
+    async fn check_signatures(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+    ) -> Vec<ProtocolResult<()>>;
+
     async fn check_transaction(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()>;
 
     async fn check_storage_exist(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<()>;
 
     async fn get_latest_height(&self, ctx: Context) -> ProtocolResult<u64>;
 
+    /// Keeps `inserted` and drops `removed` from the transactions restored
+    /// after a restart, the write may complete after this returns.
+    async fn persist_txs(
+        &self,
+        ctx: Context,
+        inserted: Vec<SignedTransaction>,
+        removed: Vec<Hash>,
+    ) -> ProtocolResult<()>;
+
+    async fn load_persisted_txs(&self, ctx: Context) -> ProtocolResult<Vec<SignedTransaction>>;
+
     fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
 }
@@ -19,8 +19,13 @@ pub use executor::{
     Dispatcher, Executor, ExecutorFactory, ExecutorParams, ExecutorResp, NoopDispatcher,
     ServiceResponse,
 };
-pub use mempool::{MemPool, MemPoolAdapter, MixedTxHashes};
-pub use network::{Gossip, MessageCodec, MessageHandler, Priority, Rpc};
-pub use storage::{Storage, StorageAdapter, StorageBatchModify, StorageCategory, StorageSchema};
+pub use mempool::{MemPool, MemPoolAdapter, MixedTxHashes, PendingTx, PoolStats, RejectionRecord};
+pub use network::{
+    Gossip, MessageCodec, MessageHandler, PeerMisbehavior, PeerTrust, Priority, Rpc,
+};
+pub use storage::{
+    ChainStats, EventRecord, Storage, StorageAdapter, StorageBatch, StorageBatchModify,
+    StorageCategory, StorageSchema, StorageSnapshot, TransactionPosition, TransactionWithPosition,
+};
 
 pub use creep::{Cloneable, Context};
@@ -82,6 +82,23 @@ pub trait Rpc: Send + Sync {
     ) -> ProtocolResult<()>
     where
         M: MessageCodec;
+
+    /// Contexts to call up to `limit` connected peers other than the one
+    /// `cx` targets.
+    fn peer_contexts(&self, cx: &Context, limit: usize) -> Vec<Context>;
+}
+
+/// Messages of a peer that fail verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerMisbehavior {
+    InvalidTransaction,
+    InvalidProposal,
+    InvalidSyncResponse,
+}
+
+pub trait PeerTrust: Send + Sync {
+    /// Reports the peer `cx` came from, nothing if it is not from remote.
+    fn report(&self, cx: Context, misbehavior: PeerMisbehavior);
 }
 
 #[async_trait]
@@ -1,18 +1,22 @@
 use async_trait::async_trait;
 use derive_more::Display;
 
-use crate::codec::ProtocolCodec;
-use crate::types::block::{Block, Proof};
-use crate::types::receipt::Receipt;
+use crate::codec::{ProtocolCodec, ProtocolCodecSync};
+use crate::types::block::{Block, BlockHeader, Proof};
+use crate::types::receipt::{Event, Receipt};
 use crate::types::{Hash, SignedTransaction};
 use crate::{Bytes, ProtocolResult};
 
-#[derive(Debug, Copy, Clone, Display)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Display)]
 pub enum StorageCategory {
     Block,
     Receipt,
     SignedTransaction,
     Wal,
+    EventIndex,
+    TransactionPool,
+    Evidence,
+    Liveness,
 }
 
 pub trait StorageSchema {
@@ -30,27 +34,130 @@ pub trait Storage: Send + Sync {
 
     async fn insert_receipts(&self, receipts: Vec<Receipt>) -> ProtocolResult<()>;
 
+    /// Write a block's transactions and receipts together, either all of
+    /// them are stored or none.
+    async fn insert_transactions_with_receipts(
+        &self,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()>;
+
+    /// Write a block together with its transactions and receipts in one
+    /// batch, either all of it is stored or none.
+    async fn insert_block_data(
+        &self,
+        block: Block,
+        signed_txs: Vec<SignedTransaction>,
+        receipts: Vec<Receipt>,
+    ) -> ProtocolResult<()>;
+
+    /// Write consecutive blocks, each with its transactions and receipts, in
+    /// one batch. The last block becomes the latest one.
+    async fn insert_blocks_data(
+        &self,
+        blocks: Vec<(Block, Vec<SignedTransaction>, Vec<Receipt>)>,
+    ) -> ProtocolResult<()>;
+
     async fn update_latest_proof(&self, proof: Proof) -> ProtocolResult<()>;
 
+    async fn remove_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<()>;
+
+    async fn remove_receipts(&self, hashes: Vec<Hash>) -> ProtocolResult<()>;
+
+    /// Removing the latest block requires `force`, the latest block pointer
+    /// then moves back to the previous height.
+    async fn remove_block(&self, height: u64, force: bool) -> ProtocolResult<()>;
+
+    /// Deletes the given categories of data for every block below `height`,
+    /// blocks and transactions are only touched when asked for. The latest
+    /// block is always kept.
+    ///
+    /// The pruned height is persisted and shared by all categories, a later
+    /// call only walks the heights above it.
+    async fn prune_below(&self, height: u64, categories: &[StorageCategory]) -> ProtocolResult<()>;
+
     async fn get_transaction_by_hash(&self, tx_hash: Hash) -> ProtocolResult<SignedTransaction>;
 
+    /// The transaction, where it was committed and the latest height, read
+    /// concurrently. Fails only if the transaction itself is missing.
+    async fn get_transaction_with_position(
+        &self,
+        tx_hash: Hash,
+    ) -> ProtocolResult<TransactionWithPosition>;
+
+    /// The i-th transaction belongs to the i-th hash, duplicated hashes
+    /// give duplicated transactions. Fails if any hash is missing.
     async fn get_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<SignedTransaction>>;
 
+    /// The hashes of `hashes` whose transaction isn't stored, in their order.
+    async fn filter_unknown_transactions(&self, hashes: Vec<Hash>) -> ProtocolResult<Vec<Hash>>;
+
     async fn get_latest_block(&self) -> ProtocolResult<Block>;
 
     async fn get_block_by_height(&self, height: u64) -> ProtocolResult<Block>;
 
     async fn get_block_by_hash(&self, block_hash: Hash) -> ProtocolResult<Block>;
 
+    /// Header only, without decoding the ordered tx hashes of the block.
+    async fn get_header_by_height(&self, height: u64) -> ProtocolResult<BlockHeader>;
+
+    async fn get_header_by_hash(&self, block_hash: Hash) -> ProtocolResult<BlockHeader>;
+
+    /// Blocks from `start` up to but not including `end`, fetched in one
+    /// batch. Fails on the first missing height.
+    async fn get_blocks(&self, start: u64, end: u64) -> ProtocolResult<Vec<Block>>;
+
     async fn get_receipt(&self, hash: Hash) -> ProtocolResult<Receipt>;
 
+    /// Same order and failure rules as `get_transactions`.
     async fn get_receipts(&self, hash: Vec<Hash>) -> ProtocolResult<Vec<Receipt>>;
 
+    /// Receipts of every transaction in the block, in block order.
+    async fn get_block_receipts(&self, height: u64) -> ProtocolResult<Vec<Receipt>>;
+
+    async fn get_block_receipts_by_hash(&self, block_hash: Hash) -> ProtocolResult<Vec<Receipt>>;
+
     async fn get_latest_proof(&self) -> ProtocolResult<Proof>;
 
     async fn update_overlord_wal(&self, info: Bytes) -> ProtocolResult<()>;
 
     async fn load_overlord_wal(&self) -> ProtocolResult<Bytes>;
+
+    /// Replaces the double signing evidence kept by consensus.
+    async fn update_evidence(&self, evidence: Bytes) -> ProtocolResult<()>;
+
+    /// Empty before any evidence is kept.
+    async fn load_evidence(&self) -> ProtocolResult<Bytes>;
+
+    /// Replaces the heights in a row each validator missed signing.
+    async fn update_liveness(&self, missed: Bytes) -> ProtocolResult<()>;
+
+    /// Empty before any count is kept.
+    async fn load_liveness(&self) -> ProtocolResult<Bytes>;
+
+    async fn get_chain_stats(&self) -> ProtocolResult<ChainStats>;
+
+    /// Events emitted by `service` in receipts of heights from `from` up to
+    /// but not including `to`, ordered by height. At most `limit` events
+    /// are returned.
+    async fn get_events(
+        &self,
+        service: &str,
+        from: u64,
+        to: u64,
+        limit: usize,
+    ) -> ProtocolResult<Vec<EventRecord>>;
+
+    /// Updates the pending transactions kept for the pool across restarts,
+    /// `removed` is applied before `inserted` in a single write.
+    async fn update_pool_transactions(
+        &self,
+        inserted: Vec<SignedTransaction>,
+        removed: Vec<Hash>,
+    ) -> ProtocolResult<()>;
+
+    /// Every transaction kept by `update_pool_transactions`, by hash.
+    async fn get_pool_transactions(&self) -> ProtocolResult<Vec<SignedTransaction>>;
 }
 
 pub enum StorageBatchModify<S: StorageSchema> {
@@ -58,6 +165,90 @@ pub enum StorageBatchModify<S: StorageSchema> {
     Insert(<S as StorageSchema>::Value),
 }
 
+/// Running totals of the stored chain.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainStats {
+    pub height:                u64,
+    pub total_blocks:          u64,
+    pub total_txs:             u64,
+    pub latest_block_tx_count: u64,
+    /// Receipts written so far, pruning doesn't lower it
+    pub total_receipts:        u64,
+}
+
+/// Where a committed transaction is found in the chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionPosition {
+    pub height:     u64,
+    pub block_hash: Hash,
+    /// Index in the ordered tx hashes of the block
+    pub index:      u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionWithPosition {
+    pub transaction:   SignedTransaction,
+    /// `None` while no stored block contains the transaction
+    pub position:      Option<TransactionPosition>,
+    pub latest_height: u64,
+}
+
+/// An event together with the receipt it was found in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventRecord {
+    pub height:  u64,
+    pub tx_hash: Hash,
+    pub event:   Event,
+}
+
+/// Writes to any number of schemas, applied by `StorageAdapter::write_batch`
+/// as one atomic update.
+#[derive(Default)]
+pub struct StorageBatch {
+    entries: Vec<(StorageCategory, Bytes, Option<Bytes>)>,
+}
+
+impl StorageBatch {
+    pub fn new() -> Self {
+        StorageBatch::default()
+    }
+
+    pub fn insert<S: StorageSchema>(
+        &mut self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        self.entries
+            .push((S::category(), key.encode_sync()?, Some(val.encode_sync()?)));
+        Ok(())
+    }
+
+    pub fn remove<S: StorageSchema>(
+        &mut self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<()> {
+        self.entries.push((S::category(), key.encode_sync()?, None));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[(StorageCategory, Bytes, Option<Bytes>)] {
+        &self.entries
+    }
+
+    /// Encoded entries in insertion order, `None` marks a removal.
+    pub fn into_entries(self) -> Vec<(StorageCategory, Bytes, Option<Bytes>)> {
+        self.entries
+    }
+}
+
 #[async_trait]
 pub trait StorageAdapter: Send + Sync {
     async fn insert<S: StorageSchema>(
@@ -91,9 +282,73 @@ pub trait StorageAdapter: Send + Sync {
         key: <S as StorageSchema>::Key,
     ) -> ProtocolResult<bool>;
 
+    /// Whether each of `keys` is stored, in the order of `keys`.
+    async fn contains_batch<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+    ) -> ProtocolResult<Vec<bool>> {
+        let mut vec = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            vec.push(self.contains::<S>(key).await?);
+        }
+
+        Ok(vec)
+    }
+
     async fn batch_modify<S: StorageSchema>(
         &self,
         keys: Vec<<S as StorageSchema>::Key>,
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()>;
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()>;
+
+    /// Up to `limit` encoded key value pairs of `category` whose key starts
+    /// with `prefix`, in ascending key order. Only keys after `start_after`
+    /// are returned, pass the last key of a page to get the next one.
+    async fn iter_prefix(
+        &self,
+        category: StorageCategory,
+        prefix: &[u8],
+        start_after: Option<Vec<u8>>,
+        limit: usize,
+    ) -> ProtocolResult<Vec<(Bytes, Bytes)>>;
+
+    /// Reclaims the space of removed values in `category`, backends that
+    /// free it right away do nothing.
+    async fn compact(&self, _category: StorageCategory) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    /// Approximate bytes used by each category, empty if the backend can't
+    /// tell.
+    async fn approximate_sizes(&self) -> ProtocolResult<Vec<(StorageCategory, u64)>> {
+        Ok(Vec::new())
+    }
+
+    /// Deletes the expired values of `category` and returns how many there
+    /// were. Expired values already read as missing, this only frees their
+    /// space.
+    async fn purge_expired(&self, _category: StorageCategory) -> ProtocolResult<u64> {
+        Ok(0)
+    }
+
+    /// A read only view of the current state, later writes don't show up in
+    /// it.
+    fn snapshot(&self) -> ProtocolResult<Box<dyn StorageSnapshot + '_>>;
+}
+
+/// Point in time view of a `StorageAdapter`, taken by
+/// `StorageAdapter::snapshot`. Keys and values are encoded.
+pub trait StorageSnapshot: Send + Sync {
+    fn get(&self, category: StorageCategory, key: &[u8]) -> ProtocolResult<Option<Bytes>>;
+
+    fn get_batch(
+        &self,
+        category: StorageCategory,
+        keys: &[Bytes],
+    ) -> ProtocolResult<Vec<Option<Bytes>>> {
+        keys.iter().map(|key| self.get(category, key)).collect()
+    }
 }
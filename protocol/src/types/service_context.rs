@@ -37,6 +37,7 @@ pub struct ServiceContext {
     service_method:  String,
     service_payload: String,
     extra:           Option<Bytes>,
+    caller_service:  Option<String>,
     timestamp:       u64,
     events:          Rc<RefCell<Vec<Event>>>,
 }
@@ -55,6 +56,7 @@ impl ServiceContext {
             service_method:  params.service_method,
             service_payload: params.service_payload,
             extra:           params.extra,
+            caller_service:  None,
             timestamp:       params.timestamp,
             events:          params.events,
         }
@@ -79,6 +81,7 @@ impl ServiceContext {
             service_method,
             service_payload,
             extra,
+            caller_service: Some(context.service_name.clone()),
             timestamp: context.get_timestamp(),
             events: Rc::clone(&context.events),
         }
@@ -141,6 +144,12 @@ impl ServiceContext {
         self.extra.clone()
     }
 
+    /// The service that made this call through the SDK, `None` for a call
+    /// from a transaction. Unlike `extra` it is set by the framework.
+    pub fn get_caller_service(&self) -> Option<&str> {
+        self.caller_service.as_ref().map(String::as_str)
+    }
+
     pub fn get_timestamp(&self) -> u64 {
         self.timestamp
     }
@@ -208,5 +217,16 @@ mod tests {
         assert_eq!(ctx.get_service_name(), "service_name");
         assert_eq!(ctx.get_service_method(), "service_method");
         assert_eq!(ctx.get_payload(), "service_payload");
+        assert_eq!(ctx.get_caller_service(), None);
+
+        let call = ServiceContext::with_context(
+            &ctx,
+            None,
+            "callee".to_owned(),
+            "method".to_owned(),
+            "".to_owned(),
+        );
+        assert_eq!(call.get_caller_service(), Some("service_name"));
+        assert_eq!(call.get_service_name(), "callee");
     }
 }
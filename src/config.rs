@@ -1,11 +1,20 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use serde_derive::Deserialize;
 
-use core_mempool::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+use core_consensus::stop::DEFAULT_STOP_TIMEOUT;
+use core_consensus::timestamp::DEFAULT_MAX_CLOCK_SKEW;
+use core_mempool::{
+    DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE, DEFAULT_REJECTION_JOURNAL_SIZE,
+    DEFAULT_REPLACE_PRICE_BUMP, DEFAULT_SENDER_LIMIT,
+};
+use core_storage::adapter::rocks::{RocksCompression, RocksConfig};
+use protocol::traits::StorageCategory;
 use protocol::types::Hex;
+use protocol::ProtocolResult;
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigGraphQL {
@@ -34,6 +43,16 @@ pub struct ConfigNetwork {
     pub recv_buffer_size:     Option<usize>,
     pub max_frame_length:     Option<usize>,
     pub max_wait_streams:     Option<usize>,
+    pub ping_interval:        Option<u64>,
+    pub ping_timeout:         Option<u64>,
+    /// Ping timeouts in a row before a peer is disconnected
+    pub ping_max_missed:      Option<usize>,
+    /// Misbehavior score at which a peer is banned
+    pub ban_threshold:        Option<u64>,
+    pub ban_duration:         Option<u64>,
+    pub score_half_life:      Option<u64>,
+    /// Ban the host of a banned peer too, off unless set
+    pub ban_host:             Option<bool>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,19 +61,63 @@ pub struct ConfigNetworkBootstrap {
     pub address: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ConfigAdaptiveInterval {
+    pub min_interval: u64,
+    pub max_interval: u64,
+    /// Transactions in a block from which the interval of the next height
+    /// shrinks
+    pub busy_txs:     usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfigConsensus {
     pub sync_txs_chunk_size: usize,
+    /// Block interval following how full the blocks are instead of the
+    /// metadata
+    #[serde(default)]
+    pub adaptive_interval:   Option<ConfigAdaptiveInterval>,
+    /// Milliseconds a proposed block may be stamped ahead of local time
+    #[serde(default = "default_max_clock_skew")]
+    pub max_clock_skew:      u64,
+    /// Most consensus intervals a block may follow its parent by
+    #[serde(default)]
+    pub max_timestamp_gap:   Option<u64>,
+    /// Heights in a row a validator may miss signing before it is reported
+    /// for jailing
+    #[serde(default)]
+    pub max_missed_heights:  Option<u64>,
+    /// Submit `jail_validator` for the validators found offline instead of
+    /// only logging them, on the admin's node
+    #[serde(default)]
+    pub jail_offline:        bool,
+    /// Milliseconds shutdown waits for the height in progress
+    #[serde(default = "default_stop_timeout")]
+    pub stop_timeout:        u64,
 }
 
 impl Default for ConfigConsensus {
     fn default() -> Self {
         Self {
             sync_txs_chunk_size: 5000,
+            adaptive_interval:   None,
+            max_clock_skew:      DEFAULT_MAX_CLOCK_SKEW,
+            max_timestamp_gap:   None,
+            max_missed_heights:  None,
+            jail_offline:        false,
+            stop_timeout:        DEFAULT_STOP_TIMEOUT,
         }
     }
 }
 
+fn default_max_clock_skew() -> u64 {
+    DEFAULT_MAX_CLOCK_SKEW
+}
+
+fn default_stop_timeout() -> u64 {
+    DEFAULT_STOP_TIMEOUT
+}
+
 fn default_broadcast_txs_size() -> usize {
     DEFAULT_BROADCAST_TXS_SIZE
 }
@@ -63,6 +126,24 @@ fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
+fn default_replace_price_bump() -> u64 {
+    DEFAULT_REPLACE_PRICE_BUMP
+}
+
+fn default_sender_limit() -> u64 {
+    DEFAULT_SENDER_LIMIT as u64
+}
+
+fn default_rejection_journal_size() -> usize {
+    DEFAULT_REJECTION_JOURNAL_SIZE
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigPriorityMethod {
+    pub service_name: String,
+    pub method:       String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size: u64,
@@ -71,6 +152,19 @@ pub struct ConfigMempool {
     pub broadcast_txs_size:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
     pub broadcast_txs_interval: u64,
+    /// Percentage a transaction must outbid the pending one with the same
+    /// sender and nonce by to replace it
+    #[serde(default = "default_replace_price_bump")]
+    pub replace_price_bump:     u64,
+    /// Pending transactions a single sender may have in the pool
+    #[serde(default = "default_sender_limit")]
+    pub sender_limit:           u64,
+    /// Calls packaged ahead of the fee market, such as metadata updates
+    #[serde(default)]
+    pub priority_methods:       Vec<ConfigPriorityMethod>,
+    /// Recently rejected transactions kept with their reason
+    #[serde(default = "default_rejection_journal_size")]
+    pub rejection_journal_size: usize,
 }
 
 #[derive(Debug, Deserialize)]
@@ -80,12 +174,60 @@ pub struct ConfigExecutor {
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigRocksDB {
-    pub max_open_files: i32,
+    pub max_open_files:      i32,
+    /// Store blocks and transactions with a checksum
+    #[serde(default)]
+    pub checksum:            bool,
+    /// Bytes, the rocksdb defaults are kept for the unset fields
+    pub block_cache_size:    Option<usize>,
+    pub write_buffer_size:   Option<usize>,
+    /// One of "none", "snappy", "lz4" and "zstd"
+    pub compression:         Option<String>,
+    pub max_background_jobs: Option<i32>,
+    /// Threads running the blocking database calls
+    pub io_threads:          Option<usize>,
+    /// Seconds the persisted transaction pool entries live
+    pub pool_ttl:            Option<u64>,
 }
 
 impl Default for ConfigRocksDB {
     fn default() -> Self {
-        Self { max_open_files: 64 }
+        Self {
+            max_open_files:      64,
+            checksum:            false,
+            block_cache_size:    None,
+            write_buffer_size:   None,
+            compression:         None,
+            max_background_jobs: None,
+            io_threads:          None,
+            pool_ttl:            None,
+        }
+    }
+}
+
+impl ConfigRocksDB {
+    pub fn rocks_config(&self) -> ProtocolResult<RocksConfig> {
+        let default = RocksConfig::default();
+        let compression = match self.compression.as_ref() {
+            Some(compression) => Some(compression.parse::<RocksCompression>()?),
+            None => None,
+        };
+
+        Ok(RocksConfig {
+            max_open_files: self.max_open_files,
+            block_cache_size: self.block_cache_size.unwrap_or(default.block_cache_size),
+            write_buffer_size: self.write_buffer_size.unwrap_or(default.write_buffer_size),
+            compression,
+            max_background_jobs: self
+                .max_background_jobs
+                .unwrap_or(default.max_background_jobs),
+            overrides: Vec::new(),
+            ttls: match self.pool_ttl {
+                Some(secs) => vec![(StorageCategory::TransactionPool, Duration::from_secs(secs))],
+                None => default.ttls,
+            },
+            io_threads: self.io_threads.unwrap_or(default.io_threads),
+        })
     }
 }
 
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures::{future, lock::Mutex};
@@ -13,18 +14,20 @@ use common_crypto::{
 };
 use core_api::adapter::DefaultAPIAdapter;
 use core_api::config::GraphQLConfig;
-use core_consensus::fixed_types::{FixedBlock, FixedSignedTxs};
+use core_consensus::fixed_types::{FixedBlock, FixedProof, FixedSignedTxs};
 use core_consensus::message::{
-    ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullTxsRpcHandler,
-    QCMessageHandler, RemoteHeightMessageHandler, VoteMessageHandler, BROADCAST_HEIGHT,
-    END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
-    END_GOSSIP_SIGNED_VOTE, RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK,
+    ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullProofRpcHandler,
+    PullTxsRpcHandler, QCMessageHandler, RemoteHeightMessageHandler, VoteMessageHandler,
+    BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE,
+    END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, RPC_RESP_SYNC_PULL_BLOCK,
+    RPC_RESP_SYNC_PULL_PROOF, RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF,
     RPC_SYNC_PULL_TXS,
 };
-use core_consensus::status::{CurrentConsensusStatus, StatusAgent};
+use core_consensus::status::{restore_latest_proof, CurrentConsensusStatus, StatusAgent};
 use core_consensus::{
-    DurationConfig, Node, OverlordConsensus, OverlordConsensusAdapter, OverlordSynchronization,
-    RichBlock, SignedTxsWAL,
+    AdaptiveInterval, AtomicConsensusMetrics, DurationConfig, JailHook, JailValidatorHook,
+    LivenessTracker, LogJailHook, Node, OverlordConsensus, OverlordConsensusAdapter,
+    OverlordSynchronization, RichBlock, SignedTxsWAL, Stopper, TimestampRules,
 };
 use core_mempool::{
     DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxsHandler, PullTxsHandler,
@@ -34,7 +37,9 @@ use core_network::{NetworkConfig, NetworkService};
 use core_storage::{adapter::rocks::RocksAdapter, ImplStorage};
 use framework::binding::state::RocksTrieDB;
 use framework::executor::{ServiceExecutor, ServiceExecutorFactory};
-use protocol::traits::{APIAdapter, Context, MemPool, NodeInfo, ServiceMapping, Storage};
+use protocol::traits::{
+    APIAdapter, ConsensusAdapter, Context, MemPool, NodeInfo, ServiceMapping, Storage,
+};
 use protocol::types::{Address, Block, BlockHeader, Genesis, Hash, Metadata, Proof, Validator};
 use protocol::{fixed_codec::FixedCodec, ProtocolResult};
 
@@ -64,11 +69,13 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
 
     // Init Block db
     let path_block = config.data_path_for_block();
-    let rocks_adapter = Arc::new(RocksAdapter::new(
+    let rocks_adapter = Arc::new(RocksAdapter::new_with_config(
         path_block,
-        config.rocksdb.max_open_files,
+        config.rocksdb.rocks_config()?,
     )?);
-    let storage = Arc::new(ImplStorage::new(Arc::clone(&rocks_adapter)));
+    let storage = Arc::new(
+        ImplStorage::new(Arc::clone(&rocks_adapter)).with_checksum(config.rocksdb.checksum),
+    );
 
     match storage.get_latest_block().await {
         Ok(genesis_block) => {
@@ -76,7 +83,7 @@ pub async fn create_genesis<Mapping: 'static + ServiceMapping>(
             return Ok(genesis_block);
         }
         Err(e) => {
-            if !e.to_string().contains("GetNone") {
+            if !e.to_string().contains("NotFound") {
                 return Err(e);
             }
         }
@@ -142,11 +149,13 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     let path_block = config.data_path_for_block();
     log::info!("Data path for block: {:?}", path_block);
 
-    let rocks_adapter = Arc::new(RocksAdapter::new(
+    let rocks_adapter = Arc::new(RocksAdapter::new_with_config(
         path_block.clone(),
-        config.rocksdb.max_open_files,
+        config.rocksdb.rocks_config()?,
     )?);
-    let storage = Arc::new(ImplStorage::new(Arc::clone(&rocks_adapter)));
+    let storage = Arc::new(
+        ImplStorage::new(Arc::clone(&rocks_adapter)).with_checksum(config.rocksdb.checksum),
+    );
 
     // Init network
     let network_config = NetworkConfig::new()
@@ -158,7 +167,14 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         .max_frame_length(config.network.max_frame_length.clone())
         .send_buffer_size(config.network.send_buffer_size.clone())
         .write_timeout(config.network.write_timeout)
-        .recv_buffer_size(config.network.recv_buffer_size.clone());
+        .recv_buffer_size(config.network.recv_buffer_size.clone())
+        .ping_interval(config.network.ping_interval)
+        .ping_timeout(config.network.ping_timeout)
+        .ping_max_missed(config.network.ping_max_missed)
+        .ban_threshold(config.network.ban_threshold)
+        .ban_duration(config.network.ban_duration)
+        .score_half_life(config.network.score_half_life)
+        .ban_host(config.network.ban_host);
 
     let network_privkey = config.privkey.as_string_trim0x();
 
@@ -191,10 +207,19 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         config.mempool.broadcast_txs_size,
         config.mempool.broadcast_txs_interval,
     );
-    let mempool = Arc::new(HashMemPool::new(
-        config.mempool.pool_size as usize,
-        mempool_adapter,
-    ));
+    let priority_methods = config
+        .mempool
+        .priority_methods
+        .iter()
+        .map(|priority| (priority.service_name.clone(), priority.method.clone()))
+        .collect();
+    let mempool = Arc::new(
+        HashMemPool::new(config.mempool.pool_size as usize, mempool_adapter)
+            .with_replace_price_bump(config.mempool.replace_price_bump)
+            .with_sender_limit(config.mempool.sender_limit as usize)
+            .with_priority_methods(priority_methods)
+            .with_rejection_journal_size(config.mempool.rejection_journal_size),
+    );
 
     // Init trie db
     let path_state = config.data_path_for_state();
@@ -246,6 +271,10 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         metadata.max_tx_size,
     );
 
+    // Bring back the transactions pending before the last shutdown, their
+    // checks need the args above
+    mempool.recover(Context::new()).await?;
+
     // register broadcast new transaction
     network_service.register_endpoint_handler(
         END_GOSSIP_NEW_TXS,
@@ -282,7 +311,22 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     let current_height = current_block.header.height;
     let exec_height = current_block.header.exec_height;
 
-    let current_consensus_status = CurrentConsensusStatus {
+    let latest_proof = restore_latest_proof(storage.as_ref(), &current_block).await?;
+
+    let adaptive = config
+        .consensus
+        .adaptive_interval
+        .as_ref()
+        .map(|adaptive| {
+            AdaptiveInterval::new(
+                adaptive.min_interval,
+                adaptive.max_interval,
+                adaptive.busy_txs,
+            )
+        })
+        .transpose()?;
+
+    let mut current_consensus_status = CurrentConsensusStatus {
         cycles_price:               metadata.cycles_price,
         cycles_limit:               metadata.cycles_limit,
         current_height:             current_block.header.height,
@@ -294,7 +338,7 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         list_state_root:            vec![],
         list_receipt_root:          vec![],
         list_cycles_used:           vec![],
-        current_proof:              current_header.proof.clone(),
+        current_proof:              latest_proof,
         validators:                 validators.clone(),
         consensus_interval:         metadata.interval,
         propose_ratio:              metadata.propose_ratio,
@@ -303,7 +347,9 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         brake_ratio:                metadata.brake_ratio,
         max_tx_size:                metadata.max_tx_size,
         tx_num_limit:               metadata.tx_num_limit,
+        adaptive_interval:          adaptive,
     };
+    current_consensus_status.adapt_interval(current_block.ordered_tx_hashes.len());
 
     let consensus_interval = current_consensus_status.consensus_interval;
     let status_agent = StatusAgent::new(current_consensus_status);
@@ -345,7 +391,29 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
     let exec_demon = consensus_adapter.take_exec_demon();
     let consensus_adapter = Arc::new(consensus_adapter);
 
+    let liveness = match config.consensus.max_missed_heights {
+        Some(max_missed) => {
+            let hook: Arc<dyn JailHook> = if config.consensus.jail_offline {
+                Arc::new(JailValidatorHook::new(
+                    Arc::clone(&mempool),
+                    status_agent.clone(),
+                    metadata.chain_id.clone(),
+                    metadata.timeout_gap,
+                    my_privkey,
+                ))
+            } else {
+                Arc::new(LogJailHook)
+            };
+            let tracker = LivenessTracker::new(max_missed, hook);
+            let missed = consensus_adapter.load_liveness(Context::new()).await?;
+            tracker.restore(&missed)?;
+            Some(tracker)
+        }
+        None => None,
+    };
+
     let lock = Arc::new(Mutex::new(()));
+    let stopper = Stopper::new(Arc::clone(&lock));
     let overlord_consensus = Arc::new(OverlordConsensus::new(
         status_agent.clone(),
         node_info,
@@ -355,15 +423,24 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         Arc::clone(&txs_wal),
         Arc::clone(&consensus_adapter),
         Arc::clone(&lock),
+        stopper.clone(),
+        TimestampRules {
+            max_clock_skew:    config.consensus.max_clock_skew,
+            max_gap_intervals: config.consensus.max_timestamp_gap,
+        },
+        Arc::new(AtomicConsensusMetrics::default()),
+        liveness,
     ));
 
+    overlord_consensus.load_evidence().await?;
     consensus_adapter.set_overlord_handler(overlord_consensus.get_overlord_handler());
 
     let synchronization = Arc::new(OverlordSynchronization::new(
         config.consensus.sync_txs_chunk_size,
         consensus_adapter,
         status_agent.clone(),
-        lock,
+        Arc::clone(&lock),
+        stopper,
     ));
 
     // Re-execute block from exec_height + 1 to current_height, so that init the
@@ -376,7 +453,7 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
             .await?;
         let rich_block = RichBlock { block, txs };
         let _ = synchronization
-            .exec_block(Context::new(), rich_block, status_agent.clone())
+            .exec_block(Context::new(), &rich_block, status_agent.clone())
             .await?;
     }
 
@@ -417,8 +494,16 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
             Arc::clone(&storage),
         )),
     )?;
+    network_service.register_endpoint_handler(
+        RPC_SYNC_PULL_PROOF,
+        Box::new(PullProofRpcHandler::new(
+            Arc::new(network_service.handle()),
+            Arc::clone(&storage),
+        )),
+    )?;
     network_service.register_rpc_response::<FixedBlock>(RPC_RESP_SYNC_PULL_BLOCK)?;
     network_service.register_rpc_response::<FixedSignedTxs>(RPC_RESP_SYNC_PULL_TXS)?;
+    network_service.register_rpc_response::<FixedProof>(RPC_RESP_SYNC_PULL_PROOF)?;
 
     // Run network
     tokio::spawn(network_service);
@@ -447,8 +532,9 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         brake_ratio:     metadata.brake_ratio,
     };
 
+    let consensus = Arc::clone(&overlord_consensus);
     tokio::spawn(async move {
-        if let Err(e) = overlord_consensus
+        if let Err(e) = consensus
             .run(consensus_interval, authority_list, Some(timer_config))
             .await
         {
@@ -494,6 +580,12 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
         }
     }
 
+    // Let a commit or sync in progress finish and keep new ones from
+    // starting, so the node stops between two heights
+    overlord_consensus
+        .stop(Duration::from_millis(config.consensus.stop_timeout))
+        .await;
+
     // Abort consensus
     abort_handle.abort();
 
@@ -4,10 +4,13 @@ mod config;
 mod default_start;
 
 use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 
 use derive_more::{Display, From};
 
+use core_storage::adapter::rocks::{BackupInfo, RocksAdapter};
+use core_storage::ImplStorage;
 use protocol::traits::ServiceMapping;
 use protocol::types::{Block, Genesis};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -102,6 +105,48 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
         Ok(())
     }
 
+    /// Writes blocks `from..=to` of the local chain to a snapshot file.
+    pub fn export_chain(self, path: &str, from: u64, to: u64) -> ProtocolResult<()> {
+        let storage = self.open_storage()?;
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        rt.block_on(storage.export_chain(Path::new(path), from, to))
+    }
+
+    /// Appends the blocks of a snapshot file to the local chain.
+    pub fn import_chain(self, path: &str) -> ProtocolResult<()> {
+        let storage = self.open_storage()?;
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        rt.block_on(storage.import_chain(Path::new(path)))
+    }
+
+    /// Backs up the block database into `backup_dir`. The node must not be
+    /// running, it holds the database lock.
+    pub fn backup(self, backup_dir: &str) -> ProtocolResult<()> {
+        self.open_adapter()?.create_backup(backup_dir)
+    }
+
+    /// Restores the latest backup of `backup_dir` as the block database,
+    /// refused when the data path already holds one.
+    pub fn restore(self, backup_dir: &str) -> ProtocolResult<()> {
+        RocksAdapter::restore_from_backup(backup_dir, self.config.data_path_for_block())
+    }
+
+    pub fn list_backups(self, backup_dir: &str) -> ProtocolResult<Vec<BackupInfo>> {
+        RocksAdapter::list_backups(backup_dir)
+    }
+
+    fn open_adapter(&self) -> ProtocolResult<RocksAdapter> {
+        RocksAdapter::new_with_config(
+            self.config.data_path_for_block(),
+            self.config.rocksdb.rocks_config()?,
+        )
+    }
+
+    fn open_storage(&self) -> ProtocolResult<ImplStorage<RocksAdapter>> {
+        let adapter = self.open_adapter()?;
+        Ok(ImplStorage::new(Arc::new(adapter)).with_checksum(self.config.rocksdb.checksum))
+    }
+
     async fn create_genesis(&self) -> ProtocolResult<Block> {
         create_genesis(
             &self.config,